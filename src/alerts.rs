@@ -0,0 +1,86 @@
+//! Audible and visual alerts for critical moments
+//!
+//! Alerts are raised from game events, so anything that publishes a critical
+//! event gets bell/flash handling for free.
+
+use crate::events::{GameEvent, Subscriber};
+use serde::Deserialize;
+
+/// Number of game ticks a screen-edge flash lasts
+const FLASH_TICKS: usize = 8;
+
+/// How an alert is signalled to the player
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertMode {
+    /// Ring the terminal bell
+    Bell,
+    /// Flash the edge of the screen
+    #[default]
+    Flash,
+    /// Don't signal alerts at all
+    None,
+}
+
+/// Turns critical game events into bell rings or edge flashes
+pub struct Alerts {
+    mode: AlertMode,
+    /// When set, flashes are shown as a steady highlight instead of blinking
+    reduced_motion: bool,
+    /// Set when the bell should ring on the next frame
+    bell_pending: bool,
+    /// Ticks left on the current flash
+    flash_ticks: usize,
+}
+
+impl Alerts {
+    /// Creates the alert handler
+    ///
+    /// # Arguments
+    /// * `mode` - How alerts are signalled
+    /// * `reduced_motion` - Whether to avoid blinking effects
+    pub fn new(mode: AlertMode, reduced_motion: bool) -> Self {
+        Alerts {
+            mode,
+            reduced_motion,
+            bell_pending: false,
+            flash_ticks: 0,
+        }
+    }
+
+    /// Raises an alert using the configured mode
+    pub fn raise(&mut self) {
+        match self.mode {
+            AlertMode::Bell => self.bell_pending = true,
+            AlertMode::Flash => self.flash_ticks = FLASH_TICKS,
+            AlertMode::None => {}
+        }
+    }
+
+    /// Counts down the current flash; call once per game tick
+    pub fn tick(&mut self) {
+        self.flash_ticks = self.flash_ticks.saturating_sub(1);
+    }
+
+    /// Returns whether the bell should ring, clearing the request
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell_pending)
+    }
+
+    /// Returns whether the screen edge should be highlighted this frame
+    pub fn flash_visible(&self) -> bool {
+        if self.flash_ticks == 0 {
+            return false;
+        }
+        // Blink on alternate ticks unless the player asked for reduced motion
+        self.reduced_motion || self.flash_ticks.is_multiple_of(2)
+    }
+}
+
+impl Subscriber for Alerts {
+    fn on_event(&mut self, event: &GameEvent) {
+        if let GameEvent::InvasionImminent = event {
+            self.raise();
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Sound effects and music played through rodio, enabled with the `audio`
+//! feature
+//!
+//! Effects are synthesized when the game starts rather than loaded from
+//! files, so the binary stays self-contained. The audio device lives on its
+//! own thread, which plays whatever the game loop sends it; a system without
+//! sound output just ends up with nobody listening.
+//!
+//! The music is the arcade original's: four descending bass notes, one after
+//! another, played faster the lower the invaders get.
+
+use rodio::buffer::SamplesBuffer;
+use rodio::{OutputStream, Source};
+use space_invaders::events::{GameEvent, Subscriber};
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Samples per second of the synthesized effects
+const SAMPLE_RATE: u32 = 44_100;
+/// Loudness of every effect at full volume, from 0.0 to 1.0
+const VOLUME: f32 = 0.25;
+/// Pitches of the music's four notes, in Hz, played in order
+const NOTES: [f32; 4] = [98.0, 87.3, 77.8, 73.4];
+/// How long each note of the music sounds for
+const NOTE_LENGTH: f32 = 0.09;
+/// Time between notes while the invaders are at the top of the playfield,
+/// and once they reach the bottom
+const NOTE_INTERVAL: (Duration, Duration) = (Duration::from_millis(600), Duration::from_millis(120));
+
+/// A sound effect
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sound {
+    /// Short falling zap, for any bullet fired by the player's side
+    Shoot,
+    /// Burst of noise, for an enemy or the player's ship being destroyed
+    Explosion,
+    /// Rising chime; the game has no power-ups yet, so it marks a cleared wave
+    PowerUp,
+    /// Long falling tone, for the end of a lost run
+    GameOver,
+}
+
+impl Sound {
+    const ALL: [Sound; 4] = [Sound::Shoot, Sound::Explosion, Sound::PowerUp, Sound::GameOver];
+
+    /// Synthesizes the effect as mono samples
+    fn synthesize(self) -> Vec<f32> {
+        match self {
+            Sound::Shoot => sweep(1200.0, 300.0, 0.08, Wave::Square),
+            Sound::Explosion => sweep(0.0, 0.0, 0.3, Wave::Noise),
+            Sound::PowerUp => [523.0, 659.0, 784.0, 1047.0]
+                .into_iter()
+                .flat_map(|pitch| sweep(pitch, pitch, 0.07, Wave::Square))
+                .collect(),
+            Sound::GameOver => sweep(440.0, 110.0, 0.9, Wave::Square),
+        }
+    }
+}
+
+/// Shape of a synthesized tone
+#[derive(Clone, Copy)]
+enum Wave {
+    Square,
+    Noise,
+}
+
+/// A tone gliding from one pitch to another while fading out
+///
+/// # Arguments
+/// * `from` - Starting pitch, in Hz; ignored for noise
+/// * `to` - Final pitch, in Hz; ignored for noise
+/// * `seconds` - Length of the tone
+/// * `wave` - Shape of the tone
+fn sweep(from: f32, to: f32, seconds: f32, wave: Wave) -> Vec<f32> {
+    let count = (seconds * SAMPLE_RATE as f32) as usize;
+    let mut phase = 0.0f32;
+    (0..count)
+        .map(|i| {
+            let progress = i as f32 / count as f32;
+            phase = (phase + (from + (to - from) * progress) / SAMPLE_RATE as f32).fract();
+            let sample = match wave {
+                Wave::Square if phase < 0.5 => 1.0,
+                Wave::Square => -1.0,
+                Wave::Noise => rand::random::<f32>() * 2.0 - 1.0,
+            };
+            // A sine under the square takes the edge off its harshest harmonics
+            let sample = match wave {
+                Wave::Square => 0.6 * sample + 0.4 * (phase * TAU).sin(),
+                Wave::Noise => sample,
+            };
+            sample * (1.0 - progress) * VOLUME
+        })
+        .collect()
+}
+
+/// What the game loop asks of the audio thread
+enum Request {
+    /// Play a sound effect
+    Play(Sound),
+    /// Play the music at a tempo, given as how far down the playfield the
+    /// invaders are from 0.0 to 1.0, or stop it
+    Music(Option<f32>),
+    /// Silence the music, or bring it back
+    Mute(bool),
+    /// Change the loudness of the music and of the effects
+    Volumes { music: f32, effects: f32 },
+}
+
+/// Sends sound effects and music changes to the audio thread
+pub struct Audio {
+    requests: Sender<Request>,
+    /// Tempo of the music last asked for
+    music: Option<f32>,
+    /// Whether the music is muted
+    muted: bool,
+}
+
+impl Audio {
+    /// Starts the audio thread on the system's default output device
+    ///
+    /// Failing to open the device isn't reported: the game just plays
+    /// silently, as it does without the `audio` feature.
+    ///
+    /// # Arguments
+    /// * `music_volume` - Loudness of the music, from 0.0 to 1.0
+    /// * `effects_volume` - Loudness of the sound effects, from 0.0 to 1.0
+    pub fn start(music_volume: f32, effects_volume: f32) -> Self {
+        let (requests, received) = mpsc::channel();
+        thread::spawn(move || {
+            // The stream has to stay alive for anything to be heard
+            let Ok((_stream, handle)) = OutputStream::try_default() else { return };
+            let effects: HashMap<Sound, Vec<f32>> = Sound::ALL.into_iter().map(|sound| (sound, sound.synthesize())).collect();
+            let notes = NOTES.map(|pitch| sweep(pitch, pitch, NOTE_LENGTH, Wave::Square));
+            let (mut music, mut muted) = (None, false);
+            let (mut music_volume, mut effects_volume) = (music_volume, effects_volume);
+            let (mut next_note, mut note) = (Instant::now(), 0);
+            loop {
+                let timeout = match music {
+                    Some(_) => next_note.saturating_duration_since(Instant::now()),
+                    None => Duration::from_secs(60),
+                };
+                match received.recv_timeout(timeout) {
+                    Ok(Request::Play(sound)) => {
+                        let samples = SamplesBuffer::new(1, SAMPLE_RATE, effects[&sound].clone());
+                        let _ = handle.play_raw(samples.amplify(effects_volume));
+                    }
+                    Ok(Request::Music(tempo)) => {
+                        if music.is_none() {
+                            next_note = Instant::now();
+                        }
+                        music = tempo;
+                    }
+                    Ok(Request::Mute(mute)) => muted = mute,
+                    Ok(Request::Volumes { music: new_music, effects: new_effects }) => {
+                        (music_volume, effects_volume) = (new_music, new_effects);
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        let Some(descent) = music else { continue };
+                        if !muted {
+                            let samples = SamplesBuffer::new(1, SAMPLE_RATE, notes[note].clone());
+                            let _ = handle.play_raw(samples.amplify(music_volume));
+                        }
+                        note = (note + 1) % NOTES.len();
+                        let (slowest, fastest) = NOTE_INTERVAL;
+                        next_note = Instant::now() + slowest.mul_f32(1.0 - descent) + fastest.mul_f32(descent);
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        });
+        Audio { requests, music: None, muted: false }
+    }
+
+    /// Plays a sound effect, mixed over any already playing
+    pub fn play(&self, sound: Sound) {
+        // The thread only stops if the device couldn't be opened
+        let _ = self.requests.send(Request::Play(sound));
+    }
+
+    /// Keeps the music playing at a tempo, or stops it
+    ///
+    /// # Arguments
+    /// * `descent` - How far down the playfield the invaders are, from 0.0
+    ///   to 1.0; `None` stops the music
+    pub fn set_music(&mut self, descent: Option<f32>) {
+        if descent != self.music {
+            self.music = descent;
+            let _ = self.requests.send(Request::Music(descent));
+        }
+    }
+
+    /// Changes the loudness of the music and of the sound effects, from the
+    /// next sound played on
+    ///
+    /// # Arguments
+    /// * `music` - Loudness of the music, from 0.0 to 1.0
+    /// * `effects` - Loudness of the sound effects, from 0.0 to 1.0
+    pub fn set_volumes(&self, music: f32, effects: f32) {
+        let _ = self.requests.send(Request::Volumes { music, effects });
+    }
+
+    /// Mutes the music, or unmutes it
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        let _ = self.requests.send(Request::Mute(self.muted));
+    }
+}
+
+impl Subscriber for Audio {
+    fn on_event(&mut self, event: &GameEvent) {
+        let sound = match event {
+            GameEvent::ShotFired => Sound::Shoot,
+            GameEvent::EnemyKilled { .. } | GameEvent::PlayerHit => Sound::Explosion,
+            GameEvent::WaveCleared => Sound::PowerUp,
+            _ => return,
+        };
+        self.play(sound);
+    }
+}
@@ -0,0 +1,405 @@
+//! Drawing backends: where the game's frames end up
+//!
+//! Screens are drawn through the `Renderer` trait as characters and lines of
+//! text at screen positions, so the game never talks to the terminal
+//! directly. `Terminal` draws with crossterm; other backends (plain output,
+//! capturing frames for tests, a window) only need to implement the trait.
+//! `Letterbox` wraps any of them to center the game on a larger screen.
+
+use crossterm::{
+    cursor, queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, ClearType},
+};
+use space_invaders::theme::Style;
+use std::io::{self, stdout, Write};
+
+/// Something frames can be drawn on
+///
+/// Positions are in cells, from the top-left corner of the screen. Nothing
+/// has to show until `present` is called.
+pub trait Renderer {
+    /// Starts drawing a frame
+    ///
+    /// # Arguments
+    /// * `clear` - Whether the frame replaces the whole screen, so anything
+    ///   it doesn't draw again is blanked, or draws over what is already there
+    fn begin_frame(&mut self, clear: bool) -> io::Result<()>;
+
+    /// Forgets what is on the screen, so the next frame that clears it wipes
+    /// it outright, e.g. after the terminal was resized; backends that always
+    /// draw from scratch ignore it
+    fn reset(&mut self) {}
+
+    /// Draws one character
+    ///
+    /// # Arguments
+    /// * `x` - Column to draw at
+    /// * `y` - Row to draw at
+    /// * `ch` - The character
+    /// * `style` - Colors to draw it in; `None` for the default colors
+    fn draw_cell(&mut self, x: usize, y: usize, ch: char, style: Option<Style>) -> io::Result<()>;
+
+    /// Draws a line of text
+    ///
+    /// # Arguments
+    /// * `x` - Column of the text's first character
+    /// * `y` - Row to draw on
+    /// * `text` - The text, without line breaks
+    /// * `color` - Color of the text; `None` for the default color
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, color: Option<Color>) -> io::Result<()>;
+
+    /// Blanks a row from a column to the right edge of the screen
+    fn clear_line(&mut self, x: usize, y: usize) -> io::Result<()>;
+
+    /// Rings the terminal bell, or whatever the backend has instead
+    fn bell(&mut self) -> io::Result<()>;
+
+    /// Sends a terminal graphics escape sequence (Kitty or Sixel) with the
+    /// cursor at a cell; backends that can't show terminal images ignore it
+    fn draw_graphics(&mut self, _x: usize, _y: usize, _sequence: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Shows everything drawn since the frame began
+    fn present(&mut self) -> io::Result<()>;
+}
+
+/// Draws on the terminal through crossterm
+///
+/// A frame is built up as one string of text and escape sequences, and
+/// written with a single write when it is presented, so slow connections
+/// never show half a frame. Colors are only set when they change, so runs
+/// of cells in the same colors cost one byte per cell.
+///
+/// The backend remembers what every cell shows, and skips cells drawn again
+/// as they already are. A frame that clears the screen doesn't wipe it:
+/// cells it leaves out are overwritten with spaces when it is presented, so
+/// the screen never flashes blank. Only after `reset`, or once terminal
+/// graphics have been drawn, which it can't keep track of, is the screen
+/// wiped for real.
+pub struct Terminal {
+    /// Where frames are written: standard output, unless they are copied
+    /// elsewhere too
+    out: Box<dyn Write>,
+    /// The frame being drawn, not yet written
+    frame: Vec<u8>,
+    /// Where the terminal's cursor is after the last thing drawn, if known,
+    /// so runs of cells are drawn without moving the cursor to each one
+    cursor: Option<(usize, usize)>,
+    /// Colors the terminal is drawing in; `None` for the default colors
+    colors: Option<Style>,
+    /// What each cell shows, as far as the backend knows, by row; grown as
+    /// cells further out are drawn
+    shown: Vec<Vec<Shown>>,
+    /// Frames begun so far, to tell which cells the current one has drawn
+    frames: u64,
+    /// Whether the current frame clears the screen, so the cells it leaves
+    /// out are blanked when it is presented
+    sweep: bool,
+    /// Whether the next frame that clears the screen has to wipe it, as
+    /// `shown` can't be trusted
+    wipe: bool,
+}
+
+/// The backend's copy of a cell
+#[derive(Clone, Copy, Default)]
+struct Shown {
+    /// What the cell shows; `None` when it is blank
+    glyph: Option<(char, Option<Style>)>,
+    /// The last frame that drew the cell
+    frame: u64,
+}
+
+impl Terminal {
+    /// Creates a backend drawing on standard output
+    ///
+    /// The terminal should already be set up for the game; see
+    /// `screen::TerminalGuard`.
+    pub fn new() -> Self {
+        Self::with_output(Box::new(stdout()))
+    }
+
+    /// Creates a backend writing its frames somewhere of the caller's
+    /// choosing, which should pass them on to the terminal
+    ///
+    /// # Arguments
+    /// * `out` - Where each frame is written, in one write, when presented
+    pub fn with_output(out: Box<dyn Write>) -> Self {
+        Terminal {
+            out,
+            frame: Vec::new(),
+            cursor: None,
+            colors: None,
+            shown: Vec::new(),
+            frames: 0,
+            sweep: false,
+            wipe: true,
+        }
+    }
+
+    /// Draws a character in a cell, unless the cell already shows it
+    fn put(&mut self, x: usize, y: usize, ch: char, style: Option<Style>) -> io::Result<()> {
+        let glyph = Some((ch, style)).filter(|&glyph| glyph != (' ', None));
+        let shown = self.shown.get(y).and_then(|row| row.get(x)).and_then(|cell| cell.glyph);
+        // Cells beyond the copy are blank, and stay so without being kept
+        if glyph.is_none() && shown.is_none() {
+            return Ok(());
+        }
+        let frames = self.frames;
+        let cell = self.cell(x, y);
+        cell.frame = frames;
+        if shown == glyph {
+            return Ok(());
+        }
+        cell.glyph = glyph;
+        self.move_to(x, y)?;
+        self.set_colors(style)?;
+        queue!(self.frame, Print(ch))?;
+        self.cursor = Some((x + 1, y));
+        Ok(())
+    }
+
+    /// The backend's copy of a cell, growing the copy to take it in
+    fn cell(&mut self, x: usize, y: usize) -> &mut Shown {
+        if self.shown.len() <= y {
+            self.shown.resize_with(y + 1, Vec::new);
+        }
+        let row = &mut self.shown[y];
+        if row.len() <= x {
+            row.resize(x + 1, Shown::default());
+        }
+        &mut row[x]
+    }
+
+    /// Moves the cursor to a cell, unless it is already there
+    fn move_to(&mut self, x: usize, y: usize) -> io::Result<()> {
+        if self.cursor != Some((x, y)) {
+            queue!(self.frame, cursor::MoveTo(x as u16, y as u16))?;
+        }
+        Ok(())
+    }
+
+    /// Switches the colors the terminal draws in, unless they are already set
+    ///
+    /// # Arguments
+    /// * `colors` - The new colors; `None` for the default colors
+    fn set_colors(&mut self, colors: Option<Style>) -> io::Result<()> {
+        if colors == self.colors {
+            return Ok(());
+        }
+        // Only a reset takes a background color away
+        let had_background = self.colors.is_some_and(|current| current.bg.is_some());
+        if colors.is_none() || (had_background && colors.is_some_and(|new| new.bg.is_none())) {
+            queue!(self.frame, ResetColor)?;
+            self.colors = None;
+        }
+        if let Some(new) = colors {
+            if self.colors.map(|current| current.fg) != Some(new.fg) {
+                queue!(self.frame, SetForegroundColor(new.fg))?;
+            }
+            if let Some(background) = new.bg.filter(|&bg| self.colors.and_then(|current| current.bg) != Some(bg)) {
+                queue!(self.frame, SetBackgroundColor(background))?;
+            }
+        }
+        self.colors = colors;
+        Ok(())
+    }
+}
+
+impl Renderer for Terminal {
+    fn begin_frame(&mut self, clear: bool) -> io::Result<()> {
+        // Anything could have moved the cursor since the last frame
+        self.cursor = None;
+        if clear && self.wipe {
+            // Cleared cells take the current background color
+            self.set_colors(None)?;
+            queue!(self.frame, terminal::Clear(ClearType::All))?;
+            self.shown.iter_mut().flatten().for_each(|cell| cell.glyph = None);
+            self.wipe = false;
+        }
+        self.frames += 1;
+        self.sweep = clear;
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.wipe = true;
+    }
+
+    fn draw_cell(&mut self, x: usize, y: usize, ch: char, style: Option<Style>) -> io::Result<()> {
+        self.put(x, y, ch, style)
+    }
+
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, color: Option<Color>) -> io::Result<()> {
+        let style = color.map(|fg| Style { fg, bg: None });
+        for (i, ch) in text.chars().enumerate() {
+            self.put(x + i, y, ch, style)?;
+        }
+        Ok(())
+    }
+
+    fn clear_line(&mut self, x: usize, y: usize) -> io::Result<()> {
+        self.move_to(x, y)?;
+        self.set_colors(None)?;
+        queue!(self.frame, terminal::Clear(ClearType::UntilNewLine))?;
+        self.cursor = Some((x, y));
+        if let Some(row) = self.shown.get_mut(y) {
+            row.iter_mut().skip(x).for_each(|cell| cell.glyph = None);
+        }
+        Ok(())
+    }
+
+    fn bell(&mut self) -> io::Result<()> {
+        queue!(self.frame, Print('\x07'))
+    }
+
+    fn draw_graphics(&mut self, x: usize, y: usize, sequence: &str) -> io::Result<()> {
+        self.move_to(x, y)?;
+        queue!(self.frame, Print(sequence))?;
+        // Sixel images leave the cursor below them
+        self.cursor = None;
+        self.wipe = true;
+        Ok(())
+    }
+
+    fn present(&mut self) -> io::Result<()> {
+        // Blank whatever a clearing frame didn't draw again, row by row
+        if std::mem::take(&mut self.sweep) {
+            for y in 0..self.shown.len() {
+                for x in 0..self.shown[y].len() {
+                    let cell = self.shown[y][x];
+                    if cell.glyph.is_some() && cell.frame != self.frames {
+                        self.put(x, y, ' ', None)?;
+                    }
+                }
+            }
+        }
+        // Leave the terminal in its default colors between frames
+        self.set_colors(None)?;
+        self.out.write_all(&self.frame)?;
+        self.frame.clear();
+        self.out.flush()
+    }
+}
+
+/// Centers everything drawn in a block of the screen, filling the margin
+/// around it
+///
+/// Positions given to a `Letterbox` are relative to the block's top-left
+/// corner. The margin is filled in whenever a frame starts by clearing the
+/// screen.
+pub struct Letterbox {
+    inner: Box<dyn Renderer>,
+    /// Size of the screen, in cells
+    screen: (usize, usize),
+    /// Size of the block, in cells
+    block: (usize, usize),
+    /// Top-left cell of the block, on the screen
+    origin: (usize, usize),
+    /// Character filling the margin, and its color
+    margin: (char, Option<Color>),
+}
+
+impl Letterbox {
+    /// Wraps a backend, with the block filling the whole screen until `fit`
+    /// is called
+    pub fn new(inner: Box<dyn Renderer>) -> Self {
+        Letterbox { inner, screen: (0, 0), block: (0, 0), origin: (0, 0), margin: (' ', None) }
+    }
+
+    /// Centers a block on the screen
+    ///
+    /// # Arguments
+    /// * `screen` - Size of the screen, in cells
+    /// * `block` - Size of the block; a block larger than the screen is
+    ///   drawn from the top-left corner
+    ///
+    /// # Returns
+    /// Whether the block moved, so what was drawn has to be drawn again
+    pub fn fit(&mut self, screen: (usize, usize), block: (usize, usize)) -> bool {
+        let origin = (screen.0.saturating_sub(block.0) / 2, screen.1.saturating_sub(block.1) / 2);
+        let moved = origin != self.origin;
+        (self.screen, self.block, self.origin) = (screen, block, origin);
+        moved
+    }
+
+    /// Sets how the margin around the block is filled
+    ///
+    /// # Arguments
+    /// * `ch` - Character repeated over the margin
+    /// * `color` - Color of the character; `None` for the default color
+    pub fn set_margin(&mut self, ch: char, color: Option<Color>) {
+        self.margin = (ch, color);
+    }
+
+    /// Fills the screen around the block
+    fn fill_margin(&mut self) -> io::Result<()> {
+        let (ch, color) = self.margin;
+        if ch == ' ' {
+            // The screen was just cleared
+            return Ok(());
+        }
+        let (left, top) = self.origin;
+        let right = (left + self.block.0).min(self.screen.0);
+        let bottom = (top + self.block.1).min(self.screen.1);
+        let span = |width: usize| std::iter::repeat_n(ch, width).collect::<String>();
+        for y in 0..self.screen.1 {
+            if y < top || y >= bottom {
+                self.inner.draw_text(0, y, &span(self.screen.0), color)?;
+            } else {
+                if left > 0 {
+                    self.inner.draw_text(0, y, &span(left), color)?;
+                }
+                if right < self.screen.0 {
+                    self.inner.draw_text(right, y, &span(self.screen.0 - right), color)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Renderer for Letterbox {
+    fn begin_frame(&mut self, clear: bool) -> io::Result<()> {
+        self.inner.begin_frame(clear)?;
+        if clear {
+            self.fill_margin()?;
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset()
+    }
+
+    fn draw_cell(&mut self, x: usize, y: usize, ch: char, style: Option<Style>) -> io::Result<()> {
+        self.inner.draw_cell(self.origin.0 + x, self.origin.1 + y, ch, style)
+    }
+
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, color: Option<Color>) -> io::Result<()> {
+        self.inner.draw_text(self.origin.0 + x, self.origin.1 + y, text, color)
+    }
+
+    fn clear_line(&mut self, x: usize, y: usize) -> io::Result<()> {
+        // Only as far as the block, so the margin to its right stays
+        let right = self.origin.0 + self.block.0;
+        if right >= self.screen.0 {
+            return self.inner.clear_line(self.origin.0 + x, self.origin.1 + y);
+        }
+        let blank = " ".repeat(self.block.0.saturating_sub(x));
+        self.inner.draw_text(self.origin.0 + x, self.origin.1 + y, &blank, None)
+    }
+
+    fn bell(&mut self) -> io::Result<()> {
+        self.inner.bell()
+    }
+
+    fn draw_graphics(&mut self, x: usize, y: usize, sequence: &str) -> io::Result<()> {
+        self.inner.draw_graphics(self.origin.0 + x, self.origin.1 + y, sequence)
+    }
+
+    fn present(&mut self) -> io::Result<()> {
+        self.inner.present()
+    }
+}
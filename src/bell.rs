@@ -0,0 +1,69 @@
+//! Sound cues rung on the terminal bell
+//!
+//! For terminals with no other way to make a sound, shots, kills and hits
+//! can ring the bell. By default every cue is a single ring; with patterns
+//! on, each kind of event rings its own rhythm so they can be told apart
+//! without looking.
+//!
+//! Rings are spread over ticks rather than sent together, since terminals
+//! merge bells that arrive at once into one.
+
+use crate::events::{GameEvent, Subscriber};
+
+/// Ticks between the rings of a pattern
+const RING_GAP: usize = 2;
+
+/// Turns game events into rings of the terminal bell
+pub struct BellCues {
+    /// Whether each kind of event rings its own pattern
+    patterns: bool,
+    /// Ticks until each queued ring
+    queued: Vec<usize>,
+}
+
+impl BellCues {
+    /// Creates the cue handler
+    ///
+    /// # Arguments
+    /// * `patterns` - Whether each kind of event rings its own pattern
+    pub fn new(patterns: bool) -> Self {
+        BellCues { patterns, queued: Vec::new() }
+    }
+
+    /// Counts down the queued rings; call once per game tick
+    ///
+    /// # Returns
+    /// Whether the bell should ring this tick
+    pub fn tick(&mut self) -> bool {
+        let due = self.queued.contains(&0);
+        self.queued.retain(|&ticks| ticks > 0);
+        for ticks in &mut self.queued {
+            *ticks -= 1;
+        }
+        due
+    }
+
+    /// Queues a number of rings, starting this tick
+    fn ring(&mut self, times: usize) {
+        for n in 0..times {
+            let ticks = n * RING_GAP;
+            if !self.queued.contains(&ticks) {
+                self.queued.push(ticks);
+            }
+        }
+    }
+}
+
+impl Subscriber for BellCues {
+    /// Shots ring once, kills twice and hits three times with patterns on;
+    /// otherwise each rings once
+    fn on_event(&mut self, event: &GameEvent) {
+        let times = match event {
+            GameEvent::ShotFired => 1,
+            GameEvent::EnemyKilled { .. } => 2,
+            GameEvent::PlayerHit => 3,
+            _ => return,
+        };
+        self.ring(if self.patterns { times } else { 1 });
+    }
+}
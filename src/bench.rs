@@ -0,0 +1,168 @@
+//! Stress runs timing the simulation and the drawing, for `--bench`
+//!
+//! The playfield is the largest the configuration allows, packed with
+//! invaders that fire far more often than in a normal run, while the ship
+//! sweeps back and forth firing on every tick, so collisions and drawing have
+//! as much to do as they ever will. A new wave starts whenever one ends.
+//!
+//! Ticks and frames are timed separately, so a change to either can be
+//! measured on its own. Frames are drawn in full every tick, through the
+//! same backend as the game but into nothing, so the terminal's own speed
+//! doesn't count. What would have been sent is counted instead, as that is
+//! what a slow terminal or a remote link pays for.
+
+use crate::backend::{Renderer, Terminal};
+use space_invaders::config::{Config, MAX_HEIGHT, MAX_WIDTH};
+use space_invaders::game::{Game, Snapshot};
+use space_invaders::input::PlayerCommand;
+use space_invaders::skin::{self, Skin};
+use space_invaders::theme::{self, Theme};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Rows of invaders in a stress wave
+const ROWS: usize = 8;
+/// Columns of invaders in a stress wave, as many as fit the widest playfield
+const COLUMNS: usize = (MAX_WIDTH - 6) / 5;
+/// Chance of each invader firing on a tick, five times the usual
+const FIRE_CHANCE: f64 = 0.1;
+/// Ships the player has, so the run never ends for lack of them
+const LIVES: usize = 1_000_000;
+/// Ticks the ship flies one way before turning around
+const SWEEP_TICKS: u64 = 40;
+/// Percentiles reported for ticks and frames
+const PERCENTILES: [usize; 3] = [50, 90, 99];
+/// Speed of the serial line a frame's output is timed over, in bits per
+/// second, to show what it would cost on a slow terminal
+const BAUD: u64 = 115_200;
+
+/// Throws output away, counting the bytes and the writes it came in
+#[derive(Clone, Default)]
+struct Meter {
+    bytes: Arc<AtomicU64>,
+    writes: Arc<AtomicU64>,
+}
+
+impl Write for Meter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Plays stress waves for a number of ticks and prints how long ticks and
+/// frames took
+///
+/// # Arguments
+/// * `config` - Settings the run is drawn with, the skin and theme, and its
+///   caps on bullets and particles
+/// * `seed` - Seeds the first wave; each later wave takes the next seed
+/// * `ticks` - Ticks to play
+pub fn run(config: &Config, seed: u64, ticks: u64) -> io::Result<()> {
+    let skin = Skin::by_name(&config.display.skin).unwrap_or(&skin::ASCII);
+    let theme = Theme::by_name(&config.display.theme).unwrap_or(&theme::CLASSIC);
+    let meter = Meter::default();
+    let mut terminal = Terminal::with_output(Box::new(meter.clone()));
+    let wave = |seed| {
+        Game::builder()
+            .width(MAX_WIDTH)
+            .height(MAX_HEIGHT)
+            .enemy_rows(ROWS)
+            .enemy_columns(COLUMNS)
+            .fire_chance(FIRE_CHANCE)
+            .lives(LIVES)
+            .max_bullets(config.game.max_player_bullets, config.game.max_enemy_bullets)
+            .max_particles(config.game.max_particles)
+            .seed(seed)
+            .build()
+    };
+
+    let mut game = wave(seed);
+    let mut waves = 1;
+    let mut most_bullets = 0;
+    let mut tick_times = Vec::with_capacity(ticks as usize);
+    let mut frame_times = Vec::with_capacity(ticks as usize);
+    for tick in 0..ticks {
+        let direction = if (tick / SWEEP_TICKS).is_multiple_of(2) { PlayerCommand::MoveLeft } else { PlayerCommand::MoveRight };
+        let started = Instant::now();
+        game.step(&[PlayerCommand::Fire, direction]);
+        game.dispatch_events(&mut []);
+        tick_times.push(started.elapsed());
+
+        let started = Instant::now();
+        draw(&mut terminal, &game.snapshot(), skin, theme)?;
+        frame_times.push(started.elapsed());
+
+        most_bullets = most_bullets.max(game.player_bullets.iter().count() + game.enemy_bullets.iter().count());
+        if game.outcome().is_some() {
+            game = wave(seed.wrapping_add(waves));
+            waves += 1;
+        }
+    }
+
+    println!("bench: {} ticks on a {}x{} playfield   seed: {}", ticks, MAX_WIDTH, MAX_HEIGHT, seed);
+    println!("waves: {} of {} invaders   most bullets at once: {}", waves, ROWS * COLUMNS, most_bullets);
+    println!("tick:  {}", summary(&mut tick_times));
+    println!("frame: {}", summary(&mut frame_times));
+    let bytes = meter.bytes.load(Ordering::Relaxed) / ticks.max(1);
+    let writes = meter.writes.load(Ordering::Relaxed) as f64 / ticks.max(1) as f64;
+    println!(
+        "output: {} bytes in {:.1} writes per frame   {:.1}ms per frame at {} baud",
+        bytes,
+        writes,
+        (bytes * 10) as f64 * 1000.0 / BAUD as f64,
+        BAUD
+    );
+    Ok(())
+}
+
+/// Draws every cell of the playfield, as a frame drawn from scratch would
+///
+/// # Arguments
+/// * `terminal` - Where to draw
+/// * `snapshot` - The game state to draw
+/// * `skin` - Glyphs to draw sprites with
+/// * `theme` - Colors to draw sprites in
+fn draw(terminal: &mut Terminal, snapshot: &Snapshot, skin: &Skin, theme: &Theme) -> io::Result<()> {
+    // Otherwise cells that haven't changed would be skipped
+    terminal.reset();
+    terminal.begin_frame(true)?;
+    for (y, row) in snapshot.sprites(0.5).into_iter().enumerate() {
+        for (x, cell) in row.into_iter().enumerate() {
+            match cell {
+                Some(cell) => terminal.draw_cell(x, y, skin.glyph(cell), Some(theme.style(cell.sprite)))?,
+                None => terminal.draw_cell(x, y, ' ', None)?,
+            }
+        }
+    }
+    terminal.present()
+}
+
+/// Percentiles and the longest of a set of timings, as one line
+///
+/// # Arguments
+/// * `times` - The timings; sorted in place
+fn summary(times: &mut [Duration]) -> String {
+    times.sort();
+    let Some(&longest) = times.last() else { return "-".to_string() };
+    let mut parts: Vec<String> = PERCENTILES
+        .iter()
+        .map(|&percentile| format!("p{} {}", percentile, millis(times[(times.len() - 1) * percentile / 100])))
+        .collect();
+    parts.push(format!("max {}", millis(longest)));
+    let total: Duration = times.iter().sum();
+    parts.push(format!("total {:.1}ms", total.as_secs_f64() * 1000.0));
+    parts.join("   ")
+}
+
+/// A duration in milliseconds, to the microsecond
+fn millis(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,180 @@
+//! Graphical frontend: the same game in a window, drawn with pixels
+//!
+//! Built with `--features gui`. It runs the library's `Game` directly and
+//! draws its sprites from the fine bitmaps the Braille renderer uses, scaled
+//! up to blocks of pixels. Settings come from the same `config.toml` as the
+//! terminal game; the score and lives are shown in the window title.
+//!
+//! Arrows (or A/D, H/L) move, Space (or W, K, J) fires, P pauses, Enter
+//! starts a new run once one is over and Esc quits.
+
+use crossterm::style::Color;
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use space_invaders::config::Config;
+use space_invaders::game::{Game, GameBuilder, Snapshot};
+use space_invaders::input::PlayerCommand;
+use space_invaders::raster;
+use space_invaders::theme::{self, Theme};
+use std::process;
+use std::time::Instant;
+
+/// Screen pixels per bitmap pixel, horizontally and vertically
+const PIXEL_SIZE: usize = 5;
+/// Color of empty space
+const BACKGROUND: u32 = 0x000000;
+
+/// Builds a run from the configured game settings, with the configured seed
+/// or a fresh one
+fn new_game(builder: &GameBuilder, seed: Option<u64>) -> Game {
+    builder.clone().seed(seed.unwrap_or_else(rand::random)).build()
+}
+
+/// Approximates the RGB value of a terminal color, as `0xRRGGBB`
+///
+/// Named colors use the xterm defaults.
+fn rgb(color: Color) -> u32 {
+    let (r, g, b) = match color {
+        Color::Rgb { r, g, b } => (r, g, b),
+        Color::Black | Color::Reset => (0, 0, 0),
+        Color::DarkRed => (205, 0, 0),
+        Color::DarkGreen => (0, 205, 0),
+        Color::DarkYellow => (205, 205, 0),
+        Color::DarkBlue => (0, 0, 238),
+        Color::DarkMagenta => (205, 0, 205),
+        Color::DarkCyan => (0, 205, 205),
+        Color::Grey => (229, 229, 229),
+        Color::DarkGrey => (127, 127, 127),
+        Color::Red => (255, 0, 0),
+        Color::Green => (0, 255, 0),
+        Color::Yellow => (255, 255, 0),
+        Color::Blue => (92, 92, 255),
+        Color::Magenta => (255, 0, 255),
+        Color::Cyan => (0, 255, 255),
+        Color::White | Color::AnsiValue(_) => (255, 255, 255),
+    };
+    (u32::from(r) << 16) | (u32::from(g) << 8) | u32::from(b)
+}
+
+/// Draws a snapshot into a frame buffer the size of the window
+///
+/// # Arguments
+/// * `buffer` - The window's pixels, row by row
+/// * `snapshot` - The game state to draw
+/// * `alpha` - How far rendering is between the last tick and the next one
+/// * `theme` - Colors of the sprites
+fn draw(buffer: &mut [u32], snapshot: &Snapshot, alpha: f32, theme: &Theme) {
+    buffer.fill(BACKGROUND);
+    let pixels = raster::rasterize(snapshot, alpha, raster::FINE_SCALE, raster::fine_bitmap);
+    let width = pixels.first().map_or(0, Vec::len) * PIXEL_SIZE;
+    for (y, row) in pixels.iter().enumerate() {
+        for (x, pixel) in row.iter().enumerate() {
+            let Some(sprite) = pixel else { continue };
+            let color = rgb(theme.style(*sprite).fg);
+            for dy in 0..PIXEL_SIZE {
+                let start = (y * PIXEL_SIZE + dy) * width + x * PIXEL_SIZE;
+                buffer[start..start + PIXEL_SIZE].fill(color);
+            }
+        }
+    }
+}
+
+/// Window title showing the state of the run
+fn title(snapshot: &Snapshot, paused: bool) -> String {
+    let status = match snapshot.outcome() {
+        Some(true) => " - You won! Enter to play again",
+        Some(false) => " - Game over. Enter to play again",
+        None if paused => " - Paused",
+        None => "",
+    };
+    format!("Space Invaders - Score: {}  Lives: {}{}", snapshot.score, snapshot.lives, status)
+}
+
+fn main() {
+    let config = Config::load().unwrap_or_else(|diagnostics| {
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        Config::default()
+    });
+    let theme = Theme::by_name(&config.display.theme).unwrap_or(&theme::CLASSIC);
+    let campaign = config.game.campaign().unwrap_or_else(|diagnostics| {
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        None
+    });
+    let mut builder = Game::builder()
+        .enemy_rows(config.game.enemy_rows)
+        .enemy_columns(config.game.enemy_columns)
+        .enemy_speed(config.game.scaled_enemy_speed())
+        .fire_chance(config.game.scaled_fire_chance())
+        .max_bullets(config.game.max_player_bullets, config.game.max_enemy_bullets)
+        .max_particles(config.game.max_particles)
+        .campaign(campaign);
+    if let Some(width) = config.display.width {
+        builder = builder.width(width);
+    }
+    if let Some(height) = config.display.height {
+        builder = builder.height(height);
+    }
+
+    let tick_interval = config.game.tick_interval();
+
+    let mut game = new_game(&builder, config.game.seed);
+    let mut snapshot = game.snapshot();
+    let (scale_x, scale_y) = raster::FINE_SCALE;
+    let width = snapshot.width * scale_x * PIXEL_SIZE;
+    let height = snapshot.height * scale_y * PIXEL_SIZE;
+    let mut window = Window::new(&title(&snapshot, false), width, height, WindowOptions::default())
+        .unwrap_or_else(|e| {
+            eprintln!("could not open a window: {}", e);
+            process::exit(1);
+        });
+    window.set_target_fps(60);
+
+    let mut buffer = vec![BACKGROUND; width * height];
+    let mut last_tick = Instant::now();
+    let mut paused = false;
+    // Shots are fired on key presses, which can land between ticks
+    let mut fire = false;
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            paused = !paused;
+        }
+        fire |= [Key::Space, Key::W, Key::K, Key::J].into_iter().any(|key| window.is_key_pressed(key, KeyRepeat::Yes));
+
+        if snapshot.outcome().is_some() {
+            if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+                game = new_game(&builder, config.game.seed);
+                snapshot = game.snapshot();
+                last_tick = Instant::now();
+            }
+        } else if paused {
+            last_tick = Instant::now();
+        } else if last_tick.elapsed() >= tick_interval {
+            let mut commands = Vec::new();
+            if [Key::Left, Key::A, Key::H].into_iter().any(|key| window.is_key_down(key)) {
+                commands.push(PlayerCommand::MoveLeft);
+            }
+            if [Key::Right, Key::D, Key::L].into_iter().any(|key| window.is_key_down(key)) {
+                commands.push(PlayerCommand::MoveRight);
+            }
+            if fire {
+                commands.push(PlayerCommand::Fire);
+                fire = false;
+            }
+            game.step(&commands);
+            game.dispatch_events(&mut []);
+            snapshot = game.snapshot();
+            last_tick = Instant::now();
+        }
+
+        let alpha = (last_tick.elapsed().as_secs_f32() / tick_interval.as_secs_f32()).min(1.0);
+        draw(&mut buffer, &snapshot, alpha, theme);
+        window.set_title(&title(&snapshot, paused));
+        if let Err(e) = window.update_with_buffer(&buffer, width, height) {
+            eprintln!("could not draw the window: {}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Computer players
+//!
+//! A bot sees only what a frontend sees, a `Snapshot` of each tick, and
+//! answers with the same `PlayerCommand`s a player would give, so its runs
+//! are ordinary runs that can be recorded and replayed. Bots implement the
+//! `Bot` trait; the built-in ones that fly the ship are picked by `Strategy`,
+//! and `Invaders` plays the invaders' side of a versus run.
+
+use crate::game::Snapshot;
+use crate::input::PlayerCommand;
+use crate::skin::Sprite;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Names of the strategies, as given on the command line
+pub const STRATEGY_NAMES: &[&str] = &["idle", "random", "hunter", "sweeper"];
+
+/// Fewest ticks between two of a bot's shots, so it can't fill the sky
+const FIRE_INTERVAL: u32 = 3;
+/// Rows above the ship in which an enemy bullet makes the hunter dodge
+const DODGE_RANGE: f32 = 5.0;
+/// Columns from either edge at which the sweeper turns around
+const SWEEP_MARGIN: f32 = 2.0;
+/// Ticks the invaders' bot waits between picking columns, so a ship can
+/// outrun it
+const SHIFT_INTERVAL: u64 = 2;
+
+/// A computer player
+pub trait Bot: Send {
+    /// Decides what to do on the next tick
+    ///
+    /// # Arguments
+    /// * `snapshot` - The game as it was at the end of the last tick
+    ///
+    /// # Returns
+    /// The commands to apply on the next tick
+    fn decide(&mut self, snapshot: &Snapshot) -> Vec<PlayerCommand>;
+}
+
+/// How a bot flying the ship plays
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Never moves or fires, for a baseline
+    Idle,
+    /// Wanders and fires at random
+    Random,
+    /// Lines up under the nearest invader and fires, dodging bullets on the
+    /// way
+    Hunter,
+    /// Sweeps from edge to edge, firing whenever an invader is overhead
+    Sweeper,
+}
+
+impl Strategy {
+    /// Looks up a strategy by name
+    ///
+    /// # Returns
+    /// The strategy, or `None` if the name isn't one of `STRATEGY_NAMES`
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "idle" => Some(Strategy::Idle),
+            "random" => Some(Strategy::Random),
+            "hunter" => Some(Strategy::Hunter),
+            "sweeper" => Some(Strategy::Sweeper),
+            _ => None,
+        }
+    }
+
+    /// Creates a bot that plays this way
+    ///
+    /// # Arguments
+    /// * `seed` - Seeds the bot's own choices, so a run can be played again
+    pub fn bot(self, seed: u64) -> Box<dyn Bot> {
+        match self {
+            Strategy::Idle => Box::new(Idle),
+            Strategy::Random => Box::new(Wanderer { rng: StdRng::seed_from_u64(seed), trigger: Trigger::default() }),
+            Strategy::Hunter => Box::new(Hunter::default()),
+            Strategy::Sweeper => Box::new(Sweeper::default()),
+        }
+    }
+}
+
+/// Keeps a bot's shots `FIRE_INTERVAL` ticks apart
+#[derive(Default)]
+struct Trigger {
+    /// Ticks until the bot may fire again
+    cooldown: u32,
+}
+
+impl Trigger {
+    /// Counts down a tick, then fires if the bot wants to and may
+    ///
+    /// # Arguments
+    /// * `fire` - Whether the bot wants to fire
+    /// * `command` - The command that fires
+    fn pull(&mut self, fire: bool, command: PlayerCommand) -> Option<PlayerCommand> {
+        self.cooldown = self.cooldown.saturating_sub(1);
+        if fire && self.cooldown == 0 {
+            self.cooldown = FIRE_INTERVAL;
+            Some(command)
+        } else {
+            None
+        }
+    }
+}
+
+/// Never moves or fires
+struct Idle;
+
+impl Bot for Idle {
+    fn decide(&mut self, _snapshot: &Snapshot) -> Vec<PlayerCommand> {
+        Vec::new()
+    }
+}
+
+/// Wanders and fires at random
+struct Wanderer {
+    rng: StdRng,
+    trigger: Trigger,
+}
+
+impl Bot for Wanderer {
+    fn decide(&mut self, _snapshot: &Snapshot) -> Vec<PlayerCommand> {
+        let movement = match self.rng.gen_range(0..3) {
+            0 => Some(PlayerCommand::MoveLeft),
+            1 => Some(PlayerCommand::MoveRight),
+            _ => None,
+        };
+        let fire = self.rng.gen_bool(0.5);
+        movement.into_iter().chain(self.trigger.pull(fire, PlayerCommand::Fire)).collect()
+    }
+}
+
+/// Dodges bullets about to land on the ship, and otherwise lines up under
+/// the nearest invader and fires
+#[derive(Default)]
+struct Hunter {
+    trigger: Trigger,
+}
+
+impl Bot for Hunter {
+    fn decide(&mut self, snapshot: &Snapshot) -> Vec<PlayerCommand> {
+        let (movement, fire) = hunt(snapshot);
+        movement.into_iter().chain(self.trigger.pull(fire, PlayerCommand::Fire)).collect()
+    }
+}
+
+/// Sweeps from edge to edge without dodging, firing whenever an invader is
+/// overhead
+#[derive(Default)]
+struct Sweeper {
+    /// Whether the ship is heading left
+    leftwards: bool,
+    trigger: Trigger,
+}
+
+impl Bot for Sweeper {
+    fn decide(&mut self, snapshot: &Snapshot) -> Vec<PlayerCommand> {
+        let Some(ship_x) = ship(snapshot) else { return Vec::new() };
+        if ship_x <= SWEEP_MARGIN {
+            self.leftwards = false;
+        } else if ship_x >= snapshot.width as f32 - 1.0 - SWEEP_MARGIN {
+            self.leftwards = true;
+        }
+        let movement = if self.leftwards { PlayerCommand::MoveLeft } else { PlayerCommand::MoveRight };
+        let overhead = invaders(snapshot).any(|x| (x - ship_x).abs() < 1.0);
+        std::iter::once(movement).chain(self.trigger.pull(overhead, PlayerCommand::Fire)).collect()
+    }
+}
+
+/// Plays the invaders' side of a versus run, as a practice opponent
+///
+/// It keeps picking the column above the ship, fires from it whenever it
+/// can and sends an invader diving when the ship is right below. It picks a
+/// new column only every `SHIFT_INTERVAL` ticks, so a moving ship can keep
+/// ahead of it.
+#[derive(Default)]
+pub struct Invaders {
+    /// Ticks seen, to pace the column picks
+    ticks: u64,
+    trigger: Trigger,
+}
+
+impl Bot for Invaders {
+    fn decide(&mut self, snapshot: &Snapshot) -> Vec<PlayerCommand> {
+        self.ticks += 1;
+        let (Some(status), Some(ship_x)) = (snapshot.commander, ship(snapshot)) else { return Vec::new() };
+        let Some(target) = status.target else { return Vec::new() };
+        let offset = ship_x - target as f32;
+        let mut commands = Vec::new();
+        if offset.abs() >= 1.0 && self.ticks.is_multiple_of(SHIFT_INTERVAL) {
+            commands.push(if offset < 0.0 { PlayerCommand::PlayerTwoLeft } else { PlayerCommand::PlayerTwoRight });
+        }
+        let aimed = offset.abs() < 2.0;
+        commands.extend(self.trigger.pull(aimed, PlayerCommand::PlayerTwoFire));
+        if aimed && offset.abs() < 1.0 && status.dive_in == 0 {
+            commands.push(PlayerCommand::PlayerTwoDive);
+        }
+        commands
+    }
+}
+
+/// Column of the player's ship, if it is flying
+fn ship(snapshot: &Snapshot) -> Option<f32> {
+    snapshot
+        .positions(1.0)
+        .find(|(sprite, _)| matches!(sprite, Sprite::Player | Sprite::PlayerHit))
+        .map(|(_, (x, _))| x)
+}
+
+/// Columns of every invader
+fn invaders(snapshot: &Snapshot) -> impl Iterator<Item = f32> + '_ {
+    snapshot
+        .positions(1.0)
+        .filter(|(sprite, _)| matches!(sprite, Sprite::Enemy | Sprite::EnemyAlt))
+        .map(|(_, (x, _))| x)
+}
+
+/// The hunter's move: away from a bullet about to land on the ship, or else
+/// towards the nearest invader, firing once under it
+///
+/// # Returns
+/// Which way to move, if at all, and whether to fire
+fn hunt(snapshot: &Snapshot) -> (Option<PlayerCommand>, bool) {
+    let positions: Vec<(Sprite, (f32, f32))> = snapshot.positions(1.0).collect();
+    let Some(&(_, (ship_x, ship_y))) = positions
+        .iter()
+        .find(|(sprite, _)| matches!(sprite, Sprite::Player | Sprite::PlayerHit))
+    else {
+        return (None, false);
+    };
+    let toward = |x: f32| if x < ship_x { PlayerCommand::MoveLeft } else { PlayerCommand::MoveRight };
+
+    let incoming = positions.iter().find(|(sprite, (x, y))| {
+        *sprite == Sprite::EnemyBullet && (x - ship_x).abs() <= 1.5 && *y < ship_y && ship_y - y <= DODGE_RANGE
+    });
+    if let Some(&(_, (bullet_x, _))) = incoming {
+        // Step away from the bullet, towards the middle when it is dead on
+        let away = if (bullet_x - ship_x).abs() < 0.5 { snapshot.width as f32 / 2.0 } else { 2.0 * ship_x - bullet_x };
+        return (Some(toward(away)), false);
+    }
+
+    let target = invaders(snapshot).min_by(|a, b| (a - ship_x).abs().total_cmp(&(b - ship_x).abs()));
+    match target {
+        Some(x) if (x - ship_x).abs() < 1.0 => (None, true),
+        Some(x) => (Some(toward(x)), false),
+        None => (None, false),
+    }
+}
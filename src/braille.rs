@@ -0,0 +1,53 @@
+//! High-resolution playfield rendering with Unicode Braille patterns
+//!
+//! Every terminal cell holds a 2x4 grid of dots, so sprites are drawn as dot
+//! bitmaps placed at sub-cell precision. Bullets and the formation glide a
+//! dot at a time where the cell renderer jumps a whole cell. The terminal
+//! font has to cover the Braille Patterns block (U+2800 to U+28FF).
+
+use crate::Glyph;
+use space_invaders::game::Snapshot;
+use space_invaders::raster;
+
+/// Dots per cell, horizontally
+const DOTS_X: usize = raster::FINE_SCALE.0;
+/// Dots per cell, vertically
+const DOTS_Y: usize = raster::FINE_SCALE.1;
+/// First character of the Braille Patterns block, with no dots raised
+const BLANK: u32 = 0x2800;
+
+/// Bit raised in a Braille character for each dot of its cell, by row then column
+const DOT_BITS: [[u8; DOTS_X]; DOTS_Y] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Lays out the playfield as Braille characters
+///
+/// # Arguments
+/// * `snapshot` - The game state to draw
+/// * `alpha` - How far rendering is between the last tick and the next one
+///
+/// # Returns
+/// A `height` x `width` grid of characters, each in the colors of the first
+/// sprite raising a dot in its cell; `None` where a cell is empty
+pub fn render(snapshot: &Snapshot, alpha: f32) -> Vec<Vec<Option<Glyph>>> {
+    let dots = raster::rasterize(snapshot, alpha, raster::FINE_SCALE, raster::fine_bitmap);
+    (0..snapshot.height)
+        .map(|y| {
+            (0..snapshot.width)
+                .map(|x| {
+                    let mut bits = 0;
+                    let mut sprite = None;
+                    for (dy, row_bits) in DOT_BITS.iter().enumerate() {
+                        for (dx, bit) in row_bits.iter().enumerate() {
+                            if let Some(dot) = dots[y * DOTS_Y + dy][x * DOTS_X + dx] {
+                                bits |= bit;
+                                sprite = sprite.or(Some(dot));
+                            }
+                        }
+                    }
+                    let glyph = char::from_u32(BLANK + bits as u32)?;
+                    sprite.map(|sprite| Glyph::new(glyph, sprite))
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,283 @@
+//! Campaigns: the waves of invaders a run is played through
+//!
+//! A campaign is a TOML file listing its waves in order, each with the
+//! layout of its formation, how fast it marches and how often its invaders
+//! fire, and the types of invader the layouts are drawn with:
+//!
+//! ```toml
+//! name = "Two waves"
+//!
+//! [types]
+//! x = { points = 10 }
+//! o = { points = 30, hits = 2 }
+//!
+//! [[waves]]
+//! speed = 2.0
+//! fire_chance = 0.02
+//! layout = [
+//!     "xxxxxxxxxx",
+//!     "x.x.x.x.x.",
+//! ]
+//!
+//! [[waves]]
+//! speed = 3.0
+//! fire_chance = 0.03
+//! layout = ["oooooooooo", "xxxxxxxxxx"]
+//! ```
+//!
+//! Each character of a layout row is an invader of the type it names, or a
+//! gap when it is a `.` or a space. Invaders are spaced out the same as in
+//! any formation, and columns that don't fit the playfield are left out. A
+//! layout has at most [`MAX_ROWS`] rows, all of which start above the ship
+//! on any playfield, and an invader in its first [`MIN_FIT_COLUMNS`]
+//! columns, so that no wave is left empty.
+//!
+//! Campaigns are read from the `levels` directory next to `config.toml`, so
+//! new ones can be written without building the game. The `classic`
+//! campaign is bundled with the game, from `levels/classic.toml`; a file of
+//! the same name in the `levels` directory takes its place.
+
+use crate::config::{MIN_HEIGHT, MIN_WIDTH};
+use crate::game::{self, DEFAULT_ENEMY_SPEED, DEFAULT_FIRE_CHANCE};
+use crate::paths;
+use crate::schema::{self, Diagnostic, Field, FieldKind};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Campaigns bundled with the game, by name
+const BUNDLED: &[(&str, &str)] = &[("classic", include_str!("../levels/classic.toml"))];
+
+/// Most rows in a wave's layout: as many as start above the ship on the
+/// shortest playfield
+pub const MAX_ROWS: usize = game::formation_fit(MIN_WIDTH, MIN_HEIGHT).0;
+/// Most invaders in a row of a wave's layout
+pub const MAX_COLUMNS: usize = 40;
+/// Columns of a layout that fit on the narrowest playfield, which every wave
+/// needs an invader in
+pub const MIN_FIT_COLUMNS: usize = game::formation_fit(MIN_WIDTH, MIN_HEIGHT).1;
+/// Points scored for an invader of a type that doesn't say
+const DEFAULT_POINTS: usize = 10;
+
+/// Expected layout of a campaign file
+const SCHEMA: FieldKind = FieldKind::Table(&[
+    Field { name: "name", kind: FieldKind::Text },
+    Field {
+        name: "types",
+        kind: FieldKind::Map(&FieldKind::Table(&[
+            Field { name: "points", kind: FieldKind::Integer { min: 0, max: 10_000 } },
+            Field { name: "hits", kind: FieldKind::Integer { min: 1, max: 100 } },
+        ])),
+    },
+    Field {
+        name: "waves",
+        kind: FieldKind::List(&FieldKind::Table(&[
+            Field { name: "speed", kind: FieldKind::Float { min: 0.5, max: 20.0 } },
+            Field { name: "fire_chance", kind: FieldKind::Float { min: 0.0, max: 1.0 } },
+            Field { name: "layout", kind: FieldKind::List(&FieldKind::Text) },
+        ])),
+    },
+]);
+
+/// The waves of a run, in the order they are played
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Campaign {
+    /// Name shown with the run's settings
+    pub name: String,
+    /// Types of invader, by the character standing for them in layouts
+    #[serde(default)]
+    pub types: BTreeMap<char, EnemyType>,
+    /// The waves, first to last; the run is won once the last is cleared
+    pub waves: Vec<Wave>,
+}
+
+/// A kind of invader
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct EnemyType {
+    /// Points scored for destroying one
+    pub points: usize,
+    /// Shots it takes to destroy one
+    pub hits: usize,
+}
+
+impl Default for EnemyType {
+    /// The standard invader, destroyed by one shot
+    fn default() -> Self {
+        EnemyType { points: DEFAULT_POINTS, hits: 1 }
+    }
+}
+
+/// One formation of a campaign
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Wave {
+    /// Horizontal speed of the formation, in cells per second
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    /// Chance of each invader firing on any one tick
+    #[serde(default = "default_fire_chance")]
+    pub fire_chance: f64,
+    /// Rows of the formation, top first, one character per invader
+    pub layout: Vec<String>,
+}
+
+fn default_speed() -> f32 {
+    DEFAULT_ENEMY_SPEED
+}
+
+fn default_fire_chance() -> f64 {
+    DEFAULT_FIRE_CHANCE
+}
+
+impl Wave {
+    /// The invaders of the wave's layout
+    ///
+    /// # Arguments
+    /// * `types` - The campaign's types of invader
+    ///
+    /// # Returns
+    /// The row, column and type of every invader, row by row; characters
+    /// that aren't types are left out
+    pub fn invaders<'a>(&'a self, types: &'a BTreeMap<char, EnemyType>) -> impl Iterator<Item = (usize, usize, EnemyType)> + 'a {
+        self.layout.iter().enumerate().flat_map(move |(row, line)| {
+            line.chars()
+                .enumerate()
+                .filter_map(move |(column, symbol)| types.get(&symbol).map(|&kind| (row, column, kind)))
+        })
+    }
+}
+
+impl Campaign {
+    /// Loads a campaign by name, from the `levels` directory or else from
+    /// those bundled with the game
+    ///
+    /// # Arguments
+    /// * `name` - The campaign's file name, without `.toml`
+    ///
+    /// # Returns
+    /// The campaign, or every problem found in its file
+    pub fn load(name: &str) -> Result<Self, Vec<Diagnostic>> {
+        let path = Self::dir().join(format!("{}.toml", name));
+        let file = path.display().to_string();
+        let problem = |message: String| {
+            vec![Diagnostic { file: file.clone(), line: None, message, suggestion: None }]
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&file, &text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => match BUNDLED.iter().find(|(bundled, _)| *bundled == name) {
+                Some((bundled, text)) => Self::parse(&format!("levels/{}.toml", bundled), text),
+                None => {
+                    let names: Vec<&str> = BUNDLED.iter().map(|(bundled, _)| *bundled).collect();
+                    Err(problem(format!("no campaign called `{}`; the game comes with {}", name, names.join(", "))))
+                }
+            },
+            Err(e) => Err(problem(format!("could not be read: {}", e))),
+        }
+    }
+
+    /// Validates and reads a campaign from the text of its file
+    ///
+    /// # Arguments
+    /// * `file` - Name of the file the text came from, for diagnostics
+    /// * `text` - Contents of the file
+    ///
+    /// # Returns
+    /// The campaign, or every problem found in the text
+    pub fn parse(file: &str, text: &str) -> Result<Self, Vec<Diagnostic>> {
+        let diagnostics = schema::validate(file, text, &SCHEMA);
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        let raw: RawCampaign = toml::from_str(text).map_err(|e| vec![schema::parse_error(file, text, &e)])?;
+
+        let mut diagnostics = Vec::new();
+        let mut report = |path: &[&str], message: String, suggestion: Option<String>| {
+            diagnostics.push(Diagnostic { file: file.to_string(), line: schema::find_line(text, path), message, suggestion });
+        };
+        let mut types = BTreeMap::new();
+        for (key, kind) in raw.types {
+            let mut symbols = key.chars();
+            match (symbols.next(), symbols.next()) {
+                (Some(symbol), None) if !is_gap(symbol) => {
+                    types.insert(symbol, kind);
+                }
+                _ => report(
+                    &["types", &key],
+                    format!("enemy type `{}` must be named by one character other than `.` or a space", key),
+                    None,
+                ),
+            }
+        }
+        if raw.waves.is_empty() {
+            report(&[], "a campaign needs at least one `[[waves]]` table".to_string(), None);
+        }
+        let known = || types.keys().map(|symbol| format!("`{}`", symbol)).collect::<Vec<_>>().join(", ");
+        for (index, wave) in raw.waves.iter().enumerate() {
+            let number = index + 1;
+            let index = index.to_string();
+            let path = ["waves", &index, "layout"];
+            if wave.layout.len() > MAX_ROWS {
+                report(&path, format!("wave {} has {} rows, but at most {} fit", number, wave.layout.len(), MAX_ROWS), None);
+            }
+            if let Some(row) = wave.layout.iter().find(|row| row.chars().count() > MAX_COLUMNS) {
+                let message = format!("wave {} has a row of {} invaders, but at most {} fit", number, row.chars().count(), MAX_COLUMNS);
+                report(&path, message, None);
+            }
+            let unknown = wave.layout.iter().flat_map(|row| row.chars()).find(|&symbol| !is_gap(symbol) && !types.contains_key(&symbol));
+            if let Some(symbol) = unknown {
+                let suggestion = Some(format!("the types are {}; use `.` for a gap", known()));
+                report(&path, format!("wave {} uses `{}`, which isn't an enemy type", number, symbol), suggestion);
+            } else if wave.invaders(&types).next().is_none() {
+                report(&path, format!("wave {} has no invaders", number), None);
+            } else if !wave.invaders(&types).any(|(_, column, _)| column < MIN_FIT_COLUMNS) {
+                let message = format!(
+                    "wave {} has no invaders in its first {} columns, so none would fit the narrowest playfield",
+                    number, MIN_FIT_COLUMNS
+                );
+                report(&path, message, Some("move some invaders to the left".to_string()));
+            }
+        }
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        Ok(Campaign { name: raw.name, types, waves: raw.waves })
+    }
+
+    /// The campaign with every wave faster and firing more often, or less
+    ///
+    /// # Arguments
+    /// * `speed` - Factor the formations' speeds are multiplied by
+    /// * `fire` - Factor the chances of firing are multiplied by
+    pub fn scaled(mut self, speed: f32, fire: f64) -> Self {
+        for wave in &mut self.waves {
+            wave.speed *= speed;
+            wave.fire_chance = (wave.fire_chance * fire).min(1.0);
+        }
+        self
+    }
+
+    /// Directory campaigns are read from
+    pub fn dir() -> PathBuf {
+        paths::config_dir().join("levels")
+    }
+}
+
+/// A campaign file as written, before its types are checked
+#[derive(Deserialize)]
+struct RawCampaign {
+    name: String,
+    #[serde(default)]
+    types: BTreeMap<String, EnemyType>,
+    #[serde(default)]
+    waves: Vec<Wave>,
+}
+
+/// Whether a character of a layout leaves a gap in the formation
+fn is_gap(symbol: char) -> bool {
+    symbol == '.' || symbol == ' '
+}
@@ -0,0 +1,122 @@
+//! The invaders' side of a versus run, played by the second player
+//!
+//! Instead of firing at random, the formation fires when the second player
+//! says so, from the bottom of the column they picked, and can send that
+//! invader diving at the player's ship. Cooldowns keep the invaders from
+//! firing every tick or emptying the formation in one go.
+
+use crate::entity::{Bullet, Enemy, Entity};
+use serde::{Deserialize, Serialize};
+
+/// Fewest ticks between two shots by the invaders
+pub const FIRE_COOLDOWN: u64 = 4;
+/// Fewest ticks between two dives
+pub const DIVE_COOLDOWN: u64 = 30;
+/// Speed of a diving invader, in cells per second
+const DIVE_SPEED: f32 = 8.0;
+
+/// The second player's control over the formation in a versus run
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Commander {
+    /// Picked column, counted from the left among the columns still flying
+    column: usize,
+    /// Tick from which the invaders may fire again
+    fire_ready_at: u64,
+    /// Tick from which an invader may dive again
+    dive_ready_at: u64,
+}
+
+impl Commander {
+    /// Where each of the formation's columns is, left to right; diving
+    /// invaders have left the formation and aren't counted
+    ///
+    /// # Arguments
+    /// * `enemies` - Every enemy in the game
+    pub fn columns(enemies: &[Enemy]) -> Vec<f32> {
+        let mut columns: Vec<f32> = enemies.iter().filter(|e| e.is_alive() && !e.diving).map(|e| e.body.x).collect();
+        columns.sort_by(f32::total_cmp);
+        // The formation moves as one, so a column's invaders share their x
+        columns.dedup();
+        columns
+    }
+
+    /// Where the picked column is, if any invaders are left in formation
+    ///
+    /// The pick stays at the rightmost column once columns to its right are
+    /// destroyed.
+    ///
+    /// # Arguments
+    /// * `enemies` - Every enemy in the game
+    pub fn target(&self, enemies: &[Enemy]) -> Option<f32> {
+        let columns = Self::columns(enemies);
+        columns.get(self.column.min(columns.len().saturating_sub(1))).copied()
+    }
+
+    /// Picks the next column over
+    ///
+    /// # Arguments
+    /// * `direction` - -1 for the column to the left, 1 for the one to the right
+    /// * `enemies` - Every enemy in the game
+    pub fn shift(&mut self, direction: i32, enemies: &[Enemy]) {
+        let last = Self::columns(enemies).len().saturating_sub(1);
+        self.column = self.column.min(last).saturating_add_signed(direction as isize).min(last);
+    }
+
+    /// The lowest invader in the picked column, which fires and dives
+    ///
+    /// # Arguments
+    /// * `enemies` - Every enemy in the game
+    fn lowest(&self, enemies: &[Enemy]) -> Option<usize> {
+        let x = self.target(enemies)?;
+        enemies
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.is_alive() && !e.diving && e.body.x == x)
+            .max_by(|(_, a), (_, b)| a.body.y.total_cmp(&b.body.y))
+            .map(|(i, _)| i)
+    }
+
+    /// Fires from the bottom of the picked column, unless the invaders fired
+    /// too recently
+    ///
+    /// # Arguments
+    /// * `enemies` - Every enemy in the game
+    /// * `tick` - The game's current tick
+    ///
+    /// # Returns
+    /// The bullet fired, if any
+    pub fn fire(&mut self, enemies: &[Enemy], tick: u64) -> Option<Bullet> {
+        if tick < self.fire_ready_at {
+            return None;
+        }
+        let shooter = &enemies[self.lowest(enemies)?];
+        self.fire_ready_at = tick + FIRE_COOLDOWN;
+        Some(Bullet::enemy(shooter.body.x, shooter.body.y + 1.0))
+    }
+
+    /// Sends the bottom invader of the picked column diving straight down,
+    /// unless one dived too recently
+    ///
+    /// # Arguments
+    /// * `enemies` - Every enemy in the game
+    /// * `tick` - The game's current tick
+    pub fn dive(&mut self, enemies: &mut [Enemy], tick: u64) {
+        if tick < self.dive_ready_at {
+            return;
+        }
+        let Some(diver) = self.lowest(enemies) else { return };
+        let diver = &mut enemies[diver];
+        diver.diving = true;
+        diver.body.vx = 0.0;
+        diver.body.vy = DIVE_SPEED;
+        self.dive_ready_at = tick + DIVE_COOLDOWN;
+    }
+
+    /// Ticks left until an invader may dive again, 0 if one may now
+    ///
+    /// # Arguments
+    /// * `tick` - The game's current tick
+    pub fn dive_in(&self, tick: u64) -> u64 {
+        self.dive_ready_at.saturating_sub(tick)
+    }
+}
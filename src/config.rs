@@ -0,0 +1,618 @@
+//! User configuration loaded from `config.toml` in the config directory
+
+use crate::alerts::AlertMode;
+use crate::campaign::Campaign;
+use crate::game;
+use crate::paths;
+use crate::schema::{self, Field, FieldKind};
+use crate::skin::{self, SKIN_NAMES};
+use crate::theme::{self, THEME_NAMES};
+use crossterm::style::Color;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub use crate::schema::Diagnostic;
+
+/// Smallest playfield, in cells, that fits the title and results screens
+pub const MIN_WIDTH: usize = 56;
+/// Smallest playfield height, in cells
+pub const MIN_HEIGHT: usize = 20;
+/// Largest playfield width, in cells
+pub const MAX_WIDTH: usize = 250;
+/// Largest playfield height, in cells
+pub const MAX_HEIGHT: usize = 100;
+/// Frames drawn per second during a run, unless set in the file
+pub const DEFAULT_FRAME_RATE: u32 = 30;
+/// Slowest game speed, as a multiple of the normal tick rate
+pub const MIN_SPEED: f32 = 0.5;
+/// Fastest game speed, as a multiple of the normal tick rate
+pub const MAX_SPEED: f32 = 2.0;
+/// Campaign played unless the file names another
+pub const DEFAULT_CAMPAIGN: &str = "classic";
+/// The `campaign` setting that plays a single formation instead
+pub const NO_CAMPAIGN: &str = "none";
+
+/// `config.toml` as written on first run: every setting, commented out at
+/// its default, with what it does
+const DEFAULT_FILE: &str = include_str!("../data/config.toml");
+
+/// Expected layout of `config.toml`, checked before deserializing
+const SCHEMA: FieldKind = FieldKind::Table(&[
+    Field {
+        name: "alerts",
+        kind: FieldKind::Table(&[Field {
+            name: "mode",
+            kind: FieldKind::Choice(&["bell", "flash", "none"]),
+        }]),
+    },
+    Field {
+        name: "display",
+        kind: FieldKind::Table(&[
+            Field {
+                name: "skin",
+                kind: FieldKind::Choice(SKIN_NAMES),
+            },
+            Field {
+                name: "theme",
+                kind: FieldKind::Choice(THEME_NAMES),
+            },
+            Field {
+                name: "background",
+                kind: FieldKind::Choice(&["dark", "light"]),
+            },
+            Field {
+                name: "ensure_visible",
+                kind: FieldKind::Bool,
+            },
+            Field {
+                name: "width",
+                kind: FieldKind::Integer { min: MIN_WIDTH as i64, max: MAX_WIDTH as i64 },
+            },
+            Field {
+                name: "height",
+                kind: FieldKind::Integer { min: MIN_HEIGHT as i64, max: MAX_HEIGHT as i64 },
+            },
+            Field {
+                name: "colored",
+                kind: FieldKind::Bool,
+            },
+            Field {
+                name: "formation_wobble",
+                kind: FieldKind::Bool,
+            },
+            Field {
+                name: "renderer",
+                kind: FieldKind::Choice(&["cells", "braille", "halfblock"]),
+            },
+            Field {
+                name: "graphics",
+                kind: FieldKind::Bool,
+            },
+            Field {
+                name: "star_layers",
+                kind: FieldKind::Integer { min: 0, max: 3 },
+            },
+            Field {
+                name: "frame_rate",
+                kind: FieldKind::Integer { min: 10, max: 240 },
+            },
+        ]),
+    },
+    Field {
+        name: "game",
+        kind: FieldKind::Table(&[
+            Field {
+                name: "campaign",
+                kind: FieldKind::Text,
+            },
+            Field {
+                name: "enemy_rows",
+                kind: FieldKind::Integer { min: 1, max: 8 },
+            },
+            Field {
+                name: "enemy_columns",
+                kind: FieldKind::Integer { min: 1, max: 40 },
+            },
+            Field {
+                name: "enemy_speed",
+                kind: FieldKind::Float { min: 0.5, max: 20.0 },
+            },
+            Field {
+                name: "fire_chance",
+                kind: FieldKind::Float { min: 0.0, max: 1.0 },
+            },
+            Field {
+                name: "difficulty",
+                kind: FieldKind::Choice(&["easy", "normal", "hard"]),
+            },
+            Field {
+                name: "speed",
+                kind: FieldKind::Float { min: MIN_SPEED as f64, max: MAX_SPEED as f64 },
+            },
+            Field {
+                name: "seed",
+                kind: FieldKind::Integer { min: 0, max: i64::MAX },
+            },
+            Field {
+                name: "max_player_bullets",
+                kind: FieldKind::Integer { min: 1, max: 10_000 },
+            },
+            Field {
+                name: "max_enemy_bullets",
+                kind: FieldKind::Integer { min: 1, max: 10_000 },
+            },
+            Field {
+                name: "max_particles",
+                kind: FieldKind::Integer { min: 0, max: 10_000 },
+            },
+        ]),
+    },
+    Field {
+        name: "input",
+        kind: FieldKind::Table(&[
+            Field {
+                name: "move_speed",
+                kind: FieldKind::Float { min: 1.0, max: 100.0 },
+            },
+            Field {
+                name: "hold_timeout",
+                kind: FieldKind::Integer { min: 50, max: 1000 },
+            },
+            Field {
+                name: "key_releases",
+                kind: FieldKind::Bool,
+            },
+        ]),
+    },
+    Field {
+        name: "wingman",
+        kind: FieldKind::Table(&[
+            Field {
+                name: "enabled",
+                kind: FieldKind::Bool,
+            },
+            Field {
+                name: "aggressiveness",
+                kind: FieldKind::Float { min: 0.0, max: 1.0 },
+            },
+        ]),
+    },
+    Field {
+        name: "sound",
+        kind: FieldKind::Table(&[
+            Field {
+                name: "bell",
+                kind: FieldKind::Bool,
+            },
+            Field {
+                name: "bell_patterns",
+                kind: FieldKind::Bool,
+            },
+            Field {
+                name: "music_volume",
+                kind: FieldKind::Float { min: 0.0, max: 1.0 },
+            },
+            Field {
+                name: "effects_volume",
+                kind: FieldKind::Float { min: 0.0, max: 1.0 },
+            },
+        ]),
+    },
+    Field {
+        name: "accessibility",
+        kind: FieldKind::Table(&[Field {
+            name: "reduced_motion",
+            kind: FieldKind::Bool,
+        }]),
+    },
+]);
+
+/// All user-tunable settings; every field has a default so the file is optional
+#[derive(Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Config {
+    /// How critical moments are signalled
+    pub alerts: AlertsConfig,
+    /// How the game looks
+    pub display: DisplayConfig,
+    /// Enemy formation and difficulty
+    pub game: GameConfig,
+    /// How the ship responds to the keyboard
+    pub input: InputConfig,
+    /// AI-controlled helper ship
+    pub wingman: WingmanConfig,
+    /// Sound cues
+    pub sound: SoundConfig,
+    /// Accessibility options
+    pub accessibility: AccessibilityConfig,
+}
+
+/// The `[alerts]` section
+#[derive(Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct AlertsConfig {
+    /// Bell, screen-edge flash or nothing
+    pub mode: AlertMode,
+}
+
+/// The `[display]` section
+#[derive(Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct DisplayConfig {
+    /// Name of the skin used to draw sprites
+    pub skin: String,
+    /// Name of the color theme
+    pub theme: String,
+    /// Whether the terminal has a dark or light background
+    pub background: Background,
+    /// Automatically adjust colors that would be hard to see
+    pub ensure_visible: bool,
+    /// Width of the playfield, in cells; fits the terminal when unset
+    pub width: Option<usize>,
+    /// Height of the playfield, in cells; fits the terminal when unset
+    pub height: Option<usize>,
+    /// Draw sprites in color; plain text otherwise
+    pub colored: bool,
+    /// Let the enemy formation sway and flick its antennae while idle
+    pub formation_wobble: bool,
+    /// Number of parallax starfield layers drawn behind the game, 0 for none
+    pub star_layers: usize,
+    /// How the playfield is drawn
+    pub renderer: Renderer,
+    /// Draw ships and invaders as images on terminals that support the
+    /// Kitty graphics protocol or Sixel
+    pub graphics: bool,
+    /// Frames drawn per second during a run; the game itself always ticks
+    /// at the same rate
+    pub frame_rate: u32,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            skin: skin::ASCII.name.to_string(),
+            theme: theme::CLASSIC.name.to_string(),
+            background: Background::Dark,
+            ensure_visible: false,
+            width: None,
+            height: None,
+            colored: true,
+            formation_wobble: false,
+            star_layers: 3,
+            renderer: Renderer::Cells,
+            graphics: true,
+            frame_rate: DEFAULT_FRAME_RATE,
+        }
+    }
+}
+
+/// How the playfield is drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Renderer {
+    /// One sprite cell per terminal cell, drawn with the skin's glyphs
+    #[default]
+    Cells,
+    /// Unicode Braille patterns, with a 2x4 grid of dots per terminal cell
+    /// for smoother motion
+    Braille,
+    /// Half-block characters, with two square pixels per terminal cell
+    #[serde(rename = "halfblock")]
+    HalfBlock,
+}
+
+/// Brightness of the terminal's background, used for contrast checks
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Background {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Background {
+    /// The color the background is assumed to be
+    pub fn color(self) -> Color {
+        match self {
+            Background::Dark => Color::Black,
+            Background::Light => Color::White,
+        }
+    }
+}
+
+/// The `[game]` section
+#[derive(Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct GameConfig {
+    /// Name of the campaign played, a file in the `levels` directory or one
+    /// bundled with the game; `"none"` plays a single formation set by the
+    /// settings below instead
+    pub campaign: String,
+    /// Number of rows in the enemy formation
+    pub enemy_rows: usize,
+    /// Number of enemies in each formation row
+    pub enemy_columns: usize,
+    /// Horizontal speed of the enemy formation, in cells per second
+    pub enemy_speed: f32,
+    /// Chance of each enemy firing on any one tick
+    pub fire_chance: f64,
+    /// Preset scaling the formation's speed and rate of fire
+    pub difficulty: Difficulty,
+    /// Multiple of the normal tick rate the game runs at, from `MIN_SPEED`
+    /// to `MAX_SPEED`
+    pub speed: f32,
+    /// Seed every run is built with, so each plays out the same; a fresh
+    /// seed for each run when unset
+    pub seed: Option<u64>,
+    /// Most bullets the players can have in flight at once
+    pub max_player_bullets: usize,
+    /// Most bullets the enemies can have in flight at once
+    pub max_enemy_bullets: usize,
+    /// Most explosion particles at once
+    pub max_particles: usize,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            campaign: DEFAULT_CAMPAIGN.to_string(),
+            enemy_rows: game::DEFAULT_ENEMY_ROWS,
+            enemy_columns: game::DEFAULT_ENEMY_COLUMNS,
+            enemy_speed: game::DEFAULT_ENEMY_SPEED,
+            fire_chance: game::DEFAULT_FIRE_CHANCE,
+            difficulty: Difficulty::Normal,
+            speed: 1.0,
+            seed: None,
+            max_player_bullets: game::DEFAULT_MAX_PLAYER_BULLETS,
+            max_enemy_bullets: game::DEFAULT_MAX_ENEMY_BULLETS,
+            max_particles: game::DEFAULT_MAX_PARTICLES,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Loads the campaign to play, with the difficulty applied
+    ///
+    /// # Returns
+    /// The campaign, `None` when set to `"none"`, or every problem found in
+    /// the campaign's file
+    pub fn campaign(&self) -> Result<Option<Campaign>, Vec<Diagnostic>> {
+        if self.campaign == NO_CAMPAIGN {
+            return Ok(None);
+        }
+        let (speed, fire) = self.difficulty.scale();
+        Campaign::load(&self.campaign).map(|campaign| Some(campaign.scaled(speed, fire)))
+    }
+
+    /// Speed of the enemy formation once the difficulty is applied
+    pub fn scaled_enemy_speed(&self) -> f32 {
+        self.enemy_speed * self.difficulty.scale().0
+    }
+
+    /// Chance of each enemy firing on a tick once the difficulty is applied
+    pub fn scaled_fire_chance(&self) -> f64 {
+        (self.fire_chance * self.difficulty.scale().1).min(1.0)
+    }
+
+    /// Real time between two ticks once the game speed is applied
+    ///
+    /// Only the clock changes: every tick plays out the same at any speed,
+    /// so the formation, its fire and the bullets all speed up or slow down
+    /// together, and replays stay valid.
+    pub fn tick_interval(&self) -> Duration {
+        game::TICK_DURATION.div_f32(self.speed)
+    }
+}
+
+/// How hard the invaders are, on top of the `[game]` settings
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Difficulty {
+    /// Slower formation that fires half as often
+    Easy,
+    /// The settings as given
+    #[default]
+    Normal,
+    /// Faster formation that fires twice as often
+    Hard,
+}
+
+impl Difficulty {
+    /// Factors the formation's speed and chance of firing are multiplied by
+    fn scale(self) -> (f32, f64) {
+        match self {
+            Difficulty::Easy => (0.75, 0.5),
+            Difficulty::Normal => (1.0, 1.0),
+            Difficulty::Hard => (1.5, 2.0),
+        }
+    }
+}
+
+/// The `[input]` section
+#[derive(Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct InputConfig {
+    /// Speed of the ship while Left or Right is held, in cells per second
+    pub move_speed: f32,
+    /// Milliseconds after the last key repeat that a held ship stops
+    pub hold_timeout: u64,
+    /// Whether to ask the terminal to report keys being released, where it
+    /// supports the enhanced keyboard protocol
+    pub key_releases: bool,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        InputConfig {
+            move_speed: 20.0,
+            hold_timeout: 150,
+            key_releases: true,
+        }
+    }
+}
+
+/// The `[wingman]` section
+#[derive(Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct WingmanConfig {
+    /// Whether runs start with a wingman
+    pub enabled: bool,
+    /// From 0.0 (cautious, mostly dodges) to 1.0 (fires as often as it can)
+    pub aggressiveness: f32,
+}
+
+impl Default for WingmanConfig {
+    fn default() -> Self {
+        WingmanConfig {
+            enabled: false,
+            aggressiveness: 0.5,
+        }
+    }
+}
+
+/// The `[sound]` section
+#[derive(Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SoundConfig {
+    /// Ring the terminal bell for shots, kills and hits
+    pub bell: bool,
+    /// Give each kind of event its own pattern of rings
+    pub bell_patterns: bool,
+    /// Loudness of the background music, from 0.0 (off) to 1.0; only
+    /// played in builds with the `audio` feature
+    pub music_volume: f32,
+    /// Loudness of the sound effects, from 0.0 (off) to 1.0; only played in
+    /// builds with the `audio` feature
+    pub effects_volume: f32,
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        SoundConfig {
+            bell: false,
+            bell_patterns: false,
+            music_volume: 0.5,
+            effects_volume: 1.0,
+        }
+    }
+}
+
+/// The `[accessibility]` section
+#[derive(Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct AccessibilityConfig {
+    /// Avoid flashing and other rapid motion
+    pub reduced_motion: bool,
+}
+
+impl Config {
+    /// Loads and validates `config.toml`, or the defaults when the file doesn't exist
+    ///
+    /// A missing file is replaced with one listing every setting, commented
+    /// out, for the player to edit.
+    ///
+    /// # Returns
+    /// The configuration, or every problem found in the file
+    pub fn load() -> Result<Self, Vec<Diagnostic>> {
+        let path = Self::path();
+        let file = path.display().to_string();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                // A read-only config directory only costs the player the example
+                let _ = Self::write_default(&path);
+                return Ok(Config::default());
+            }
+            Err(e) => {
+                return Err(vec![Diagnostic {
+                    file,
+                    line: None,
+                    message: format!("could not be read: {}", e),
+                    suggestion: None,
+                }])
+            }
+        };
+
+        Self::parse(&file, &text)
+    }
+
+    /// Validates and reads configuration from the text of a `config.toml`
+    ///
+    /// # Arguments
+    /// * `file` - Name of the file the text came from, for diagnostics
+    /// * `text` - Contents of the file
+    ///
+    /// # Returns
+    /// The configuration, or every problem found in the text
+    pub fn parse(file: &str, text: &str) -> Result<Self, Vec<Diagnostic>> {
+        let diagnostics = schema::validate(file, text, &SCHEMA);
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        toml::from_str(text).map_err(|e| vec![schema::parse_error(file, text, &e)])
+    }
+
+    /// Changes settings of one section in `config.toml`, leaving the rest of
+    /// the file as it was, comments included
+    ///
+    /// The file is created if it doesn't exist. It is written next to the
+    /// original and moved into place, so a crash can't leave it half-written.
+    ///
+    /// # Arguments
+    /// * `section` - Table the settings are in, e.g. `sound`
+    /// * `values` - Names of the settings and their new values
+    pub fn store(section: &str, values: &[(&str, f64)]) -> io::Result<()> {
+        let path = Self::path();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let mut document: toml_edit::DocumentMut = text.parse().map_err(io::Error::other)?;
+        let table = document
+            .entry(section)
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+            .ok_or_else(|| io::Error::other(format!("`{}` isn't a table", section)))?;
+        for &(key, value) in values {
+            table[key] = toml_edit::value(value);
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = path.with_extension("toml.tmp");
+        fs::write(&temporary, document.to_string())?;
+        fs::rename(temporary, path)
+    }
+
+    /// Writes the commented default `config.toml`
+    ///
+    /// # Arguments
+    /// * `path` - Where the file goes
+    fn write_default(path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, DEFAULT_FILE)
+    }
+
+    /// Location of the configuration file
+    pub fn path() -> PathBuf {
+        paths::config_dir().join("config.toml")
+    }
+}
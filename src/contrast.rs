@@ -0,0 +1,259 @@
+//! Contrast checking for themes
+//!
+//! Each sprite's colors are converted to the RGB values the terminal is likely
+//! to show (given its color depth) and compared against the background using
+//! the WCAG contrast ratio. Combinations below `MIN_CONTRAST` are reported,
+//! and can optionally be fixed up automatically.
+
+use crossterm::style::Color;
+use space_invaders::skin::Sprite;
+use space_invaders::theme::{Style, Theme};
+use std::env;
+
+/// Contrast ratio below which a sprite is considered hard to see (WCAG's
+/// minimum for graphical objects)
+pub const MIN_CONTRAST: f32 = 3.0;
+
+/// How many colors the terminal can show
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorDepth {
+    /// The 16 basic ANSI colors
+    Ansi16,
+    /// The xterm 256-color palette
+    Ansi256,
+    /// 24-bit RGB
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Guesses the terminal's color depth from `$COLORTERM` and `$TERM`
+    pub fn detect() -> Self {
+        Self::guess(&env::var("COLORTERM").unwrap_or_default(), &env::var("TERM").unwrap_or_default())
+    }
+
+    /// Guesses a terminal's color depth from what its `$COLORTERM` and
+    /// `$TERM` are, e.g. as an SSH client reported them
+    pub fn guess(colorterm: &str, term: &str) -> Self {
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+/// A sprite whose colors are likely to be hard to see
+#[derive(Clone, Debug)]
+pub struct ContrastWarning {
+    pub sprite: Sprite,
+    /// Best contrast ratio the sprite achieves
+    pub ratio: f32,
+}
+
+/// Finds sprites in a theme that are likely to be invisible
+///
+/// A sprite is visible if its glyph stands out from its cell, or its cell
+/// background stands out from the terminal background.
+///
+/// # Arguments
+/// * `theme` - Colors to check
+/// * `background` - The terminal's background color
+/// * `depth` - The terminal's color depth
+pub fn check(theme: &Theme, background: Color, depth: ColorDepth) -> Vec<ContrastWarning> {
+    Sprite::ALL
+        .iter()
+        // Drawn in the same colors as a sprite already checked
+        .filter(|&&sprite| !matches!(sprite, Sprite::EnemyAlt | Sprite::Ember | Sprite::Ash))
+        // Background stars are meant to be faint
+        .filter(|&&sprite| !matches!(sprite, Sprite::StarFar | Sprite::StarMid | Sprite::StarNear))
+        .map(|&sprite| ContrastWarning {
+            sprite,
+            ratio: visibility(theme.style(sprite), background, depth),
+        })
+        .filter(|warning| warning.ratio < MIN_CONTRAST)
+        .collect()
+}
+
+/// Adjusts every hard-to-see sprite in a theme so it stands out
+///
+/// The glyph color is swapped for black or white, whichever contrasts more
+/// with the cell behind it.
+///
+/// # Returns
+/// The sprites that were adjusted
+pub fn ensure_visible(theme: &mut Theme, background: Color, depth: ColorDepth) -> Vec<Sprite> {
+    let warnings = check(theme, background, depth);
+    for warning in &warnings {
+        let style = theme.style(warning.sprite);
+        let cell = style.bg.unwrap_or(background);
+        let fg = [Color::White, Color::Black]
+            .into_iter()
+            .max_by(|a, b| {
+                contrast_ratio(*a, cell, depth).total_cmp(&contrast_ratio(*b, cell, depth))
+            })
+            .unwrap_or(Color::White);
+        theme.set_style(warning.sprite, Style { fg, ..style });
+    }
+    warnings.into_iter().map(|w| w.sprite).collect()
+}
+
+/// Best contrast a style achieves against the terminal background
+fn visibility(style: Style, background: Color, depth: ColorDepth) -> f32 {
+    let cell = style.bg.unwrap_or(background);
+    let glyph = contrast_ratio(style.fg, cell, depth);
+    let block = contrast_ratio(cell, background, depth);
+    glyph.max(block)
+}
+
+/// WCAG contrast ratio between two colors, from 1.0 (identical) to 21.0
+pub fn contrast_ratio(a: Color, b: Color, depth: ColorDepth) -> f32 {
+    let (la, lb) = (luminance(to_rgb(a, depth)), luminance(to_rgb(b, depth)));
+    let (light, dark) = if la > lb { (la, lb) } else { (lb, la) };
+    (light + 0.05) / (dark + 0.05)
+}
+
+/// WCAG relative luminance of an sRGB color
+fn luminance((r, g, b): (u8, u8, u8)) -> f32 {
+    let channel = |c: u8| {
+        let c = f32::from(c) / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+}
+
+/// Approximates the RGB value a terminal shows for a color
+///
+/// Named colors use the xterm defaults. Colors the terminal can't show at its
+/// depth are reduced to the nearest one it can.
+pub fn to_rgb(color: Color, depth: ColorDepth) -> (u8, u8, u8) {
+    let rgb = match color {
+        Color::Rgb { r, g, b } => (r, g, b),
+        Color::AnsiValue(n) => ansi256_to_rgb(n),
+        named => return ANSI16[ansi16_index(named)],
+    };
+    match depth {
+        ColorDepth::TrueColor => rgb,
+        ColorDepth::Ansi256 => ansi256_to_rgb(nearest_ansi256(rgb)),
+        ColorDepth::Ansi16 => nearest(rgb, &ANSI16),
+    }
+}
+
+/// The color closest to an RGB value that a terminal can show at its depth
+pub fn from_rgb(rgb: (u8, u8, u8), depth: ColorDepth) -> Color {
+    match depth {
+        ColorDepth::TrueColor => Color::Rgb { r: rgb.0, g: rgb.1, b: rgb.2 },
+        ColorDepth::Ansi256 => Color::AnsiValue(nearest_ansi256(rgb)),
+        ColorDepth::Ansi16 => {
+            let index = (0..ANSI16.len())
+                .min_by_key(|&i| distance(rgb, ANSI16[i]))
+                .unwrap_or(0);
+            ANSI16_NAMED[index]
+        }
+    }
+}
+
+/// The 16 basic colors, in ANSI order
+const ANSI16_NAMED: [Color; 16] = [
+    Color::Black,
+    Color::DarkRed,
+    Color::DarkGreen,
+    Color::DarkYellow,
+    Color::DarkBlue,
+    Color::DarkMagenta,
+    Color::DarkCyan,
+    Color::Grey,
+    Color::DarkGrey,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+
+/// xterm's default RGB values for the 16 basic colors, in ANSI order
+const ANSI16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Position of a named color in the ANSI palette
+fn ansi16_index(color: Color) -> usize {
+    match color {
+        Color::Black | Color::Reset => 0,
+        Color::DarkRed => 1,
+        Color::DarkGreen => 2,
+        Color::DarkYellow => 3,
+        Color::DarkBlue => 4,
+        Color::DarkMagenta => 5,
+        Color::DarkCyan => 6,
+        Color::Grey => 7,
+        Color::DarkGrey => 8,
+        Color::Red => 9,
+        Color::Green => 10,
+        Color::Yellow => 11,
+        Color::Blue => 12,
+        Color::Magenta => 13,
+        Color::Cyan => 14,
+        Color::White | Color::Rgb { .. } | Color::AnsiValue(_) => 15,
+    }
+}
+
+/// RGB value of an entry in the xterm 256-color palette
+fn ansi256_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI16[n as usize],
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let n = n - 16;
+            (level(n / 36), level((n / 6) % 6), level(n % 6))
+        }
+        _ => {
+            let v = 8 + (n - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+/// Closest entry in the 256-color cube or grey ramp to an RGB value
+fn nearest_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    (16..=255u8)
+        .min_by_key(|&n| distance(rgb, ansi256_to_rgb(n)))
+        .unwrap_or(16)
+}
+
+/// Closest color in a palette to an RGB value
+fn nearest(rgb: (u8, u8, u8), palette: &[(u8, u8, u8)]) -> (u8, u8, u8) {
+    palette
+        .iter()
+        .copied()
+        .min_by_key(|&candidate| distance(rgb, candidate))
+        .unwrap_or(rgb)
+}
+
+/// Squared distance between two RGB colors
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (i32::from(x) - i32::from(y)).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
@@ -0,0 +1,78 @@
+//! Connected game controllers and which one drives the player's ship
+//!
+//! Controllers can come and go at any time. The roster here tracks what is
+//! connected and which controller is assigned to the player, independent of
+//! the backend that talks to the hardware (see `gamepad.rs`).
+
+use crate::input::PlayerCommand;
+
+/// Identifier of a controller, stable for as long as it stays connected
+pub type ControllerId = usize;
+
+/// Something a controller backend reports
+#[non_exhaustive]
+pub enum ControllerEvent {
+    /// A controller was plugged in (or was already connected at startup)
+    Connected { id: ControllerId, name: String },
+    /// A controller was unplugged
+    Disconnected { id: ControllerId },
+    /// A controller button mapped to a command was pressed
+    Command { id: ControllerId, command: PlayerCommand },
+}
+
+/// A connected controller
+pub struct Controller {
+    /// Backend identifier of the controller
+    pub id: ControllerId,
+    /// Human-readable name, as reported by the device
+    pub name: String,
+}
+
+/// Every connected controller and the one assigned to the player
+#[derive(Default)]
+pub struct Controllers {
+    /// Connected controllers, in the order they were plugged in
+    pub connected: Vec<Controller>,
+    /// The controller driving player 1, if any
+    assigned: Option<ControllerId>,
+}
+
+impl Controllers {
+    /// Adds a newly connected controller
+    ///
+    /// The first controller to connect while none is assigned is assigned to
+    /// the player automatically.
+    pub fn connect(&mut self, id: ControllerId, name: String) {
+        self.connected.retain(|c| c.id != id);
+        self.connected.push(Controller { id, name });
+        if self.assigned.is_none() {
+            self.assigned = Some(id);
+        }
+    }
+
+    /// Removes a controller that was unplugged
+    ///
+    /// # Returns
+    /// `true` if it was the player's controller
+    pub fn disconnect(&mut self, id: ControllerId) -> bool {
+        self.connected.retain(|c| c.id != id);
+        if self.assigned == Some(id) {
+            self.assigned = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Makes a connected controller the player's
+    pub fn assign(&mut self, id: ControllerId) {
+        if self.connected.iter().any(|c| c.id == id) {
+            self.assigned = Some(id);
+        }
+    }
+
+    /// Whether the given controller is the player's
+    pub fn is_assigned(&self, id: ControllerId) -> bool {
+        self.assigned == Some(id)
+    }
+}
@@ -0,0 +1,321 @@
+//! Entities: the things that live on the playfield
+//!
+//! Every entity wraps a `GameObject` body and implements `Entity`, so the game
+//! loop can update and draw them all through one iterator. Game-specific
+//! behavior (formation marching, firing, AI) is layered on top by `Game`.
+
+use crate::game::{GameObject, ENEMY_BULLET_SPEED, PLAYER_BULLET_SPEED};
+use crate::skin::Sprite;
+use serde::{Deserialize, Serialize};
+
+/// How long the player can't be hit after losing a life, in seconds
+pub const INVULNERABLE_TIME: f32 = 2.0;
+/// How long the player's colors stay inverted after a hit, in seconds
+const HIT_FLASH_TIME: f32 = 0.3;
+/// How long the invulnerable player stays shown, then hidden, while blinking
+const BLINK_INTERVAL: f32 = 0.2;
+/// How long an explosion particle lasts, in seconds
+pub const PARTICLE_LIFETIME: f32 = 0.6;
+/// Speed explosion particles fly apart at, in cells per second
+const PARTICLE_SPEED: f32 = 6.0;
+/// Number of particles thrown out by one explosion
+const PARTICLES_PER_BURST: usize = 6;
+
+/// Rectangle of screen cells an entity occupies
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    /// Leftmost column
+    pub x: usize,
+    /// Top row
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Bounds {
+    /// Returns whether two rectangles share at least one cell
+    pub fn intersects(&self, other: &Bounds) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// Returns the smallest rectangle covering both rectangles
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Bounds { x, y, width: right - x, height: bottom - y }
+    }
+}
+
+/// Shared behavior of everything on the playfield
+pub trait Entity {
+    /// The entity's physical body
+    fn body(&self) -> &GameObject;
+
+    /// Mutable access to the entity's physical body
+    fn body_mut(&mut self) -> &mut GameObject;
+
+    /// The glyph the entity is drawn with, as a sprite the active skin maps to a character
+    fn render_glyph(&self) -> Sprite;
+
+    /// Advances the entity by one tick
+    ///
+    /// The default moves the body along its velocity.
+    ///
+    /// # Arguments
+    /// * `dt` - Elapsed simulation time, in seconds
+    fn update(&mut self, dt: f32) {
+        let body = self.body_mut();
+        body.snapshot();
+        body.advance(dt);
+    }
+
+    /// The cells the entity's sprite covers, used for collisions
+    ///
+    /// The sprite hangs down from the body's cell and is centered on its column.
+    fn bounds(&self) -> Bounds {
+        let (x, y) = self.body().cell();
+        let (width, height) = self.render_glyph().size();
+        Bounds { x: x.saturating_sub(width / 2), y, width, height }
+    }
+
+    /// The cells the entity's sprite swept through this tick, from where it
+    /// started to where it is, used for collisions of things moving fast
+    /// enough to pass each other between ticks
+    fn swept_bounds(&self) -> Bounds {
+        let (x, y) = self.body().previous_cell();
+        let (width, height) = self.render_glyph().size();
+        self.bounds().union(&Bounds { x: x.saturating_sub(width / 2), y, width, height })
+    }
+
+    /// Whether the entity is drawn this tick; blinking entities are hidden
+    /// on alternate intervals
+    fn is_visible(&self) -> bool {
+        true
+    }
+
+    /// Reacts to being hit; the default destroys the entity
+    fn on_hit(&mut self) {
+        self.body_mut().alive = false;
+    }
+
+    /// Whether the entity is still in play
+    fn is_alive(&self) -> bool {
+        self.body().alive
+    }
+}
+
+/// The player's ship
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Player {
+    pub body: GameObject,
+    /// Ships left, including this one
+    pub lives: usize,
+    /// Seconds left during which enemy bullets pass through the ship
+    pub invulnerable: f32,
+    /// Seconds left on the inverted-colors flash of the last hit
+    pub hit_flash: f32,
+    /// Sprite the ship is drawn with between hits, telling the two players
+    /// of a two-player run apart
+    pub sprite: Sprite,
+}
+
+impl Player {
+    /// Creates the player's ship at the given position
+    ///
+    /// # Arguments
+    /// * `x` - Column of the ship's middle
+    /// * `y` - Top row of the ship
+    /// * `lives` - Number of ships the player starts with
+    pub fn new(x: f32, y: f32, lives: usize) -> Self {
+        Player { body: GameObject::new(x, y), lives, invulnerable: 0.0, hit_flash: 0.0, sprite: Sprite::Player }
+    }
+
+    /// Whether an enemy bullet would hit the ship right now
+    pub fn is_vulnerable(&self) -> bool {
+        self.is_alive() && self.invulnerable <= 0.0
+    }
+}
+
+impl Entity for Player {
+    fn body(&self) -> &GameObject {
+        &self.body
+    }
+
+    fn body_mut(&mut self) -> &mut GameObject {
+        &mut self.body
+    }
+
+    fn render_glyph(&self) -> Sprite {
+        if self.hit_flash > 0.0 {
+            Sprite::PlayerHit
+        } else {
+            self.sprite
+        }
+    }
+
+    /// Moves the ship and counts down its hit timers
+    fn update(&mut self, dt: f32) {
+        self.invulnerable = (self.invulnerable - dt).max(0.0);
+        self.hit_flash = (self.hit_flash - dt).max(0.0);
+        self.body.snapshot();
+        self.body.advance(dt);
+    }
+
+    /// Blinks while invulnerable, but stays shown during the hit flash
+    fn is_visible(&self) -> bool {
+        self.hit_flash > 0.0
+            || self.invulnerable <= 0.0
+            || ((self.invulnerable / BLINK_INTERVAL) as usize).is_multiple_of(2)
+    }
+
+    /// Loses a life, leaving the ship briefly invulnerable; losing the last
+    /// one destroys it
+    fn on_hit(&mut self) {
+        self.lives = self.lives.saturating_sub(1);
+        if self.lives == 0 {
+            self.body.alive = false;
+        } else {
+            self.invulnerable = INVULNERABLE_TIME;
+            self.hit_flash = HIT_FLASH_TIME;
+        }
+    }
+}
+
+/// An invader in the enemy formation
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Enemy {
+    pub body: GameObject,
+    /// Whether the invader has left the formation to dive at the player,
+    /// which only happens in versus runs
+    pub diving: bool,
+    /// Points scored for destroying it
+    pub points: usize,
+    /// Shots it takes to destroy it, from now
+    pub hits: usize,
+}
+
+impl Entity for Enemy {
+    fn body(&self) -> &GameObject {
+        &self.body
+    }
+
+    fn body_mut(&mut self) -> &mut GameObject {
+        &mut self.body
+    }
+
+    fn render_glyph(&self) -> Sprite {
+        Sprite::Enemy
+    }
+
+    /// Takes one of the invader's hits, destroying it after the last
+    fn on_hit(&mut self) {
+        self.hits = self.hits.saturating_sub(1);
+        if self.hits == 0 {
+            self.body.alive = false;
+        }
+    }
+}
+
+/// A projectile fired by the player's side or by an enemy
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bullet {
+    pub body: GameObject,
+    /// Whether the bullet was fired by an enemy
+    pub hostile: bool,
+    /// Whether the bullet was fired by the second player, who scores its kills
+    pub player_two: bool,
+}
+
+impl Bullet {
+    /// Creates a bullet fired upwards by the player's side
+    pub fn player(x: f32, y: f32) -> Self {
+        Bullet {
+            body: GameObject::moving(x, y, 0.0, -PLAYER_BULLET_SPEED),
+            hostile: false,
+            player_two: false,
+        }
+    }
+
+    /// Creates a bullet fired downwards by an enemy
+    pub fn enemy(x: f32, y: f32) -> Self {
+        Bullet {
+            body: GameObject::moving(x, y, 0.0, ENEMY_BULLET_SPEED),
+            hostile: true,
+            player_two: false,
+        }
+    }
+}
+
+impl Entity for Bullet {
+    fn body(&self) -> &GameObject {
+        &self.body
+    }
+
+    fn body_mut(&mut self) -> &mut GameObject {
+        &mut self.body
+    }
+
+    fn render_glyph(&self) -> Sprite {
+        if self.hostile {
+            Sprite::EnemyBullet
+        } else {
+            Sprite::PlayerBullet
+        }
+    }
+}
+
+/// A short-lived spark thrown out by an explosion
+///
+/// Particles are purely visual: nothing collides with them, and they
+/// scatter in a fixed pattern so they don't draw from the game's RNG.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Particle {
+    pub body: GameObject,
+    /// Seconds left before the particle vanishes
+    pub life: f32,
+}
+
+impl Particle {
+    /// Creates the particles of an explosion, flying out evenly in all directions
+    ///
+    /// # Arguments
+    /// * `x` - Column of the explosion's center
+    /// * `y` - Row of the explosion's center
+    pub fn burst(x: f32, y: f32) -> impl Iterator<Item = Particle> {
+        (0..PARTICLES_PER_BURST).map(move |i| {
+            let angle = i as f32 * std::f32::consts::TAU / PARTICLES_PER_BURST as f32;
+            // Cells are about twice as tall as they are wide
+            let (vx, vy) = (angle.cos() * PARTICLE_SPEED, angle.sin() * PARTICLE_SPEED / 2.0);
+            Particle {
+                body: GameObject::moving(x, y, vx, vy),
+                life: PARTICLE_LIFETIME,
+            }
+        })
+    }
+}
+
+impl Entity for Particle {
+    fn body(&self) -> &GameObject {
+        &self.body
+    }
+
+    fn body_mut(&mut self) -> &mut GameObject {
+        &mut self.body
+    }
+
+    /// Sparks fade to embers, then ash, as their life runs out
+    fn render_glyph(&self) -> Sprite {
+        let remaining = self.life / PARTICLE_LIFETIME;
+        if remaining > 2.0 / 3.0 {
+            Sprite::Spark
+        } else if remaining > 1.0 / 3.0 {
+            Sprite::Ember
+        } else {
+            Sprite::Ash
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Internal event bus
+//!
+//! Game logic publishes events as things happen; subscribers such as scoring
+//! consume them later, so the logic doesn't need to know who is listening.
+
+use std::collections::VecDeque;
+
+/// Something notable that happened during a game update
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum GameEvent {
+    /// The player or the wingman fired a bullet
+    ShotFired,
+    /// An enemy at the given position was destroyed by a player bullet,
+    /// scoring `points`; `player_two` if the second player fired it
+    EnemyKilled { x: usize, y: usize, player_two: bool, points: usize },
+    /// An enemy bullet hit the player, or either player in a two-player run
+    PlayerHit,
+    /// The last enemy of the wave was destroyed; the campaign's next wave,
+    /// if any, comes in at once
+    WaveCleared,
+    /// The enemy formation is one row above the invasion line
+    InvasionImminent,
+}
+
+/// A consumer of game events
+pub trait Subscriber {
+    /// Called once for every dispatched event, in publish order
+    fn on_event(&mut self, event: &GameEvent);
+}
+
+/// Queue of events waiting to be dispatched to subscribers
+#[derive(Default)]
+pub struct EventBus {
+    queue: VecDeque<GameEvent>,
+}
+
+impl EventBus {
+    /// Adds an event to the end of the queue
+    pub fn publish(&mut self, event: GameEvent) {
+        self.queue.push_back(event);
+    }
+
+    /// Removes and returns every queued event, oldest first
+    pub fn drain(&mut self) -> impl Iterator<Item = GameEvent> + '_ {
+        self.queue.drain(..)
+    }
+}
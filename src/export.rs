@@ -0,0 +1,103 @@
+//! Summaries of finished runs written as JSON, for `--export-json`
+//!
+//! The file is replaced at the end of every run, so scripts watching it
+//! always find the latest run. Times are seconds since the Unix epoch.
+
+use crate::stats::RunStats;
+use serde::Serialize;
+use space_invaders::game::Snapshot;
+use space_invaders::modifiers::RunModifiers;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What a finished run came to
+#[derive(Serialize)]
+pub struct RunSummary<'a> {
+    /// Game mode the run was played in
+    pub mode: &'a str,
+    /// Final score
+    pub score: usize,
+    /// Whether the run ended by clearing every enemy
+    pub won: bool,
+    /// Wave the run ended on, counting from 1
+    pub wave: usize,
+    /// Waves destroyed down to the last invader
+    pub waves_cleared: usize,
+    /// Bullets fired by the player and the wingman
+    pub shots: usize,
+    /// Invaders destroyed
+    pub kills: usize,
+    /// Share of shots that destroyed an invader, as a percentage; `null` if
+    /// nothing was fired
+    pub accuracy: Option<usize>,
+    /// Hits the player's ships took
+    pub hits_taken: usize,
+    /// Ticks simulated
+    pub ticks: u64,
+    /// Game time the run lasted, in seconds; pauses don't count
+    pub duration: f64,
+    /// Seed the run's game was built with, to play it again
+    pub seed: Option<u64>,
+    /// Settings the run was played with
+    pub modifiers: &'a RunModifiers,
+    /// When the run started
+    pub started_at: u64,
+    /// When the run ended
+    pub ended_at: u64,
+}
+
+impl<'a> RunSummary<'a> {
+    /// Sums up a finished run
+    ///
+    /// # Arguments
+    /// * `mode` - Game mode the run was played in
+    /// * `stats` - What happened during the run
+    /// * `won` - Whether the run ended by clearing every enemy
+    /// * `started_at` - When the run started
+    /// * `seed` - Seed the run's game was built with, if known
+    /// * `snapshot` - The run's final state
+    pub fn new(
+        mode: &'a str,
+        stats: &RunStats,
+        won: bool,
+        started_at: SystemTime,
+        seed: Option<u64>,
+        snapshot: &'a Snapshot,
+    ) -> Self {
+        RunSummary {
+            mode,
+            score: snapshot.score,
+            won,
+            // A lost run ends part way through the wave after the last one cleared
+            wave: stats.waves_cleared + usize::from(!won),
+            waves_cleared: stats.waves_cleared,
+            shots: stats.shots,
+            kills: stats.kills,
+            accuracy: stats.accuracy(),
+            hits_taken: stats.hits_taken,
+            ticks: snapshot.tick_count,
+            duration: snapshot.elapsed.as_secs_f64(),
+            seed,
+            modifiers: &snapshot.modifiers,
+            started_at: unix_seconds(started_at),
+            ended_at: unix_seconds(SystemTime::now()),
+        }
+    }
+
+    /// Writes the summary as pretty-printed JSON, replacing the file
+    ///
+    /// # Arguments
+    /// * `path` - Where to write it
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_string_pretty(self)?)?;
+        fs::rename(temporary, path)
+    }
+}
+
+/// Seconds since the Unix epoch, or 0 for a clock set before it
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs()
+}
@@ -0,0 +1,1207 @@
+//! Core game simulation: the playfield, its entities and the rules that move them
+//!
+//! Nothing in here touches the terminal, reads a clock or starts a thread;
+//! `main.rs` decides how the playfield is drawn, feeds player input in and
+//! says when time passes.
+
+use crate::campaign::{Campaign, EnemyType};
+use crate::commander::Commander;
+use crate::entity::{Bounds, Bullet, Enemy, Entity, Particle, Player};
+use crate::events::{EventBus, GameEvent, Subscriber};
+use crate::grid::CellGrid;
+use crate::input::PlayerCommand;
+use crate::modifiers::RunModifiers;
+use crate::pool::BulletPool;
+use crate::score::ScoreKeeper;
+use crate::skin::{Cell, Skin, Sprite};
+use crate::systems::{EnemyFire, FormationMarch, Particles, System, INVASION_MARGIN};
+use crate::wingman::Wingman;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::mem;
+use std::time::Duration;
+
+/// Default width of the playfield, in cells
+pub const DEFAULT_WIDTH: usize = 60;
+/// Default height of the playfield, in cells
+pub const DEFAULT_HEIGHT: usize = 25;
+/// Narrowest playfield a game can be built with, in cells: room for one
+/// column of the formation
+pub const MIN_PLAYFIELD_WIDTH: usize = 8;
+/// Shortest playfield a game can be built with, in cells: room for the ship
+/// and the rows above and below it
+pub const MIN_PLAYFIELD_HEIGHT: usize = 6;
+/// Default number of rows in the enemy formation
+pub const DEFAULT_ENEMY_ROWS: usize = 5;
+/// Default number of columns in the enemy formation
+pub const DEFAULT_ENEMY_COLUMNS: usize = 10;
+/// Default horizontal speed of the enemy formation, in cells per second
+pub const DEFAULT_ENEMY_SPEED: f32 = 2.0;
+/// Default chance of each enemy firing on any one tick
+pub const DEFAULT_FIRE_CHANCE: f64 = 0.02;
+/// Default number of ships the player starts with
+pub const DEFAULT_LIVES: usize = 3;
+/// Default cap on bullets fired by the players (and the wingman) at once
+pub const DEFAULT_MAX_PLAYER_BULLETS: usize = 64;
+/// Default cap on bullets fired by enemies at once
+pub const DEFAULT_MAX_ENEMY_BULLETS: usize = 256;
+/// Default cap on explosion particles at once
+pub const DEFAULT_MAX_PARTICLES: usize = 512;
+/// Cells between neighbouring enemies in a formation row
+const ENEMY_SPACING_X: usize = 5;
+/// Cells between neighbouring enemy rows
+const ENEMY_SPACING_Y: usize = 3;
+/// Column of a formation's first column of enemies
+const FORMATION_LEFT: usize = 5;
+/// Row of a formation's top row of enemies
+const FORMATION_TOP: usize = 2;
+/// Simulated time covered by one `Game::step`
+pub const TICK_DURATION: Duration = Duration::from_millis(100);
+/// How many rows above the player an enemy bullet may be before it is flagged as a threat
+const THREAT_LOOKAHEAD: usize = 8;
+
+/// Speed of player bullets, in cells per second (upwards)
+pub const PLAYER_BULLET_SPEED: f32 = 10.0;
+/// Speed of enemy bullets, in cells per second (downwards)
+pub const ENEMY_BULLET_SPEED: f32 = 10.0;
+
+/// Represents a game object with position, velocity and alive status
+///
+/// Positions are continuous, measured in screen cells; they are only
+/// quantized to whole cells for collisions and rendering.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameObject {
+    /// X-coordinate of the object
+    pub x: f32,
+    /// Y-coordinate of the object
+    pub y: f32,
+    /// Horizontal velocity, in cells per second
+    pub vx: f32,
+    /// Vertical velocity, in cells per second
+    pub vy: f32,
+    /// Whether the object is still active in the game
+    pub alive: bool,
+    /// X-coordinate at the start of the current tick, used for interpolation
+    prev_x: f32,
+    /// Y-coordinate at the start of the current tick, used for interpolation
+    prev_y: f32,
+}
+
+impl GameObject {
+    /// Creates a live, stationary object at the given position
+    pub fn new(x: f32, y: f32) -> Self {
+        GameObject { x, y, vx: 0.0, vy: 0.0, alive: true, prev_x: x, prev_y: y }
+    }
+
+    /// Creates a live object at the given position moving with the given velocity
+    pub fn moving(x: f32, y: f32, vx: f32, vy: f32) -> Self {
+        GameObject { vx, vy, ..GameObject::new(x, y) }
+    }
+
+    /// Remembers the current position as the start of the next tick's movement
+    pub fn snapshot(&mut self) {
+        self.prev_x = self.x;
+        self.prev_y = self.y;
+    }
+
+    /// Moves the object along its velocity
+    ///
+    /// # Arguments
+    /// * `dt` - Elapsed simulation time, in seconds
+    pub fn advance(&mut self, dt: f32) {
+        self.x += self.vx * dt;
+        self.y += self.vy * dt;
+    }
+
+    /// Returns the screen cell the object currently occupies
+    pub fn cell(&self) -> (usize, usize) {
+        to_cell(self.x, self.y)
+    }
+
+    /// Returns the screen cell the object occupied at the start of the
+    /// current tick
+    pub fn previous_cell(&self) -> (usize, usize) {
+        to_cell(self.prev_x, self.prev_y)
+    }
+
+    /// Returns the cell of the position blended between the previous and current tick
+    ///
+    /// # Arguments
+    /// * `alpha` - How far into the next tick rendering is, from 0.0 to 1.0
+    pub fn interpolated(&self, alpha: f32) -> (usize, usize) {
+        let (x, y) = self.interpolated_position(alpha);
+        to_cell(x, y)
+    }
+
+    /// Returns the position blended between the previous and current tick,
+    /// without rounding it to a cell
+    ///
+    /// # Arguments
+    /// * `alpha` - How far into the next tick rendering is, from 0.0 to 1.0
+    pub fn interpolated_position(&self, alpha: f32) -> (f32, f32) {
+        (
+            self.prev_x + (self.x - self.prev_x) * alpha,
+            self.prev_y + (self.y - self.prev_y) * alpha,
+        )
+    }
+}
+
+/// Quantizes a continuous position to the nearest screen cell
+///
+/// Positions left of or above the screen map to its first column or row;
+/// cells past the right or bottom edge are left for the caller to skip.
+fn to_cell(x: f32, y: f32) -> (usize, usize) {
+    (x.round().max(0.0) as usize, y.round().max(0.0) as usize)
+}
+
+/// Rows and columns of a formation that fit on a playfield, with every enemy
+/// starting inside it and above the row at which the formation invades
+///
+/// # Arguments
+/// * `width` - Width of the playfield, in cells
+/// * `height` - Height of the playfield, in cells
+///
+/// # Returns
+/// The number of rows and of columns
+pub const fn formation_fit(width: usize, height: usize) -> (usize, usize) {
+    let rows = height.saturating_sub(INVASION_MARGIN + FORMATION_TOP + 1) / ENEMY_SPACING_Y + 1;
+    let columns = width.saturating_sub(FORMATION_LEFT + 2) / ENEMY_SPACING_X + 1;
+    (rows, columns)
+}
+
+/// Manages the entire game state and logic
+pub struct Game {
+    /// Width of the playfield, in cells
+    pub width: usize,
+    /// Height of the playfield, in cells
+    pub height: usize,
+    /// The player's ship
+    pub player: Player,
+    /// The second player's ship, in a two-player run
+    pub player_two: Option<Player>,
+    /// List of enemies in the formation
+    pub enemies: Vec<Enemy>,
+    /// AI-controlled helper ship, if one is flying
+    pub wingman: Option<Wingman>,
+    /// The second player's control over the invaders, in a versus run
+    pub commander: Option<Commander>,
+    /// Bullets fired by the player (and the wingman)
+    pub player_bullets: BulletPool,
+    /// Bullets fired by enemies
+    pub enemy_bullets: BulletPool,
+    /// Debris of destroyed ships, drawn above everything else
+    pub particles: Vec<Particle>,
+    /// Most particles kept at once; the oldest go first
+    max_particles: usize,
+    /// Keeps the player's score from published events
+    scoring: ScoreKeeper,
+    /// Flag to indicate if the game is over
+    pub game_over: bool,
+    /// Events published by game logic, waiting to be dispatched
+    events: EventBus,
+    /// Systems run every tick, in registration order
+    systems: Vec<Box<dyn System>>,
+    /// Source of all randomness in the simulation, seeded per run
+    ///
+    /// The same generator as `rand`'s `StdRng`, named so its state can be
+    /// saved with the rest of the run.
+    pub rng: ChaCha12Rng,
+    /// Gameplay settings in effect at any point during the run
+    modifiers: RunModifiers,
+    /// Number of ticks simulated since the run started
+    tick_count: u64,
+    /// Simulated time since the run started
+    elapsed: Duration,
+    /// Commands from `handle_command`, waiting for the next tick
+    queued: Vec<PlayerCommand>,
+    /// Time handed to `update` that didn't add up to a whole tick yet
+    lag: Duration,
+    /// Enemies by the cells they occupy, rebuilt for each collision check
+    enemy_cells: CellGrid,
+    /// Waves the run is played through, if it follows a campaign
+    campaign: Option<Campaign>,
+    /// Index of the campaign's wave being played
+    wave: usize,
+}
+
+impl Game {
+    /// Starts configuring a new game, with every setting at its default
+    ///
+    /// # Returns
+    /// A builder; call `build` to create the game
+    pub fn builder() -> GameBuilder {
+        GameBuilder::default()
+    }
+
+    /// Registers a system to run every tick, after the ones already registered
+    ///
+    /// # Arguments
+    /// * `system` - The system to add
+    pub fn add_system(&mut self, system: Box<dyn System>) {
+        self.systems.push(system);
+    }
+
+    /// Queues an event for the next `dispatch_events`
+    ///
+    /// # Arguments
+    /// * `event` - The event to publish
+    pub fn publish(&mut self, event: GameEvent) {
+        self.events.publish(event);
+    }
+
+    /// Spawns a formation of enemies
+    ///
+    /// Rows and columns that wouldn't fit on the playfield, as given by
+    /// [`formation_fit`], are left out.
+    ///
+    /// # Arguments
+    /// * `invaders` - The row and column of each enemy in the formation, and
+    ///   its type
+    /// * `speed` - Initial horizontal speed, in cells per second
+    fn spawn_enemies(&mut self, invaders: impl Iterator<Item = (usize, usize, EnemyType)>, speed: f32) {
+        let (rows, columns) = formation_fit(self.width, self.height);
+        for (row, col, kind) in invaders {
+            if row >= rows || col >= columns {
+                continue;
+            }
+            let x = col * ENEMY_SPACING_X + FORMATION_LEFT;
+            let y = row * ENEMY_SPACING_Y + FORMATION_TOP;
+            self.enemies.push(Enemy {
+                body: GameObject::moving(x as f32, y as f32, speed, 0.0),
+                diving: false,
+                points: kind.points,
+                hits: kind.hits,
+            });
+        }
+    }
+
+    /// Sends in the campaign's next wave, if there is one, and tells the
+    /// systems it has begun
+    fn next_wave(&mut self) {
+        let Some(campaign) = self.campaign.take() else { return };
+        if let Some(wave) = campaign.waves.get(self.wave + 1) {
+            self.wave += 1;
+            self.spawn_enemies(wave.invaders(&campaign.types), wave.speed);
+            for system in &mut self.systems {
+                system.start_wave(wave);
+            }
+        }
+        self.campaign = Some(campaign);
+    }
+
+    /// Iterates over every entity on the playfield, in drawing order
+    fn entities(&self) -> impl Iterator<Item = &dyn Entity> {
+        std::iter::once(&self.player as &dyn Entity)
+            .chain(self.player_two.iter().map(|p| p as &dyn Entity))
+            .chain(self.wingman.iter().map(|w| w as &dyn Entity))
+            .chain(self.enemies.iter().map(|e| e as &dyn Entity))
+            .chain(self.player_bullets.iter().map(|b| b as &dyn Entity))
+            .chain(self.enemy_bullets.iter().map(|b| b as &dyn Entity))
+            .chain(self.particles.iter().map(|p| p as &dyn Entity))
+    }
+
+    /// Iterates mutably over every entity on the playfield
+    fn entities_mut(&mut self) -> impl Iterator<Item = &mut dyn Entity> {
+        std::iter::once(&mut self.player as &mut dyn Entity)
+            .chain(self.player_two.iter_mut().map(|p| p as &mut dyn Entity))
+            .chain(self.wingman.iter_mut().map(|w| w as &mut dyn Entity))
+            .chain(self.enemies.iter_mut().map(|e| e as &mut dyn Entity))
+            .chain(self.player_bullets.iter_mut().map(|b| b as &mut dyn Entity))
+            .chain(self.enemy_bullets.iter_mut().map(|b| b as &mut dyn Entity))
+            .chain(self.particles.iter_mut().map(|p| p as &mut dyn Entity))
+    }
+
+    /// Carries out a player command
+    ///
+    /// `Pause` and `Quit` concern the app rather than the simulation and are
+    /// ignored here, as are the second player's commands in a one-player run.
+    /// In a versus run the second player's commands go to the invaders.
+    ///
+    /// # Arguments
+    /// * `command` - The command to carry out
+    fn apply(&mut self, command: PlayerCommand) {
+        match command {
+            PlayerCommand::PlayerTwoLeft
+            | PlayerCommand::PlayerTwoRight
+            | PlayerCommand::PlayerTwoFire
+            | PlayerCommand::PlayerTwoDive
+                if self.commander.is_some() =>
+            {
+                self.command_invaders(command)
+            }
+            PlayerCommand::MoveLeft => self.move_player(-1, false),
+            PlayerCommand::MoveRight => self.move_player(1, false),
+            PlayerCommand::Fire => self.shoot_bullet(false),
+            PlayerCommand::PlayerTwoLeft => self.move_player(-1, true),
+            PlayerCommand::PlayerTwoRight => self.move_player(1, true),
+            PlayerCommand::PlayerTwoFire => self.shoot_bullet(true),
+            PlayerCommand::PlayerTwoDive | PlayerCommand::Pause | PlayerCommand::Quit => {}
+        }
+    }
+
+    /// Carries out one of the second player's commands on the invaders'
+    /// side of a versus run
+    ///
+    /// # Arguments
+    /// * `command` - The command to carry out
+    fn command_invaders(&mut self, command: PlayerCommand) {
+        let Some(commander) = &mut self.commander else { return };
+        match command {
+            PlayerCommand::PlayerTwoLeft => commander.shift(-1, &self.enemies),
+            PlayerCommand::PlayerTwoRight => commander.shift(1, &self.enemies),
+            PlayerCommand::PlayerTwoFire => {
+                if let Some(bullet) = commander.fire(&self.enemies, self.tick_count) {
+                    self.enemy_bullets.spawn(bullet);
+                }
+            }
+            PlayerCommand::PlayerTwoDive => commander.dive(&mut self.enemies, self.tick_count),
+            _ => {}
+        }
+    }
+
+    /// The ship of one of the players, if it is still flying
+    ///
+    /// # Arguments
+    /// * `player_two` - Whether to look for the second player's ship
+    fn ship_mut(&mut self, player_two: bool) -> Option<&mut Player> {
+        let ship = if player_two { self.player_two.as_mut()? } else { &mut self.player };
+        ship.is_alive().then_some(ship)
+    }
+
+    /// Moves a player's ship horizontally
+    ///
+    /// # Arguments
+    /// * `direction` - Movement direction (-1 for left, 1 for right)
+    /// * `player_two` - Whether the second player's ship moves
+    fn move_player(&mut self, direction: i32, player_two: bool) {
+        let width = self.width;
+        let Some(ship) = self.ship_mut(player_two) else { return };
+        let new_x = ship.body.x + direction as f32;
+        if new_x > 0.0 && new_x < (width - 1) as f32 {
+            ship.body.x = new_x;
+        }
+    }
+
+    /// Fires a bullet from a player's ship
+    ///
+    /// # Arguments
+    /// * `player_two` - Whether the second player fires
+    fn shoot_bullet(&mut self, player_two: bool) {
+        let Some(ship) = self.ship_mut(player_two) else { return };
+        let bullet = Bullet { player_two, ..Bullet::player(ship.body.x, ship.body.y - 1.0) };
+        self.player_bullets.spawn(bullet);
+        self.events.publish(GameEvent::ShotFired);
+    }
+
+    /// Adds a wingman next to the player, or removes the current one
+    ///
+    /// Two-player and versus runs have no room for a wingman, so this does
+    /// nothing in them.
+    ///
+    /// # Arguments
+    /// * `aggressiveness` - From 0.0 (cautious) to 1.0 (aggressive), for a new wingman
+    pub fn toggle_wingman(&mut self, aggressiveness: f32) {
+        if self.player_two.is_some() || self.commander.is_some() {
+            return;
+        }
+        self.wingman = match self.wingman {
+            Some(_) => None,
+            None => {
+                let wingman = Wingman::new(&self.player.body, self.width, aggressiveness);
+                self.modifiers.wingman = Some(wingman.aggressiveness());
+                Some(wingman)
+            }
+        };
+    }
+
+    /// Lets the wingman pick its heading and fire
+    ///
+    /// # Arguments
+    /// * `dt` - Elapsed simulation time, in seconds
+    fn steer_wingman(&mut self, dt: f32) {
+        if let Some(mut wingman) = self.wingman.take() {
+            if let Some(bullet) = wingman.think(self, dt) {
+                self.player_bullets.spawn(bullet);
+                self.events.publish(GameEvent::ShotFired);
+            }
+            self.wingman = Some(wingman);
+        }
+    }
+
+    /// Advances the simulation by exactly one tick of `TICK_DURATION`
+    ///
+    /// The commands are applied in order before the tick runs. Stepping does
+    /// no I/O and reads no clock; all randomness comes from the game's own
+    /// seeded generator, so the same seed and the same inputs always produce
+    /// the same run. This makes the simulation usable headless, in tests and
+    /// for replays.
+    ///
+    /// # Arguments
+    /// * `inputs` - Commands issued by the player since the previous step
+    pub fn step(&mut self, inputs: &[PlayerCommand]) {
+        for &command in inputs {
+            self.apply(command);
+        }
+        self.tick(TICK_DURATION);
+    }
+
+    /// Queues a command to be carried out at the start of the next tick run
+    /// by `update`
+    ///
+    /// # Arguments
+    /// * `command` - The command, e.g. from a key press
+    pub fn handle_command(&mut self, command: PlayerCommand) {
+        self.queued.push(command);
+    }
+
+    /// Advances the simulation by an amount of time, for callers with a
+    /// clock of their own, e.g. a frame loop
+    ///
+    /// The time is simulated in whole ticks of `TICK_DURATION`, the same as
+    /// `step`; what is left over is carried into the next call, so a run
+    /// plays out the same however the time is split up. Commands queued with
+    /// `handle_command` are applied on the first tick. Once the run is over,
+    /// no more ticks are run.
+    ///
+    /// # Arguments
+    /// * `dt` - Time passed since the last call
+    ///
+    /// # Returns
+    /// How many ticks were run
+    pub fn update(&mut self, dt: Duration) -> u64 {
+        self.lag += dt;
+        let mut ticks = 0;
+        while self.lag >= TICK_DURATION && self.outcome().is_none() {
+            self.lag -= TICK_DURATION;
+            let inputs = mem::take(&mut self.queued);
+            self.step(&inputs);
+            ticks += 1;
+        }
+        ticks
+    }
+
+    /// Advances the simulation by one tick
+    ///
+    /// # Arguments
+    /// * `dt` - Length of the tick
+    fn tick(&mut self, dt: Duration) {
+        self.tick_count += 1;
+        self.elapsed += dt;
+
+        // Decide where the wingman is heading, then move all entities together
+        self.steer_wingman(dt.as_secs_f32());
+        for entity in self.entities_mut() {
+            entity.update(dt.as_secs_f32());
+        }
+
+        // Systems may add or remove entities, so they get the game to themselves
+        let mut systems = mem::take(&mut self.systems);
+        for system in &mut systems {
+            system.update(self, dt);
+        }
+        self.systems = systems;
+
+        self.retire_stray_bullets();
+        self.check_collisions();
+    }
+
+    /// Marks bullets that have left the screen as spent
+    fn retire_stray_bullets(&mut self) {
+        for bullet in self.player_bullets.iter_mut().chain(self.enemy_bullets.iter_mut()) {
+            if bullet.body.y < 0.0 || bullet.body.y > (self.height - 1) as f32 {
+                bullet.body.alive = false;
+            }
+        }
+    }
+
+    /// Checks and handles collisions between bullets and game objects
+    ///
+    /// Entities collide when their bounds share a screen cell.
+    fn check_collisions(&mut self) {
+        // Player bullets hitting enemies. Only the enemies in the cells a
+        // bullet passed through are looked at, and it hits the first of them
+        // in the formation's order, as if every enemy had been checked in
+        // turn. Bullets are checked along their whole path, so one can't slip
+        // past an enemy it swapped cells with, e.g. a diver flying at it.
+        self.enemy_cells.rebuild(self.enemies.iter().map(|enemy| enemy.bounds()));
+        for bullet in self.player_bullets.iter_mut() {
+            if !bullet.is_alive() { continue; }
+
+            let enemies = &self.enemies;
+            let hit = self.enemy_cells.overlapping(&bullet.swept_bounds()).filter(|&index| enemies[index].is_alive()).min();
+            if let Some(enemy) = hit.map(|index| &mut self.enemies[index]) {
+                bullet.on_hit();
+                enemy.on_hit();
+                self.particles.extend(Particle::burst(enemy.body.x, enemy.body.y));
+                if !enemy.is_alive() {
+                    let Bounds { x, y, .. } = enemy.bounds();
+                    self.events.publish(GameEvent::EnemyKilled { x, y, player_two: bullet.player_two, points: enemy.points });
+                }
+            }
+        }
+
+        // Enemy bullets hitting either player
+        for bullet in self.enemy_bullets.iter_mut() {
+            if !bullet.is_alive() { continue; }
+
+            let ships = std::iter::once(&mut self.player).chain(self.player_two.as_mut());
+            if let Some(ship) = ships.filter(|ship| ship.is_vulnerable()).find(|ship| bullet.swept_bounds().intersects(&ship.bounds())) {
+                bullet.on_hit();
+                ship.on_hit();
+                self.particles.extend(Particle::burst(ship.body.x, ship.body.y));
+                self.events.publish(GameEvent::PlayerHit);
+                // The run goes on while either player has a ship left
+                if !self.player.is_alive() && !self.player_two.as_ref().is_some_and(|p| p.is_alive()) {
+                    self.game_over = true;
+                    break;
+                }
+                continue;
+            }
+
+            // Enemy bullets shooting down the wingman
+            if let Some(wingman) = &mut self.wingman {
+                if wingman.is_alive() && bullet.swept_bounds().intersects(&wingman.bounds()) {
+                    bullet.on_hit();
+                    wingman.on_hit();
+                    self.particles.extend(Particle::burst(wingman.body.x, wingman.body.y));
+                }
+            }
+        }
+
+        // Diving invaders ramming either player, or flying off the bottom
+        for enemy in self.enemies.iter_mut().filter(|e| e.diving && e.is_alive()) {
+            if enemy.body.y > (self.height - 1) as f32 {
+                enemy.body.alive = false;
+                continue;
+            }
+            let ships = std::iter::once(&mut self.player).chain(self.player_two.as_mut());
+            if let Some(ship) = ships.filter(|ship| ship.is_vulnerable()).find(|ship| enemy.swept_bounds().intersects(&ship.bounds())) {
+                // Ramming destroys the invader, however many hits it had left
+                enemy.body.alive = false;
+                ship.on_hit();
+                self.particles.extend(Particle::burst(ship.body.x, ship.body.y));
+                self.events.publish(GameEvent::PlayerHit);
+            }
+        }
+        if !self.player.is_alive() && !self.player_two.as_ref().is_some_and(|p| p.is_alive()) {
+            self.game_over = true;
+        }
+
+        // Clean up dead objects, and the oldest debris past the cap
+        let excess = self.particles.len().saturating_sub(self.max_particles);
+        self.particles.drain(..excess);
+        let had_enemies = !self.enemies.is_empty();
+        self.player_bullets.recycle();
+        self.enemy_bullets.recycle();
+        self.enemies.retain(|e| e.is_alive());
+        if self.wingman.as_ref().is_some_and(|w| !w.is_alive()) {
+            self.wingman = None;
+        }
+
+        if had_enemies && self.enemies.is_empty() {
+            self.events.publish(GameEvent::WaveCleared);
+            self.next_wave();
+        }
+    }
+
+    /// Delivers pending game events to the score keeper and any other subscribers
+    ///
+    /// # Arguments
+    /// * `subscribers` - Additional consumers, notified after scoring
+    pub fn dispatch_events(&mut self, subscribers: &mut [&mut dyn Subscriber]) {
+        for event in self.events.drain() {
+            self.scoring.on_event(&event);
+            for subscriber in subscribers.iter_mut() {
+                subscriber.on_event(&event);
+            }
+        }
+    }
+
+    /// Returns the current score
+    pub fn score(&self) -> usize {
+        self.scoring.score
+    }
+
+    /// Returns how many ticks have been simulated since the run started
+    ///
+    /// The counter only ever increases and does not depend on the render rate,
+    /// so it can be used to refer to a moment in a run (in logs, replays or
+    /// bug reports) the same way on every machine.
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    /// Returns the gameplay settings the run has been played with so far
+    ///
+    /// Assists stay recorded once used, even if they are turned off again.
+    pub fn modifiers(&self) -> &RunModifiers {
+        &self.modifiers
+    }
+
+    /// Returns the simulated time since the run started
+    ///
+    /// This is the sum of every tick's length; time spent paused or in menus
+    /// is not counted.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Whether the run has ended
+    ///
+    /// # Returns
+    /// `Some(true)` if every enemy of the last wave was destroyed,
+    /// `Some(false)` if the player lost, `None` while the run continues
+    pub fn outcome(&self) -> Option<bool> {
+        if self.game_over {
+            Some(false)
+        } else if self.enemies.is_empty() {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    /// Finds the columns where an enemy bullet is about to reach a player
+    ///
+    /// A bullet counts as a threat when it is in one of a live ship's columns and no
+    /// more than `THREAT_LOOKAHEAD` rows above it, giving the player a moment to react.
+    ///
+    /// # Returns
+    /// The x-coordinates that should get a threat marker on the bottom edge
+    fn threat_columns(&self) -> Vec<usize> {
+        let ships: Vec<Bounds> = std::iter::once(&self.player)
+            .chain(self.player_two.as_ref())
+            .filter(|ship| ship.is_alive())
+            .map(|ship| ship.bounds())
+            .collect();
+        let mut columns: Vec<usize> = self
+            .enemy_bullets
+            .iter()
+            .filter(|b| b.is_alive())
+            .map(|b| b.body.cell())
+            .filter(|&(x, y)| {
+                ships.iter().any(|ship| {
+                    (ship.x..ship.x + ship.width).contains(&x) && y < ship.y && ship.y - y <= THREAT_LOOKAHEAD
+                })
+            })
+            .map(|(x, _)| x)
+            .collect();
+        columns.sort_unstable();
+        columns.dedup();
+        columns
+    }
+
+    /// Captures everything needed to draw the game and report on the run
+    ///
+    /// Snapshots are plain data, so they can be handed to another thread
+    /// while the simulation carries on.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            width: self.width,
+            height: self.height,
+            bodies: self
+                .entities()
+                .filter(|e| e.is_alive() && e.is_visible())
+                .map(|e| (e.render_glyph(), e.body().clone()))
+                .collect(),
+            threats: self.threat_columns(),
+            score: self.score(),
+            lives: self.player.lives,
+            player_two: self.player_two.as_ref().map(|ship| PlayerTwoStatus {
+                lives: ship.lives,
+                score: self.scoring.player_two,
+            }),
+            commander: self.commander.as_ref().map(|commander| CommanderStatus {
+                target: commander.target(&self.enemies).map(|x| x.round().max(0.0) as usize),
+                dive_in: commander.dive_in(self.tick_count),
+            }),
+            tick_count: self.tick_count(),
+            elapsed: self.elapsed(),
+            modifiers: self.modifiers().clone(),
+            has_wingman: self.wingman.is_some(),
+            game_over: self.game_over,
+            enemies_left: self.enemies.len(),
+            wave: self.wave + 1,
+            waves: self.campaign.as_ref().map_or(1, |campaign| campaign.waves.len().max(1)),
+        }
+    }
+
+    /// Saves everything about the run that changes as it is played, e.g. to
+    /// seek through a long replay without playing it all again
+    ///
+    /// Save between ticks, once events have been dispatched; events still
+    /// waiting aren't saved.
+    pub fn save(&self) -> SavedGame {
+        SavedGame {
+            player: self.player.clone(),
+            player_two: self.player_two.clone(),
+            enemies: self.enemies.clone(),
+            wingman: self.wingman.clone(),
+            commander: self.commander.clone(),
+            player_bullets: self.player_bullets.clone(),
+            enemy_bullets: self.enemy_bullets.clone(),
+            particles: self.particles.clone(),
+            scoring: self.scoring.clone(),
+            game_over: self.game_over,
+            systems: self.systems.iter().map(|system| system.save()).collect(),
+            rng: self.rng.clone(),
+            modifiers: self.modifiers.clone(),
+            tick_count: self.tick_count,
+            elapsed: self.elapsed,
+            wave: self.wave,
+        }
+    }
+
+    /// Picks a saved run up where it was saved
+    ///
+    /// The game must have been built with the same settings and systems as
+    /// the one saved, e.g. by the run's `InputLog::start`; from then on it
+    /// plays out exactly as the saved one would have.
+    ///
+    /// # Arguments
+    /// * `saved` - What `save` returned
+    pub fn restore(&mut self, saved: SavedGame) {
+        self.player = saved.player;
+        self.player_two = saved.player_two;
+        self.enemies = saved.enemies;
+        self.wingman = saved.wingman;
+        self.commander = saved.commander;
+        self.player_bullets = saved.player_bullets;
+        self.enemy_bullets = saved.enemy_bullets;
+        self.particles = saved.particles;
+        self.scoring = saved.scoring;
+        self.game_over = saved.game_over;
+        for (system, state) in self.systems.iter_mut().zip(saved.systems) {
+            system.restore(state);
+        }
+        self.rng = saved.rng;
+        self.modifiers = saved.modifiers;
+        self.tick_count = saved.tick_count;
+        self.elapsed = saved.elapsed;
+        self.wave = saved.wave;
+        self.events = EventBus::default();
+        self.queued.clear();
+        self.lag = Duration::ZERO;
+    }
+}
+
+/// Everything about a run that changes as it is played, saved between two
+/// ticks by [`Game::save`]
+///
+/// The settings the game was built with aren't included, so a run can only
+/// be picked up again on a game built the same way.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    player: Player,
+    player_two: Option<Player>,
+    enemies: Vec<Enemy>,
+    wingman: Option<Wingman>,
+    commander: Option<Commander>,
+    player_bullets: BulletPool,
+    enemy_bullets: BulletPool,
+    particles: Vec<Particle>,
+    scoring: ScoreKeeper,
+    game_over: bool,
+    /// What each system kept, in registration order
+    systems: Vec<Value>,
+    rng: ChaCha12Rng,
+    modifiers: RunModifiers,
+    tick_count: u64,
+    elapsed: Duration,
+    wave: usize,
+}
+
+impl SavedGame {
+    /// The tick the game was saved at the end of
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+}
+
+/// A copy of the game's state at the end of a tick, for drawing and results
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct Snapshot {
+    /// Width of the playfield, in cells
+    pub width: usize,
+    /// Height of the playfield, in cells
+    pub height: usize,
+    /// Every live entity's sprite and body, in drawing order
+    bodies: Vec<(Sprite, GameObject)>,
+    /// Columns that get a threat marker on the bottom edge
+    threats: Vec<usize>,
+    /// Score at the end of the tick, of both players together in a
+    /// two-player run
+    pub score: usize,
+    /// Ships the (first) player has left
+    pub lives: usize,
+    /// How the second player is doing, in a two-player run
+    pub player_two: Option<PlayerTwoStatus>,
+    /// What the invaders' player can do, in a versus run
+    pub commander: Option<CommanderStatus>,
+    /// Ticks simulated since the run started
+    pub tick_count: u64,
+    /// Simulated time since the run started
+    pub elapsed: Duration,
+    /// Gameplay settings the run has been played with so far
+    pub modifiers: RunModifiers,
+    /// Whether a wingman is flying
+    pub has_wingman: bool,
+    /// Whether the player has lost
+    pub game_over: bool,
+    /// Number of enemies still alive
+    pub enemies_left: usize,
+    /// The wave being played, counting from 1
+    pub wave: usize,
+    /// Waves in the run: those of its campaign, or 1 without one
+    pub waves: usize,
+}
+
+impl Snapshot {
+    /// Lays out which part of which sprite occupies each screen cell
+    ///
+    /// Sprites are blitted at their full size, centered on their entity's
+    /// column; parts falling off the screen are clipped.
+    ///
+    /// # Arguments
+    /// * `alpha` - How far rendering is between the last tick and the next one;
+    ///   moving objects are drawn at their interpolated positions
+    ///
+    /// # Returns
+    /// A `height` x `width` grid, `None` where a cell is empty
+    pub fn sprites(&self, alpha: f32) -> Vec<Vec<Option<Cell>>> {
+        let mut screen = vec![vec![None; self.width]; self.height];
+
+        // Draw every live entity: player and wingman, then enemies, then bullets
+        for &(sprite, ref body) in &self.bodies {
+            let (x, y) = body.interpolated(alpha);
+            let (width, height) = sprite.size();
+            for dy in 0..height {
+                for dx in 0..width {
+                    let Some(column) = (x + dx).checked_sub(width / 2) else { continue };
+                    if let Some(slot) = screen.get_mut(y + dy).and_then(|row| row.get_mut(column)) {
+                        *slot = Some(Cell { sprite, dx, dy });
+                    }
+                }
+            }
+        }
+
+        // Draw threat markers on the bottom edge, below the player
+        for &x in &self.threats {
+            screen[self.height - 1][x] = Some(Cell { sprite: Sprite::Threat, dx: 0, dy: 0 });
+        }
+
+        // In a versus run, mark the column the invaders will fire from on the top edge
+        if let Some(x) = self.commander.and_then(|c| c.target).filter(|&x| x < self.width) {
+            screen[0][x] = Some(Cell { sprite: Sprite::Threat, dx: 0, dy: 0 });
+        }
+
+        screen
+    }
+
+    /// Draws the playfield as plain text, e.g. for golden tests or to paste
+    /// into a bug report
+    ///
+    /// The format is stable: one line per row of the playfield, top first,
+    /// each `width` characters long and ending in `\n`. A cell holds the
+    /// skin's glyph for the sprite in it, or a space if it is empty. There is
+    /// no border, HUD, color or starfield. Printing anything else for the
+    /// same snapshot and skin is a breaking change.
+    ///
+    /// # Arguments
+    /// * `alpha` - How far rendering is between the last tick and the next one
+    /// * `skin` - Glyphs to draw sprites with
+    pub fn render(&self, alpha: f32, skin: &Skin) -> String {
+        let mut output = String::with_capacity((self.width + 1) * self.height);
+        for row in self.sprites(alpha) {
+            output.extend(row.iter().map(|cell| cell.map_or(' ', |c| skin.glyph(c))));
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Every live entity's sprite and exact position, in drawing order
+    ///
+    /// For renderers that place sprites more finely than whole cells; a
+    /// position is the top row of the sprite, at its middle column.
+    ///
+    /// # Arguments
+    /// * `alpha` - How far rendering is between the last tick and the next one
+    pub fn positions(&self, alpha: f32) -> impl Iterator<Item = (Sprite, (f32, f32))> + '_ {
+        self.bodies
+            .iter()
+            .map(move |(sprite, body)| (*sprite, body.interpolated_position(alpha)))
+    }
+
+    /// Columns that get a threat marker on the bottom edge
+    pub fn threats(&self) -> &[usize] {
+        &self.threats
+    }
+
+    /// Whether the run has ended
+    ///
+    /// # Returns
+    /// `Some(true)` if every enemy of the last wave was destroyed,
+    /// `Some(false)` if the player lost, `None` while the run continues
+    pub fn outcome(&self) -> Option<bool> {
+        if self.game_over {
+            Some(false)
+        } else if self.enemies_left == 0 {
+            Some(true)
+        } else {
+            None
+        }
+    }
+}
+
+/// The second player's share of a two-player run
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct PlayerTwoStatus {
+    /// Ships the second player has left
+    pub lives: usize,
+    /// Points the second player scored, part of the run's score
+    pub score: usize,
+}
+
+/// The invaders' side of a versus run
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct CommanderStatus {
+    /// Column the invaders fire and dive from, if any are left in formation
+    pub target: Option<usize>,
+    /// Ticks until an invader may dive again, 0 if one may now
+    pub dive_in: u64,
+}
+
+/// Configures and creates a `Game`
+///
+/// Every setting starts at its default, so only the ones that differ need
+/// to be given:
+///
+/// ```
+/// # use space_invaders::game::Game;
+/// let game = Game::builder().width(80).height(30).enemy_rows(6).seed(42).build();
+/// ```
+#[derive(Clone)]
+pub struct GameBuilder {
+    width: usize,
+    height: usize,
+    enemy_rows: usize,
+    enemy_columns: usize,
+    enemy_speed: f32,
+    fire_chance: f64,
+    lives: usize,
+    two_players: bool,
+    versus: bool,
+    seed: u64,
+    max_player_bullets: usize,
+    max_enemy_bullets: usize,
+    max_particles: usize,
+    campaign: Option<Campaign>,
+}
+
+impl Default for GameBuilder {
+    fn default() -> Self {
+        GameBuilder {
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            enemy_rows: DEFAULT_ENEMY_ROWS,
+            enemy_columns: DEFAULT_ENEMY_COLUMNS,
+            enemy_speed: DEFAULT_ENEMY_SPEED,
+            fire_chance: DEFAULT_FIRE_CHANCE,
+            lives: DEFAULT_LIVES,
+            two_players: false,
+            versus: false,
+            seed: 0,
+            max_player_bullets: DEFAULT_MAX_PLAYER_BULLETS,
+            max_enemy_bullets: DEFAULT_MAX_ENEMY_BULLETS,
+            max_particles: DEFAULT_MAX_PARTICLES,
+            campaign: None,
+        }
+    }
+}
+
+impl GameBuilder {
+    /// Sets the width of the playfield, in cells
+    ///
+    /// Widths below [`MIN_PLAYFIELD_WIDTH`] are raised to it.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width.max(MIN_PLAYFIELD_WIDTH);
+        self
+    }
+
+    /// Sets the height of the playfield, in cells
+    ///
+    /// Heights below [`MIN_PLAYFIELD_HEIGHT`] are raised to it.
+    pub fn height(mut self, height: usize) -> Self {
+        self.height = height.max(MIN_PLAYFIELD_HEIGHT);
+        self
+    }
+
+    /// Sets the number of rows in the enemy formation
+    pub fn enemy_rows(mut self, rows: usize) -> Self {
+        self.enemy_rows = rows;
+        self
+    }
+
+    /// Sets the number of enemies in each formation row
+    pub fn enemy_columns(mut self, columns: usize) -> Self {
+        self.enemy_columns = columns;
+        self
+    }
+
+    /// Sets the horizontal speed of the enemy formation, in cells per second
+    pub fn enemy_speed(mut self, speed: f32) -> Self {
+        self.enemy_speed = speed;
+        self
+    }
+
+    /// Sets the chance, from 0.0 to 1.0, of each enemy firing on any one tick
+    pub fn fire_chance(mut self, chance: f64) -> Self {
+        self.fire_chance = chance.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the number of ships the player starts with; at least one
+    pub fn lives(mut self, lives: usize) -> Self {
+        self.lives = lives.max(1);
+        self
+    }
+
+    /// Adds a second player's ship, for two players sharing the playfield;
+    /// each has `lives` ships and the run lasts until both are out of them
+    pub fn two_players(mut self, two_players: bool) -> Self {
+        self.two_players = two_players;
+        self
+    }
+
+    /// Hands the invaders to a second player, who picks the columns that
+    /// fire and sends invaders diving instead of the invaders firing at
+    /// random; a versus run has one ship, so this overrides `two_players`
+    pub fn versus(mut self, versus: bool) -> Self {
+        self.versus = versus;
+        self
+    }
+
+    /// Sets the seed for the game's random number generator; runs with the
+    /// same seed and inputs play out identically
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Caps the bullets in flight at once, the players' and the enemies'
+    /// apart; past a cap, a new bullet replaces the oldest. Each is at least
+    /// one.
+    pub fn max_bullets(mut self, player: usize, enemy: usize) -> Self {
+        self.max_player_bullets = player.max(1);
+        self.max_enemy_bullets = enemy.max(1);
+        self
+    }
+
+    /// Caps the explosion particles at once; past the cap, the oldest go
+    /// first
+    pub fn max_particles(mut self, particles: usize) -> Self {
+        self.max_particles = particles;
+        self
+    }
+
+    /// Plays the waves of a campaign, one after the other, instead of a
+    /// single formation of `enemy_rows` by `enemy_columns` moving at
+    /// `enemy_speed` and firing with `fire_chance`; `None` goes back to the
+    /// single formation
+    ///
+    /// A wave's rows and columns that don't fit the playfield are left out.
+    /// Campaigns read from files fit every playfield from
+    /// [`MIN_WIDTH`](crate::config::MIN_WIDTH) by
+    /// [`MIN_HEIGHT`](crate::config::MIN_HEIGHT) up.
+    pub fn campaign(mut self, campaign: Option<Campaign>) -> Self {
+        self.campaign = campaign;
+        self
+    }
+
+    /// The gameplay settings this builder describes, for the run summary
+    pub fn modifiers(&self) -> RunModifiers {
+        RunModifiers {
+            width: self.width,
+            height: self.height,
+            enemy_rows: self.enemy_rows,
+            enemy_columns: self.enemy_columns,
+            enemy_speed: self.enemy_speed,
+            fire_chance: self.fire_chance,
+            wingman: None,
+            two_players: self.two_players && !self.versus,
+            versus: self.versus,
+            max_player_bullets: self.max_player_bullets,
+            max_enemy_bullets: self.max_enemy_bullets,
+            max_particles: self.max_particles,
+            campaign: self.campaign.clone(),
+        }
+    }
+
+    /// Creates the game
+    ///
+    /// # Returns
+    /// A new Game with spawned enemies and default player position
+    pub fn build(&self) -> Game {
+        let (width, height) = (self.width, self.height);
+        // Leave the bottom row free for threat markers
+        let ship_y = (height - 1 - Sprite::Player.size().1) as f32;
+        // Two players start a third of the way in from either side
+        let (player_x, player_two) = if self.two_players && !self.versus {
+            let player_two = Player { sprite: Sprite::PlayerTwo, ..Player::new((width * 2 / 3) as f32, ship_y, self.lives) };
+            ((width / 3) as f32, Some(player_two))
+        } else {
+            ((width / 2) as f32, None)
+        };
+        let mut game = Game {
+            width,
+            height,
+            player: Player::new(player_x, ship_y, self.lives),
+            player_two,
+            enemies: Vec::new(),
+            wingman: None,
+            commander: self.versus.then(Commander::default),
+            player_bullets: BulletPool::with_limit(self.max_player_bullets),
+            enemy_bullets: BulletPool::with_limit(self.max_enemy_bullets),
+            particles: Vec::new(),
+            max_particles: self.max_particles,
+            scoring: ScoreKeeper::default(),
+            game_over: false,
+            events: EventBus::default(),
+            systems: Vec::new(),
+            rng: ChaCha12Rng::seed_from_u64(self.seed),
+            modifiers: self.modifiers(),
+            tick_count: 0,
+            elapsed: Duration::ZERO,
+            queued: Vec::new(),
+            lag: Duration::ZERO,
+            enemy_cells: CellGrid::default(),
+            campaign: self.campaign.clone(),
+            wave: 0,
+        };
+        let first = self.campaign.as_ref().and_then(|campaign| Some((campaign, campaign.waves.first()?)));
+        let (speed, fire_chance) = match first {
+            Some((campaign, wave)) => {
+                game.spawn_enemies(wave.invaders(&campaign.types), wave.speed);
+                (wave.speed, wave.fire_chance)
+            }
+            None => {
+                let (rows, columns) = (self.enemy_rows, self.enemy_columns);
+                let grid = (0..rows).flat_map(|row| (0..columns).map(move |col| (row, col, EnemyType::default())));
+                game.spawn_enemies(grid, self.enemy_speed);
+                (self.enemy_speed, self.fire_chance)
+            }
+        };
+        game.add_system(Box::new(FormationMarch::new(speed)));
+        // In a versus run the invaders only fire when the second player says so
+        if !self.versus {
+            game.add_system(Box::new(EnemyFire::new(fire_chance)));
+        }
+        game.add_system(Box::new(Particles));
+        game
+    }
+}
@@ -0,0 +1,123 @@
+//! Gamepad backend built on gilrs, enabled with the `gamepad` feature
+//!
+//! Reports hot-plugging and translates button presses into player commands:
+//! the d-pad or left stick moves, the bottom face button fires and Start
+//! pauses. Holding a direction repeats its command, like a held key, so the
+//! ship keeps moving.
+
+use gilrs::{Axis, Button, EventType, Gilrs};
+use space_invaders::controllers::{ControllerEvent, ControllerId};
+use space_invaders::input::PlayerCommand;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How far the left stick has to be pushed to move the ship, from 0.0 to 1.0
+const STICK_DEADZONE: f32 = 0.5;
+/// How often a held direction repeats its command
+const REPEAT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Directions a controller is holding
+#[derive(Default)]
+struct Held {
+    dpad: Option<PlayerCommand>,
+    stick: Option<PlayerCommand>,
+}
+
+/// Source of controller events from the operating system
+pub struct Gamepads {
+    gilrs: Gilrs,
+    /// Events found at startup, reported on the first poll
+    startup: Vec<ControllerEvent>,
+    /// Directions held on each controller
+    held: HashMap<ControllerId, Held>,
+    /// When held directions were last repeated
+    last_repeat: Instant,
+}
+
+impl Gamepads {
+    /// Connects to the system's gamepad support
+    ///
+    /// # Returns
+    /// The backend, or `None` if gamepads aren't supported on this system
+    pub fn new() -> Option<Self> {
+        let gilrs = Gilrs::new().ok()?;
+        // Gamepads plugged in before startup don't produce connect events
+        let startup = gilrs
+            .gamepads()
+            .map(|(id, pad)| ControllerEvent::Connected {
+                id: id.into(),
+                name: pad.name().to_string(),
+            })
+            .collect();
+        Some(Gamepads { gilrs, startup, held: HashMap::new(), last_repeat: Instant::now() })
+    }
+
+    /// Collects everything that happened since the last poll, without blocking
+    pub fn poll(&mut self) -> Vec<ControllerEvent> {
+        let mut events = std::mem::take(&mut self.startup);
+        while let Some(event) = self.gilrs.next_event() {
+            let id = event.id.into();
+            match event.event {
+                EventType::Connected => events.push(ControllerEvent::Connected {
+                    id,
+                    name: self.gilrs.gamepad(event.id).name().to_string(),
+                }),
+                EventType::Disconnected => {
+                    self.held.remove(&id);
+                    events.push(ControllerEvent::Disconnected { id });
+                }
+                EventType::ButtonPressed(button, _) => {
+                    if let Some(command) = command_for(button) {
+                        if matches!(button, Button::DPadLeft | Button::DPadRight) {
+                            self.held.entry(id).or_default().dpad = Some(command);
+                        }
+                        events.push(ControllerEvent::Command { id, command });
+                    }
+                }
+                EventType::ButtonReleased(button, _) => {
+                    if let Some(held) = self.held.get_mut(&id).filter(|held| held.dpad == command_for(button)) {
+                        held.dpad = None;
+                    }
+                }
+                EventType::AxisChanged(Axis::LeftStickX, value, _) => {
+                    let direction = if value <= -STICK_DEADZONE {
+                        Some(PlayerCommand::MoveLeft)
+                    } else if value >= STICK_DEADZONE {
+                        Some(PlayerCommand::MoveRight)
+                    } else {
+                        None
+                    };
+                    let held = self.held.entry(id).or_default();
+                    if direction != held.stick {
+                        held.stick = direction;
+                        if let Some(command) = direction {
+                            events.push(ControllerEvent::Command { id, command });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if self.last_repeat.elapsed() >= REPEAT_INTERVAL {
+            self.last_repeat = Instant::now();
+            for (&id, held) in &self.held {
+                if let Some(command) = held.dpad.or(held.stick) {
+                    events.push(ControllerEvent::Command { id, command });
+                }
+            }
+        }
+        events
+    }
+}
+
+/// The command a gamepad button triggers, if any
+fn command_for(button: Button) -> Option<PlayerCommand> {
+    match button {
+        Button::DPadLeft => Some(PlayerCommand::MoveLeft),
+        Button::DPadRight => Some(PlayerCommand::MoveRight),
+        Button::South => Some(PlayerCommand::Fire),
+        Button::Start => Some(PlayerCommand::Pause),
+        _ => None,
+    }
+}
@@ -0,0 +1,88 @@
+//! A recorded run raced alongside the live one, for `--ghost`
+//!
+//! The ghost's run is played again tick for tick with the live run, which is
+//! built from the same seed and settings, so the two start out identical and
+//! the player can see where they pull ahead or fall behind. Only the ghost's
+//! ship and shots are drawn, dimmed and only into empty cells; like trails,
+//! they never collide with anything.
+
+use crate::Glyph;
+use space_invaders::game::{Game, Snapshot};
+use space_invaders::input::PlayerCommand;
+use space_invaders::replay::InputLog;
+use space_invaders::skin::{Skin, Sprite};
+use space_invaders::theme::{Style, Theme};
+use std::collections::VecDeque;
+
+/// A recorded run played back in step with the live one
+pub struct Ghost {
+    game: Game,
+    /// Commands not yet applied, of each tick that had any, in tick order
+    inputs: VecDeque<(u64, Vec<PlayerCommand>)>,
+    /// Last tick of the recorded run
+    ticks: u64,
+    /// The ghost's game as of its last tick
+    view: Snapshot,
+}
+
+impl Ghost {
+    /// Starts the ghost of a recorded run, before its first tick
+    ///
+    /// # Arguments
+    /// * `log` - The recorded run
+    pub fn new(log: &InputLog) -> Self {
+        let game = log.start();
+        let view = game.snapshot();
+        Ghost { game, inputs: log.inputs.iter().cloned().collect(), ticks: log.ticks, view }
+    }
+
+    /// Plays the ghost's run on to a tick of the live run
+    ///
+    /// # Arguments
+    /// * `tick` - The live run's current tick
+    pub fn advance(&mut self, tick: u64) {
+        while self.game.tick_count() < tick.min(self.ticks) && self.view.outcome().is_none() {
+            let next = self.game.tick_count() + 1;
+            let commands = if self.inputs.front().is_some_and(|(t, _)| *t == next) {
+                self.inputs.pop_front().map(|(_, c)| c).unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            self.game.step(&commands);
+            self.view = self.game.snapshot();
+        }
+    }
+
+    /// The ghost's score so far, or its final score once its run is over
+    pub fn score(&self) -> usize {
+        self.view.score
+    }
+
+    /// Whether the ghost is still flying: its run hasn't ended, and its
+    /// recording hasn't run out
+    fn flying(&self) -> bool {
+        self.view.outcome().is_none() && self.game.tick_count() < self.ticks
+    }
+
+    /// Adds the ghost's ship and shots to a playfield frame, into empty cells
+    ///
+    /// # Arguments
+    /// * `frame` - The playfield, ready to be drawn
+    /// * `alpha` - How far rendering is between the last tick and the next one
+    /// * `skin` - Glyphs the ghost is drawn with
+    /// * `theme` - Colors of the run; the ghost is drawn in its muted color
+    pub fn add(&self, frame: &mut [Vec<Option<Glyph>>], alpha: f32, skin: &Skin, theme: &Theme) {
+        if !self.flying() {
+            return;
+        }
+        let style = Style { fg: theme.ui.muted, bg: None };
+        for (y, row) in self.view.sprites(alpha).into_iter().enumerate() {
+            for (x, cell) in row.into_iter().enumerate() {
+                let Some(cell) = cell.filter(|c| matches!(c.sprite, Sprite::Player | Sprite::PlayerBullet)) else { continue };
+                if let Some(slot) = frame.get_mut(y).and_then(|row| row.get_mut(x)).filter(|slot| slot.is_none()) {
+                    *slot = Some(Glyph { style: Some(style), ..Glyph::new(skin.glyph(cell), cell.sprite) });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Smooth color effects for terminals with more than the 16 basic colors
+//!
+//! On 256-color and truecolor terminals the enemy formation is shaded from
+//! row to row, bullet trails fade into the sky, and the sky behind the
+//! playfield darkens towards the ground. Terminals limited to 16 colors keep
+//! the theme's flat colors.
+
+use crate::contrast::{self, ColorDepth};
+use crate::Glyph;
+use crossterm::style::Color;
+use space_invaders::skin::Sprite;
+use space_invaders::theme::{Style, Theme};
+
+/// Color the sky is shaded towards at the top of the playfield
+const SKY: (u8, u8, u8) = (24, 24, 96);
+/// How far the top row's backdrop is blended from the terminal background
+/// towards `SKY`
+const SKY_SHADE: f32 = 0.35;
+/// How far the formation's top row is lightened towards white
+const ENEMY_LIGHTEN: f32 = 0.4;
+/// How far the formation's bottom row is darkened towards black
+const ENEMY_DARKEN: f32 = 0.3;
+/// How far each cell of a bullet's trail is faded into the backdrop, nearest
+/// the bullet first; see `trails`
+const TRAIL_FADE: [f32; 2] = [0.5, 0.75];
+
+/// Gradient colors for the playfield, at the terminal's color depth
+pub struct Gradients {
+    depth: ColorDepth,
+    /// The terminal's background color, as RGB
+    background: (u8, u8, u8),
+}
+
+impl Gradients {
+    /// Sets up gradients if the terminal can show them
+    ///
+    /// # Arguments
+    /// * `depth` - The terminal's color depth
+    /// * `background` - The terminal's background color
+    ///
+    /// # Returns
+    /// `None` on terminals limited to the 16 basic colors
+    pub fn new(depth: ColorDepth, background: Color) -> Option<Self> {
+        match depth {
+            ColorDepth::Ansi16 => None,
+            _ => Some(Gradients { depth, background: contrast::to_rgb(background, depth) }),
+        }
+    }
+
+    /// Background color of a playfield row, shaded from the top down
+    ///
+    /// # Arguments
+    /// * `y` - Row of the playfield
+    /// * `height` - Height of the playfield, in rows
+    pub fn backdrop(&self, y: usize, height: usize) -> Color {
+        contrast::from_rgb(self.backdrop_rgb(y, height), self.depth)
+    }
+
+    /// RGB value of a row's backdrop, before reducing it to the color depth
+    fn backdrop_rgb(&self, y: usize, height: usize) -> (u8, u8, u8) {
+        let altitude = 1.0 - fraction(y, height);
+        mix(self.background, SKY, altitude * SKY_SHADE)
+    }
+
+    /// Color of a cell of a bullet's trail, faded into the sky behind it
+    ///
+    /// # Arguments
+    /// * `color` - Color of the bullet
+    /// * `y` - Row of the trail cell
+    /// * `height` - Height of the playfield, in rows
+    /// * `index` - Which cell of the trail it is, 0 nearest the bullet
+    pub fn fade(&self, color: Color, y: usize, height: usize, index: usize) -> Color {
+        let fade = TRAIL_FADE[index.min(TRAIL_FADE.len() - 1)];
+        let rgb = mix(contrast::to_rgb(color, self.depth), self.backdrop_rgb(y, height), fade);
+        contrast::from_rgb(rgb, self.depth)
+    }
+
+    /// Shades the enemy formation from row to row
+    ///
+    /// # Arguments
+    /// * `frame` - The playfield, ready to be drawn
+    /// * `theme` - Colors the shading is derived from
+    pub fn apply(&self, frame: &mut [Vec<Option<Glyph>>], theme: &Theme) {
+        let is_enemy = |glyph: &Glyph| matches!(glyph.sprite, Sprite::Enemy | Sprite::EnemyAlt);
+        let enemy_rows: Vec<usize> = frame
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.iter().flatten().any(is_enemy))
+            .map(|(y, _)| y)
+            .collect();
+        if let (Some(&top), Some(&bottom)) = (enemy_rows.first(), enemy_rows.last()) {
+            let base = theme.style(Sprite::Enemy);
+            let rgb = contrast::to_rgb(base.fg, self.depth);
+            for (i, row) in frame[top..=bottom].iter_mut().enumerate() {
+                let t = fraction(i, bottom - top + 1);
+                let light = mix(rgb, (255, 255, 255), ENEMY_LIGHTEN * (1.0 - t));
+                let fg = contrast::from_rgb(mix(light, (0, 0, 0), ENEMY_DARKEN * t), self.depth);
+                for glyph in row.iter_mut().flatten().filter(|glyph| is_enemy(glyph)) {
+                    glyph.style = Some(Style { fg, ..base });
+                }
+            }
+        }
+
+    }
+}
+
+/// How far a row is down a span of rows, from 0.0 at the first row to 1.0 at
+/// the last
+fn fraction(index: usize, len: usize) -> f32 {
+    if len <= 1 {
+        0.0
+    } else {
+        index as f32 / (len - 1) as f32
+    }
+}
+
+/// Blends two RGB colors
+///
+/// # Arguments
+/// * `from` - Color at `t` = 0.0
+/// * `to` - Color at `t` = 1.0
+/// * `t` - How far to blend, from 0.0 to 1.0
+fn mix(from: (u8, u8, u8), to: (u8, u8, u8), t: f32) -> (u8, u8, u8) {
+    let channel = |a: u8, b: u8| (f32::from(a) + (f32::from(b) - f32::from(a)) * t).round() as u8;
+    (channel(from.0, to.0), channel(from.1, to.1), channel(from.2, to.2))
+}
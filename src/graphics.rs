@@ -0,0 +1,209 @@
+//! Ships and invaders drawn as images on terminals that can show them
+//!
+//! Terminals speaking the Kitty graphics protocol or Sixel get real bitmaps
+//! for the larger sprites, placed over the cells the sprites cover; bullets,
+//! particles and everything else stay characters. Support is guessed from the
+//! environment, and any other terminal keeps drawing every sprite with the
+//! skin's glyphs.
+
+use crate::contrast::{self, ColorDepth};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crossterm::style::Color;
+use crossterm::terminal;
+use space_invaders::raster;
+use space_invaders::skin::Sprite;
+use std::env;
+use std::fmt::Write;
+
+/// Pixel size of a terminal cell when the terminal doesn't report it
+const DEFAULT_CELL_SIZE: (usize, usize) = (10, 20);
+/// Most base64 bytes a single Kitty graphics command may carry
+const KITTY_CHUNK: usize = 4096;
+
+/// Escape-sequence protocols for drawing images in a terminal
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Protocol {
+    /// Kitty's graphics protocol, also spoken by WezTerm and Ghostty
+    Kitty,
+    /// DEC Sixel graphics
+    Sixel,
+}
+
+impl Protocol {
+    /// Guesses which protocol the terminal speaks from its environment
+    ///
+    /// # Returns
+    /// `None` if the terminal isn't known to show images
+    pub fn detect() -> Option<Self> {
+        let term = env::var("TERM").unwrap_or_default();
+        let program = env::var("TERM_PROGRAM").unwrap_or_default();
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || term == "xterm-ghostty"
+            || program == "WezTerm"
+        {
+            Some(Protocol::Kitty)
+        } else if term.contains("sixel") || term.starts_with("foot") || term == "mlterm" {
+            Some(Protocol::Sixel)
+        } else {
+            None
+        }
+    }
+}
+
+/// Draws sprites as images with one of the graphics protocols
+pub struct Graphics {
+    protocol: Protocol,
+    /// Size of a terminal cell, in pixels
+    cell_size: (usize, usize),
+    /// Images already sent to a Kitty terminal, by sprite and color, with
+    /// the id they were sent under
+    sent: Vec<(Sprite, Color, u32)>,
+}
+
+impl Graphics {
+    /// Sets up image drawing, if the terminal supports it
+    ///
+    /// # Returns
+    /// `None` on terminals without a known graphics protocol
+    pub fn detect() -> Option<Self> {
+        let protocol = Protocol::detect()?;
+        let cell_size = match terminal::window_size() {
+            Ok(size) if size.width > 0 && size.columns > 0 && size.rows > 0 => (
+                usize::from(size.width / size.columns),
+                usize::from(size.height / size.rows),
+            ),
+            _ => DEFAULT_CELL_SIZE,
+        };
+        Some(Graphics { protocol, cell_size, sent: Vec::new() })
+    }
+
+    /// Whether a sprite is drawn as an image rather than with glyphs
+    pub fn draws(sprite: Sprite) -> bool {
+        matches!(
+            sprite,
+            Sprite::Player | Sprite::PlayerHit | Sprite::PlayerTwo | Sprite::Wingman | Sprite::Enemy | Sprite::EnemyAlt
+        )
+    }
+
+    /// Escape sequence removing every image from the screen, if the protocol
+    /// keeps images apart from the text
+    ///
+    /// Sixel images are part of the cells they cover, so drawing over the
+    /// cells erases them.
+    pub fn clear(&self) -> Option<&'static str> {
+        match self.protocol {
+            Protocol::Kitty => Some("\x1b_Ga=d,d=a,q=2\x1b\\"),
+            Protocol::Sixel => None,
+        }
+    }
+
+    /// Escape sequence drawing a sprite at the cursor, which is left where it
+    /// was
+    ///
+    /// # Arguments
+    /// * `sprite` - The sprite to draw
+    /// * `color` - Color of the sprite's pixels
+    pub fn image(&mut self, sprite: Sprite, color: Color) -> String {
+        let (columns, rows) = sprite.size();
+        let (width, height) = (columns * self.cell_size.0, rows * self.cell_size.1);
+        let pixels = scale(raster::fine_bitmap(sprite), width, height);
+        match self.protocol {
+            Protocol::Kitty => {
+                let mut sequence = String::new();
+                let id = match self.sent.iter().find(|&&(s, c, _)| s == sprite && c == color) {
+                    Some(&(_, _, id)) => id,
+                    None => {
+                        let id = self.sent.len() as u32 + 1;
+                        sequence = kitty_transmit(id, &pixels, width, height, contrast::to_rgb(color, ColorDepth::TrueColor));
+                        self.sent.push((sprite, color, id));
+                        id
+                    }
+                };
+                let _ = write!(sequence, "\x1b_Ga=p,i={},c={},r={},C=1,q=2\x1b\\", id, columns, rows);
+                sequence
+            }
+            Protocol::Sixel => sixel(&pixels, width, height, contrast::to_rgb(color, ColorDepth::TrueColor)),
+        }
+    }
+}
+
+/// Scales a sprite bitmap up to an image of set and unset pixels
+///
+/// # Arguments
+/// * `bitmap` - The sprite's bitmap, `#` for a set pixel
+/// * `width` - Width of the image, in pixels
+/// * `height` - Height of the image, in pixels
+fn scale(bitmap: &[&str], width: usize, height: usize) -> Vec<Vec<bool>> {
+    let rows: Vec<Vec<bool>> = bitmap.iter().map(|row| row.chars().map(|c| c == '#').collect()).collect();
+    let bitmap_width = rows.first().map_or(1, Vec::len).max(1);
+    (0..height)
+        .map(|y| {
+            let row = &rows[y * rows.len() / height];
+            (0..width).map(|x| row.get(x * bitmap_width / width).copied().unwrap_or(false)).collect()
+        })
+        .collect()
+}
+
+/// Kitty command storing an image under an id, without showing it
+///
+/// Unset pixels are transparent. Large images are split over several
+/// commands, as the protocol requires.
+fn kitty_transmit(id: u32, pixels: &[Vec<bool>], width: usize, height: usize, (r, g, b): (u8, u8, u8)) -> String {
+    let rgba: Vec<u8> = pixels
+        .iter()
+        .flatten()
+        .flat_map(|&set| if set { [r, g, b, 255] } else { [0, 0, 0, 0] })
+        .collect();
+    let payload = STANDARD.encode(rgba);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut sequence = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = String::from_utf8_lossy(chunk);
+        if i == 0 {
+            let _ = write!(sequence, "\x1b_Ga=t,f=32,s={},v={},i={},q=2,m={};{}\x1b\\", width, height, id, more, chunk);
+        } else {
+            let _ = write!(sequence, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    sequence
+}
+
+/// Sixel image of a one-color sprite, with unset pixels left transparent
+fn sixel(pixels: &[Vec<bool>], width: usize, height: usize, (r, g, b): (u8, u8, u8)) -> String {
+    // Sixel colors are given in percent
+    let percent = |c: u8| u32::from(c) * 100 / 255;
+    let mut sequence = format!(
+        "\x1bP0;1q\"1;1;{};{}#1;2;{};{};{}",
+        width,
+        height,
+        percent(r),
+        percent(g),
+        percent(b)
+    );
+    // Each sixel character covers a column of six pixels
+    for band in pixels.chunks(6) {
+        sequence.push_str("#1");
+        let mut x = 0;
+        while x < width {
+            let bits = |x: usize| band.iter().enumerate().fold(0u8, |bits, (i, row)| bits | (u8::from(row[x]) << i));
+            let value = bits(x);
+            let mut run = 1;
+            while x + run < width && bits(x + run) == value {
+                run += 1;
+            }
+            let ch = char::from(63 + value);
+            if run > 3 {
+                let _ = write!(sequence, "!{}{}", run, ch);
+            } else {
+                sequence.extend(std::iter::repeat_n(ch, run));
+            }
+            x += run;
+        }
+        sequence.push('-');
+    }
+    sequence.push_str("\x1b\\");
+    sequence
+}
@@ -0,0 +1,61 @@
+//! An index of the cells entities occupy, for collision checks
+//!
+//! Checking every bullet against every invader grows with the product of the
+//! two, which adds up quickly on a big playfield under heavy fire. The grid
+//! is rebuilt once a tick from the invaders' bounds, after which a bullet only
+//! looks at the invaders sharing its cells.
+//!
+//! The grid keeps its cells between ticks and only empties them, so once it
+//! has seen every cell the formation passes through it stops allocating.
+
+use crate::entity::Bounds;
+use std::collections::HashMap;
+
+/// Entities by the cells they occupy, each named by its index in a list kept
+/// by the caller
+#[derive(Default)]
+pub struct CellGrid {
+    cells: HashMap<(usize, usize), Vec<usize>>,
+    /// The bounds of each entity, by index
+    bounds: Vec<Bounds>,
+}
+
+impl CellGrid {
+    /// Empties the grid and fills it again
+    ///
+    /// # Arguments
+    /// * `bounds` - The bounds of each entity, in the order of the caller's list
+    pub fn rebuild(&mut self, bounds: impl Iterator<Item = Bounds>) {
+        self.cells.values_mut().for_each(Vec::clear);
+        self.bounds.clear();
+        for (index, bounds) in bounds.enumerate() {
+            for cell in cells(&bounds) {
+                self.cells.entry(cell).or_default().push(index);
+            }
+            self.bounds.push(bounds);
+        }
+    }
+
+    /// The entities occupying any of a rectangle's cells
+    ///
+    /// Each entity is given once, however many of the cells it spans, in no
+    /// particular order.
+    pub fn overlapping<'a>(&'a self, bounds: &Bounds) -> impl Iterator<Item = usize> + 'a {
+        let area = *bounds;
+        cells(bounds).flat_map(move |cell| {
+            // An entity is given at the first cell it shares with the
+            // rectangle, its top left one within it, and skipped at the others
+            let first = move |&index: &usize| {
+                let entity = &self.bounds[index];
+                cell == (entity.x.max(area.x), entity.y.max(area.y))
+            };
+            self.cells.get(&cell).into_iter().flatten().copied().filter(first)
+        })
+    }
+}
+
+/// Every cell of a rectangle
+fn cells(bounds: &Bounds) -> impl Iterator<Item = (usize, usize)> {
+    let Bounds { x, y, width, height } = *bounds;
+    (y..y + height).flat_map(move |row| (x..x + width).map(move |column| (column, row)))
+}
@@ -0,0 +1,55 @@
+//! Playfield rendering with half-block characters
+//!
+//! Each terminal cell shows two square pixels, one above the other: `▀` in
+//! the top pixel's color over a background in the bottom pixel's color.
+//! Terminal cells are about twice as tall as they are wide, so this doubles
+//! the vertical resolution and the playfield no longer looks squashed.
+
+use crate::Glyph;
+use space_invaders::game::Snapshot;
+use space_invaders::raster;
+use space_invaders::skin::Sprite;
+
+/// Pixels per cell, vertically
+const PIXELS_Y: usize = 2;
+
+/// Pixel bitmap of a sprite, `#` for a set pixel
+///
+/// Bitmaps cover the sprite's whole `Sprite::size`, one pixel wide and
+/// `PIXELS_Y` pixels tall per cell.
+fn bitmap(sprite: Sprite) -> &'static [&'static str] {
+    match sprite {
+        Sprite::Player | Sprite::PlayerHit | Sprite::PlayerTwo => &[".#.", "###", "###", "#.#"],
+        Sprite::Wingman => &[".#.", "###"],
+        Sprite::Enemy => &["###", "#.#"],
+        Sprite::EnemyAlt => &["###", ".#."],
+        Sprite::PlayerBullet | Sprite::EnemyBullet | Sprite::Spark | Sprite::Ash => &["#", "."],
+        Sprite::Ember => &[".", "#"],
+        _ => &["#", "#"],
+    }
+}
+
+/// Lays out the playfield as half-block characters
+///
+/// # Arguments
+/// * `snapshot` - The game state to draw
+/// * `alpha` - How far rendering is between the last tick and the next one
+///
+/// # Returns
+/// A `height` x `width` grid of characters, `None` where a cell is empty
+pub fn render(snapshot: &Snapshot, alpha: f32) -> Vec<Vec<Option<Glyph>>> {
+    let pixels = raster::rasterize(snapshot, alpha, (1, PIXELS_Y), bitmap);
+    (0..snapshot.height)
+        .map(|y| {
+            (0..snapshot.width)
+                .map(|x| match (pixels[y * PIXELS_Y][x], pixels[y * PIXELS_Y + 1][x]) {
+                    (Some(top), Some(bottom)) if top == bottom => Some(Glyph::new('█', top)),
+                    (Some(top), Some(bottom)) => Some(Glyph { under: Some(bottom), ..Glyph::new('▀', top) }),
+                    (Some(top), None) => Some(Glyph::new('▀', top)),
+                    (None, Some(bottom)) => Some(Glyph::new('▄', bottom)),
+                    (None, None) => None,
+                })
+                .collect()
+        })
+        .collect()
+}
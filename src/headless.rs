@@ -0,0 +1,81 @@
+//! Runs played by a bot with no terminal, for `--headless`
+//!
+//! The game is stepped as fast as it will go rather than on the tick clock,
+//! and a summary is printed at the end, so runs can be used to balance
+//! settings, smoke-test a build in CI or time the game logic on its own.
+
+use crate::export::RunSummary;
+use crate::stats::RunStats;
+use crate::GAME_MODE;
+use space_invaders::bot::Strategy;
+use space_invaders::config::Config;
+use space_invaders::game::Game;
+use std::io;
+use std::path::Path;
+use std::time::{Instant, SystemTime};
+
+/// Plays a run with a bot and prints how it went
+///
+/// # Arguments
+/// * `config` - Settings the run is built with
+/// * `seed` - Seeds the game and the bot
+/// * `ticks` - Most ticks to play, if the run doesn't end first
+/// * `strategy` - How the bot plays
+/// * `export` - File to write the run's summary to as JSON, if any
+pub fn run(config: &Config, seed: u64, ticks: u64, strategy: Strategy, export: Option<&Path>) -> io::Result<()> {
+    // Like the configuration's, problems with the campaign don't stop a run
+    let campaign = config.game.campaign().unwrap_or_else(|diagnostics| {
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        None
+    });
+    let mut builder = Game::builder()
+        .enemy_rows(config.game.enemy_rows)
+        .enemy_columns(config.game.enemy_columns)
+        .enemy_speed(config.game.scaled_enemy_speed())
+        .fire_chance(config.game.scaled_fire_chance())
+        .max_bullets(config.game.max_player_bullets, config.game.max_enemy_bullets)
+        .max_particles(config.game.max_particles)
+        .campaign(campaign)
+        .seed(seed);
+    if let Some(width) = config.display.width {
+        builder = builder.width(width);
+    }
+    if let Some(height) = config.display.height {
+        builder = builder.height(height);
+    }
+    let mut game = builder.build();
+    let mut bot = strategy.bot(seed);
+    let mut stats = RunStats::default();
+
+    let started_at = SystemTime::now();
+    let started = Instant::now();
+    let mut snapshot = game.snapshot();
+    while snapshot.tick_count < ticks && snapshot.outcome().is_none() {
+        let commands = bot.decide(&snapshot);
+        game.step(&commands);
+        game.dispatch_events(&mut [&mut stats]);
+        snapshot = game.snapshot();
+    }
+    let took = started.elapsed();
+
+    let outcome = match snapshot.outcome() {
+        Some(true) => "won",
+        Some(false) => "lost",
+        None => "unfinished",
+    };
+    let accuracy = stats.accuracy().map_or("-".to_string(), |percent| format!("{}%", percent));
+    println!("bot: {:?}   seed: {}   modifiers: {}", strategy, seed, snapshot.modifiers);
+    println!("{} after {} ticks ({:.1}s of game time)", outcome, snapshot.tick_count, snapshot.elapsed.as_secs_f32());
+    println!("score: {}   lives left: {}   waves cleared: {}", snapshot.score, snapshot.lives, stats.waves_cleared);
+    println!("shots: {}   kills: {}   accuracy: {}   hits taken: {}", stats.shots, stats.kills, accuracy, stats.hits_taken);
+    let rate = snapshot.tick_count as f64 / took.as_secs_f64().max(f64::EPSILON);
+    println!("simulated in {:.1}ms ({:.0} ticks per second)", took.as_secs_f64() * 1000.0, rate);
+
+    if let Some(path) = export {
+        let won = snapshot.outcome() == Some(true);
+        RunSummary::new(GAME_MODE, &stats, won, started_at, Some(seed), &snapshot).write(path)?;
+    }
+    Ok(())
+}
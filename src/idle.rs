@@ -0,0 +1,108 @@
+//! Idle animations for the title menu and the enemy formation
+//!
+//! The menu runs on its own slow clock instead of the gameplay frame rate:
+//! stars drift and a mascot invader marches at `FRAME_INTERVAL`, and the
+//! screen is only redrawn when that clock moves on, so sitting on the menu
+//! costs almost no CPU.
+//!
+//! The same clock drives the optional formation wobble during a run. It only
+//! touches the frame being drawn, never the simulation, so it has no effect on
+//! collisions or on replaying a run.
+
+use space_invaders::skin::{Cell, Sprite};
+use std::time::Duration;
+
+/// Time between animation frames (2 fps)
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Frame counter for the menu's idle animations
+#[derive(Default)]
+pub struct IdleAnimation {
+    /// Frames shown so far
+    frame: usize,
+    /// Time accumulated towards the next frame
+    pending: Duration,
+}
+
+impl IdleAnimation {
+    /// Moves the animation along by real elapsed time
+    ///
+    /// # Arguments
+    /// * `dt` - Time since the last call
+    ///
+    /// # Returns
+    /// `true` if a new frame is due and the menu should be redrawn
+    pub fn advance(&mut self, dt: Duration) -> bool {
+        self.pending += dt;
+        if self.pending < FRAME_INTERVAL {
+            return false;
+        }
+        while self.pending >= FRAME_INTERVAL {
+            self.pending -= FRAME_INTERVAL;
+            self.frame += 1;
+        }
+        true
+    }
+
+    /// Whether blinking text is lit this frame; it blinks once a second
+    pub fn blink_on(&self) -> bool {
+        self.frame.is_multiple_of(2)
+    }
+
+    /// Time shown by the animation so far, in whole frames
+    pub fn clock(&self) -> Duration {
+        FRAME_INTERVAL * self.frame as u32
+    }
+
+    /// Sways the enemy formation in a playfield frame
+    ///
+    /// Every row of enemies shifts one cell right and back on its own phase,
+    /// and flicks its antennae (switches pose) every other frame. Enemies only
+    /// move into empty cells.
+    ///
+    /// # Arguments
+    /// * `frame` - The playfield's sprites, as laid out by the simulation
+    pub fn wobble_formation(&self, frame: &mut [Vec<Option<Cell>>]) {
+        let is_enemy = |cell: &Option<Cell>| {
+            cell.is_some_and(|c| matches!(c.sprite, Sprite::Enemy | Sprite::EnemyAlt))
+        };
+        for (y, row) in frame.iter_mut().enumerate() {
+            let phase = self.frame + y;
+            if phase % 4 >= 2 {
+                // Right to left, so each enemy cell moves into a cell already vacated
+                for x in (0..row.len().saturating_sub(1)).rev() {
+                    if is_enemy(&row[x]) && row[x + 1].is_none() {
+                        row[x + 1] = row[x].take();
+                    }
+                }
+            }
+            if phase % 2 == 1 {
+                for cell in row.iter_mut().flatten() {
+                    cell.sprite = match cell.sprite {
+                        Sprite::Enemy => Sprite::EnemyAlt,
+                        Sprite::EnemyAlt => Sprite::Enemy,
+                        other => other,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Column of the marching mascot for the current frame
+    ///
+    /// The mascot walks back and forth between the given columns, one cell
+    /// per frame.
+    ///
+    /// # Arguments
+    /// * `left` - Leftmost column of the walk
+    /// * `right` - Rightmost column of the walk
+    pub fn mascot_x(&self, left: usize, right: usize) -> usize {
+        let span = right.saturating_sub(left).max(1);
+        let step = self.frame % (span * 2);
+        if step < span {
+            left + step
+        } else {
+            right - (step - span)
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! Player input as commands
+//!
+//! Raw key presses are translated into `PlayerCommand`s through a set of key
+//! bindings, so the game itself never sees terminal key codes. Anything that
+//! can produce commands (a rebinding, a replay, a bot) can drive a run.
+//!
+//! Bindings can be changed in `keys.toml` in the config directory, which maps
+//! actions to lists of keys:
+//!
+//! ```toml
+//! move_left = ["Left", "a"]
+//! fire = ["Space", "Up"]
+//! ```
+//!
+//! The second player of a two-player run has bindings of their own, which
+//! take over the keys they share with the first player's during such a run.
+
+use crate::config::Diagnostic;
+use crate::paths;
+use crate::schema::{self, Field, FieldKind};
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Something the player asks the game to do
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum PlayerCommand {
+    /// Move the ship one cell to the left
+    MoveLeft,
+    /// Move the ship one cell to the right
+    MoveRight,
+    /// Fire a bullet from the ship
+    Fire,
+    /// Pause or resume the run
+    Pause,
+    /// Leave the game
+    Quit,
+    /// Move the second player's ship one cell to the left
+    PlayerTwoLeft,
+    /// Move the second player's ship one cell to the right
+    PlayerTwoRight,
+    /// Fire a bullet from the second player's ship, or from the invaders'
+    /// picked column in a versus run
+    PlayerTwoFire,
+    /// Send an invader from the picked column diving, in a versus run
+    PlayerTwoDive,
+}
+
+/// Actions that can be bound in `keys.toml`, and the command each triggers
+const ACTIONS: [(&str, PlayerCommand); 9] = [
+    ("move_left", PlayerCommand::MoveLeft),
+    ("move_right", PlayerCommand::MoveRight),
+    ("fire", PlayerCommand::Fire),
+    ("pause", PlayerCommand::Pause),
+    ("quit", PlayerCommand::Quit),
+    ("p2_move_left", PlayerCommand::PlayerTwoLeft),
+    ("p2_move_right", PlayerCommand::PlayerTwoRight),
+    ("p2_fire", PlayerCommand::PlayerTwoFire),
+    ("p2_dive", PlayerCommand::PlayerTwoDive),
+];
+
+impl PlayerCommand {
+    /// Looks up a command by the name of its action in `keys.toml`, e.g.
+    /// `move_left` or `p2_fire`
+    ///
+    /// # Returns
+    /// The command, or `None` if no action has that name
+    pub fn by_name(name: &str) -> Option<Self> {
+        ACTIONS.iter().find(|(action, _)| *action == name).map(|&(_, command)| command)
+    }
+}
+
+/// Whether a command is one of the second player's, which are bound apart
+/// from the first player's
+fn is_player_two(command: PlayerCommand) -> bool {
+    matches!(
+        command,
+        PlayerCommand::PlayerTwoLeft | PlayerCommand::PlayerTwoRight | PlayerCommand::PlayerTwoFire | PlayerCommand::PlayerTwoDive
+    )
+}
+
+/// Expected layout of `keys.toml`, checked before deserializing
+const SCHEMA: FieldKind = FieldKind::Table(&[
+    Field { name: "move_left", kind: FieldKind::List(&FieldKind::Text) },
+    Field { name: "move_right", kind: FieldKind::List(&FieldKind::Text) },
+    Field { name: "fire", kind: FieldKind::List(&FieldKind::Text) },
+    Field { name: "pause", kind: FieldKind::List(&FieldKind::Text) },
+    Field { name: "quit", kind: FieldKind::List(&FieldKind::Text) },
+    Field { name: "p2_move_left", kind: FieldKind::List(&FieldKind::Text) },
+    Field { name: "p2_move_right", kind: FieldKind::List(&FieldKind::Text) },
+    Field { name: "p2_fire", kind: FieldKind::List(&FieldKind::Text) },
+    Field { name: "p2_dive", kind: FieldKind::List(&FieldKind::Text) },
+]);
+
+/// Names of the keys that aren't a single character, as written in
+/// `keys.toml`
+const KEY_NAMES: [(&str, KeyCode); 15] = [
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Space", KeyCode::Char(' ')),
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Insert", KeyCode::Insert),
+    ("Delete", KeyCode::Delete),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+];
+
+/// The classic cheat code: Up Up Down Down Left Right Left Right B A
+pub const KONAMI_CODE: [KeyCode; 10] = [
+    KeyCode::Up,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Char('b'),
+    KeyCode::Char('a'),
+];
+
+/// Watches key presses for a fixed sequence typed in order
+///
+/// Letters match in either case. A wrong key doesn't always start the
+/// sequence over: pressing Up three times still leaves the last two
+/// counting towards `KONAMI_CODE`.
+pub struct KeySequence {
+    keys: &'static [KeyCode],
+    /// How many keys of the sequence the latest presses matched
+    matched: usize,
+}
+
+impl KeySequence {
+    /// Starts watching for a sequence
+    ///
+    /// # Arguments
+    /// * `keys` - The sequence, at least one key long
+    pub fn new(keys: &'static [KeyCode]) -> Self {
+        KeySequence { keys, matched: 0 }
+    }
+
+    /// Notes a key press
+    ///
+    /// # Returns
+    /// Whether the press completed the sequence, which then starts over
+    pub fn push(&mut self, key: KeyCode) -> bool {
+        let key = lowercase(key);
+        // The longest run of the latest presses, this one included, that
+        // starts the sequence
+        let typed = &self.keys[..self.matched];
+        self.matched = (0..=self.matched)
+            .rev()
+            .find(|&n| typed[self.matched - n..] == self.keys[..n] && lowercase(self.keys[n]) == key)
+            .map_or(0, |n| n + 1);
+        if self.matched == self.keys.len() {
+            self.matched = 0;
+            return true;
+        }
+        false
+    }
+}
+
+/// Maps keys to the commands they trigger
+pub struct KeyBindings {
+    keys: HashMap<KeyCode, PlayerCommand>,
+    /// The second player's keys, only used in two-player runs
+    player_two: HashMap<KeyCode, PlayerCommand>,
+}
+
+impl Default for KeyBindings {
+    /// Arrows, A/D or H/L move, Space, W, K or J fires, P pauses and Esc
+    /// quits; in two-player runs A/D move the second player, W fires and
+    /// S dives
+    fn default() -> Self {
+        let mut bindings = KeyBindings {
+            keys: HashMap::new(),
+            player_two: HashMap::new(),
+        };
+        bindings.bind(KeyCode::Left, PlayerCommand::MoveLeft);
+        bindings.bind(KeyCode::Right, PlayerCommand::MoveRight);
+        bindings.bind(KeyCode::Char(' '), PlayerCommand::Fire);
+        // WASD and vim-style keys, for layouts where the arrows are awkward
+        for (keys, command) in [("aAhH", PlayerCommand::MoveLeft), ("dDlL", PlayerCommand::MoveRight), ("wWkKjJ", PlayerCommand::Fire)] {
+            for key in keys.chars() {
+                bindings.bind(KeyCode::Char(key), command);
+            }
+        }
+        bindings.bind(KeyCode::Char('p'), PlayerCommand::Pause);
+        bindings.bind(KeyCode::Char('P'), PlayerCommand::Pause);
+        bindings.bind(KeyCode::Esc, PlayerCommand::Quit);
+        let player_two = [
+            ("aA", PlayerCommand::PlayerTwoLeft),
+            ("dD", PlayerCommand::PlayerTwoRight),
+            ("wW", PlayerCommand::PlayerTwoFire),
+            ("sS", PlayerCommand::PlayerTwoDive),
+        ];
+        for (keys, command) in player_two {
+            for key in keys.chars() {
+                bindings.bind(KeyCode::Char(key), command);
+            }
+        }
+        bindings
+    }
+}
+
+impl KeyBindings {
+    /// Makes a key trigger a command, replacing whatever it did before
+    ///
+    /// The second player's commands are bound apart, so a key can do one
+    /// thing in a one-player run and another in a two-player run.
+    ///
+    /// # Arguments
+    /// * `key` - The key to bind
+    /// * `command` - The command the key triggers
+    pub fn bind(&mut self, key: KeyCode, command: PlayerCommand) {
+        let keys = if is_player_two(command) { &mut self.player_two } else { &mut self.keys };
+        keys.insert(key, command);
+    }
+
+    /// Looks up the command bound to a key in a one-player run
+    ///
+    /// # Returns
+    /// The command, or `None` if the key is unbound
+    pub fn command_for(&self, key: KeyCode) -> Option<PlayerCommand> {
+        self.keys.get(&key).copied()
+    }
+
+    /// Looks up the command bound to a key in a two-player run, where the
+    /// second player's keys come first
+    ///
+    /// # Returns
+    /// The command, or `None` if the key is unbound
+    pub fn two_player_command_for(&self, key: KeyCode) -> Option<PlayerCommand> {
+        self.player_two.get(&key).or_else(|| self.keys.get(&key)).copied()
+    }
+
+    /// Loads and validates `keys.toml`, or the default bindings when the file
+    /// doesn't exist
+    ///
+    /// # Returns
+    /// The bindings, or every problem found in the file
+    pub fn load() -> Result<Self, Vec<Diagnostic>> {
+        let path = Self::path();
+        let file = path.display().to_string();
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&file, &text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(KeyBindings::default()),
+            Err(e) => Err(vec![Diagnostic {
+                file,
+                line: None,
+                message: format!("could not be read: {}", e),
+                suggestion: None,
+            }]),
+        }
+    }
+
+    /// Reads bindings from the text of a `keys.toml`
+    ///
+    /// Actions listed in the file get exactly the keys listed for them;
+    /// the others keep their default keys.
+    ///
+    /// # Arguments
+    /// * `file` - Name of the file the text came from, for diagnostics
+    /// * `text` - Contents of the file
+    ///
+    /// # Returns
+    /// The bindings, or every problem found in the text
+    pub fn parse(file: &str, text: &str) -> Result<Self, Vec<Diagnostic>> {
+        let mut diagnostics = schema::validate(file, text, &SCHEMA);
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        let actions: BTreeMap<String, Vec<String>> =
+            toml::from_str(text).map_err(|e| vec![schema::parse_error(file, text, &e)])?;
+
+        let mut bindings = KeyBindings::default();
+        // The players' keys may overlap, so each player's are checked apart
+        let (mut bound_one, mut bound_two): (HashMap<KeyCode, &str>, HashMap<KeyCode, &str>) = Default::default();
+        for (action, keys) in &actions {
+            let Some(command) = PlayerCommand::by_name(action) else { continue };
+            let mut report = |message: String, suggestion: Option<String>| {
+                diagnostics.push(Diagnostic {
+                    file: file.to_string(),
+                    line: schema::find_line(text, &[action]),
+                    message,
+                    suggestion,
+                })
+            };
+            if keys.is_empty() {
+                report(format!("`{}` has no keys, so it could never be used", action), None);
+            }
+            let (map, bound) = if is_player_two(command) {
+                (&mut bindings.player_two, &mut bound_two)
+            } else {
+                (&mut bindings.keys, &mut bound_one)
+            };
+            map.retain(|_, bound_command| *bound_command != command);
+            for name in keys {
+                let Some(key) = parse_key(name) else {
+                    report(
+                        format!("`{}` has unknown key \"{}\"", action, name),
+                        Some("use a single character, a name like Left, Space or Enter, or F1 to F12".to_string()),
+                    );
+                    continue;
+                };
+                if let Some(other) = bound.insert(key, action).filter(|other| other != action) {
+                    report(format!("\"{}\" is bound to both `{}` and `{}`", name, other, action), None);
+                }
+                for key in with_other_case(key) {
+                    bindings.bind(key, command);
+                }
+            }
+        }
+        if diagnostics.is_empty() {
+            Ok(bindings)
+        } else {
+            Err(diagnostics)
+        }
+    }
+
+    /// Location of the key bindings file
+    pub fn path() -> PathBuf {
+        paths::config_dir().join("keys.toml")
+    }
+}
+
+/// Reads a key as written in `keys.toml`: a single character, `F1` to `F12`
+/// or one of `KEY_NAMES`, ignoring case
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    if let Some(n) = name.strip_prefix(['F', 'f']).and_then(|n| n.parse().ok()).filter(|n| (1..=12).contains(n)) {
+        return Some(KeyCode::F(n));
+    }
+    KEY_NAMES.iter().find(|(key_name, _)| key_name.eq_ignore_ascii_case(name)).map(|&(_, key)| key)
+}
+
+/// A key with letters made lowercase
+fn lowercase(key: KeyCode) -> KeyCode {
+    match key {
+        KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+        _ => key,
+    }
+}
+
+/// A key, and for letters the same letter in the other case, so bindings
+/// work with Caps Lock on
+fn with_other_case(key: KeyCode) -> Vec<KeyCode> {
+    match key {
+        KeyCode::Char(c) if c.is_ascii_lowercase() => vec![key, KeyCode::Char(c.to_ascii_uppercase())],
+        KeyCode::Char(c) if c.is_ascii_uppercase() => vec![key, KeyCode::Char(c.to_ascii_lowercase())],
+        _ => vec![key],
+    }
+}
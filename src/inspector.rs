@@ -0,0 +1,123 @@
+//! Developer overlay describing the entities on the playfield
+//!
+//! Shown over the top of the playfield with F4: how many of each kind of
+//! entity are alive, where every bullet is, the enemy formation's bounding
+//! box and where the last collision happened. It only reads snapshots and
+//! events, so it can't change how a run plays out.
+
+use space_invaders::events::GameEvent;
+use space_invaders::game::Snapshot;
+use space_invaders::skin::Sprite;
+
+/// A bullet hitting something, as seen in the events of a tick
+#[derive(Clone, Copy)]
+struct Collision {
+    /// What was hit
+    target: &'static str,
+    /// Top-left cell of what was hit
+    x: usize,
+    y: usize,
+    /// Tick the collision happened on
+    tick: u64,
+}
+
+/// Keeps track of what the overlay shows between ticks
+#[derive(Default)]
+pub struct Inspector {
+    last_collision: Option<Collision>,
+}
+
+impl Inspector {
+    /// Notes the collisions of a tick
+    ///
+    /// # Arguments
+    /// * `snapshot` - The game state at the end of the tick
+    /// * `events` - Events the tick published
+    pub fn observe(&mut self, snapshot: &Snapshot, events: &[GameEvent]) {
+        for event in events {
+            let (target, x, y) = match *event {
+                GameEvent::EnemyKilled { x, y, .. } => ("enemy", x, y),
+                GameEvent::PlayerHit => {
+                    // Hits on the player don't say where; the ship is where it was
+                    let Some((x, y)) = snapshot
+                        .positions(1.0)
+                        .find(|(sprite, _)| matches!(sprite, Sprite::Player | Sprite::PlayerHit))
+                        .map(|(sprite, position)| top_left(sprite, position))
+                    else {
+                        continue;
+                    };
+                    ("player", x, y)
+                }
+                _ => continue,
+            };
+            self.last_collision = Some(Collision { target, x, y, tick: snapshot.tick_count });
+        }
+    }
+
+    /// Lines of the overlay, each cut to a width
+    ///
+    /// # Arguments
+    /// * `snapshot` - The game state being drawn
+    /// * `width` - Most characters a line may have
+    pub fn lines(&self, snapshot: &Snapshot, width: usize) -> Vec<String> {
+        let count = |kinds: &[Sprite]| snapshot.positions(1.0).filter(|(sprite, _)| kinds.contains(sprite)).count();
+        let mut lines = vec![format!(
+            "player {}  wingman {}  enemies {}  bullets {}/{}  particles {}",
+            count(&[Sprite::Player, Sprite::PlayerHit, Sprite::PlayerTwo]),
+            count(&[Sprite::Wingman]),
+            count(&[Sprite::Enemy, Sprite::EnemyAlt]),
+            count(&[Sprite::PlayerBullet]),
+            count(&[Sprite::EnemyBullet]),
+            count(&[Sprite::Spark, Sprite::Ember, Sprite::Ash]),
+        )];
+        lines.push(match formation_bounds(snapshot) {
+            Some((left, top, right, bottom)) => format!("formation ({},{})-({},{})", left, top, right, bottom),
+            None => "formation none".to_string(),
+        });
+        lines.push(match self.last_collision {
+            Some(Collision { target, x, y, tick }) => format!("last hit {} at ({},{}) on tick {}", target, x, y, tick),
+            None => "last hit none".to_string(),
+        });
+        // Player bullets fly up and enemy bullets down
+        let bullets: Vec<String> = snapshot
+            .positions(1.0)
+            .filter_map(|(sprite, (x, y))| match sprite {
+                Sprite::PlayerBullet => Some(format!("^{:.0},{:.0}", x, y)),
+                Sprite::EnemyBullet => Some(format!("v{:.0},{:.0}", x, y)),
+                _ => None,
+            })
+            .collect();
+        if !bullets.is_empty() {
+            lines.push(format!("bullets {}", bullets.join(" ")));
+        }
+        for line in &mut lines {
+            if let Some((cut, _)) = line.char_indices().nth(width) {
+                line.truncate(cut);
+            }
+        }
+        lines
+    }
+}
+
+/// Cells the enemy formation covers, as the leftmost column, top row,
+/// rightmost column and bottom row
+///
+/// # Returns
+/// `None` once every enemy is gone
+pub fn formation_bounds(snapshot: &Snapshot) -> Option<(usize, usize, usize, usize)> {
+    snapshot
+        .positions(1.0)
+        .filter(|(sprite, _)| matches!(sprite, Sprite::Enemy | Sprite::EnemyAlt))
+        .map(|(sprite, position)| {
+            let (x, y) = top_left(sprite, position);
+            let (width, height) = sprite.size();
+            (x, y, x + width - 1, y + height - 1)
+        })
+        .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+}
+
+/// Top-left cell of a sprite, from the position `Snapshot::positions` gives
+fn top_left(sprite: Sprite, (x, y): (f32, f32)) -> (usize, usize) {
+    let (x, y) = (x.round().max(0.0) as usize, y.round().max(0.0) as usize);
+    (x.saturating_sub(sprite.size().0 / 2), y)
+}
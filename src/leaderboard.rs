@@ -0,0 +1,159 @@
+//! The ten best runs played on this machine, under the initials of whoever
+//! played them
+//!
+//! Unlike the profile's high score, the leaderboard is shared by everyone
+//! using the data directory, like an arcade cabinet's. It is kept in
+//! `leaderboard.toml` in the data directory. Several games can be running on
+//! it at once, e.g. one per SSH session with `--arcade`, so a run is recorded
+//! on the file as it is then rather than on the copy loaded at startup.
+
+use serde::{Deserialize, Serialize};
+use space_invaders::paths;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Number of runs the leaderboard keeps
+pub const SIZE: usize = 10;
+/// Number of letters in a set of initials
+pub const INITIALS: usize = 3;
+/// Longest to wait for another game to finish recording a run
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One run on the leaderboard
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Initials entered by the player, in capitals
+    pub initials: String,
+    /// Final score of the run
+    pub score: usize,
+}
+
+/// The best runs, highest score first
+#[derive(Default, Serialize, Deserialize)]
+pub struct Leaderboard {
+    #[serde(default)]
+    entries: Vec<Entry>,
+}
+
+impl Leaderboard {
+    /// Loads the leaderboard, or starts an empty one
+    ///
+    /// A missing or unreadable file gives an empty leaderboard rather than an
+    /// error, so a run can always start.
+    pub fn load() -> Self {
+        let mut leaderboard: Leaderboard = fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+        leaderboard.entries.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        leaderboard.entries.truncate(SIZE);
+        leaderboard
+    }
+
+    /// The runs on the leaderboard, highest score first
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Whether a score would make it onto the leaderboard
+    pub fn qualifies(&self, score: usize) -> bool {
+        score > 0 && (self.entries.len() < SIZE || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    /// Puts a run on the leaderboard and saves it
+    ///
+    /// The leaderboard is loaded again first, while no other game can write
+    /// it, so runs other games recorded since this one loaded it are kept.
+    ///
+    /// # Arguments
+    /// * `initials` - The player's initials
+    /// * `score` - Final score of the run
+    ///
+    /// # Returns
+    /// The run's place, counting from 1, or an error if it couldn't be saved
+    pub fn record(&mut self, initials: String, score: usize) -> io::Result<usize> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let _lock = Lock::take(&path.with_extension("lock"))?;
+        *self = Self::load();
+        let place = self.insert(initials, score);
+        self.save().map(|()| place)
+    }
+
+    /// Puts a run on the leaderboard, pushing the lowest off if it is full
+    ///
+    /// Runs with the same score keep the order they were played in.
+    ///
+    /// # Returns
+    /// The run's place, counting from 1
+    fn insert(&mut self, initials: String, score: usize) -> usize {
+        let index = self.entries.iter().position(|entry| score > entry.score).unwrap_or(self.entries.len());
+        self.entries.insert(index, Entry { initials, score });
+        self.entries.truncate(SIZE);
+        index + 1
+    }
+
+    /// Writes the leaderboard to the data directory
+    ///
+    /// The file is written next to the old one and moved into place, so
+    /// quitting part way through can't lose the scores in it.
+    fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        // Named after the process, so games saving at once can't mix their files
+        let temporary = path.with_extension(format!("toml.{}.tmp", process::id()));
+        fs::write(&temporary, text)?;
+        fs::rename(temporary, path)
+    }
+
+    /// Location of the leaderboard file
+    fn path() -> PathBuf {
+        paths::data_dir().join("leaderboard.toml")
+    }
+}
+
+/// Keeps other games from recording runs while held; released when dropped
+struct Lock(PathBuf);
+
+impl Lock {
+    /// Waits until no other game holds the lock, then takes it
+    ///
+    /// A lock still held after `LOCK_TIMEOUT` is taken over: it was most
+    /// likely left behind by a game that crashed while recording.
+    ///
+    /// # Arguments
+    /// * `path` - The lock file, which exists while the lock is held
+    fn take(path: &Path) -> io::Result<Self> {
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(_) => return Ok(Lock(path.to_path_buf())),
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+                Err(_) if started.elapsed() >= LOCK_TIMEOUT => return Ok(Lock(path.to_path_buf())),
+                Err(_) => thread::sleep(Duration::from_millis(20)),
+            }
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Initials to start the entry screen with: the first letters of a name,
+/// padded with `A`
+///
+/// # Arguments
+/// * `name` - The player's name, e.g. their profile's
+pub fn default_initials(name: &str) -> [char; INITIALS] {
+    let mut letters = name.chars().filter(char::is_ascii_alphabetic).map(|c| c.to_ascii_uppercase());
+    [(); INITIALS].map(|()| letters.next().unwrap_or('A'))
+}
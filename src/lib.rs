@@ -0,0 +1,73 @@
+//! # Space Invaders
+//!
+//! The game's simulation, input model and configuration, usable without a
+//! terminal. The `space-invaders` binary is one frontend built on this crate;
+//! bots, alternative frontends and tools can be built on it the same way.
+//!
+//! ## Stability
+//!
+//! The documented modules are the public API and follow semver:
+//! - `game`: `Game`, its builder and `Snapshot`s of its state
+//! - `campaign`: campaigns of waves and the level file format
+//! - `entity`: the things on the playfield
+//! - `events`: `GameEvent`s and subscribing to them
+//! - `input`: `PlayerCommand`s and key bindings
+//! - `systems`: the `System` trait for adding game logic
+//! - `controllers`: controller roster and events
+//! - `config`: the `config.toml` format
+//! - `modifiers`: the settings a run was played with
+//! - `replay`: the replay file format
+//! - `skin`: sprites and the glyphs they are drawn with
+//!
+//! Enums and structs that are expected to grow are `#[non_exhaustive]`, so
+//! adding a variant or field is not a breaking change.
+//!
+//! Hidden modules are internals shared with the binary. They can change in
+//! any release.
+
+pub mod campaign;
+pub mod config;
+pub mod controllers;
+pub mod entity;
+pub mod events;
+pub mod game;
+pub mod input;
+pub mod modifiers;
+pub mod replay;
+pub mod skin;
+pub mod systems;
+
+#[doc(hidden)]
+pub mod alerts;
+#[doc(hidden)]
+pub mod bell;
+#[doc(hidden)]
+pub mod bot;
+#[doc(hidden)]
+pub mod commander;
+#[doc(hidden)]
+pub mod grid;
+#[doc(hidden)]
+pub mod netplay;
+#[doc(hidden)]
+pub mod paths;
+#[doc(hidden)]
+pub mod pool;
+#[doc(hidden)]
+pub mod raster;
+#[doc(hidden)]
+pub mod schema;
+#[doc(hidden)]
+pub mod script;
+#[doc(hidden)]
+pub mod score;
+#[doc(hidden)]
+pub mod sim;
+#[doc(hidden)]
+pub mod ssh;
+#[doc(hidden)]
+pub mod theme;
+#[doc(hidden)]
+pub mod tournament;
+#[doc(hidden)]
+pub mod wingman;
@@ -7,378 +7,2962 @@
 //! - Player controls a ship at the bottom of the screen
 //! - Enemies move across and down the screen
 //! - Player can move left and right, shoot bullets
-//! - Game ends when enemies reach bottom or player is hit
+//! - Game ends when enemies reach bottom or the last of the player's ships is hit
 
+#[cfg(feature = "audio")]
+mod audio;
+mod backend;
+mod bench;
+mod braille;
+mod contrast;
+mod export;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod ghost;
+mod gradient;
+mod graphics;
+mod halfblock;
+mod headless;
+mod idle;
+mod inspector;
+mod leaderboard;
+mod medals;
+mod movement;
+mod perf;
+mod profile;
+mod screen;
+mod shake;
+mod spectate;
+mod starfield;
+mod stats;
+mod suspend;
+mod trails;
+
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::builder::PossibleValuesParser;
+use clap::error::ErrorKind;
+use clap::{value_parser, CommandFactory, Parser};
 use crossterm::{
-    cursor,
-    event::{self, Event, KeyCode},
-    execute,
-    terminal::{self, Clear, ClearType},
-    style::{Color, SetForegroundColor, SetBackgroundColor, ResetColor},
+    cursor, execute,
+    event::{self, Event, KeyCode, KeyEventKind},
+    style::Color,
+    terminal,
 };
-use rand::Rng;
-use std::io::{stdout, Write};
-use std::time::{Duration, Instant};
-use std::thread;
-use std::io;
 
-const SCREEN_WIDTH: usize = 60;  // Increased screen width
-const SCREEN_HEIGHT: usize = 25; // Increased screen height
-const PLAYER_CHAR: char = '^';
-const ENEMY_CHAR: char = 'W';
-const BULLET_CHAR: char = '|';
-
-/// Represents a game object with position and alive status
-#[derive(Clone, PartialEq)]
-struct GameObject {
-    /// X-coordinate of the object
-    x: usize,
-    /// Y-coordinate of the object
-    y: usize,
-    // Whether the object is still active in the game
-    alive: bool,
+#[cfg(feature = "audio")]
+use audio::{Audio, Sound};
+use backend::{Letterbox, Renderer as _};
+use contrast::{ColorDepth, ContrastWarning};
+use export::RunSummary;
+#[cfg(feature = "gamepad")]
+use gamepad::Gamepads;
+use ghost::Ghost;
+use gradient::Gradients;
+use graphics::Graphics;
+use idle::IdleAnimation;
+use inspector::Inspector;
+use leaderboard::{default_initials, Leaderboard};
+use medals::{Medal, MedalTable, MedalThresholds};
+use movement::HeldMovement;
+use perf::PerfStats;
+use profile::Profile;
+use screen::TerminalGuard;
+use shake::ScreenShake;
+use spectate::Spectators;
+use starfield::Starfield;
+use stats::RunStats;
+use suspend::SuspendedRun;
+use space_invaders::alerts::Alerts;
+use space_invaders::bell::BellCues;
+use space_invaders::bot::{Bot, Invaders, Strategy, STRATEGY_NAMES};
+use space_invaders::config::{
+    Config, Difficulty, Renderer, MAX_HEIGHT, MAX_SPEED, MAX_WIDTH, MIN_HEIGHT, MIN_SPEED, MIN_WIDTH,
+};
+use space_invaders::controllers::{ControllerEvent, Controllers};
+use space_invaders::events::Subscriber;
+use space_invaders::game::{self, CommanderStatus, Game, GameBuilder, Snapshot, TICK_DURATION};
+use space_invaders::input::{KeyBindings, KeySequence, PlayerCommand, KONAMI_CODE};
+use space_invaders::modifiers::RunModifiers;
+use space_invaders::netplay::{Peer, Session};
+use space_invaders::paths;
+use space_invaders::replay::{InputLog, ReplayFile, ReplayStream};
+use space_invaders::schema::Diagnostic;
+use space_invaders::script::InputScript;
+use space_invaders::sim::{Control, Simulation, Update};
+use space_invaders::skin::{self, Cell, Skin, Sprite};
+use space_invaders::ssh;
+use space_invaders::theme::{self, Style, Theme, THEME_NAMES};
+use space_invaders::tournament::{self, RunRecord, Token};
+
+/// Game mode used to look up medal thresholds and record medals
+const GAME_MODE: &str = "classic";
+/// Game mode two-player runs record their scores under, kept apart from
+/// one-player runs since two ships score faster than one
+const TWO_PLAYER_MODE: &str = "co-op";
+/// Game mode versus runs record the ship's scores under
+const VERSUS_MODE: &str = "versus";
+/// Game title in large lettering, shown on the title screen
+const TITLE_ART: [&str; 8] = [
+    r" ___ ___  _   ___ ___ ",
+    r"/ __| _ \/_\ / __| __|",
+    r"\__ \  _/ _ \ (__| _| ",
+    r"|___/_|/_/ \_\___|___|",
+    r" ___ _  _  ___   ___ _____ ___ ___  ___ ",
+    r"/ __| || |/ _ \ / _ \_   _| __| _ \/ __|",
+    r"\__ \ __ | (_) | (_) || | | _||   /\__ \",
+    r"|___/_||_|\___/ \___/ |_| |___|_|_\|___/",
+];
+/// A character on the playfield and the sprites whose colors it is drawn in
+#[derive(Clone, Copy, PartialEq)]
+struct Glyph {
+    ch: char,
+    /// Sprite whose colors the character is drawn in
+    sprite: Sprite,
+    /// Sprite whose color fills the cell behind the character, for renderers
+    /// that stack two sprites in one cell
+    under: Option<Sprite>,
+    /// Colors to draw the character in instead of the sprite's
+    style: Option<Style>,
+}
+
+impl Glyph {
+    /// A character drawn in a sprite's colors
+    fn new(ch: char, sprite: Sprite) -> Self {
+        Glyph { ch, sprite, under: None, style: None }
+    }
+}
+
+/// Rows below the playfield's border: the HUD strip and the debug overlay
+const HUD_ROWS: usize = 2;
+/// Columns the HUD sidebar needs to the right of the border
+const SIDEBAR_WIDTH: usize = 24;
+/// Rows on the settings screen: music volume, effects volume and game speed
+const SETTINGS: usize = 3;
+/// How long the title menu sits untouched before the demo starts
+const DEMO_DELAY: Duration = Duration::from_secs(15);
+/// How far the arrow keys seek through a watched replay, in ticks: ten
+/// seconds of play at normal speed
+const SEEK_TICKS: u64 = 100;
+/// How long a networked run can go without a tick before the player is told
+/// it is waiting on the other computer
+const WAIT_NOTICE: Duration = Duration::from_millis(500);
+/// Colors the player's bullets cycle through once the Konami code is entered
+const RAINBOW: [Color; 6] = [Color::Red, Color::Yellow, Color::Green, Color::Cyan, Color::Blue, Color::Magenta];
+
+/// Who is playing a run
+#[derive(Clone, Copy, PartialEq)]
+enum Players {
+    /// One player against invaders that fire at random
+    One,
+    /// Two players with a ship each, side by side
+    CoOp,
+    /// Two players, one flying the ship and one commanding the invaders
+    Versus,
+    /// Two players with a ship each, on two computers connected over TCP
+    Online,
+}
+
+impl Players {
+    /// Who played a recorded run
+    ///
+    /// # Arguments
+    /// * `modifiers` - The settings the run was played with
+    fn of(modifiers: &RunModifiers) -> Self {
+        if modifiers.versus {
+            Players::Versus
+        } else if modifiers.two_players {
+            Players::CoOp
+        } else {
+            Players::One
+        }
+    }
 }
 
-/// Manages the entire game state and logic
-struct Game {
-    /// Player's game object
-    player: GameObject,
-    // List of enemy game objects
-    enemies: Vec<GameObject>,
-    /// Bullets fired by the player
-    player_bullets: Vec<GameObject>,
-    /// Bullets fired by enemies
-    enemy_bullets: Vec<GameObject>,
-    // Current player's score
-    score: usize,
-    // Flag to indicate if the game is over
-    game_over: bool,
-    /// Counter to control enemy movement speed
-    enemy_move_counter: usize, // New field to slow down enemy movement
+/// The screens the game can be in
+///
+/// Each state has its own input, update and render handling in `App`.
+#[derive(Clone, Copy, PartialEq)]
+enum GameState {
+    /// Title menu shown before a run starts
+    Menu,
+    /// A run is in progress
+    Playing,
+    /// A run is frozen until the player resumes it
+    Paused,
+    /// The run has ended, either by clearing all enemies or by losing
+    GameOver { won: bool },
+    /// Problems were found in the configuration or data files at startup
+    ConfigErrors,
+    /// Diagnostic grid showing every glyph of the active skin
+    GlyphTest,
+    /// The player's controller was unplugged mid-run; the run is frozen
+    ControllerLost,
+    /// Picker for which connected controller drives the ship
+    Controllers { selected: usize },
+    /// Music and sound effect volumes and the game speed, saved to the config
+    /// file on leaving
+    Settings { selected: usize },
+    /// A run made the leaderboard and the player is entering their initials;
+    /// the results screen follows
+    Initials { won: bool, letters: [char; leaderboard::INITIALS], cursor: usize },
+    /// Totals of every run the player has finished
+    Stats,
+    /// Waiting for the other computer of a networked run to connect
+    Connecting,
 }
 
+/// Outcome of a finished run, shown on the results screen
+struct RunResult {
+    /// Medal earned by the run's score, if any
+    medal: Option<Medal>,
+    /// Whether the medal improved on the profile's best for this mode
+    new_best: bool,
+    /// Error message if the profile could not be saved
+    save_error: Option<String>,
+    /// Where the tournament result was saved, or why it couldn't be
+    tournament_result: Option<Result<PathBuf, String>>,
+    /// Place the run took on the leaderboard, or why it couldn't be saved
+    leaderboard_result: Option<Result<usize, String>>,
+    /// Why the run's summary couldn't be exported, if it couldn't
+    export_error: Option<String>,
+}
+
+/// Drives the game through its states and owns the current run
+struct App {
+    /// Screen currently being shown
+    state: GameState,
+    /// The latest state of the current (or most recent) run
+    view: Snapshot,
+    /// The current run's simulation thread, while the run lasts
+    sim: Option<Simulation>,
+    /// When the latest tick arrived, used to interpolate between ticks
+    last_tick: Instant,
+    /// Settings every new run is built with; only the seed changes per run
+    game_builder: GameBuilder,
+    /// When `update` last ran, used to time the menu animation
+    last_update: Instant,
+    /// Set when the player asks to leave the program
+    quit: bool,
+    /// Medal thresholds for the current game mode, if they could be loaded
+    medals: Option<MedalThresholds>,
+    /// The player's saved progress
+    profile: Profile,
+    /// Best runs played on this machine
+    leaderboard: Leaderboard,
+    /// Run put aside by quitting part way through, if there is one
+    suspended: Option<SuspendedRun>,
+    /// File each finished run's summary is written to, from `--export-json`
+    export_path: Option<PathBuf>,
+    /// Raised when someone watching with `--spectate` needs the whole screen
+    /// drawn again
+    spectators: Option<Arc<AtomicBool>>,
+    /// Whether this is one session of an arcade served with `--arcade`,
+    /// played as a guest who leaves nothing behind but leaderboard entries
+    arcade: bool,
+    /// When the current or last run started
+    run_started: SystemTime,
+    /// Seed every run is built with, from `--seed` or the configuration;
+    /// random when unset
+    seed: Option<u64>,
+    /// Whether the run on screen is a recording being played back, from
+    /// `--replay`
+    watching: bool,
+    /// The replay file being watched, for seeking through it with the arrow
+    /// keys; `None` when not watching one
+    watched: Option<ReplayFile>,
+    /// Plays the demo run shown when the title menu is left alone; `None`
+    /// outside the demo
+    demo: Option<Box<dyn Bot>>,
+    /// Commands the invaders of a versus run played against the computer,
+    /// started with `B`; `None` when a second player commands them
+    opponent: Option<Box<dyn Bot>>,
+    /// The networked run being set up in the background, and what the
+    /// player is waiting for
+    connecting: Option<(Receiver<io::Result<Session>>, String)>,
+    /// Why the networked run couldn't start or go on, if it couldn't
+    connection_error: Option<String>,
+    /// When the title menu was last touched, so the demo starts once it has
+    /// sat idle for `DEMO_DELAY`
+    menu_idle_since: Instant,
+    /// Outcome of the last finished run
+    result: Option<RunResult>,
+    /// Bell and flash alerts for critical moments
+    alerts: Alerts,
+    /// Bell rings for shots, kills and hits, if turned on
+    bell_cues: Option<BellCues>,
+    /// Problems found while loading configuration and data files
+    diagnostics: Vec<Diagnostic>,
+    /// Where screens are drawn, centered on the terminal
+    out: Letterbox,
+    /// Glyphs used to draw sprites
+    skin: &'static Skin,
+    /// Colors used to draw sprites
+    theme: Theme,
+    /// Whether sprites are drawn in color or as plain text
+    colored: bool,
+    /// Shading and trail fading on the playfield, if the terminal has the
+    /// colors for them
+    gradients: Option<Gradients>,
+    /// The terminal's background color, as set in the configuration
+    background: Color,
+    /// How many colors the terminal can show
+    color_depth: ColorDepth,
+    /// Whether hard-to-see theme colors are adjusted automatically
+    ensure_visible: bool,
+    /// Sprites whose colors are likely to be invisible on this terminal
+    contrast_warnings: Vec<ContrastWarning>,
+    /// Whether hard-to-see colors were adjusted automatically
+    contrast_adjusted: bool,
+    /// Keys mapped to player commands during a run
+    bindings: KeyBindings,
+    /// Keeps the ship moving while Left or Right is held
+    movement: HeldMovement,
+    /// Keeps the second player's ship moving in a two-player run
+    movement_two: HeldMovement,
+    /// Who is playing the current (or most recent) run
+    players: Players,
+    /// Watches the title screen for the Konami code
+    konami: KeySequence,
+    /// Whether the player's bullets are drawn in rainbow colors, unlocked
+    /// with the Konami code
+    rainbow_bullets: bool,
+    /// Loudness of the music and of the sound effects, from 0.0 to 1.0
+    volumes: (f32, f32),
+    /// Why the settings couldn't be saved, shown until they are
+    settings_error: Option<String>,
+    /// Whether the tick counter, game clock and frame rates are shown;
+    /// toggled with F3
+    debug_overlay: bool,
+    /// Frame and tick rates shown in the debug overlay
+    perf: PerfStats,
+    /// Whether entity counts, bullets, the formation's bounds and the last
+    /// collision are shown over the playfield; toggled with F4
+    entity_overlay: bool,
+    /// What the entity overlay shows
+    inspector: Inspector,
+    /// Clock for the title menu's animations and the formation wobble
+    idle: IdleAnimation,
+    /// Stars drifting behind the menu and the playfield
+    starfield: Starfield,
+    /// Jolts the playfield when something explodes
+    shake: ScreenShake,
+    /// Shots, kills and waves of the current or last run
+    stats: RunStats,
+    /// Whether the title menu animates; off with reduced motion
+    animate_menu: bool,
+    /// Whether the enemy formation wobbles during a run; off with reduced motion
+    formation_wobble: bool,
+    /// Set when a screen other than a running game needs to be redrawn
+    redraw: bool,
+    /// Sprites drawn in the last playfield frame, so the next one only
+    /// redraws the cells that changed; `None` to redraw in full
+    last_frame: Option<Vec<Vec<Option<Glyph>>>>,
+    /// How the playfield is drawn
+    renderer: Renderer,
+    /// Frames rendered per second during a run; the simulation tick rate is
+    /// independent of this
+    frame_rate: u32,
+    /// Multiple of the normal tick rate runs are played at; always 1.0 in
+    /// tournaments, so every entrant plays at the same pace
+    speed: f32,
+    /// Image drawing for ships and invaders, if the terminal supports it
+    graphics: Option<Graphics>,
+    /// Sprites drawn as images in the last playfield frame, by cell, with the
+    /// color they were drawn in; `None` when no images are on screen
+    last_images: Option<Vec<(usize, usize, Sprite, Color)>>,
+    /// Whether the border was last drawn in the alert flash's color
+    border_flashing: bool,
+    /// Lines shown in the HUD sidebar, so only the ones that changed are
+    /// redrawn; `None` to redraw it in full
+    last_sidebar: Option<Vec<(String, Color)>>,
+    /// Rows below the border as last drawn, the HUD strip and then the debug
+    /// overlay, so unchanged rows aren't drawn again; `None` to redraw them
+    last_status: Option<[Vec<(String, Color)>; 2]>,
+    /// Connected controllers and the one assigned to the player
+    controllers: Controllers,
+    /// Gamepad backend, if the system supports gamepads
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<Gamepads>,
+    /// Sound effects for game events
+    #[cfg(feature = "audio")]
+    audio: Audio,
+    /// Whether runs start with a wingman; toggled from the pause screen
+    wingman_enabled: bool,
+    /// Aggressiveness given to new wingmen
+    wingman_aggressiveness: f32,
+    /// Tournament the runs are played in, locking the game to its ruleset
+    tournament: Option<Token>,
+    /// Seed and commands of the current run, enough to play it again
+    input_log: Option<InputLog>,
+    /// Replay of the current run being written to disk, unless writing failed
+    replay: Option<ReplayStream>,
+    /// Recorded run raced by every one-player run, from `--ghost`
+    ghost_log: Option<InputLog>,
+    /// The recorded run flying alongside the current run, if it is raced
+    ghost: Option<Ghost>,
+    /// Current size of the terminal, in columns and rows
+    terminal_size: (usize, usize),
+    /// Playfield width and height from the configuration, if set
+    configured_size: (Option<usize>, Option<usize>),
+}
+
+impl App {
+    /// Creates the app on the title menu
+    ///
+    /// If any problems were found while loading files, the app starts on an
+    /// error screen listing them instead.
+    ///
+    /// # Arguments
+    /// * `config` - User configuration
+    /// * `diagnostics` - Problems found while loading the configuration
+    /// * `tournament` - Tournament to play in, overriding the game settings
+    /// * `out` - Where screens are drawn
+    fn new(
+        config: &Config,
+        mut diagnostics: Vec<Diagnostic>,
+        tournament: Option<Token>,
+        out: Box<dyn backend::Renderer>,
+    ) -> Self {
+        let medals = match MedalTable::bundled() {
+            Ok(table) => {
+                let thresholds = table.thresholds(GAME_MODE);
+                if thresholds.is_none() {
+                    diagnostics.push(Diagnostic {
+                        file: "data/medals.toml".to_string(),
+                        line: None,
+                        message: format!("no medal thresholds for the `{}` mode", GAME_MODE),
+                        suggestion: None,
+                    });
+                }
+                thresholds
+            }
+            Err(problems) => {
+                diagnostics.extend(problems);
+                None
+            }
+        };
+
+        let bindings = KeyBindings::load().unwrap_or_else(|problems| {
+            diagnostics.extend(problems);
+            KeyBindings::default()
+        });
+
+        let background = config.display.background.color();
+        let depth = ColorDepth::detect();
+
+        let game_builder = match &tournament {
+            Some(token) => token.ruleset.builder(),
+            None => Game::builder()
+                .enemy_rows(config.game.enemy_rows)
+                .enemy_columns(config.game.enemy_columns)
+                .enemy_speed(config.game.scaled_enemy_speed())
+                .fire_chance(config.game.scaled_fire_chance())
+                .max_bullets(config.game.max_player_bullets, config.game.max_enemy_bullets)
+                .max_particles(config.game.max_particles)
+                .campaign(config.game.campaign().unwrap_or_else(|problems| {
+                    diagnostics.extend(problems);
+                    None
+                })),
+        };
+
+        let mut app = App {
+            state: if diagnostics.is_empty() {
+                GameState::Menu
+            } else {
+                GameState::ConfigErrors
+            },
+            view: game_builder.build().snapshot(),
+            sim: None,
+            last_tick: Instant::now(),
+            game_builder,
+            last_update: Instant::now(),
+            quit: false,
+            medals,
+            profile: Profile::load_current(),
+            leaderboard: Leaderboard::load(),
+            // A run put aside can't be continued as part of a tournament
+            suspended: SuspendedRun::load().filter(|_| tournament.is_none()),
+            export_path: None,
+            spectators: None,
+            arcade: false,
+            run_started: SystemTime::now(),
+            seed: None,
+            watching: false,
+            watched: None,
+            demo: None,
+            opponent: None,
+            connecting: None,
+            connection_error: None,
+            menu_idle_since: Instant::now(),
+            result: None,
+            alerts: Alerts::new(config.alerts.mode, config.accessibility.reduced_motion),
+            bell_cues: config.sound.bell.then(|| BellCues::new(config.sound.bell_patterns)),
+            diagnostics,
+            out: Letterbox::new(out),
+            skin: Skin::by_name(&config.display.skin).unwrap_or(&skin::ASCII),
+            theme: theme::CLASSIC,
+            colored: config.display.colored,
+            gradients: Gradients::new(depth, background).filter(|_| config.display.colored),
+            background,
+            color_depth: depth,
+            ensure_visible: config.display.ensure_visible,
+            contrast_warnings: Vec::new(),
+            contrast_adjusted: false,
+            bindings,
+            movement: HeldMovement::new(config.input.move_speed, Duration::from_millis(config.input.hold_timeout)),
+            movement_two: HeldMovement::new(config.input.move_speed, Duration::from_millis(config.input.hold_timeout)),
+            players: Players::One,
+            konami: KeySequence::new(&KONAMI_CODE),
+            rainbow_bullets: false,
+            volumes: (config.sound.music_volume, config.sound.effects_volume),
+            settings_error: None,
+            debug_overlay: false,
+            perf: PerfStats::default(),
+            entity_overlay: false,
+            inspector: Inspector::default(),
+            idle: IdleAnimation::default(),
+            starfield: Starfield::new(config.display.star_layers, !config.accessibility.reduced_motion),
+            shake: ScreenShake::new(!config.accessibility.reduced_motion),
+            stats: RunStats::default(),
+            animate_menu: !config.accessibility.reduced_motion,
+            formation_wobble: config.display.formation_wobble && !config.accessibility.reduced_motion,
+            redraw: true,
+            last_frame: None,
+            renderer: config.display.renderer,
+            frame_rate: config.display.frame_rate,
+            speed: if tournament.is_none() { config.game.speed } else { 1.0 },
+            // Images stand in for the skin's glyphs, so pixel renderers go without
+            graphics: Graphics::detect().filter(|_| {
+                config.display.graphics && config.display.colored && config.display.renderer == Renderer::Cells
+            }),
+            last_images: None,
+            border_flashing: false,
+            last_sidebar: None,
+            last_status: None,
+            controllers: Controllers::default(),
+            #[cfg(feature = "gamepad")]
+            gamepads: Gamepads::new(),
+            #[cfg(feature = "audio")]
+            audio: Audio::start(config.sound.music_volume, config.sound.effects_volume),
+            // Assists aren't part of any tournament ruleset
+            wingman_enabled: config.wingman.enabled && tournament.is_none(),
+            wingman_aggressiveness: config.wingman.aggressiveness,
+            tournament,
+            input_log: None,
+            replay: None,
+            ghost_log: None,
+            ghost: None,
+            terminal_size: terminal::size()
+                .map(|(columns, rows)| (columns as usize, rows as usize))
+                .unwrap_or((game::DEFAULT_WIDTH + 2, game::DEFAULT_HEIGHT + 2 + HUD_ROWS)),
+            configured_size: (config.display.width, config.display.height),
+        };
+        app.set_theme(Theme::by_name(&config.display.theme).unwrap_or(&theme::CLASSIC));
+        app.fit_playfield();
+        app
+    }
+
+    /// Whether a run is in progress, paused or not
+    fn in_run(&self) -> bool {
+        matches!(self.state, GameState::Playing | GameState::Paused | GameState::ControllerLost)
+    }
+
+    /// Sizes the playfield of the next run to the terminal
+    ///
+    /// Sizes set in the configuration or by a tournament ruleset are kept.
+    fn fit_playfield(&mut self) {
+        if self.tournament.is_none() {
+            // Leave room for the border and the HUD
+            let (columns, rows) = self.terminal_size;
+            let width = self
+                .configured_size
+                .0
+                .unwrap_or_else(|| columns.saturating_sub(2).clamp(MIN_WIDTH, MAX_WIDTH));
+            let height = self
+                .configured_size
+                .1
+                .unwrap_or_else(|| rows.saturating_sub(2 + HUD_ROWS).clamp(MIN_HEIGHT, MAX_HEIGHT));
+            self.game_builder = self.game_builder.clone().width(width).height(height);
+        }
+        // The results screen keeps showing the finished run
+        if !matches!(self.state, GameState::GameOver { .. }) {
+            self.view = self.game_builder.build().snapshot();
+        }
+    }
+
+    /// Terminal size needed by the current screen: the playfield plus the
+    /// border around it and the HUD rows
+    ///
+    /// # Returns
+    /// The number of columns and rows needed
+    fn required_size(&self) -> (usize, usize) {
+        let (width, height) = if self.in_run() {
+            (self.view.width, self.view.height)
+        } else {
+            let next = self.game_builder.modifiers();
+            (next.width, next.height)
+        };
+        (width + 2, height + 2 + HUD_ROWS)
+    }
+
+    /// Whether the terminal is big enough for the current screen
+    fn fits(&self) -> bool {
+        let (columns, rows) = self.required_size();
+        self.terminal_size.0 >= columns && self.terminal_size.1 >= rows
+    }
+
+    /// Reacts to the terminal being resized
+    ///
+    /// Between runs the playfield is refitted to the new size. A run keeps
+    /// its playfield, so if it no longer fits the run is paused until the
+    /// terminal grows again.
+    ///
+    /// # Arguments
+    /// * `columns` - New width of the terminal
+    /// * `rows` - New height of the terminal
+    fn handle_resize(&mut self, columns: u16, rows: u16) {
+        self.terminal_size = (columns as usize, rows as usize);
+        // Whatever the terminal made of the old screen has to go
+        self.out.reset();
+        if !self.in_run() {
+            self.fit_playfield();
+        }
+        if self.state == GameState::Playing && !self.fits() {
+            self.state = GameState::Paused;
+        }
+        self.last_frame = None;
+        self.redraw = true;
+    }
+
+    /// Starts a fresh run and switches to the `Playing` state
+    ///
+    /// A networked run can't be played again, as it was agreed on with the
+    /// other computer; this does nothing after one.
+    fn start_game(&mut self) {
+        if self.players == Players::Online {
+            return;
+        }
+        self.watching = false;
+        self.watched = None;
+        // A ghost is raced on its own run's seed and settings, wingman included
+        self.ghost = self.ghost_log.as_ref().filter(|_| self.players == Players::One).map(Ghost::new);
+        let (seed, mut game) = match &self.ghost_log {
+            Some(log) if self.ghost.is_some() => (log.seed, log.start()),
+            _ => {
+                // Tournament runs all use the ruleset's seed
+                let seed = match (&self.tournament, self.seed) {
+                    (Some(token), _) => token.ruleset.seed,
+                    (None, Some(seed)) => seed,
+                    (None, None) => rand::random(),
+                };
+                let game = self.game_builder.clone().seed(seed)
+                    .two_players(self.players == Players::CoOp)
+                    .versus(self.players == Players::Versus)
+                    .build();
+                (seed, game)
+            }
+        };
+        self.shake.reset();
+        self.stats = RunStats::default();
+        self.inspector = Inspector::default();
+        self.stop_movement();
+        if self.wingman_enabled && self.ghost.is_none() {
+            game.toggle_wingman(self.wingman_aggressiveness);
+        }
+        self.input_log = Some(InputLog::new(seed, game.modifiers().clone()));
+        // Arcade sessions would all write the same file, which no guest can watch
+        self.replay = if self.arcade { None } else { ReplayStream::create(seed, game.modifiers()).ok() };
+        self.view = game.snapshot();
+        self.sim = Some(Simulation::spawn(game, self.tick_interval()));
+        self.last_tick = Instant::now();
+        self.result = None;
+        self.run_started = SystemTime::now();
+        self.state = GameState::Playing;
+    }
+
+    /// Continues the run put aside when the game was last quit part way
+    /// through, paused so the player can get ready
+    fn continue_run(&mut self) {
+        let Some(run) = self.suspended.take() else { return };
+        self.watching = false;
+        self.watched = None;
+        // Otherwise the run could be continued from the same point again and again
+        let _ = SuspendedRun::discard();
+        self.shake.reset();
+        self.stats = RunStats::default();
+        self.inspector = Inspector::default();
+        self.stop_movement();
+        self.players = Players::of(&run.log.modifiers);
+        self.ghost = None;
+        self.opponent = None;
+        let mut game = run.log.replay_with(&mut [&mut self.stats]);
+        if game.wingman.is_some() != run.has_wingman {
+            game.toggle_wingman(self.wingman_aggressiveness);
+        }
+        self.wingman_enabled = run.has_wingman;
+        // The new replay file starts with everything played before the break
+        self.replay = ReplayStream::create(run.log.seed, &run.log.modifiers)
+            .and_then(|mut replay| {
+                for (tick, inputs) in &run.log.inputs {
+                    replay.record(*tick, inputs)?;
+                }
+                // So seeking doesn't play the whole first part again
+                replay.checkpoint(&game.save())?;
+                Ok(replay)
+            })
+            .ok();
+        self.input_log = Some(run.log);
+        self.view = game.snapshot();
+        let mut sim = Simulation::spawn(game, self.tick_interval());
+        sim.set_paused(true);
+        self.sim = Some(sim);
+        self.last_tick = Instant::now();
+        self.result = None;
+        self.run_started = SystemTime::now();
+        self.state = GameState::Paused;
+    }
+
+    /// Plays a recorded run back, from its first tick
+    ///
+    /// Nothing about the run is recorded again: it earns no medals or
+    /// scores, and isn't written to the replay file.
+    ///
+    /// # Arguments
+    /// * `log` - The recorded run, e.g. from an input script
+    fn watch_replay(&mut self, log: InputLog) {
+        self.watched = None;
+        self.play_back(log.start(), log.inputs.into_iter());
+    }
+
+    /// Plays a replay file back, from its first tick; the arrow keys then
+    /// seek through it
+    ///
+    /// # Arguments
+    /// * `file` - The replay file, opened to seek through
+    fn watch_replay_file(&mut self, file: ReplayFile) {
+        self.watched = Some(file);
+        self.seek_replay(0);
+    }
+
+    /// Picks the watched replay file up again at another tick
+    ///
+    /// The run is left where it is if the file can no longer be read.
+    ///
+    /// # Arguments
+    /// * `tick` - The tick to play on from, past the end meaning the last
+    fn seek_replay(&mut self, tick: u64) {
+        let Some(file) = &self.watched else { return };
+        let Ok((game, inputs)) = file.seek(tick.min(file.log().ticks)) else { return };
+        self.play_back(game, inputs);
+    }
+
+    /// Plays a recorded run back from wherever its game is
+    ///
+    /// # Arguments
+    /// * `game` - The run's game, before the first tick to play back
+    /// * `inputs` - Commands of each later tick that had any, in tick order
+    fn play_back<I>(&mut self, game: Game, inputs: I)
+    where
+        I: Iterator<Item = (u64, Vec<PlayerCommand>)> + Send + 'static,
+    {
+        self.shake.reset();
+        self.stats = RunStats::default();
+        self.inspector = Inspector::default();
+        self.stop_movement();
+        self.players = Players::of(game.modifiers());
+        self.input_log = None;
+        self.replay = None;
+        self.ghost = None;
+        self.opponent = None;
+        self.view = game.snapshot();
+        self.sim = Some(Simulation::spawn_playback(game, inputs, self.tick_interval()));
+        self.last_tick = Instant::now();
+        self.result = None;
+        self.watching = true;
+        self.state = GameState::Playing;
+    }
+
+    /// Starts the demo, a wave played by the hunter bot on the title
+    /// screen's behalf, like an arcade cabinet's attract mode
+    ///
+    /// Like a watched replay, nothing about the run is recorded.
+    fn start_demo(&mut self) {
+        let seed = rand::random();
+        let game = self.game_builder.clone().seed(seed).build();
+        self.shake.reset();
+        self.stats = RunStats::default();
+        self.inspector = Inspector::default();
+        self.stop_movement();
+        self.players = Players::One;
+        self.input_log = None;
+        self.replay = None;
+        self.ghost = None;
+        self.view = game.snapshot();
+        self.sim = Some(Simulation::spawn(game, self.tick_interval()));
+        self.last_tick = Instant::now();
+        self.result = None;
+        self.watching = false;
+        self.watched = None;
+        self.opponent = None;
+        self.demo = Some(Strategy::Hunter.bot(seed));
+        self.state = GameState::Playing;
+    }
+
+    /// Waits in the background for another computer to join a networked
+    /// co-op run, sized to this terminal
+    ///
+    /// # Arguments
+    /// * `address` - Address and TCP port to listen on
+    fn host(&mut self, address: SocketAddr) {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let modifiers = self.game_builder.clone().two_players(true).modifiers();
+        let (sender, receiver) = mpsc::channel();
+        // Nobody is listening any more if the player quit while waiting
+        thread::spawn(move || {
+            let _ = sender.send(Peer::host(address, seed, modifiers));
+        });
+        self.connecting = Some((receiver, format!("Waiting for another player to join on {}", address)));
+        self.state = GameState::Connecting;
+    }
+
+    /// Connects in the background to a networked co-op run hosted on another
+    /// computer
+    ///
+    /// # Arguments
+    /// * `address` - The host's address and port
+    fn join(&mut self, address: String) {
+        let description = format!("Connecting to {}", address);
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(Peer::join(address.as_str()));
+        });
+        self.connecting = Some((receiver, description));
+        self.state = GameState::Connecting;
+    }
+
+    /// Starts the networked run agreed on with the other computer
+    ///
+    /// # Arguments
+    /// * `session` - The run, and the connection it is played over
+    fn start_online_game(&mut self, session: Session) {
+        let Session { seed, modifiers, peer } = session;
+        let log = InputLog::new(seed, modifiers);
+        let game = log.start();
+        self.players = Players::Online;
+        // Both computers have to tick at the same rate
+        self.speed = 1.0;
+        self.watching = false;
+        self.watched = None;
+        self.shake.reset();
+        self.stats = RunStats::default();
+        self.inspector = Inspector::default();
+        self.stop_movement();
+        self.replay = ReplayStream::create(seed, &log.modifiers).ok();
+        self.ghost = None;
+        self.opponent = None;
+        self.input_log = Some(log);
+        self.view = game.snapshot();
+        self.sim = Some(Simulation::spawn_networked(game, peer, self.tick_interval()));
+        self.last_tick = Instant::now();
+        self.result = None;
+        self.run_started = SystemTime::now();
+        self.state = GameState::Playing;
+    }
+
+    /// Ends a networked run the other computer dropped out of; nothing about
+    /// the unfinished run is recorded
+    ///
+    /// # Arguments
+    /// * `error` - What happened to the connection
+    fn lose_connection(&mut self, error: String) {
+        self.sim = None;
+        self.replay = None;
+        self.result = None;
+        self.connection_error = Some(error);
+        self.state = GameState::GameOver { won: false };
+        self.redraw = true;
+    }
+
+    /// Stops the demo and goes back to the title menu
+    fn end_demo(&mut self) {
+        self.sim = None;
+        self.demo = None;
+        self.state = GameState::Menu;
+        self.fit_playfield();
+        self.menu_idle_since = Instant::now();
+        self.redraw = true;
+    }
+
+    /// Stops both ships, e.g. when a run starts or is paused
+    fn stop_movement(&mut self) {
+        self.movement.stop();
+        self.movement_two.stop();
+    }
+
+    /// Looks up the command bound to a key, including the second player's
+    /// keys in a co-op or versus run
+    ///
+    /// # Returns
+    /// The command, or `None` if the key is unbound
+    fn command_for(&self, code: KeyCode) -> Option<PlayerCommand> {
+        // Each player of a networked run has a keyboard of their own, and
+        // the computer needs none
+        if matches!(self.players, Players::CoOp | Players::Versus) && self.opponent.is_none() {
+            self.bindings.two_player_command_for(code)
+        } else {
+            self.bindings.command_for(code)
+        }
+    }
+
+    /// Game mode the current run's scores are recorded under
+    fn mode(&self) -> &'static str {
+        match self.players {
+            Players::One => GAME_MODE,
+            Players::CoOp | Players::Online => TWO_PLAYER_MODE,
+            Players::Versus => VERSUS_MODE,
+        }
+    }
+
+    /// Medal thresholds for the current run; two-player runs earn no medals
+    fn medals(&self) -> Option<MedalThresholds> {
+        self.medals.filter(|_| self.players == Players::One)
+    }
+
+    /// Makes this game one session of an arcade: it is played as a guest,
+    /// and nothing but the leaderboard is read from or written to the data
+    /// directory, which every session shares
+    ///
+    /// # Arguments
+    /// * `config` - User configuration, as the game was created with
+    /// * `session` - The player's terminal type and size, as their SSH
+    ///   client gave them; the server's own terminal has nothing to do
+    ///   with theirs
+    fn serve_arcade(&mut self, config: &Config, session: &ssh::Session) {
+        self.arcade = true;
+        self.profile = Profile::guest();
+        self.suspended = None;
+        self.graphics = None;
+        #[cfg(feature = "gamepad")]
+        {
+            self.gamepads = None;
+        }
+        self.color_depth = ColorDepth::guess("", &session.term);
+        self.gradients = Gradients::new(self.color_depth, self.background).filter(|_| self.colored);
+        self.set_theme(Theme::by_name(&config.display.theme).unwrap_or(&theme::CLASSIC));
+        self.handle_resize(session.size.0, session.size.1);
+    }
+
+    /// Puts the run being played aside to continue later, if there is one;
+    /// called when the game is quit
+    ///
+    /// Tournament runs are never put aside, as they are played in one go,
+    /// and nor are runs against the computer, which couldn't be told apart
+    /// from a versus run when continued.
+    fn suspend_run(&mut self) -> io::Result<()> {
+        let online = self.players == Players::Online;
+        let computer = self.demo.is_some() || self.opponent.is_some();
+        if self.sim.is_none() || self.tournament.is_some() || self.watching || computer || online || self.arcade {
+            return Ok(());
+        }
+        let Some(log) = self.input_log.take() else { return Ok(()) };
+        SuspendedRun::new(log, self.view.score, self.view.has_wingman).save()
+    }
+
+    /// Ends the current run, awarding its medal to the profile
+    ///
+    /// # Arguments
+    /// * `won` - Whether the run ended by clearing all enemies
+    fn finish_run(&mut self, won: bool) {
+        #[cfg(feature = "audio")]
+        if !won {
+            self.audio.play(Sound::GameOver);
+        }
+        self.sim = None;
+        self.replay = None;
+        // A recording already earned its rewards when it was played
+        if self.watching {
+            self.result = None;
+            self.state = GameState::GameOver { won };
+            self.redraw = true;
+            return;
+        }
+        let medal = self.medals().and_then(|m| m.medal_for(self.view.score));
+        let new_best = medal.is_some_and(|m| self.profile.record_medal(self.mode(), m));
+        // A lost run ends part way through the wave after the last one cleared
+        let wave = self.stats.waves_cleared + usize::from(!won);
+        self.profile.record_score(self.mode(), self.view.score, wave);
+        self.profile.lifetime.add(&self.stats, self.view.elapsed);
+        let save_error = if self.arcade { None } else { self.profile.save().err().map(|e| e.to_string()) };
+        let export_error = self.export_path.as_ref().and_then(|path| {
+            let seed = self.input_log.as_ref().map(|log| log.seed);
+            let summary = RunSummary::new(self.mode(), &self.stats, won, self.run_started, seed, &self.view);
+            summary.write(path).err().map(|e| e.to_string())
+        });
+        let tournament_result = self.tournament.as_ref().map(|token| {
+            let record = RunRecord {
+                token: token.as_str().to_string(),
+                score: self.view.score,
+                ticks: self.view.tick_count,
+                inputs: self.input_log.take().map(|log| log.inputs).unwrap_or_default(),
+            };
+            record.save(&token.ruleset).map_err(|e| e.to_string())
+        });
+        self.result = Some(RunResult {
+            medal,
+            new_best,
+            save_error,
+            tournament_result,
+            leaderboard_result: None,
+            export_error,
+        });
+        // Tournament runs are ranked by the organizer instead, and the
+        // leaderboard is for one-player runs only
+        let ranked = self.tournament.is_none() && self.players == Players::One;
+        // Other games sharing the leaderboard may have recorded runs since
+        self.leaderboard = Leaderboard::load();
+        self.state = if ranked && self.leaderboard.qualifies(self.view.score) {
+            GameState::Initials { won, letters: default_initials(&self.profile.name), cursor: 0 }
+        } else {
+            GameState::GameOver { won }
+        };
+        self.redraw = true;
+    }
+
+    /// Handles a key press according to the current state
+    ///
+    /// # Arguments
+    /// * `code` - The key that was pressed
+    fn handle_key(&mut self, code: KeyCode) {
+        self.redraw = true;
+        self.menu_idle_since = Instant::now();
+        // Any key ends the demo, and does nothing else
+        if self.demo.is_some() {
+            self.end_demo();
+            return;
+        }
+        // Only quitting works until the terminal is big enough again
+        if !self.fits() {
+            self.quit = code == KeyCode::Esc;
+            return;
+        }
+        if code == KeyCode::F(3) {
+            self.debug_overlay = !self.debug_overlay;
+            return;
+        }
+        if code == KeyCode::F(4) {
+            self.entity_overlay = !self.entity_overlay;
+            self.redraw = true;
+            return;
+        }
+        #[cfg(feature = "audio")]
+        if matches!(code, KeyCode::Char('m' | 'M')) && self.command_for(code).is_none() {
+            self.audio.toggle_mute();
+            return;
+        }
+        if self.state == GameState::Menu && self.konami.push(code) {
+            self.rainbow_bullets = !self.rainbow_bullets;
+            return;
+        }
+        match self.state {
+            GameState::Menu => match code {
+                KeyCode::Enter => {
+                    self.players = Players::One;
+                    self.opponent = None;
+                    self.start_game()
+                }
+                // Tournaments are played alone
+                KeyCode::Char('2') if self.tournament.is_none() => {
+                    self.players = Players::CoOp;
+                    self.opponent = None;
+                    self.start_game()
+                }
+                KeyCode::Char('v') | KeyCode::Char('V') if self.tournament.is_none() => {
+                    self.players = Players::Versus;
+                    self.opponent = None;
+                    self.start_game()
+                }
+                KeyCode::Char('b') | KeyCode::Char('B') if self.tournament.is_none() => {
+                    self.players = Players::Versus;
+                    self.opponent = Some(Box::new(Invaders::default()));
+                    self.start_game()
+                }
+                KeyCode::Char('r') | KeyCode::Char('R') => self.continue_run(),
+                KeyCode::Char('g') | KeyCode::Char('G') => self.state = GameState::GlyphTest,
+                KeyCode::Char('t') | KeyCode::Char('T') => self.set_theme(self.theme.next()),
+                KeyCode::Char('s') | KeyCode::Char('S') => self.state = GameState::Settings { selected: 0 },
+                KeyCode::Char('l') | KeyCode::Char('L') => self.state = GameState::Stats,
+                KeyCode::Char('c') | KeyCode::Char('C') if self.controllers.connected.len() > 1 => {
+                    let selected = self
+                        .controllers
+                        .connected
+                        .iter()
+                        .position(|c| self.controllers.is_assigned(c.id))
+                        .unwrap_or(0);
+                    self.state = GameState::Controllers { selected };
+                }
+                KeyCode::Esc => self.quit = true,
+                _ => {}
+            },
+            GameState::Playing => match code {
+                KeyCode::Left | KeyCode::Right | KeyCode::Home if self.watched.is_some() => {
+                    let tick = self.view.tick_count;
+                    self.seek_replay(match code {
+                        KeyCode::Left => tick.saturating_sub(SEEK_TICKS),
+                        KeyCode::Right => tick + SEEK_TICKS,
+                        _ => 0,
+                    });
+                }
+                _ => {
+                    if let Some(command) = self.command_for(code) {
+                        self.handle_command(command);
+                    }
+                }
+            },
+            GameState::Paused => match code {
+                _ if self.command_for(code) == Some(PlayerCommand::Pause) => self.state = GameState::Playing,
+                KeyCode::Char('w') | KeyCode::Char('W') if self.tournament.is_none() && self.players == Players::One => {
+                    if let Some(sim) = &self.sim {
+                        sim.send(Control::ToggleWingman(self.wingman_aggressiveness));
+                    }
+                }
+                KeyCode::Esc => self.quit = true,
+                _ => {}
+            },
+            GameState::GameOver { .. } => match code {
+                KeyCode::Enter => self.start_game(),
+                KeyCode::Esc => self.quit = true,
+                _ => {}
+            },
+            GameState::GlyphTest => match code {
+                KeyCode::Char('a') | KeyCode::Char('A') => self.skin = &skin::ASCII,
+                KeyCode::Enter | KeyCode::Esc => self.state = GameState::Menu,
+                _ => {}
+            },
+            GameState::ConfigErrors => match code {
+                KeyCode::Enter => self.state = GameState::Menu,
+                KeyCode::Esc => self.quit = true,
+                _ => {}
+            },
+            GameState::ControllerLost => match code {
+                KeyCode::Char('p') | KeyCode::Char('P') => self.state = GameState::Playing,
+                KeyCode::Esc => self.quit = true,
+                _ => {}
+            },
+            GameState::Connecting => self.quit = code == KeyCode::Esc,
+            GameState::Controllers { selected } => {
+                let count = self.controllers.connected.len();
+                match code {
+                    KeyCode::Up => {
+                        self.state = GameState::Controllers { selected: selected.saturating_sub(1) }
+                    }
+                    KeyCode::Down => {
+                        let selected = (selected + 1).min(count.saturating_sub(1));
+                        self.state = GameState::Controllers { selected };
+                    }
+                    KeyCode::Enter => {
+                        if let Some(controller) = self.controllers.connected.get(selected) {
+                            self.controllers.assign(controller.id);
+                        }
+                        self.state = GameState::Menu;
+                    }
+                    KeyCode::Esc => self.state = GameState::Menu,
+                    _ => {}
+                }
+            }
+            GameState::Initials { won, mut letters, mut cursor } => {
+                match code {
+                    KeyCode::Up | KeyCode::Down => {
+                        let offset = if code == KeyCode::Up { 1 } else { 25 };
+                        letters[cursor] = (b'A' + (letters[cursor] as u8 - b'A' + offset) % 26) as char;
+                    }
+                    KeyCode::Left | KeyCode::Backspace => cursor = cursor.saturating_sub(1),
+                    KeyCode::Right => cursor = (cursor + 1).min(letters.len() - 1),
+                    KeyCode::Char(c) if c.is_ascii_alphabetic() => {
+                        letters[cursor] = c.to_ascii_uppercase();
+                        cursor = (cursor + 1).min(letters.len() - 1);
+                    }
+                    KeyCode::Enter => {
+                        let saved = self.leaderboard.record(letters.iter().collect(), self.view.score).map_err(|e| e.to_string());
+                        if let Some(result) = &mut self.result {
+                            result.leaderboard_result = Some(saved);
+                        }
+                        self.state = GameState::GameOver { won };
+                        return;
+                    }
+                    _ => {}
+                }
+                self.state = GameState::Initials { won, letters, cursor };
+            }
+            GameState::Stats => {
+                if matches!(code, KeyCode::Enter | KeyCode::Esc) {
+                    self.state = GameState::Menu;
+                }
+            }
+            GameState::Settings { selected } => match code {
+                KeyCode::Up => self.state = GameState::Settings { selected: (selected + SETTINGS - 1) % SETTINGS },
+                KeyCode::Down => self.state = GameState::Settings { selected: (selected + 1) % SETTINGS },
+                KeyCode::Left | KeyCode::Right => {
+                    let step = if code == KeyCode::Left { -0.1 } else { 0.1 };
+                    let (value, min, max) = match selected {
+                        0 => (&mut self.volumes.0, 0.0, 1.0),
+                        1 => (&mut self.volumes.1, 0.0, 1.0),
+                        // Tournament runs are all played at normal speed
+                        _ if self.tournament.is_some() => return,
+                        _ => (&mut self.speed, MIN_SPEED, MAX_SPEED),
+                    };
+                    *value = ((*value + step) * 10.0).round().clamp(min * 10.0, max * 10.0) / 10.0;
+                    #[cfg(feature = "audio")]
+                    self.audio.set_volumes(self.volumes.0, self.volumes.1);
+                }
+                // Arcade players share the server's configuration, so theirs
+                // last as long as their session
+                KeyCode::Enter | KeyCode::Esc if self.arcade => {
+                    self.settings_error = None;
+                    self.state = GameState::Menu;
+                }
+                KeyCode::Enter | KeyCode::Esc => {
+                    // Rounded so the file gets 0.7 rather than 0.699999988
+                    let round = |value: f32| (value as f64 * 10.0).round() / 10.0;
+                    let values = [("music_volume", round(self.volumes.0)), ("effects_volume", round(self.volumes.1))];
+                    let stored = Config::store("sound", &values).and_then(|()| match self.tournament {
+                        Some(_) => Ok(()),
+                        None => Config::store("game", &[("speed", round(self.speed))]),
+                    });
+                    match stored {
+                        Ok(()) => {
+                            self.settings_error = None;
+                            self.state = GameState::Menu;
+                        }
+                        Err(e) => self.settings_error = Some(format!("Could not save the settings: {}", e)),
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    /// Carries out a player command from the keyboard or a controller
+    ///
+    /// # Arguments
+    /// * `command` - The command to carry out
+    fn handle_command(&mut self, command: PlayerCommand) {
+        if !self.fits() {
+            return;
+        }
+        match (self.state, command) {
+            (GameState::Playing, PlayerCommand::Pause) => {
+                self.stop_movement();
+                self.state = GameState::Paused
+            }
+            (GameState::Playing, PlayerCommand::Quit) => self.quit = true,
+            (GameState::Playing, command) => {
+                let command = match command {
+                    PlayerCommand::MoveLeft | PlayerCommand::MoveRight => self.movement.press(command),
+                    // In a versus run these pick the invaders' column, a step per press
+                    PlayerCommand::PlayerTwoLeft | PlayerCommand::PlayerTwoRight if self.players == Players::CoOp => {
+                        self.movement_two.press(command)
+                    }
+                    command => Some(command),
+                };
+                if let (Some(sim), Some(command)) = (&self.sim, command) {
+                    sim.send(Control::Command(command));
+                }
+            }
+            (GameState::Paused | GameState::ControllerLost, PlayerCommand::Pause) => {
+                self.state = GameState::Playing
+            }
+            (GameState::Menu | GameState::GameOver { .. }, PlayerCommand::Pause) => {
+                self.start_game()
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles a key being let go, on terminals that report it
+    ///
+    /// # Arguments
+    /// * `code` - The key that was let go
+    fn handle_key_release(&mut self, code: KeyCode) {
+        match self.command_for(code) {
+            Some(command @ (PlayerCommand::MoveLeft | PlayerCommand::MoveRight)) => self.movement.release(command),
+            Some(command @ (PlayerCommand::PlayerTwoLeft | PlayerCommand::PlayerTwoRight)) => {
+                self.movement_two.release(command)
+            }
+            _ => {}
+        }
+    }
+
+    /// Picks up controller hot-plug events and button presses
+    fn poll_controllers(&mut self) {
+        #[cfg(feature = "gamepad")]
+        let events = self.gamepads.as_mut().map(Gamepads::poll).unwrap_or_default();
+        #[cfg(not(feature = "gamepad"))]
+        let events: Vec<ControllerEvent> = Vec::new();
 
+        for event in events {
+            self.handle_controller_event(event);
+        }
+    }
+
+    /// Reacts to a controller being plugged in, unplugged or used
+    ///
+    /// Unplugging the player's controller mid-run freezes the run until a
+    /// controller is reconnected or the player carries on with the keyboard.
+    ///
+    /// # Arguments
+    /// * `event` - What the controller backend reported
+    fn handle_controller_event(&mut self, event: ControllerEvent) {
+        self.redraw = true;
+        match event {
+            ControllerEvent::Connected { id, name } => {
+                self.controllers.connect(id, name);
+                if self.state == GameState::ControllerLost && self.controllers.is_assigned(id) {
+                    self.state = GameState::Paused;
+                }
+            }
+            ControllerEvent::Disconnected { id } => {
+                let was_players = self.controllers.disconnect(id);
+                if was_players && matches!(self.state, GameState::Playing | GameState::Paused) {
+                    self.state = GameState::ControllerLost;
+                }
+                if let GameState::Controllers { .. } = self.state {
+                    self.state = GameState::Menu;
+                }
+            }
+            ControllerEvent::Command { .. } if self.demo.is_some() => self.end_demo(),
+            ControllerEvent::Command { id, command } if self.controllers.is_assigned(id) => {
+                self.menu_idle_since = Instant::now();
+                self.handle_command(command)
+            }
+            _ => {}
+        }
+    }
+
+    /// Advances the current state; `Playing` takes in the simulation's
+    /// ticks and the formation wobble, `Menu` its idle animation and, once
+    /// left alone long enough, the demo
+    ///
+    /// The simulation ticks on its own thread and is only paused or resumed
+    /// here to follow the current state.
+    fn update(&mut self) {
+        let now = Instant::now();
+        let frame_time = now - self.last_update;
+        self.last_update = now;
+
+        let animating = match self.state {
+            GameState::Menu => self.animate_menu,
+            GameState::Playing => self.formation_wobble,
+            _ => false,
+        };
+        if animating && self.idle.advance(frame_time) {
+            self.redraw = true;
+        }
+        // The demo is all motion, so it stays off with reduced motion
+        if self.state == GameState::Menu
+            && self.animate_menu
+            && self.fits()
+            && self.menu_idle_since.elapsed() >= DEMO_DELAY
+        {
+            self.start_demo();
+        }
+        if self.spectators.as_ref().is_some_and(|catch_up| catch_up.swap(false, Ordering::Relaxed)) {
+            // Cells already on screen would otherwise be left out
+            self.out.reset();
+            self.last_frame = None;
+            self.redraw = true;
+        }
+        if let Some((connection, _)) = &self.connecting {
+            match connection.try_recv() {
+                Ok(Ok(session)) => {
+                    self.connecting = None;
+                    self.start_online_game(session);
+                }
+                Ok(Err(e)) => {
+                    self.connecting = None;
+                    self.connection_error = Some(e.to_string());
+                    self.redraw = true;
+                }
+                Err(_) => {}
+            }
+        }
+        // The music speeds up as the invaders come down, and only plays
+        // during a run; the demo plays silently
+        #[cfg(feature = "audio")]
+        {
+            let height = self.view.height as f32;
+            let descent = inspector::formation_bounds(&self.view).map(|(_, _, _, bottom)| bottom as f32 / height);
+            let playing = self.state == GameState::Playing && self.demo.is_none();
+            self.audio.set_music(descent.filter(|_| playing));
+        }
+
+        let Some(sim) = &mut self.sim else { return };
+        sim.set_paused(self.state != GameState::Playing);
+        while let Some(update) = sim.try_recv() {
+            match update {
+                Update::Ticked { snapshot, events, inputs, checkpoint } => {
+                    self.perf.record_tick();
+                    for command in self.movement.tick().into_iter().chain(self.movement_two.tick()) {
+                        sim.send(Control::Command(command));
+                    }
+                    for bot in self.demo.iter_mut().chain(&mut self.opponent) {
+                        for command in bot.decide(&snapshot) {
+                            sim.send(Control::Command(command));
+                        }
+                    }
+                    if let Some(replay) = &mut self.replay {
+                        let written = replay.record(snapshot.tick_count, &inputs);
+                        if written.and_then(|_| checkpoint.map_or(Ok(()), |game| replay.checkpoint(&game))).is_err() {
+                            self.replay = None;
+                        }
+                    }
+                    if let Some(log) = &mut self.input_log {
+                        log.record(snapshot.tick_count, &inputs);
+                    }
+                    if let Some(ghost) = &mut self.ghost {
+                        ghost.advance(snapshot.tick_count);
+                    }
+                    self.shake.tick();
+                    for event in &events {
+                        self.alerts.on_event(event);
+                        self.shake.on_event(event);
+                        self.stats.on_event(event);
+                        if let Some(cues) = &mut self.bell_cues {
+                            cues.on_event(event);
+                        }
+                        #[cfg(feature = "audio")]
+                        if self.demo.is_none() {
+                            self.audio.on_event(event);
+                        }
+                    }
+                    self.inspector.observe(&snapshot, &events);
+                    self.alerts.tick();
+                    let cue = self.bell_cues.as_mut().is_some_and(BellCues::tick);
+                    if (self.alerts.take_bell() || cue) && self.demo.is_none() {
+                        // A bell that can't be rung isn't worth ending the run over
+                        let _ = self.out.bell();
+                    }
+
+                    self.view = snapshot;
+                    self.last_tick = now;
+                    if let Some(won) = self.view.outcome() {
+                        if self.demo.is_some() {
+                            self.end_demo();
+                        } else {
+                            self.finish_run(won);
+                        }
+                        return;
+                    }
+                }
+                Update::Changed(snapshot) => {
+                    self.wingman_enabled = snapshot.has_wingman;
+                    self.view = snapshot;
+                    self.redraw = true;
+                }
+                Update::Disconnected(error) => {
+                    self.lose_connection(error);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Switches to a color theme, checking it against the terminal
+    ///
+    /// Hard-to-see colors are reported on the title menu, or fixed up if
+    /// `ensure_visible` is set.
+    fn set_theme(&mut self, theme: &Theme) {
+        let mut theme = theme.clone();
+        self.contrast_warnings = contrast::check(&theme, self.background, self.color_depth);
+        if self.ensure_visible {
+            contrast::ensure_visible(&mut theme, self.background, self.color_depth);
+        }
+        self.contrast_adjusted = self.ensure_visible && !self.contrast_warnings.is_empty();
+        self.theme = theme;
+    }
+
+    /// Real time between two ticks at the chosen game speed
+    fn tick_interval(&self) -> Duration {
+        TICK_DURATION.div_f32(self.speed)
+    }
+
+    /// How far the simulation is between the last tick and the next, from 0.0 to 1.0
+    fn alpha(&self) -> f32 {
+        (self.last_tick.elapsed().as_secs_f32() / self.tick_interval().as_secs_f32()).min(1.0)
+    }
+
+    /// Shade of the sky behind a playfield row, if the sky is shaded
+    fn backdrop(&self, y: usize) -> Option<Color> {
+        self.gradients.as_ref().map(|gradients| gradients.backdrop(y, self.view.height))
+    }
+
+    /// Whether the screen has changed since it was last rendered
+    ///
+    /// A running game changes every frame; every other screen only changes
+    /// on input or when its animation moves on.
+    fn needs_render(&self) -> bool {
+        self.state == GameState::Playing || self.redraw
+    }
+
+    /// How long to wait between frames in the current state
+    ///
+    /// Only a running game renders at the configured frame rate; the other
+    /// screens run at the idle animation's much lower rate (and wake early
+    /// for input).
+    fn frame_interval(&self) -> Duration {
+        let interval = match self.state {
+            GameState::Playing => Duration::from_secs(1) / self.frame_rate,
+            _ => idle::FRAME_INTERVAL,
+        };
+        // Controllers can't wake the loop early, so check them more often
+        #[cfg(feature = "gamepad")]
+        if self.gamepads.is_some() {
+            return interval.min(Duration::from_secs(1) / self.frame_rate);
+        }
+        interval
+    }
+
+    /// Renders the screen for the current state
+    ///
+    /// # Returns
+    /// A `Result` indicating successful rendering or an error
+    fn render(&mut self) -> io::Result<()> {
+        // A terminal larger than the game gets it centered, with a margin
+        let (mut columns, rows) = self.required_size();
+        if self.terminal_size.0 >= columns + SIDEBAR_WIDTH {
+            columns += SIDEBAR_WIDTH;
+        }
+        if self.out.fit(self.terminal_size, (columns, rows)) {
+            self.last_frame = None;
+        }
+        self.out.set_margin(self.skin.border.margin, self.colored.then_some(self.theme.ui.muted));
+        // Other screens draw over the playfield, so its next frame starts afresh
+        if self.state != GameState::Playing || !self.fits() {
+            self.last_frame = None;
+        }
+        // The playfield works out for itself whether it needs a clean screen
+        let playfield = matches!(self.state, GameState::Playing | GameState::Paused | GameState::ControllerLost);
+        self.out.begin_frame(!playfield || !self.fits())?;
+        if !playfield || !self.fits() {
+            self.clear_images()?;
+        }
+        if !self.fits() {
+            self.render_too_small()?;
+            return self.out.present();
+        }
+        match self.state {
+            GameState::Menu => {
+                let mid = self.view.height / 2;
+                if self.animate_menu {
+                    self.render_menu_idle(mid)?;
+                }
+                let title_top = mid - 1 - TITLE_ART.len();
+                for (i, line) in TITLE_ART.iter().enumerate() {
+                    self.draw_centered(title_top + i, line, self.theme.ui.title)?;
+                }
+                if let Some(token) = &self.tournament {
+                    let line = format!(
+                        "Tournament: {} (organizer {})",
+                        token.ruleset.name,
+                        token.organizer_fingerprint()
+                    );
+                    self.draw_centered(mid - 1, &line, self.theme.ui.highlight)?;
+                }
+                if let Some(run) = &self.suspended {
+                    let line = format!("R: continue your last run (score {})", run.score);
+                    self.draw_centered(mid - 1, &line, self.theme.ui.highlight)?;
+                }
+                // The prompt blinks with the idle animation, and stays lit without it
+                if !self.animate_menu || self.idle.blink_on() {
+                    let prompt = if self.tournament.is_none() {
+                        "Enter: play  2: co-op  V: versus  B: vs computer"
+                    } else {
+                        "Press Enter to start"
+                    };
+                    self.draw_centered(mid, prompt, self.theme.ui.text)?;
+                }
+                if let Some(&high_score) = self.profile.high_scores.get(GAME_MODE) {
+                    let wave = self.profile.best_waves.get(GAME_MODE).copied().unwrap_or(1);
+                    let line = format!("High Score: {}   Best wave: {}", high_score, wave);
+                    self.draw_centered(mid + 1, &line, self.theme.ui.score)?;
+                }
+                if self.rainbow_bullets {
+                    self.draw_centered(self.view.height - 2, "Rainbow bullets unlocked!", self.theme.ui.highlight)?;
+                }
+                self.draw_centered(mid + 2, "Arrows: move   Space: shoot   P: pause   Esc: quit", self.theme.ui.muted)?;
+                let theme = format!("T: color theme ({})   S: settings   L: stats", self.theme.name);
+                self.draw_centered(mid + 3, &theme, self.theme.ui.muted)?;
+                self.draw_centered(mid + 4, "G: check that your font shows every glyph", self.theme.ui.muted)?;
+                let controllers = self.controllers.connected.len();
+                if controllers > 1 {
+                    let line = format!("C: choose controller ({} connected)", controllers);
+                    self.draw_centered(mid + 5, &line, self.theme.ui.muted)?;
+                }
+                let warnings = self.render_contrast_warnings(mid + 6)?;
+                // The unlock notice takes the row the leaderboard would end on
+                let bottom = if self.rainbow_bullets { self.view.height - 2 } else { self.view.height };
+                self.render_leaderboard(mid + 6 + warnings, bottom, None)?;
+            }
+            GameState::GlyphTest => self.render_glyph_test()?,
+            GameState::Playing => {
+                self.render_playfield()?;
+                if self.demo.is_some() {
+                    let mid = self.view.height / 2;
+                    self.draw_centered(mid, " DEMO - press any key ", self.theme.ui.highlight)?;
+                }
+                // The other computer is paused, or its commands are late
+                if self.players == Players::Online && self.last_tick.elapsed() > WAIT_NOTICE {
+                    let mid = self.view.height / 2;
+                    self.draw_centered(mid, " Waiting for the other player ", self.theme.ui.highlight)?;
+                }
+            }
+            GameState::Paused => {
+                self.render_playfield()?;
+                let mid = self.view.height / 2;
+                self.draw_centered(mid, " PAUSED - press P to resume ", self.theme.ui.highlight)?;
+                if self.players == Players::One {
+                    let wingman = if self.view.has_wingman { "on" } else { "off" };
+                    self.draw_centered(mid + 1, &format!(" W: wingman ({}) ", wingman), self.theme.ui.highlight)?;
+                }
+                // Networked runs can't be continued later
+                if self.tournament.is_none() && self.players != Players::Online {
+                    self.draw_centered(mid + 2, " Esc: save and quit ", self.theme.ui.highlight)?;
+                }
+            }
+            GameState::GameOver { won } => {
+                let mid = self.view.height / 2;
+                match (self.players, won) {
+                    (Players::Versus, true) => self.draw_centered(mid - 4, "The ship wins!", self.theme.ui.title)?,
+                    (Players::Versus, false) => self.draw_centered(mid - 4, "The invaders win!", self.theme.ui.title)?,
+                    (_, true) => self.draw_centered(mid - 4, "Congratulations! You won!", self.theme.ui.title)?,
+                    (_, false) => self.draw_centered(mid - 4, "Game Over!", self.theme.ui.danger)?,
+                }
+                let score = match self.view.player_two {
+                    Some(two) => {
+                        let one = self.view.score - two.score;
+                        format!("Final Score: {} (P1: {}  P2: {})", self.view.score, one, two.score)
+                    }
+                    None => format!("Final Score: {}", self.view.score),
+                };
+                self.draw_centered(mid - 2, &score, self.theme.ui.score)?;
+                self.render_run_stats(mid - 1)?;
+                self.render_medal_results(mid + 1)?;
+                let clock = format!(
+                    "Run length: {} ticks ({:.1}s)",
+                    self.view.tick_count,
+                    self.view.elapsed.as_secs_f32()
+                );
+                self.draw_centered(mid + 4, &clock, self.theme.ui.muted)?;
+                let modifiers = format!("Modifiers: {}", self.view.modifiers);
+                self.draw_centered(mid + 5, &modifiers, self.theme.ui.muted)?;
+                // Each note on how the run ended or was saved gets a row of
+                // its own, below which the prompt and leaderboard move down
+                let mut status = Vec::new();
+                match self.result.as_ref().and_then(|r| r.tournament_result.as_ref()) {
+                    Some(Ok(path)) => {
+                        status.push((format!("Tournament result: {}", path.display()), self.theme.ui.highlight));
+                    }
+                    Some(Err(error)) => {
+                        status.push((format!("Could not save tournament result: {}", error), self.theme.ui.danger));
+                    }
+                    None => {}
+                }
+                match self.result.as_ref().and_then(|r| r.leaderboard_result.as_ref()) {
+                    Some(Ok(place)) => status.push((format!("#{} on the leaderboard", place), self.theme.ui.highlight)),
+                    Some(Err(error)) => {
+                        status.push((format!("Could not save the leaderboard: {}", error), self.theme.ui.danger));
+                    }
+                    None => {}
+                }
+                if let Some(error) = self.result.as_ref().and_then(|r| r.export_error.as_ref()) {
+                    status.push((format!("Could not export the run: {}", error), self.theme.ui.danger));
+                }
+                if let Some(error) = &self.connection_error {
+                    status.push((format!("Connection lost: {}", error), self.theme.ui.danger));
+                }
+                let prompt = if self.players == Players::Online {
+                    "Press Esc to quit"
+                } else {
+                    "Press Enter to play again or Esc to quit"
+                };
+                for (row, (line, color)) in status.iter().enumerate() {
+                    self.draw_centered(mid + 6 + row, line, *color)?;
+                }
+                let prompt_row = mid + 6 + status.len();
+                self.draw_centered(prompt_row, prompt, self.theme.ui.text)?;
+                let place = self.result.as_ref().and_then(|r| r.leaderboard_result.clone()?.ok());
+                self.render_leaderboard(prompt_row + 1, self.view.height, place)?;
+            }
+            GameState::Initials { letters, cursor, .. } => self.render_initials(letters, cursor)?,
+            GameState::ConfigErrors => self.render_diagnostics()?,
+            GameState::ControllerLost => {
+                self.render_playfield()?;
+                let mid = self.view.height / 2;
+                self.draw_centered(mid, " CONTROLLER DISCONNECTED ", self.theme.ui.danger)?;
+                self.draw_centered(mid + 1, " Reconnect it, or press P to use the keyboard ", self.theme.ui.highlight)?;
+            }
+            GameState::Controllers { selected } => self.render_controller_picker(selected)?,
+            GameState::Settings { selected } => self.render_settings(selected)?,
+            GameState::Stats => self.render_lifetime_stats()?,
+            GameState::Connecting => {
+                let mid = self.view.height / 2;
+                if let Some((_, description)) = &self.connecting {
+                    let line = format!("{}...", description);
+                    self.draw_centered(mid - 1, &line, self.theme.ui.text)?;
+                }
+                if let Some(error) = &self.connection_error {
+                    let line = format!("Could not connect: {}", error);
+                    self.draw_centered(mid, &line, self.theme.ui.danger)?;
+                }
+                self.draw_centered(mid + 2, "Esc: quit", self.theme.ui.muted)?;
+            }
+        }
+        self.out.present()
+    }
 
-impl Game {
-    /// Creates a new game instance with initial setup
+    /// Renders the playfield inside its border, and the HUD below it, in
+    /// color or as plain text
+    ///
+    /// Only cells that changed since the last frame are redrawn, unless the
+    /// screen was showing something else and has to be drawn in full.
     ///
     /// # Returns
-    /// A new Game with spawned enemies and default player position
-    
-    fn new() -> Self {
-        let mut game = Game {
-            player: GameObject { 
-                x: SCREEN_WIDTH / 2, 
-                y: SCREEN_HEIGHT - 2,  // Moved up slightly
-                alive: true 
-            },
-            enemies: Vec::new(),
-            player_bullets: Vec::new(),
-            enemy_bullets: Vec::new(),
-            score: 0,
-            game_over: false,
-            enemy_move_counter: 0, // Initialize counter
+    /// A `Result` indicating successful rendering or an error
+    fn render_playfield(&mut self) -> io::Result<()> {
+        // Top-left cell of each sprite drawn as an image
+        let mut anchors = Vec::new();
+        let mut frame = match self.renderer {
+            Renderer::Braille => braille::render(&self.view, self.alpha()),
+            Renderer::HalfBlock => halfblock::render(&self.view, self.alpha()),
+            _ => {
+                let mut sprites = self.view.sprites(self.alpha());
+                walk_enemies(&mut sprites);
+                if self.formation_wobble {
+                    self.idle.wobble_formation(&mut sprites);
+                }
+                if self.graphics.is_some() {
+                    for (y, row) in sprites.iter().enumerate() {
+                        for (x, cell) in row.iter().enumerate() {
+                            if let Some(cell) = cell.filter(|c| c.dx == 0 && c.dy == 0 && Graphics::draws(c.sprite)) {
+                                anchors.push((x, y, cell.sprite));
+                            }
+                        }
+                    }
+                }
+                sprites
+                    .into_iter()
+                    .map(|row| row.into_iter().map(|cell| cell.map(|c| Glyph::new(self.skin.glyph(c), c.sprite))).collect())
+                    .collect()
+            }
+        };
+        if let Some(gradients) = &self.gradients {
+            gradients.apply(&mut frame, &self.theme);
+        }
+        trails::add(&mut frame, &self.view, self.alpha(), &self.theme, self.gradients.as_ref());
+        if let Some(ghost) = &self.ghost {
+            ghost.add(&mut frame, self.alpha(), self.skin, &self.theme);
+        }
+        // Colors roll up the screen with the ticks, trails included
+        if self.rainbow_bullets {
+            let tick = self.view.tick_count as usize;
+            for (y, row) in frame.iter_mut().enumerate() {
+                for glyph in row.iter_mut().flatten().filter(|glyph| glyph.sprite == Sprite::PlayerBullet) {
+                    glyph.style = Some(Style { fg: RAINBOW[(y + tick) % RAINBOW.len()], bg: None });
+                }
+            }
+        }
+        // Stars drift with the game clock, so they stop while paused
+        let star = |sprite| Glyph::new(self.skin.glyph(Cell { sprite, dx: 0, dy: 0 }), sprite);
+        self.starfield.fill(&mut frame, self.view.elapsed, star);
+        self.shake.apply(&mut frame);
+        // A frozen run is dimmed behind its notice, images included
+        if matches!(self.state, GameState::Paused | GameState::ControllerLost) {
+            let dim = Style { fg: self.theme.ui.muted, bg: None };
+            for glyph in frame.iter_mut().flatten().flatten() {
+                *glyph = Glyph { style: Some(dim), under: None, ..*glyph };
+            }
+        }
+        let images = self.cut_out_images(&mut frame, anchors);
+        if self.entity_overlay {
+            self.overlay_entities(&mut frame);
+        }
+
+        // Render game area, one cell in from the border. Pixel renderers only
+        // cover part of a cell per sprite, so they go without cell backgrounds.
+        let backgrounds = self.renderer == Renderer::Cells;
+        let flashing = self.state == GameState::Playing && self.alerts.flash_visible();
+        match self.last_frame.take() {
+            Some(previous) if previous.len() == frame.len() => {
+                for (y, (row, previous_row)) in frame.iter().zip(&previous).enumerate() {
+                    if row == previous_row {
+                        continue;
+                    }
+                    let backdrop = self.backdrop(y);
+                    for (x, (cell, previous_cell)) in row.iter().zip(previous_row).enumerate() {
+                        if cell != previous_cell {
+                            self.draw_glyph(x + 1, y + 1, *cell, backgrounds, backdrop)?;
+                        }
+                    }
+                }
+                if flashing != self.border_flashing {
+                    self.render_border(flashing)?;
+                }
+            }
+            _ => {
+                self.out.begin_frame(true)?;
+                self.clear_images()?;
+                for (y, row) in frame.iter().enumerate() {
+                    let backdrop = self.backdrop(y);
+                    for (x, cell) in row.iter().enumerate() {
+                        self.draw_glyph(x + 1, y + 1, *cell, backgrounds, backdrop)?;
+                    }
+                }
+                self.render_border(flashing)?;
+                self.last_sidebar = None;
+                self.last_status = None;
+            }
+        }
+        self.last_frame = Some(frame);
+        self.border_flashing = flashing;
+        self.render_images(images)?;
+
+        // Wide terminals get the HUD as a sidebar, others as a strip below
+        // the border
+        let strip = if self.terminal_size.0 >= self.view.width + 2 + SIDEBAR_WIDTH {
+            self.render_sidebar()?;
+            Vec::new()
+        } else {
+            self.hud_strip()
         };
-        game.spawn_enemies();
-        game
+        let debug = if self.debug_overlay { vec![(self.debug_line(), self.theme.ui.muted)] } else { Vec::new() };
+        let status = [strip, debug];
+        let previous = self.last_status.take();
+        for (i, row) in status.iter().enumerate() {
+            if previous.as_ref().is_some_and(|previous| previous[i] == *row) {
+                continue;
+            }
+            let y = self.view.height + 2 + i;
+            self.out.clear_line(0, y)?;
+            let mut x = 0;
+            for (text, color) in row {
+                self.draw_text(x, y, text, *color)?;
+                x += text.chars().count();
+            }
+        }
+        self.last_status = Some(status);
+        Ok(())
     }
 
-    /// Spawns enemies in a grid pattern
-    fn spawn_enemies(&mut self) {
-        for row in 0..5 {  // Increased rows
-            for col in 0..10 {  // Increased columns
-                self.enemies.push(GameObject {
-                    x: col * 5 + 5,
-                    y: row * 3 + 2,
-                    alive: true,
-                });
+    /// Writes the entity overlay over the top of the playfield and marks the
+    /// corners of the enemy formation's bounding box
+    ///
+    /// # Arguments
+    /// * `frame` - The playfield, ready to be drawn
+    fn overlay_entities(&self, frame: &mut [Vec<Option<Glyph>>]) {
+        // The style overrides the sprite's colors, so any sprite will do
+        let glyph = |ch| Glyph { style: Some(Style { fg: self.theme.ui.muted, bg: None }), ..Glyph::new(ch, Sprite::Threat) };
+        if let Some((left, top, right, bottom)) = inspector::formation_bounds(&self.view) {
+            for (x, y) in [(left, top), (right, top), (left, bottom), (right, bottom)] {
+                if let Some(cell) = frame.get_mut(y).and_then(|row| row.get_mut(x)).filter(|cell| cell.is_none()) {
+                    *cell = Some(glyph('+'));
+                }
+            }
+        }
+        for (row, line) in frame.iter_mut().zip(self.inspector.lines(&self.view, self.view.width)) {
+            for (cell, ch) in row.iter_mut().zip(line.chars()) {
+                *cell = Some(glyph(ch));
             }
         }
     }
-    /// Moves the player horizontally
+
+    /// Blanks the cells of the sprites drawn as images, leaving room for them
     ///
     /// # Arguments
-    /// * `direction` - Movement direction (-1 for left, 1 for right)
-    fn move_player(&mut self, direction: i32) {
-        let new_x = self.player.x as i32 + direction;
-        if new_x > 0 && new_x < SCREEN_WIDTH as i32 - 1 {
-            self.player.x = new_x as usize;
+    /// * `frame` - The playfield, ready to be drawn
+    /// * `anchors` - Top-left cell of each sprite to draw as an image, before
+    ///   the screen shake moved it
+    ///
+    /// # Returns
+    /// Where to draw each image, and in what color
+    fn cut_out_images(&self, frame: &mut [Vec<Option<Glyph>>], anchors: Vec<(usize, usize, Sprite)>) -> Vec<(usize, usize, Sprite, Color)> {
+        let (dx, dy) = self.shake.offset();
+        let mut images = Vec::new();
+        for (x, y, sprite) in anchors {
+            let (Some(x), Some(y)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) else {
+                continue;
+            };
+            let Some(&Some(anchor)) = frame.get(y).and_then(|row| row.get(x)) else {
+                continue;
+            };
+            // Gradients may have recolored the sprite
+            let color = anchor.style.unwrap_or_else(|| self.theme.style(sprite)).fg;
+            let (width, height) = sprite.size();
+            for row in frame.iter_mut().skip(y).take(height) {
+                for cell in row.iter_mut().skip(x).take(width) {
+                    if let Some(glyph) = cell.as_mut().filter(|glyph| glyph.sprite == sprite) {
+                        *glyph = Glyph { ch: ' ', style: Some(Style { fg: Color::Reset, bg: None }), ..*glyph };
+                    }
+                }
+            }
+            images.push((x, y, sprite, color));
         }
+        images
     }
 
-    /// Fires a bullet from the player's current position
-    fn shoot_bullet(&mut self) {
-        self.player_bullets.push(GameObject {
-            x: self.player.x,
-            y: self.player.y - 1,
-            alive: true,
-        });
+    /// Draws ships and invaders as images over the playfield, unless they
+    /// are already on screen where they should be
+    ///
+    /// # Arguments
+    /// * `images` - Where to draw each image, and in what color
+    fn render_images(&mut self, images: Vec<(usize, usize, Sprite, Color)>) -> io::Result<()> {
+        let Some(graphics) = &mut self.graphics else {
+            return Ok(());
+        };
+        if self.last_images.as_ref() == Some(&images) {
+            return Ok(());
+        }
+        if let Some(clear) = graphics.clear() {
+            self.out.draw_graphics(0, 0, clear)?;
+        }
+        for &(x, y, sprite, color) in &images {
+            let image = graphics.image(sprite, color);
+            self.out.draw_graphics(x + 1, y + 1, &image)?;
+        }
+        self.last_images = Some(images);
+        Ok(())
     }
 
-    /// Updates bullet positions and checks for collisions
-    fn move_bullets(&mut self) {
-        // Move player bullets up
-        for bullet in &mut self.player_bullets {
-            if bullet.y > 0 && bullet.alive {
-                bullet.y -= 1;
-            } else {
-                bullet.alive = false;
+    /// Removes any images from the screen
+    fn clear_images(&mut self) -> io::Result<()> {
+        if let Some(clear) = self.graphics.as_ref().and_then(Graphics::clear) {
+            if self.last_images.is_some() {
+                self.out.draw_graphics(0, 0, clear)?;
             }
         }
+        self.last_images = None;
+        Ok(())
+    }
 
-        // Move enemy bullets down
-        for bullet in &mut self.enemy_bullets {
-            if bullet.y < SCREEN_HEIGHT - 1 && bullet.alive {
-                bullet.y += 1;
-            } else {
-                bullet.alive = false;
+    /// Score, lives and medal progress for the row below the border, each
+    /// part with the color it is printed in
+    fn hud_strip(&self) -> Vec<(String, Color)> {
+        let game = &self.view;
+        let high_score = self.profile.high_scores.get(self.mode()).copied().unwrap_or(0).max(game.score);
+        if let Some(two) = game.player_two {
+            let one = format!("P1: {}  Lives: {}", game.score - two.score, game.lives);
+            let two = format!("  P2: {}  Lives: {}", two.score, two.lives);
+            return vec![
+                (one, self.theme.style(Sprite::Player).fg),
+                (two, self.theme.style(Sprite::PlayerTwo).fg),
+                (format!("  Total: {}  Hi: {}", game.score, high_score), self.theme.ui.score),
+            ];
+        }
+        let mut parts = vec![(format!("Score: {}  Hi: {}  Lives: {}", game.score, high_score, game.lives), self.theme.ui.score)];
+        if game.waves > 1 {
+            parts.push((format!("  Wave: {}/{}", game.wave, game.waves), self.theme.ui.text));
+        }
+        if let Some(commander) = game.commander {
+            parts.push((format!("  Invaders: {}", dive_status(commander)), self.theme.style(Sprite::Enemy).fg));
+        }
+        if let Some(ghost) = &self.ghost {
+            parts.push((format!("  Ghost: {}", ghost.score()), self.theme.ui.muted));
+        }
+        if let Some(file) = &self.watched {
+            let seconds = |ticks: u64| (TICK_DURATION * ticks as u32).as_secs();
+            parts.push((format!("  Replay: {}s/{}s", seconds(game.tick_count), seconds(file.log().ticks)), self.theme.ui.muted));
+        }
+
+        // Render medal progress next to the score
+        if let Some(medals) = &self.medals() {
+            if let Some(medal) = medals.medal_for(game.score) {
+                parts.push((format!("  [{}]", medal), self.medal_color(medal)));
             }
+            let next = match medals.next_medal(game.score) {
+                Some((next, threshold)) => format!("  {} at {}", next, threshold),
+                None => "  All medals earned".to_string(),
+            };
+            parts.push((next, self.theme.ui.muted));
         }
 
-        // Check for collisions
-        self.check_collisions();
+        parts
     }
 
-    /// Randomly makes enemies shoot bullets
-    fn enemy_shoot(&mut self) {
-        let mut rng = rand::thread_rng();
-        for enemy in &self.enemies {
-            if enemy.alive && rng.gen_bool(0.02) {
-                self.enemy_bullets.push(GameObject {
-                    x: enemy.x,
-                    y: enemy.y + 1,
-                    alive: true,
-                });
+    /// Lines of the HUD sidebar, each with the color it is printed in
+    fn sidebar_lines(&self) -> Vec<(String, Color)> {
+        let game = &self.view;
+        let high_score = self.profile.high_scores.get(self.mode()).copied().unwrap_or(0);
+        // Lives are shown as the bottom row of the player's ship
+        let ships = |sprite, lives| {
+            let ship = self.skin.shape(sprite).last().copied().unwrap_or_default();
+            format!("  {}", vec![ship; lives].join(" "))
+        };
+        if let Some(two) = game.player_two {
+            return vec![
+                ("PLAYER 1".to_string(), self.theme.ui.muted),
+                (format!("  {}", game.score - two.score), self.theme.ui.score),
+                (ships(Sprite::Player, game.lives), self.theme.style(Sprite::Player).fg),
+                ("PLAYER 2".to_string(), self.theme.ui.muted),
+                (format!("  {}", two.score), self.theme.ui.score),
+                (ships(Sprite::PlayerTwo, two.lives), self.theme.style(Sprite::PlayerTwo).fg),
+                ("HIGH SCORE".to_string(), self.theme.ui.muted),
+                (format!("  {}", high_score.max(game.score)), self.theme.ui.text),
+            ];
+        }
+
+        let mut lines = vec![
+            ("SCORE".to_string(), self.theme.ui.muted),
+            (format!("  {}", game.score), self.theme.ui.score),
+            ("HIGH SCORE".to_string(), self.theme.ui.muted),
+            (format!("  {}", high_score.max(game.score)), self.theme.ui.text),
+            ("LIVES".to_string(), self.theme.ui.muted),
+            (ships(Sprite::Player, game.lives), self.theme.style(Sprite::Player).fg),
+        ];
+        if game.waves > 1 {
+            lines.push(("WAVE".to_string(), self.theme.ui.muted));
+            lines.push((format!("  {} of {}", game.wave, game.waves), self.theme.ui.text));
+        }
+        if let Some(commander) = game.commander {
+            lines.push(("INVADERS".to_string(), self.theme.ui.muted));
+            lines.push((format!("  {}", dive_status(commander)), self.theme.style(Sprite::Enemy).fg));
+        }
+        if let Some(ghost) = &self.ghost {
+            lines.push(("GHOST".to_string(), self.theme.ui.muted));
+            lines.push((format!("  {}", ghost.score()), self.theme.ui.muted));
+        }
+        if let Some(medals) = &self.medals() {
+            lines.push(("MEDAL".to_string(), self.theme.ui.muted));
+            match medals.medal_for(game.score) {
+                Some(medal) => lines.push((format!("  {}", medal), self.medal_color(medal))),
+                None => lines.push(("  -".to_string(), self.theme.ui.muted)),
+            }
+            match medals.next_medal(game.score) {
+                Some((next, threshold)) => lines.push((format!("  {} at {}", next, threshold), self.theme.ui.muted)),
+                None => lines.push(("  All medals earned".to_string(), self.theme.ui.muted)),
             }
         }
+        lines
     }
 
-    /// Moves enemies across and down the screen
-    fn move_enemies(&mut self) {
-        // Slow down enemy movement
-        self.enemy_move_counter += 1;
-        if self.enemy_move_counter < 5 {  // Only move every 5 frames
-            return;
+    /// Prints the HUD to the right of the border, one item under another
+    ///
+    /// Only lines that changed since the sidebar was last drawn are printed,
+    /// so an unchanged HUD costs nothing.
+    fn render_sidebar(&mut self) -> io::Result<()> {
+        let x = self.view.width + 3;
+        let lines = self.sidebar_lines();
+        let previous = self.last_sidebar.take().unwrap_or_default();
+        for (y, line) in lines.iter().enumerate() {
+            if previous.get(y) != Some(line) {
+                let (text, color) = line;
+                self.out.clear_line(x, y + 1)?;
+                let text: String = text.chars().take(SIDEBAR_WIDTH - 2).collect();
+                self.draw_text(x, y + 1, &text, *color)?;
+            }
         }
-        self.enemy_move_counter = 0;
+        self.last_sidebar = Some(lines);
+        Ok(())
+    }
+
+    /// Asks for a bigger terminal, in place of a screen that doesn't fit
+    fn render_too_small(&mut self) -> io::Result<()> {
+        let (columns, rows) = self.required_size();
+        self.draw_text(0, 0, "Terminal too small", self.theme.ui.danger)?;
+        let need = format!("Need {}x{}, have {}x{}", columns, rows, self.terminal_size.0, self.terminal_size.1);
+        self.out.draw_text(0, 1, &need, None)?;
+        self.out.draw_text(0, 2, "Enlarge the window, or press Esc to quit", None)
+    }
 
-        let mut move_down = false;
-        let mut direction = 1;
+    /// Draws the menu's drifting stars and marching invader mascot
+    ///
+    /// # Arguments
+    /// * `mid` - Middle row of the screen; the menu text sits just below it
+    ///   and the title just above
+    fn render_menu_idle(&mut self, mid: usize) -> io::Result<()> {
+        let text_rows = mid - 1 - TITLE_ART.len()..=mid + 8;
 
-        for enemy in &mut self.enemies {
-            if enemy.alive {
-                enemy.x = (enemy.x as i32 + direction).max(0).min(SCREEN_WIDTH as i32 - 1) as usize;
-                
-                // Change direction and move down when hitting screen edges
-                if enemy.x == 0 || enemy.x == SCREEN_WIDTH - 1 {
-                    move_down = true;
-                    direction *= -1;
-                }
+        for (x, y, sprite) in self.starfield.stars(self.view.width, self.view.height, self.idle.clock()) {
+            if !text_rows.contains(&y) {
+                self.draw_sprite(sprite, x, y)?;
             }
         }
 
-        if move_down {
-            for enemy in &mut self.enemies {
-                if enemy.alive {
-                    enemy.y += 1;
-                    
-                    // Game over if enemies reach bottom
-                    if enemy.y >= SCREEN_HEIGHT - 3 {
-                        self.game_over = true;
-                    }
-                }
-            }
+        let x = self.idle.mascot_x(10, self.view.width - 10);
+        self.draw_sprite(Sprite::Enemy, x, mid - 2 - TITLE_ART.len())
+    }
+
+    /// Simulation tick, game clock, frame and tick rates and frame times, for
+    /// the row below the HUD
+    fn debug_line(&self) -> String {
+        let mut line = format!(
+            "tick {}  time {:.1}s  fps {}  tps {}",
+            self.view.tick_count,
+            self.view.elapsed.as_secs_f32(),
+            self.perf.fps(),
+            self.perf.tps()
+        );
+        if let Some((min, average, max)) = self.perf.frame_times() {
+            let ms = |duration: Duration| duration.as_secs_f32() * 1000.0;
+            line += &format!("  frame {:.1}/{:.1}/{:.1}ms", ms(min), ms(average), ms(max));
+        }
+        line
+    }
+
+    /// Draws the box around the playfield
+    ///
+    /// While an alert flash is showing the box is drawn in the flash's color,
+    /// or with the flash glyph when plain rendering is on.
+    ///
+    /// # Arguments
+    /// * `flashing` - Whether an alert flash is showing
+    fn render_border(&mut self, flashing: bool) -> io::Result<()> {
+        let border = self.skin.border;
+        let flash_glyph = self.skin.glyph(Cell { sprite: Sprite::Flash, dx: 0, dy: 0 });
+        let plain = !self.colored;
+        let glyph = |c: char| if flashing && plain { flash_glyph } else { c };
+        let color = if flashing { self.theme.style(Sprite::Flash).fg } else { self.theme.ui.muted };
+
+        let (right, bottom) = (self.view.width + 1, self.view.height + 1);
+        let horizontal: String = std::iter::repeat_n(glyph(border.horizontal), self.view.width).collect();
+        let [top_left, top_right, bottom_left, bottom_right] = border.corners.map(glyph);
+        self.draw_text(0, 0, &format!("{}{}{}", top_left, horizontal, top_right), color)?;
+        let vertical = glyph(border.vertical).to_string();
+        for y in 1..bottom {
+            self.draw_text(0, y, &vertical, color)?;
+            self.draw_text(right, y, &vertical, color)?;
+        }
+        self.draw_text(0, bottom, &format!("{}{}{}", bottom_left, horizontal, bottom_right), color)
+    }
+
+    /// Lists sprites whose colors are likely to be invisible on this terminal
+    ///
+    /// # Arguments
+    /// * `y` - First row to print on
+    ///
+    /// # Returns
+    /// The number of rows printed on
+    fn render_contrast_warnings(&mut self, y: usize) -> io::Result<usize> {
+        // Nothing is drawn in color, so nothing can be hard to see
+        if !self.colored {
+            return Ok(0);
+        }
+        let lines: Vec<String> = self
+            .contrast_warnings
+            .iter()
+            .map(|warning| {
+                format!(
+                    "Warning: {} may be hard to see (contrast {:.1}:1)",
+                    warning.sprite.name(),
+                    warning.ratio
+                )
+            })
+            .collect();
+        for (i, line) in lines.iter().enumerate() {
+            self.draw_centered(y + i, line, self.theme.ui.highlight)?;
         }
+        if self.contrast_adjusted {
+            let y = y + self.contrast_warnings.len();
+            self.draw_centered(y, "Colors adjusted to stay visible (ensure_visible)", self.theme.ui.muted)?;
+        } else if !self.contrast_warnings.is_empty() {
+            let y = y + self.contrast_warnings.len();
+            self.draw_centered(y, "Set display.ensure_visible = true to fix", self.theme.ui.muted)?;
+        }
+        let notes = usize::from(self.contrast_adjusted || !self.contrast_warnings.is_empty());
+        Ok(self.contrast_warnings.len() + notes)
     }
 
-    /// Checks and handles collisions between bullets and game objects
-    fn check_collisions(&mut self) {
-        // Player bullets hitting enemies
-        for bullet in &mut self.player_bullets {
-            if !bullet.alive { continue; }
-            
-            for enemy in &mut self.enemies {
-                if enemy.alive && bullet.x == enemy.x && bullet.y == enemy.y {
-                    bullet.alive = false;
-                    enemy.alive = false;
-                    self.score += 10;
-                    break;
+    /// Renders every glyph of the active skin in a grid, in its in-game colors
+    ///
+    /// Lets players confirm their terminal font displays all sprites before
+    /// starting a run.
+    fn render_glyph_test(&mut self) -> io::Result<()> {
+        const COLUMNS: usize = 2;
+        const CELL_WIDTH: usize = 24;
+
+        self.draw_centered(1, &format!("Glyph test - skin: {}", self.skin.name), self.theme.ui.title)?;
+        self.draw_centered(2, "Every sprite below should show clear, whole symbols", self.theme.ui.muted)?;
+
+        // Each row of the grid is as tall as its tallest sprite, plus a blank
+        // row in between if the screen has room for it
+        let footer = self.view.height - 3;
+        let rows = Sprite::ALL.chunks(COLUMNS);
+        let grid_height: usize = rows.clone().map(|row| row.iter().map(|s| s.size().1).max().unwrap_or(1)).sum();
+        let gap = usize::from(4 + grid_height + rows.len() <= footer);
+        let mut y = 4;
+        for row in rows {
+            for (column, &sprite) in row.iter().enumerate() {
+                let x = 6 + column * CELL_WIDTH;
+                let (width, height) = sprite.size();
+                for dy in 0..height {
+                    self.out.draw_text(x, y + dy, &format!("[{:width$}]", "", width = width), None)?;
                 }
+                self.draw_sprite(sprite, x + 1, y)?;
+                self.out.draw_text(x + width + 4, y, sprite.name(), None)?;
             }
+            y += row.iter().map(|sprite| sprite.size().1).max().unwrap_or(1) + gap;
         }
 
-        // Enemy bullets hitting player
-        for bullet in &mut self.enemy_bullets {
-            if !bullet.alive { continue; }
-            
-            if bullet.x == self.player.x && bullet.y == self.player.y {
-                bullet.alive = false;
-                self.player.alive = false;
-                self.game_over = true;
-                break;
-            }
+        if self.skin.name == skin::ASCII.name {
+            self.draw_centered(footer, "Using the ASCII fallback skin", self.theme.ui.muted)?;
+        } else {
+            self.draw_centered(footer, "A: switch to the ASCII fallback skin", self.theme.ui.text)?;
+        }
+        self.draw_centered(footer + 1, "Enter/Esc: back to menu", self.theme.ui.text)
+    }
+
+    /// Renders the list of connected controllers to pick the player's from
+    ///
+    /// # Arguments
+    /// * `selected` - Index of the highlighted controller
+    fn render_controller_picker(&mut self, selected: usize) -> io::Result<()> {
+        self.draw_centered(1, "Choose a controller", self.theme.ui.title)?;
+
+        let lines: Vec<String> = self
+            .controllers
+            .connected
+            .iter()
+            .enumerate()
+            .map(|(i, controller)| {
+                let marker = if i == selected { '>' } else { ' ' };
+                let assigned = if self.controllers.is_assigned(controller.id) { " (in use)" } else { "" };
+                format!("{} {}{}", marker, controller.name, assigned)
+            })
+            .collect();
+        for (i, line) in lines.iter().enumerate() {
+            let color = if i == selected { self.theme.ui.text } else { self.theme.ui.muted };
+            self.draw_centered(4 + i, line, color)?;
         }
 
-        // Clean up dead objects
-        self.player_bullets.retain(|b| b.alive);
-        self.enemy_bullets.retain(|b| b.alive);
-        self.enemies.retain(|e| e.alive);
+        let footer = self.view.height - 3;
+        self.draw_centered(footer, "Up/Down: select   Enter: use   Esc: back", self.theme.ui.text)
     }
 
-    /// Renders the game state with color
+    /// Renders the initials entry screen shown when a run makes the
+    /// leaderboard
     ///
-    /// # Returns
-    /// A `Result` indicating successful rendering or an error
-    fn render_colored(&self) -> io::Result<()> {
-        let mut stdout = stdout();
-        
-        // Clear the screen
-        execute!(stdout, terminal::Clear(ClearType::All))?;
-        
-        // Render game area
-        for (y, row) in self.render().lines().enumerate() {
-            execute!(stdout, cursor::MoveTo(0, y as u16))?;
-            
-            for (x, c) in row.chars().enumerate() {
-                match c {
-                    'W' => {
-                        // Enemies in red
-                        execute!(stdout, 
-                            SetForegroundColor(Color::Red), 
-                            SetBackgroundColor(Color::DarkRed)
-                        )?;
-                        print!("{}", c);
-                        execute!(stdout, ResetColor)?;
-                    },
-                    '^' => {
-                        // Player in green
-                        execute!(stdout, 
-                            SetForegroundColor(Color::Green), 
-                            SetBackgroundColor(Color::DarkGreen)
-                        )?;
-                        print!("{}", c);
-                        execute!(stdout, ResetColor)?;
-                    },
-                    '|' => {
-                        // Bullets in bright white
-                        execute!(stdout, 
-                            SetForegroundColor(Color::White), 
-                            SetBackgroundColor(Color::DarkGrey)
-                        )?;
-                        print!("{}", c);
-                        execute!(stdout, ResetColor)?;
-                    },
-                    _ => print!("{}", c),
-                }
-            }
-        }
-        
-        // Render score separately
-        execute!(
-            stdout, 
-            cursor::MoveTo(0, SCREEN_HEIGHT as u16),
-            SetForegroundColor(Color::Blue)
-        )?;
-        print!("Score: {}", self.score);
-        execute!(stdout, ResetColor)?;
-        
-        stdout.flush()?;
+    /// # Arguments
+    /// * `letters` - The initials entered so far
+    /// * `cursor` - Which letter is being changed
+    fn render_initials(&mut self, letters: [char; leaderboard::INITIALS], cursor: usize) -> io::Result<()> {
+        let mid = self.view.height / 2;
+        self.draw_centered(mid - 4, "NEW HIGH SCORE!", self.theme.ui.title)?;
+        self.draw_centered(mid - 2, &format!("Score: {}", self.view.score), self.theme.ui.score)?;
+        self.draw_centered(mid - 1, "Enter your initials", self.theme.ui.text)?;
+
+        // Letters are spaced out, with a marker under the one being changed
+        let initials: String = letters.iter().map(|letter| format!(" {}", letter)).collect();
+        let marker: String = (0..letters.len()).map(|i| if i == cursor { " ^" } else { "  " }).collect();
+        self.draw_centered(mid + 1, &initials, self.theme.ui.highlight)?;
+        self.draw_centered(mid + 2, &marker, self.theme.ui.highlight)?;
+
+        let help = "Up/Down or type: letter   Left/Right: move   Enter: done";
+        self.draw_centered(mid + 5, help, self.theme.ui.muted)
+    }
+
+    /// Prints the leaderboard in as many columns as fit, in however many rows
+    /// there are room for; lower places are left out if they don't fit
+    ///
+    /// # Arguments
+    /// * `y` - Row of the heading, with the places below it
+    /// * `bottom` - First row that can't be printed on
+    /// * `highlight` - Place to pick out, counting from 1
+    fn render_leaderboard(&mut self, y: usize, bottom: usize, highlight: Option<usize>) -> io::Result<()> {
+        const CELL_WIDTH: usize = 16;
+        let rows = bottom.saturating_sub(y + 1);
+        if self.leaderboard.entries().is_empty() || rows == 0 {
+            return Ok(());
+        }
+        self.draw_centered(y, "TOP SCORES", self.theme.ui.muted)?;
+
+        let columns = (self.view.width.saturating_sub(2) / CELL_WIDTH).max(1);
+        let left = self.view.width.saturating_sub(columns * CELL_WIDTH) / 2;
+        let cells: Vec<(String, usize)> = self
+            .leaderboard
+            .entries()
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (format!("{:>2}. {} {:>6}", i + 1, entry.initials, entry.score), i + 1))
+            .collect();
+        for (row, line) in cells.chunks(columns).take(rows).enumerate() {
+            for (column, (cell, place)) in line.iter().enumerate() {
+                let color = if highlight == Some(*place) { self.theme.ui.highlight } else { self.theme.ui.text };
+                self.draw_text(left + column * CELL_WIDTH, y + 1 + row, cell, color)?;
+            }
+        }
         Ok(())
     }
 
-    // Generates a string representation of the game screen
+    /// Renders the settings screen
     ///
-    /// # Returns
-    /// A `String` containing the current game state
-    fn render(&self) -> String {
-        let mut screen = vec![vec![' '; SCREEN_WIDTH]; SCREEN_HEIGHT];
+    /// # Arguments
+    /// * `selected` - Row the cursor is on
+    fn render_settings(&mut self, selected: usize) -> io::Result<()> {
+        self.draw_centered(1, "Settings", self.theme.ui.title)?;
+
+        // Each setting as a bar of tenths, filled up to its value
+        let steps = |value: f32, min: f32| ((value - min) * 10.0).round() as usize;
+        let speed_steps = steps(MAX_SPEED, MIN_SPEED);
+        let rows = [
+            ("Music volume", steps(self.volumes.0, 0.0), 10, format!("{:>3}%", steps(self.volumes.0, 0.0) * 10)),
+            ("Effects volume", steps(self.volumes.1, 0.0), 10, format!("{:>3}%", steps(self.volumes.1, 0.0) * 10)),
+            ("Game speed", steps(self.speed, MIN_SPEED), speed_steps, format!("{:.1}x", self.speed)),
+        ];
+        // Padded to one width so the centered rows line up
+        let width = 2 + 15 + speed_steps + 2 + 5;
+        for (i, (name, filled, total, value)) in rows.into_iter().enumerate() {
+            let marker = if i == selected { '>' } else { ' ' };
+            let bar = format!("[{}{}]", "#".repeat(filled), "-".repeat(total - filled));
+            let line = format!("{} {:<15}{} {}", marker, name, bar, value);
+            let color = if i == selected { self.theme.ui.text } else { self.theme.ui.muted };
+            self.draw_centered(4 + i, &format!("{:<width$}", line), color)?;
+        }
+        if self.tournament.is_some() {
+            self.draw_centered(8, "Tournament runs are played at normal speed", self.theme.ui.muted)?;
+        } else if !cfg!(feature = "audio") {
+            self.draw_centered(8, "This build has no sound; see Sound in the README", self.theme.ui.muted)?;
+        }
+        if let Some(error) = self.settings_error.clone() {
+            self.draw_centered(10, &error, self.theme.ui.danger)?;
+        }
+
+        let footer = self.view.height - 3;
+        self.draw_centered(footer, "Up/Down: select   Left/Right: change   Enter: save", self.theme.ui.text)
+    }
 
-        // Draw player
-        if self.player.alive {
-            screen[self.player.y][self.player.x] = PLAYER_CHAR;
+    /// Renders the totals of every run the player has finished
+    fn render_lifetime_stats(&mut self) -> io::Result<()> {
+        self.draw_centered(1, "Lifetime statistics", self.theme.ui.title)?;
+
+        let stats = &self.profile.lifetime;
+        let accuracy = match stats.accuracy() {
+            Some(percent) => format!("{}%", percent),
+            None => "-".to_string(),
+        };
+        let playtime = format!("{}h {:02}m {:02}s", stats.playtime / 3600, stats.playtime / 60 % 60, stats.playtime % 60);
+        let rows = [
+            ("Runs played", stats.runs.to_string()),
+            ("Time played", playtime),
+            ("Shots fired", stats.shots.to_string()),
+            ("Accuracy", accuracy),
+            ("Kills - invaders", stats.invader_kills.to_string()),
+            ("Waves cleared", stats.waves_cleared.to_string()),
+            ("Deaths", stats.deaths.to_string()),
+        ];
+        let lines: Vec<String> = rows.iter().map(|(name, value)| format!("{:<18}{:>12}", name, value)).collect();
+        for (i, line) in lines.iter().enumerate() {
+            self.draw_centered(4 + i, line, self.theme.ui.text)?;
+        }
+        if self.profile.lifetime.runs == 0 {
+            self.draw_centered(5 + lines.len(), "Finish a run to start counting", self.theme.ui.muted)?;
+        }
+
+        let footer = self.view.height - 3;
+        self.draw_centered(footer, "Press Enter or Esc to go back", self.theme.ui.text)
+    }
+
+    /// Renders the list of problems found in configuration and data files
+    fn render_diagnostics(&mut self) -> io::Result<()> {
+        self.draw_centered(1, "Some settings could not be loaded", self.theme.ui.danger)?;
+
+        let lines: Vec<String> = self.diagnostics.iter().map(|diagnostic| format!("- {}", diagnostic)).collect();
+        for (i, line) in lines.iter().enumerate() {
+            self.draw_text(1, i + 3, line, self.theme.ui.highlight)?;
         }
 
-        // Draw enemies
-        for enemy in &self.enemies {
-            if enemy.alive {
-                screen[enemy.y][enemy.x] = ENEMY_CHAR;
+        let y = self.diagnostics.len() + 5;
+        self.draw_centered(y, "Press Enter to continue with defaults, Esc to quit", self.theme.ui.text)
+    }
+
+    /// Renders the medal section of the results screen
+    ///
+    /// # Arguments
+    /// * `y` - First row of the section
+    fn render_medal_results(&mut self, y: usize) -> io::Result<()> {
+        let Some(result) = &self.result else {
+            return Ok(());
+        };
+        let (medal, new_best, save_error) = (result.medal, result.new_best, result.save_error.clone());
+        match medal {
+            Some(medal) if new_best => {
+                self.draw_centered(y, &format!("{} medal - new best!", medal), self.medal_color(medal))?
+            }
+            Some(medal) => self.draw_centered(y, &format!("{} medal", medal), self.medal_color(medal))?,
+            None => {
+                if let Some(medals) = &self.medals() {
+                    let line = format!("No medal - Bronze at {}", medals.bronze);
+                    self.draw_centered(y, &line, self.theme.ui.muted)?;
+                }
             }
         }
+        if let Some(best) = self.profile.medals.get(self.mode()) {
+            let line = format!("{}'s best: {}", self.profile.name, best);
+            self.draw_centered(y + 1, &line, self.theme.ui.muted)?;
+        }
+        if let Some(error) = save_error {
+            self.draw_centered(y + 2, &format!("Could not save profile: {}", error), self.theme.ui.danger)?;
+        }
+        Ok(())
+    }
+
+    /// Prints the run's statistics on the results screen
+    ///
+    /// # Arguments
+    /// * `y` - Row to print on; two rows are used
+    fn render_run_stats(&mut self, y: usize) -> io::Result<()> {
+        let stats = &self.stats;
+        let accuracy = match stats.accuracy() {
+            Some(percent) => format!("{}% ({} of {} shots)", percent, stats.kills, stats.shots),
+            None => "no shots fired".to_string(),
+        };
+        let summary = format!("Waves cleared: {}   Accuracy: {}", stats.waves_cleared, accuracy);
+        // Invaders are the only kind of enemy so far
+        let kills = format!("Kills - invaders: {}   Hits taken: {}", stats.kills, stats.hits_taken);
+        self.draw_centered(y, &summary, self.theme.ui.text)?;
+        self.draw_centered(y + 1, &kills, self.theme.ui.text)
+    }
 
-        // Draw player bullets
-        for bullet in &self.player_bullets {
-            if bullet.alive {
-                screen[bullet.y][bullet.x] = BULLET_CHAR;
+    /// Blits a whole sprite with its top-left corner at the given cell
+    ///
+    /// # Arguments
+    /// * `sprite` - The sprite to draw
+    /// * `x` - Leftmost column of the sprite
+    /// * `y` - Top row of the sprite
+    fn draw_sprite(&mut self, sprite: Sprite, x: usize, y: usize) -> io::Result<()> {
+        let (width, height) = sprite.size();
+        for dy in 0..height {
+            for dx in 0..width {
+                let glyph = Glyph::new(self.skin.glyph(Cell { sprite, dx, dy }), sprite);
+                self.draw_glyph(x + dx, y + dy, Some(glyph), true, None)?;
             }
         }
+        Ok(())
+    }
 
-        // Draw enemy bullets
-        for bullet in &self.enemy_bullets {
-            if bullet.alive {
-                screen[bullet.y][bullet.x] = BULLET_CHAR;
+    /// Prints a playfield character, in its sprite's colors unless plain
+    /// rendering is on; empty cells are blank
+    ///
+    /// # Arguments
+    /// * `x` - Column to print at
+    /// * `y` - Row to print at
+    /// * `glyph` - The character to print and the sprites it belongs to
+    /// * `background` - Whether to fill the cell with the sprite's background
+    ///   color, if it has one
+    /// * `backdrop` - Shade of the sky behind the cell, if it is shaded
+    fn draw_glyph(&mut self, x: usize, y: usize, glyph: Option<Glyph>, background: bool, backdrop: Option<Color>) -> io::Result<()> {
+        match glyph {
+            Some(glyph) if self.colored => {
+                let mut style = glyph.style.unwrap_or_else(|| self.theme.style(glyph.sprite));
+                if !background {
+                    style.bg = None;
+                }
+                if let Some(under) = glyph.under {
+                    style.bg = Some(self.theme.style(under).fg);
+                }
+                style.bg = style.bg.or(backdrop);
+                self.out.draw_cell(x, y, glyph.ch, Some(style))
             }
+            Some(glyph) => self.out.draw_cell(x, y, glyph.ch, None),
+            None if self.colored && backdrop.is_some() => {
+                self.out.draw_cell(x, y, ' ', Some(Style { fg: Color::Reset, bg: backdrop }))
+            }
+            None => self.out.draw_cell(x, y, ' ', None),
         }
+    }
 
-        // Convert screen to string
-        let mut output = String::new();
-        for row in &screen {
-            output.push_str(&row.iter().collect::<String>());
-            output.push('\n');
+    /// Color used to draw a medal's name
+    fn medal_color(&self, medal: Medal) -> Color {
+        let [bronze, silver, gold] = self.theme.ui.medals;
+        match medal {
+            Medal::Bronze => bronze,
+            Medal::Silver => silver,
+            Medal::Gold => gold,
         }
-        
-        output
+    }
+
+    /// Prints a line of text horizontally centered on the playfield
+    ///
+    /// # Arguments
+    /// * `y` - Row to print on
+    /// * `text` - Text to print
+    /// * `color` - Foreground color of the text
+    fn draw_centered(&mut self, y: usize, text: &str, color: Color) -> io::Result<()> {
+        let x = self.view.width.saturating_sub(text.len()) / 2;
+        self.draw_text(x, y, text, color)
+    }
+
+    /// Prints a line of text, in a color unless color is off
+    ///
+    /// # Arguments
+    /// * `x` - Column of the text's first character
+    /// * `y` - Row to print on
+    /// * `text` - Text to print
+    /// * `color` - Foreground color of the text
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, color: Color) -> io::Result<()> {
+        let color = self.colored.then_some(color);
+        self.out.draw_text(x, y, text, color)
     }
 }
 
-fn main() -> io::Result<()> {
-    let mut stdout = stdout();
-    terminal::enable_raw_mode()?;
-    execute!(stdout, terminal::Clear(ClearType::All))?;
-
-    let mut game = Game::new();
-    let mut last_frame = Instant::now();
-    let frame_duration = Duration::from_millis(100);
-
-    while !game.game_over {
-        // Handle input
-        if event::poll(Duration::from_millis(10))? {
-            if let Event::Key(key_event) = event::read()? {
-                match key_event.code {
-                    KeyCode::Left => game.move_player(-1),
-                    KeyCode::Right => game.move_player(1),
-                    KeyCode::Char(' ') => game.shoot_bullet(),
-                    KeyCode::Esc => break,
-                    _ => {}
+/// Whether the invaders can send a diver yet, for the HUD of a versus run
+///
+/// # Arguments
+/// * `commander` - The invaders' side of the run
+fn dive_status(commander: CommanderStatus) -> String {
+    match commander.dive_in {
+        0 => "dive ready".to_string(),
+        ticks => format!("dive in {:.1}s", (TICK_DURATION * ticks as u32).as_secs_f32()),
+    }
+}
+
+/// Puts every enemy in the walking pose for where it stands
+///
+/// Enemies switch pose each time they step onto a new column, so the
+/// formation visibly walks as it marches and stands still when it doesn't.
+/// Each pose is its own sprite, so skins draw them however they like.
+///
+/// # Arguments
+/// * `frame` - The playfield's sprites, as laid out by the simulation
+fn walk_enemies(frame: &mut [Vec<Option<Cell>>]) {
+    for row in frame.iter_mut() {
+        // Every cell of an enemy takes the pose picked at its leftmost column
+        let mut stepping = false;
+        for (x, cell) in row.iter_mut().enumerate() {
+            if let Some(cell) = cell.as_mut().filter(|c| c.sprite == Sprite::Enemy) {
+                if cell.dx == 0 {
+                    stepping = x % 2 == 1;
+                }
+                if stepping {
+                    cell.sprite = Sprite::EnemyAlt;
                 }
             }
         }
+    }
+}
+
+/// Terminal Space Invaders
+///
+/// Settings given here apply to this session only, over those in
+/// `config.toml`.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Play in a tournament, with the token from its organizer
+    #[arg(long, value_name = "TOKEN")]
+    tournament: Option<String>,
+    /// How the playfield is drawn
+    #[arg(long, value_parser = ["cells", "braille", "halfblock"])]
+    renderer: Option<String>,
+    /// Draw plain text without color
+    #[arg(long)]
+    no_color: bool,
+    /// Color theme
+    #[arg(long, value_parser = PossibleValuesParser::new(THEME_NAMES))]
+    theme: Option<String>,
+    /// How hard the invaders are
+    #[arg(long, value_parser = ["easy", "normal", "hard"])]
+    difficulty: Option<String>,
+    /// Campaign to play, by name, or "none" for a single formation
+    #[arg(long, value_name = "NAME", conflicts_with = "tournament")]
+    campaign: Option<String>,
+    /// Seed every run with this number, to play the same game again
+    #[arg(long, conflicts_with = "tournament")]
+    seed: Option<u64>,
+    /// Width of the playfield, in cells
+    #[arg(long, value_parser = value_parser!(u16).range(MIN_WIDTH as i64..=MAX_WIDTH as i64))]
+    width: Option<u16>,
+    /// Height of the playfield, in cells
+    #[arg(long, value_parser = value_parser!(u16).range(MIN_HEIGHT as i64..=MAX_HEIGHT as i64))]
+    height: Option<u16>,
+    /// Frames drawn per second during a run
+    #[arg(long, value_parser = value_parser!(u32).range(10..=240))]
+    fps: Option<u32>,
+    /// Game speed, from 0.5 (half speed) to 2.0 (double speed)
+    #[arg(long, value_parser = parse_speed)]
+    speed: Option<f32>,
+    /// Turn off music, sound effects and the bell
+    #[arg(long)]
+    mute: bool,
+    /// Watch a recorded run, e.g. replays/latest.replay in the data directory;
+    /// Left and Right seek ten seconds back or ahead, Home goes back to the start
+    #[arg(long, value_name = "FILE", conflicts_with = "tournament")]
+    replay: Option<PathBuf>,
+    /// Race a recorded one-player run, e.g. a copy of replays/latest.replay
+    /// in the data directory: runs are played on its seed and settings, with
+    /// its ship flying alongside
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["tournament", "replay", "seed", "headless", "host", "join"])]
+    ghost: Option<PathBuf>,
+    /// Watch a hand-written run: a text file of ticks and the commands to
+    /// apply on them, played on this session's settings
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["tournament", "replay", "ghost", "headless", "bench", "host", "join", "arcade"])]
+    input_script: Option<PathBuf>,
+    /// Keep configuration and data in this directory, for portable installs
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+    /// Write a summary of each finished run to this file, as JSON
+    #[arg(long, value_name = "FILE")]
+    export_json: Option<PathBuf>,
+    /// Let a bot play one run without the terminal, as fast as possible, and
+    /// print how it went
+    #[arg(long, conflicts_with_all = ["tournament", "replay"])]
+    headless: bool,
+    /// Most ticks a headless run lasts, if it doesn't end sooner
+    #[arg(long, requires = "headless", default_value_t = 10_000)]
+    ticks: u64,
+    /// How the bot plays a headless run
+    #[arg(long, requires = "headless", value_parser = PossibleValuesParser::new(STRATEGY_NAMES), default_value = "hunter")]
+    bot: String,
+    /// Time the simulation and drawing over this many ticks of stress waves,
+    /// without the terminal, and print how long ticks and frames took
+    #[arg(
+        long,
+        value_name = "TICKS",
+        num_args = 0..=1,
+        default_missing_value = "5000",
+        conflicts_with_all = ["tournament", "replay", "headless", "host", "join", "ghost", "arcade", "spectate"]
+    )]
+    bench: Option<u64>,
+    /// Host a co-op run over the network, waiting for another player to join
+    /// on this TCP port
+    #[arg(long, value_name = "PORT", conflicts_with_all = ["tournament", "replay", "headless", "join"])]
+    host: Option<u16>,
+    /// Address --host and --arcade listen on; the default only lets players
+    /// on this computer in, and 0.0.0.0 lets in any computer that can reach it
+    #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1")]
+    bind: IpAddr,
+    /// Join a co-op run hosted on another computer, e.g. 192.168.1.20:7777
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["tournament", "replay", "headless"])]
+    join: Option<String>,
+    /// Let others watch from another terminal by connecting to this TCP
+    /// port, e.g. with `nc HOST PORT`
+    #[arg(long, value_name = "PORT", conflicts_with = "headless")]
+    spectate: Option<u16>,
+    /// Serve the game as an arcade on this TCP port, for players to connect
+    /// to with `ssh -p PORT play@HOST`: each gets a game of their own, played
+    /// as a guest, and only the shared leaderboard is kept. No password is
+    /// asked for, so anyone who can reach the --bind address can play
+    #[arg(
+        long,
+        value_name = "PORT",
+        conflicts_with_all = ["tournament", "replay", "ghost", "headless", "host", "join", "spectate"]
+    )]
+    arcade: Option<u16>,
+    /// Sign a tournament ruleset file and print its token
+    #[arg(long, value_name = "RULESET", exclusive = true)]
+    sign_tournament: Option<PathBuf>,
+    /// Check a tournament result file
+    #[arg(long, value_name = "FILE", exclusive = true)]
+    verify_result: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Applies the session's settings over the configuration
+    ///
+    /// # Arguments
+    /// * `config` - Settings loaded from `config.toml`
+    fn apply(&self, config: &mut Config) {
+        if let Some(renderer) = &self.renderer {
+            config.display.renderer = match renderer.as_str() {
+                "braille" => Renderer::Braille,
+                "halfblock" => Renderer::HalfBlock,
+                _ => Renderer::Cells,
+            };
+        }
+        if let Some(theme) = &self.theme {
+            config.display.theme = theme.clone();
+        }
+        if let Some(difficulty) = &self.difficulty {
+            config.game.difficulty = match difficulty.as_str() {
+                "easy" => Difficulty::Easy,
+                "hard" => Difficulty::Hard,
+                _ => Difficulty::Normal,
+            };
+        }
+        if let Some(campaign) = &self.campaign {
+            config.game.campaign = campaign.clone();
+        }
+        if let Some(width) = self.width {
+            config.display.width = Some(width.into());
+        }
+        if let Some(height) = self.height {
+            config.display.height = Some(height.into());
+        }
+        if let Some(fps) = self.fps {
+            config.display.frame_rate = fps;
+        }
+        if let Some(speed) = self.speed {
+            config.game.speed = speed;
+        }
+        if let Some(seed) = self.seed {
+            config.game.seed = Some(seed);
+        }
+        if self.mute {
+            config.sound.music_volume = 0.0;
+            config.sound.effects_volume = 0.0;
+            config.sound.bell = false;
+        }
+        // https://no-color.org: any non-empty `NO_COLOR` turns color off
+        if self.no_color || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            config.display.colored = false;
+        }
+    }
+}
+
+/// Reads a `--speed` value, which must be within the range the
+/// configuration allows
+fn parse_speed(text: &str) -> Result<f32, String> {
+    let speed: f32 = text.parse().map_err(|_| format!("`{}` isn't a number", text))?;
+    if (MIN_SPEED..=MAX_SPEED).contains(&speed) {
+        Ok(speed)
+    } else {
+        Err(format!("must be from {} to {}", MIN_SPEED, MAX_SPEED))
+    }
+}
+
+fn main() -> io::Result<()> {
+    let cli = Cli::parse();
+    if let Some(path) = &cli.sign_tournament {
+        match tournament::sign_file(path) {
+            Ok(token) => println!("{}", token),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    if let Some(path) = &cli.verify_result {
+        match tournament::verify_file(path) {
+            Ok(summary) => println!("{}", summary),
+            Err(e) => {
+                eprintln!("rejected: {}", e);
+                process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    let tournament = cli.tournament.as_deref().map(|token| {
+        Token::parse(token).unwrap_or_else(|e| {
+            let message = format!("invalid tournament token: {}", e);
+            Cli::command().error(ErrorKind::InvalidValue, message).exit()
+        })
+    });
+    if let Some(dir) = &cli.data_dir {
+        paths::set_portable_dir(dir.clone());
+    }
+    let ghost = cli.ghost.as_deref().map(|path| {
+        let log = InputLog::load(path).unwrap_or_else(|e| {
+            eprintln!("could not load {}: {}", path.display(), e);
+            process::exit(1);
+        });
+        if log.modifiers.two_players || log.modifiers.versus {
+            eprintln!("{} isn't a one-player run, so it can't be raced", path.display());
+            process::exit(1);
+        }
+        log
+    });
+    let replay = cli.replay.as_deref().map(|path| {
+        ReplayFile::open(path).unwrap_or_else(|e| {
+            eprintln!("could not load {}: {}", path.display(), e);
+            process::exit(1);
+        })
+    });
+    let script = cli.input_script.as_deref().map(|path| {
+        InputScript::load(path).unwrap_or_else(|e| {
+            eprintln!("could not load {}: {}", path.display(), e);
+            process::exit(1);
+        })
+    });
+
+    let (mut config, diagnostics) = match Config::load() {
+        Ok(config) => (config, Vec::new()),
+        Err(diagnostics) => (Config::default(), diagnostics),
+    };
+    cli.apply(&mut config);
+    if cli.headless {
+        // Problems in the file are reported, but don't stop a run in CI
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        let strategy = Strategy::by_name(&cli.bot).unwrap_or(Strategy::Hunter);
+        let seed = config.game.seed.unwrap_or_else(rand::random);
+        return headless::run(&config, seed, cli.ticks, strategy, cli.export_json.as_deref());
+    }
+    if let Some(ticks) = cli.bench {
+        let seed = config.game.seed.unwrap_or_else(rand::random);
+        return bench::run(&config, seed, ticks);
+    }
+    if let Some(port) = cli.arcade {
+        // Nobody is at the server's terminal to read these on a screen
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        // Nor to hear anything: the sound would come out of the server
+        config.sound.music_volume = 0.0;
+        config.sound.effects_volume = 0.0;
+        let host_key = ssh::host_key()?;
+        let address = SocketAddr::new(cli.bind, port);
+        println!("Serving the arcade on {}; play with ssh -p {} play@HOST", address, port);
+        println!("No password is asked for: anyone who can reach {} can play", address);
+        return ssh::serve(address, host_key, move |session| {
+            if let Err(e) = play_session(&config, session) {
+                eprintln!("An arcade session ended with an error: {}", e);
+            }
+        });
+    }
 
-        // Game logic
-        if last_frame.elapsed() >= frame_duration {
-            game.move_bullets();
-            game.move_enemies();
-            game.enemy_shoot();
-            last_frame = Instant::now();
+    // Everything drawn is copied to spectators, if they are let in
+    let (out, spectators) = match cli.spectate {
+        Some(port) => {
+            let (spectators, catch_up) = Spectators::listen(port)?;
+            (backend::Terminal::with_output(Box::new(spectators)), Some(catch_up))
         }
+        None => (backend::Terminal::new(), None),
+    };
+
+    // Restores the terminal when dropped, whether the game loop ends
+    // normally, with an error or with a panic
+    let terminal = TerminalGuard::enter(config.input.key_releases)?;
+    let mut app = App::new(&config, diagnostics, tournament, Box::new(out));
+    app.spectators = spectators;
+    app.movement.set_key_releases(terminal.key_releases());
+    app.movement_two.set_key_releases(terminal.key_releases());
+    app.export_path = cli.export_json;
+    app.seed = config.game.seed;
+    app.ghost_log = ghost;
+    if let Some(file) = replay {
+        app.watch_replay_file(file);
+    }
+    if let Some(script) = script {
+        let seed = config.game.seed.unwrap_or_else(rand::random);
+        app.watch_replay(script.log(seed, app.game_builder.modifiers()));
+    }
+    if let Some(port) = cli.host {
+        app.host(SocketAddr::new(cli.bind, port));
+    }
+    if let Some(address) = cli.join {
+        app.join(address);
+    }
+    run(&mut app, &mut Events::Terminal)?;
+    let suspended = app.suspend_run();
+    drop(terminal);
+    if let Err(e) = suspended {
+        eprintln!("Could not save the run to continue later: {}", e);
+    }
+    Ok(())
+}
+
+/// Plays one arcade session, until the player quits or leaves
+///
+/// # Arguments
+/// * `config` - User configuration, shared by every session
+/// * `session` - The player's SSH session
+///
+/// # Returns
+/// A `Result` indicating a clean exit, or an error if the player's
+/// connection failed
+fn play_session(config: &Config, session: ssh::Session) -> io::Result<()> {
+    let mut output = session.output.clone();
+    execute!(output, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let out = backend::Terminal::with_output(Box::new(session.output.clone()));
+    let mut app = App::new(config, Vec::new(), None, Box::new(out));
+    app.serve_arcade(config, &session);
+    app.seed = config.game.seed;
+    let played = run(&mut app, &mut Events::Session(session.input));
+    let _ = execute!(output, cursor::Show, terminal::LeaveAlternateScreen);
+    output.close(if played.is_ok() { 0 } else { 1 });
+    played
+}
 
-        // Render
-        game.render_colored()?;
+/// Where the game loop's key presses and resizes come from
+enum Events {
+    /// The terminal the game was started in
+    Terminal,
+    /// An arcade player's terminal, over SSH
+    Session(ssh::Input),
+}
 
-        // Check game end conditions
-        if game.enemies.is_empty() {
-            println!("\nCongratulations! You won!");
-            break;
+impl Events {
+    /// Waits for an event until a timeout, like `event::poll`
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        match self {
+            Events::Terminal => event::poll(timeout),
+            Events::Session(input) => input.poll(timeout),
         }
+    }
 
-        // Slight pause to control game speed
-        thread::sleep(Duration::from_millis(50));
+    /// Takes the next event, like `event::read`
+    fn read(&mut self) -> io::Result<Event> {
+        match self {
+            Events::Terminal => event::read(),
+            Events::Session(input) => input.read(),
+        }
     }
+}
 
-    // Clean up terminal
-    terminal::disable_raw_mode()?;
+/// Runs the game loop until the player quits
+///
+/// # Arguments
+/// * `app` - The game
+/// * `events` - Where the player's input comes from
+///
+/// # Returns
+/// A `Result` indicating a clean exit or a terminal error
+fn run(app: &mut App, events: &mut Events) -> io::Result<()> {
+    let mut next_frame = Instant::now();
+    // Actions carried out since the last frame
+    let mut actions = Vec::new();
+    while !app.quit {
+        if Instant::now() >= next_frame {
+            app.poll_controllers();
+            app.update();
+            if app.needs_render() {
+                let render_start = Instant::now();
+                app.render()?;
+                app.perf.record_frame(render_start.elapsed());
+                app.redraw = false;
+            }
+            // Frames keep to a fixed schedule however long this one took, so
+            // they come at an even pace; one that fell a whole frame behind
+            // starts the schedule over rather than rushing to catch up
+            next_frame += app.frame_interval();
+            if next_frame <= Instant::now() {
+                next_frame = Instant::now() + app.frame_interval();
+            }
+            actions.clear();
+        }
 
-    if game.game_over {
-        println!("\nGame Over! Final Score: {}", game.score);
+        // Wait for input until the next frame is due, handling each event as
+        // it arrives so the simulation gets commands as soon as they are
+        // given, whatever the tick and render rates
+        if !events.poll(next_frame.saturating_duration_since(Instant::now()))? {
+            continue;
+        }
+        match events.read()? {
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Release => app.handle_key_release(key_event.code),
+            Event::Key(key_event) => {
+                // Each action (or unbound key) is carried out once per frame,
+                // so a burst of key repeats can't pile up, while different
+                // keys pressed together all get through
+                let action = app.command_for(key_event.code).ok_or(key_event.code);
+                if !actions.contains(&action) {
+                    actions.push(action);
+                    app.handle_key(key_event.code);
+                }
+            }
+            Event::Resize(columns, rows) => app.handle_resize(columns, rows),
+            _ => {}
+        }
+        // Screens that only redraw on demand show the result straight away
+        if app.redraw && app.state != GameState::Playing {
+            next_frame = Instant::now();
+        }
     }
 
     Ok(())
@@ -0,0 +1,138 @@
+//! Score-attack medals
+//!
+//! Bronze/silver/gold thresholds are defined per game mode in `data/medals.toml`,
+//! which is bundled into the binary.
+
+use serde::{Deserialize, Serialize};
+use space_invaders::schema::{self, Diagnostic, Field, FieldKind};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Bundled medal thresholds for every game mode
+const MEDALS_TOML: &str = include_str!("../data/medals.toml");
+
+/// Expected layout of a medal table: one table of thresholds per game mode
+const SCHEMA: FieldKind = FieldKind::Map(&FieldKind::Table(&[
+    Field { name: "bronze", kind: THRESHOLD },
+    Field { name: "silver", kind: THRESHOLD },
+    Field { name: "gold", kind: THRESHOLD },
+]));
+
+/// Allowed range for a single medal threshold
+const THRESHOLD: FieldKind = FieldKind::Integer { min: 0, max: 1_000_000 };
+
+/// A medal a run can earn, ordered from lowest to highest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl fmt::Display for Medal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Medal::Bronze => "Bronze",
+            Medal::Silver => "Silver",
+            Medal::Gold => "Gold",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Scores needed for each medal in one game mode
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct MedalThresholds {
+    pub bronze: usize,
+    pub silver: usize,
+    pub gold: usize,
+}
+
+impl MedalThresholds {
+    /// Returns the best medal earned by a score, if any
+    ///
+    /// # Arguments
+    /// * `score` - The score to rate
+    pub fn medal_for(&self, score: usize) -> Option<Medal> {
+        if score >= self.gold {
+            Some(Medal::Gold)
+        } else if score >= self.silver {
+            Some(Medal::Silver)
+        } else if score >= self.bronze {
+            Some(Medal::Bronze)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the next medal a score is working towards and its threshold
+    ///
+    /// # Arguments
+    /// * `score` - The current score
+    ///
+    /// # Returns
+    /// `None` once gold has been reached
+    pub fn next_medal(&self, score: usize) -> Option<(Medal, usize)> {
+        match self.medal_for(score) {
+            None => Some((Medal::Bronze, self.bronze)),
+            Some(Medal::Bronze) => Some((Medal::Silver, self.silver)),
+            Some(Medal::Silver) => Some((Medal::Gold, self.gold)),
+            Some(Medal::Gold) => None,
+        }
+    }
+}
+
+/// Medal thresholds for all game modes, keyed by mode name
+pub struct MedalTable {
+    modes: HashMap<String, MedalThresholds>,
+}
+
+impl MedalTable {
+    /// Loads and validates the medal table bundled with the game
+    ///
+    /// # Returns
+    /// The table, or every problem found in it
+    pub fn bundled() -> Result<Self, Vec<Diagnostic>> {
+        Self::parse("data/medals.toml", MEDALS_TOML)
+    }
+
+    /// Parses and validates a medal table
+    ///
+    /// # Arguments
+    /// * `file` - File name used in diagnostics
+    /// * `source` - Contents of the file
+    fn parse(file: &str, source: &str) -> Result<Self, Vec<Diagnostic>> {
+        let mut diagnostics = schema::validate(file, source, &SCHEMA);
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        let modes: HashMap<String, MedalThresholds> =
+            toml::from_str(source).map_err(|e| vec![schema::parse_error(file, source, &e)])?;
+
+        // Thresholds must rise from bronze to gold or medals would be skipped
+        for (mode, t) in &modes {
+            if !(t.bronze < t.silver && t.silver < t.gold) {
+                diagnostics.push(Diagnostic {
+                    file: file.to_string(),
+                    line: None,
+                    message: format!("medals for `{}` must satisfy bronze < silver < gold", mode),
+                    suggestion: None,
+                });
+            }
+        }
+        if diagnostics.is_empty() {
+            Ok(MedalTable { modes })
+        } else {
+            Err(diagnostics)
+        }
+    }
+
+    /// Returns the thresholds for a game mode
+    ///
+    /// # Arguments
+    /// * `mode` - Name of the game mode, e.g. `"classic"`
+    pub fn thresholds(&self, mode: &str) -> Option<MedalThresholds> {
+        self.modes.get(mode).copied()
+    }
+}
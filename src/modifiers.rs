@@ -0,0 +1,114 @@
+//! Settings that change how a run plays, recorded so scores stay comparable
+
+use crate::campaign::Campaign;
+use crate::game::Game;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Every gameplay-affecting setting a run was played with
+///
+/// Cosmetic settings (skin, colors, alerts) are left out since they don't
+/// change what a score means.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RunModifiers {
+    /// Width of the playfield, in cells
+    pub width: usize,
+    /// Height of the playfield, in cells
+    pub height: usize,
+    /// Number of rows in the enemy formation
+    pub enemy_rows: usize,
+    /// Number of enemies in each formation row
+    pub enemy_columns: usize,
+    /// Horizontal speed of the enemy formation, in cells per second
+    pub enemy_speed: f32,
+    /// Chance of each enemy firing on any one tick
+    pub fire_chance: f64,
+    /// Aggressiveness of the AI wingman, if one flew at any point in the run
+    pub wingman: Option<f32>,
+    /// Whether two players shared the run; missing from files written before
+    /// two-player runs existed
+    #[serde(default)]
+    pub two_players: bool,
+    /// Whether a second player controlled the invaders; missing from files
+    /// written before versus runs existed
+    #[serde(default)]
+    pub versus: bool,
+    /// Most bullets the players had in flight at once; missing from files
+    /// written before there were caps, whose runs had none
+    #[serde(default = "uncapped")]
+    pub max_player_bullets: usize,
+    /// Most bullets the enemies had in flight at once
+    #[serde(default = "uncapped")]
+    pub max_enemy_bullets: usize,
+    /// Most explosion particles at once
+    #[serde(default = "uncapped")]
+    pub max_particles: usize,
+    /// Campaign whose waves were played, with the difficulty applied,
+    /// instead of the single formation above; missing from files written
+    /// before there were campaigns
+    #[serde(default)]
+    pub campaign: Option<Campaign>,
+}
+
+/// The cap of runs recorded before there were caps
+fn uncapped() -> usize {
+    usize::MAX
+}
+
+impl fmt::Display for RunModifiers {
+    /// Summarizes the settings that differ from the defaults on one line,
+    /// e.g. `80x30 playfield, 6 enemy rows, wingman (0.5)`
+    ///
+    /// A campaign is named instead of the formation settings it replaces.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let standard = Game::builder().modifiers();
+        let mut parts = Vec::new();
+        if (self.width, self.height) != (standard.width, standard.height) {
+            parts.push(format!("{}x{} playfield", self.width, self.height));
+        }
+        if let Some(campaign) = &self.campaign {
+            parts.push(format!("{} campaign", campaign.name));
+        } else {
+            if self.enemy_rows != standard.enemy_rows {
+                parts.push(format!("{} enemy rows", self.enemy_rows));
+            }
+            if self.enemy_columns != standard.enemy_columns {
+                parts.push(format!("{} enemy columns", self.enemy_columns));
+            }
+            if self.enemy_speed != standard.enemy_speed {
+                parts.push(format!("enemy speed {:.1}", self.enemy_speed));
+            }
+            if self.fire_chance != standard.fire_chance {
+                parts.push(format!("fire chance {:.3}", self.fire_chance));
+            }
+        }
+        let caps = [
+            ("player bullets", self.max_player_bullets, standard.max_player_bullets),
+            ("enemy bullets", self.max_enemy_bullets, standard.max_enemy_bullets),
+            ("particles", self.max_particles, standard.max_particles),
+        ];
+        for (name, cap, standard) in caps {
+            match cap {
+                _ if cap == standard => {}
+                usize::MAX => parts.push(format!("uncapped {}", name)),
+                _ => parts.push(format!("{} capped at {}", name, cap)),
+            }
+        }
+        if self.two_players {
+            parts.push("two players".to_string());
+        }
+        if self.versus {
+            parts.push("versus".to_string());
+        }
+        if let Some(aggressiveness) = self.wingman {
+            parts.push(format!("wingman ({:.1})", aggressiveness));
+        }
+
+        if parts.is_empty() {
+            write!(f, "standard")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
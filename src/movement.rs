@@ -0,0 +1,129 @@
+//! Holding Left or Right to keep the ship moving
+//!
+//! Terminals don't report keys being released, only repeated presses once a
+//! key has been held for a moment. A single tap moves the ship one cell; a
+//! second press of the same key soon after (the terminal's first repeat, or
+//! a quick double tap) starts the ship moving every tick at a steady speed,
+//! until the opposite key is pressed or the presses stop coming.
+//!
+//! Terminals with the enhanced keyboard protocol do report releases. There a
+//! press starts the ship moving straight away and it keeps going until the
+//! key is let go, with no guessing from repeats.
+
+use space_invaders::game::TICK_DURATION;
+use space_invaders::input::PlayerCommand;
+use std::time::{Duration, Instant};
+
+/// Longest gap between the first two presses of a key that still counts as
+/// holding it, allowing for slow initial key repeat
+const REPEAT_DELAY: Duration = Duration::from_millis(800);
+
+/// Keeps the player's ship moving while a movement key is held
+pub struct HeldMovement {
+    /// Cells moved per second while a key is held
+    speed: f32,
+    /// How long after the last press the ship stops
+    timeout: Duration,
+    /// The last movement key pressed, and when
+    last_press: Option<(PlayerCommand, Instant)>,
+    /// Whether the last key pressed is being held
+    holding: bool,
+    /// Fraction of a cell moved but not yet sent as a command
+    progress: f32,
+    /// Whether the terminal reports keys being released
+    key_releases: bool,
+}
+
+impl HeldMovement {
+    /// Creates the tracker, with no key held
+    ///
+    /// # Arguments
+    /// * `speed` - Cells moved per second while a key is held
+    /// * `timeout` - How long after the last press the ship stops
+    pub fn new(speed: f32, timeout: Duration) -> Self {
+        HeldMovement { speed, timeout, last_press: None, holding: false, progress: 0.0, key_releases: false }
+    }
+
+    /// Switches to following key releases, for terminals that report them
+    ///
+    /// # Arguments
+    /// * `key_releases` - Whether the terminal reports keys being released
+    pub fn set_key_releases(&mut self, key_releases: bool) {
+        self.key_releases = key_releases;
+        self.stop();
+    }
+
+    /// Notes a press of a movement key
+    ///
+    /// # Arguments
+    /// * `command` - `MoveLeft` or `MoveRight`
+    ///
+    /// # Returns
+    /// The command to carry out straight away, if any
+    pub fn press(&mut self, command: PlayerCommand) -> Option<PlayerCommand> {
+        let now = Instant::now();
+        if self.key_releases {
+            // Presses of a key already held are the terminal repeating it
+            if self.holding && self.last_press.is_some_and(|(held, _)| held == command) {
+                return None;
+            }
+            self.last_press = Some((command, now));
+            self.holding = true;
+            self.progress = 0.0;
+            return Some(command);
+        }
+        match self.last_press {
+            // The opposite key stops the ship
+            Some((held, _)) if self.holding && held != command => {
+                self.stop();
+                None
+            }
+            Some((held, _)) if self.holding && held == command => {
+                self.last_press = Some((command, now));
+                None
+            }
+            Some((tapped, at)) if tapped == command && now - at <= REPEAT_DELAY => {
+                self.last_press = Some((command, now));
+                self.holding = true;
+                None
+            }
+            _ => {
+                self.last_press = Some((command, now));
+                Some(command)
+            }
+        }
+    }
+
+    /// Notes a movement key being let go, on terminals that report it
+    ///
+    /// # Arguments
+    /// * `command` - `MoveLeft` or `MoveRight`
+    pub fn release(&mut self, command: PlayerCommand) {
+        if self.last_press.is_some_and(|(held, _)| held == command) {
+            self.stop();
+        }
+    }
+
+    /// Stops the ship, e.g. when the run is paused
+    pub fn stop(&mut self) {
+        self.last_press = None;
+        self.holding = false;
+        self.progress = 0.0;
+    }
+
+    /// Moves a held ship on by a tick; call once per game tick
+    ///
+    /// # Returns
+    /// The commands for the next tick
+    pub fn tick(&mut self) -> Vec<PlayerCommand> {
+        let Some((command, at)) = self.last_press.filter(|_| self.holding) else { return Vec::new() };
+        if !self.key_releases && at.elapsed() > self.timeout {
+            self.stop();
+            return Vec::new();
+        }
+        self.progress += self.speed * TICK_DURATION.as_secs_f32();
+        let cells = self.progress.floor();
+        self.progress -= cells;
+        vec![command; cells as usize]
+    }
+}
@@ -0,0 +1,318 @@
+//! Co-op runs played over TCP, for `--host` and `--join`
+//!
+//! Both computers simulate the whole run. Since `Game::step` is deterministic,
+//! they stay in sync as long as every tick is stepped with the same commands
+//! on both, so only commands cross the network: each tick, a peer sends the
+//! commands its player issued and waits for the other peer's.
+//!
+//! To hide the round trip, commands are scheduled a few ticks ahead (the
+//! input delay), picked by the host from the latency it measures while
+//! connecting. A peer that falls behind holds the other one up rather than
+//! letting the games drift apart.
+//!
+//! Messages are JSON lines. The host's player flies the first ship and the
+//! joining player the second; each peer sends its player's commands as
+//! one-player commands and the second player's are translated on arrival.
+
+use crate::campaign::{MAX_COLUMNS, MAX_ROWS};
+use crate::config::{MAX_HEIGHT, MAX_WIDTH, MIN_HEIGHT, MIN_WIDTH};
+use crate::game::TICK_DURATION;
+use crate::input::PlayerCommand;
+use crate::modifiers::RunModifiers;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::RangeInclusive;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Version of the message format; peers only play with the same version
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Most ticks of input delay, however slow the connection
+pub const MAX_DELAY: u64 = 10;
+/// Round trips timed to pick the input delay
+const PINGS: u64 = 5;
+/// Longest to wait for the other peer before giving up on it
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest a host waits for a player to join
+pub const JOIN_TIMEOUT: Duration = Duration::from_secs(300);
+/// How often a waiting host checks for a player joining
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+/// Formation speeds `config.toml` allows, from the slowest on easy to the
+/// fastest on hard
+const ENEMY_SPEEDS: RangeInclusive<f32> = 0.375..=30.0;
+
+/// A message between the two peers
+#[derive(Serialize, Deserialize)]
+enum Message {
+    /// Asks for a `Pong` with the same number, to time a round trip
+    Ping(u64),
+    /// Answers a `Ping`
+    Pong(u64),
+    /// Sent by the host once connected: the run both peers will play
+    Hello {
+        /// Must match the joining peer's `PROTOCOL_VERSION`
+        version: u32,
+        /// Seed the run's game is built with
+        seed: u64,
+        /// Settings the run's game is built with
+        modifiers: RunModifiers,
+        /// Ticks of input delay
+        delay: u64,
+    },
+    /// The commands a peer's player issued for a tick
+    Frame {
+        /// Tick the commands are applied on
+        tick: u64,
+        /// The commands, as one-player commands
+        commands: Vec<PlayerCommand>,
+    },
+}
+
+/// The connection to the other computer in a networked co-op run
+pub struct Peer {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Whether this peer is the host, whose player flies the first ship
+    host: bool,
+    /// Ticks between a command being issued and it being applied
+    delay: u64,
+    /// Commands this peer's player issued, by the tick they're applied on
+    local: BTreeMap<u64, Vec<PlayerCommand>>,
+    /// Commands the other player issued, by the tick they're applied on
+    remote: BTreeMap<u64, Vec<PlayerCommand>>,
+}
+
+/// The run agreed on while connecting
+pub struct Session {
+    /// Seed the run's game is built with
+    pub seed: u64,
+    /// Settings the run's game is built with
+    pub modifiers: RunModifiers,
+    /// The connection, ready for the run's first tick
+    pub peer: Peer,
+}
+
+impl Peer {
+    /// Waits for a player to join on an address, then agrees on the run with
+    /// them
+    ///
+    /// # Arguments
+    /// * `address` - Address and TCP port to listen on; other computers can
+    ///   only join on an address they can reach, not a loopback one
+    /// * `seed` - Seed the run's game is built with
+    /// * `modifiers` - Settings the run's game is built with; two players
+    ///
+    /// # Returns
+    /// The run, or an error if nobody joined within [`JOIN_TIMEOUT`] or the
+    /// connection failed
+    pub fn host(address: SocketAddr, seed: u64, modifiers: RunModifiers) -> io::Result<Session> {
+        let listener = TcpListener::bind(address)?;
+        let stream = accept_within(&listener, JOIN_TIMEOUT)?;
+        let mut peer = Peer::new(stream, true)?;
+
+        // Half the round trip is how long a frame takes to arrive
+        let mut round_trips = Vec::new();
+        for ping in 0..PINGS {
+            let sent = Instant::now();
+            peer.send(&Message::Ping(ping))?;
+            loop {
+                match peer.receive()? {
+                    Message::Pong(pong) if pong == ping => break,
+                    _ => continue,
+                }
+            }
+            round_trips.push(sent.elapsed());
+        }
+        round_trips.sort();
+        let latency = round_trips[round_trips.len() / 2] / 2;
+        peer.delay = (latency.as_millis() / TICK_DURATION.as_millis()) as u64 + 1;
+        peer.delay = peer.delay.min(MAX_DELAY);
+
+        let hello = Message::Hello { version: PROTOCOL_VERSION, seed, modifiers: modifiers.clone(), delay: peer.delay };
+        peer.send(&hello)?;
+        Ok(Session { seed, modifiers, peer })
+    }
+
+    /// Connects to a host, then takes on the run it offers
+    ///
+    /// # Arguments
+    /// * `address` - The host's address and port, e.g. `192.168.1.20:7777`
+    ///
+    /// # Returns
+    /// The run, or an error if the host couldn't be reached, plays a
+    /// different version or offers a run this game can't play
+    pub fn join(address: impl ToSocketAddrs) -> io::Result<Session> {
+        let stream = TcpStream::connect(address)?;
+        let mut peer = Peer::new(stream, false)?;
+        loop {
+            match peer.receive()? {
+                Message::Ping(ping) => peer.send(&Message::Pong(ping))?,
+                Message::Hello { version, seed, modifiers, delay } => {
+                    if version != PROTOCOL_VERSION {
+                        let message = format!("the host plays version {} of the protocol, not {}", version, PROTOCOL_VERSION);
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+                    }
+                    check_offer(&modifiers)?;
+                    peer.delay = delay.min(MAX_DELAY);
+                    return Ok(Session { seed, modifiers, peer });
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    /// Wraps a connected stream
+    fn new(stream: TcpStream, host: bool) -> io::Result<Self> {
+        // Frames are tiny and sent every tick, so they shouldn't wait to be batched
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        Ok(Peer {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            host,
+            delay: 1,
+            local: BTreeMap::new(),
+            remote: BTreeMap::new(),
+        })
+    }
+
+    /// Sends this peer's commands for a later tick and waits for the other
+    /// peer's commands for `tick`
+    ///
+    /// # Arguments
+    /// * `tick` - The tick about to be stepped
+    /// * `commands` - Commands this peer's player issued since the last tick
+    ///
+    /// # Returns
+    /// Both players' commands to step `tick` with, the same on both peers,
+    /// or an error if the other peer left or stopped responding
+    pub fn exchange(&mut self, tick: u64, commands: Vec<PlayerCommand>) -> io::Result<Vec<PlayerCommand>> {
+        let due = tick + self.delay;
+        self.send(&Message::Frame { tick: due, commands: commands.clone() })?;
+        self.local.insert(due, commands);
+
+        // Nobody could have issued commands for the first ticks of delay
+        let remote = if tick <= self.delay {
+            Vec::new()
+        } else {
+            loop {
+                if let Some(commands) = self.remote.remove(&tick) {
+                    break commands;
+                }
+                match self.receive()? {
+                    Message::Frame { tick, commands } => {
+                        self.remote.insert(tick, commands);
+                    }
+                    Message::Ping(ping) => self.send(&Message::Pong(ping))?,
+                    _ => {}
+                }
+            }
+        };
+        let local = self.local.remove(&tick).unwrap_or_default();
+
+        // The host's player goes first on both peers
+        let (first, second) = if self.host { (local, remote) } else { (remote, local) };
+        Ok(first.into_iter().chain(second.into_iter().filter_map(as_player_two)).collect())
+    }
+
+    /// Tells the other peer this one is still there, e.g. while paused
+    ///
+    /// # Returns
+    /// An error if the other peer has left
+    pub fn keep_alive(&mut self) -> io::Result<()> {
+        self.send(&Message::Ping(0))
+    }
+
+    /// Writes a message
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)
+    }
+
+    /// Waits for the next message
+    fn receive(&mut self) -> io::Result<Message> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the other player left"));
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+/// Waits for a connection on a listener
+///
+/// # Returns
+/// The connection, or a `TimedOut` error once `timeout` has passed
+fn accept_within(listener: &TcpListener, timeout: Duration) -> io::Result<TcpStream> {
+    listener.set_nonblocking(true)?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                return Ok(stream);
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock && Instant::now() < deadline => thread::sleep(ACCEPT_POLL),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                let message = format!("nobody joined within {} minutes", timeout.as_secs() / 60);
+                return Err(io::Error::new(io::ErrorKind::TimedOut, message));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Checks that the run a host offers is one `config.toml` could have set up,
+/// since a host can send any settings at all
+///
+/// # Returns
+/// An `InvalidData` error naming the first setting out of range
+fn check_offer(modifiers: &RunModifiers) -> io::Result<()> {
+    let waves_fit = modifiers.campaign.as_ref().is_none_or(|campaign| {
+        !campaign.waves.is_empty()
+            && campaign.waves.iter().all(|wave| {
+                wave.layout.len() <= MAX_ROWS
+                    && wave.layout.iter().all(|row| row.chars().count() <= MAX_COLUMNS)
+                    && ENEMY_SPEEDS.contains(&wave.speed)
+                    && (0.0..=1.0).contains(&wave.fire_chance)
+            })
+    });
+    let checks = [
+        ((MIN_WIDTH..=MAX_WIDTH).contains(&modifiers.width), "playfield width"),
+        ((MIN_HEIGHT..=MAX_HEIGHT).contains(&modifiers.height), "playfield height"),
+        ((1..=8).contains(&modifiers.enemy_rows), "number of enemy rows"),
+        ((1..=40).contains(&modifiers.enemy_columns), "number of enemy columns"),
+        (ENEMY_SPEEDS.contains(&modifiers.enemy_speed), "enemy speed"),
+        ((0.0..=1.0).contains(&modifiers.fire_chance), "fire chance"),
+        (modifiers.wingman.is_none_or(|aggressiveness| (0.0..=1.0).contains(&aggressiveness)), "wingman"),
+        ((1..=10_000).contains(&modifiers.max_player_bullets), "cap on player bullets"),
+        ((1..=10_000).contains(&modifiers.max_enemy_bullets), "cap on enemy bullets"),
+        ((0..=10_000).contains(&modifiers.max_particles), "cap on particles"),
+        (waves_fit, "campaign"),
+    ];
+    if let Some((_, setting)) = checks.iter().find(|(fits, _)| !fits) {
+        let message = format!("the host offered a run with an impossible {}", setting);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+    if !modifiers.two_players || modifiers.versus {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "the host offered a run that isn't co-op"));
+    }
+    Ok(())
+}
+
+/// The second player's version of a command from their side of the network
+///
+/// # Returns
+/// The command, or `None` for one that doesn't reach the game
+fn as_player_two(command: PlayerCommand) -> Option<PlayerCommand> {
+    match command {
+        PlayerCommand::MoveLeft => Some(PlayerCommand::PlayerTwoLeft),
+        PlayerCommand::MoveRight => Some(PlayerCommand::PlayerTwoRight),
+        PlayerCommand::Fire => Some(PlayerCommand::PlayerTwoFire),
+        _ => None,
+    }
+}
@@ -0,0 +1,108 @@
+//! Locations of files the game reads and writes outside the repository
+//!
+//! Each platform's usual place is used: the XDG directories on Linux and
+//! other Unix systems, `%APPDATA%` on Windows and `Application Support` on
+//! macOS. `--data-dir` replaces them all with one directory, for portable
+//! installs.
+
+use std::env;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Name of the game's directory inside each platform directory
+const APP_DIR: &str = "space-shooters";
+
+/// Directory holding every file, when one was given with `--data-dir`
+static PORTABLE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keeps configuration and data together in one directory instead of the
+/// platform's usual places; call before anything is loaded
+///
+/// Only the first call has any effect.
+///
+/// # Arguments
+/// * `dir` - Directory to keep everything in, e.g. next to the executable
+pub fn set_portable_dir(dir: PathBuf) {
+    let _ = PORTABLE_DIR.set(dir);
+}
+
+/// Returns the directory used for persistent game data (profiles, scores)
+///
+/// Follows `$XDG_DATA_HOME`, falling back to `~/.local/share/space-shooters`,
+/// or the current directory when no home directory is known.
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = PORTABLE_DIR.get() {
+        return dir.clone();
+    }
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// Returns the directory holding the user's configuration files
+///
+/// Follows `$XDG_CONFIG_HOME`, falling back to `~/.config/space-shooters`,
+/// or the current directory when no home directory is known.
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = PORTABLE_DIR.get() {
+        return dir.clone();
+    }
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// The game's directory in an XDG base directory
+///
+/// # Arguments
+/// * `variable` - Environment variable naming the base directory
+/// * `fallback` - The base directory relative to the home directory, when
+///   the variable isn't set
+#[cfg(not(any(windows, target_os = "macos")))]
+fn xdg_dir(variable: &str, fallback: &str) -> PathBuf {
+    if let Some(dir) = env::var_os(variable) {
+        return PathBuf::from(dir).join(APP_DIR);
+    }
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(fallback).join(APP_DIR),
+        None => PathBuf::from("."),
+    }
+}
+
+/// Returns the directory used for persistent game data (profiles, scores)
+///
+/// `%APPDATA%\space-shooters`, or the current directory when `%APPDATA%`
+/// isn't set.
+#[cfg(windows)]
+pub fn data_dir() -> PathBuf {
+    match (PORTABLE_DIR.get(), env::var_os("APPDATA")) {
+        (Some(dir), _) => dir.clone(),
+        (None, Some(app_data)) => PathBuf::from(app_data).join(APP_DIR),
+        (None, None) => PathBuf::from("."),
+    }
+}
+
+/// Returns the directory holding the user's configuration files; the same
+/// as `data_dir`, as is usual on Windows
+#[cfg(windows)]
+pub fn config_dir() -> PathBuf {
+    data_dir()
+}
+
+/// Returns the directory used for persistent game data (profiles, scores)
+///
+/// `~/Library/Application Support/space-shooters`, or the current directory
+/// when no home directory is known.
+#[cfg(target_os = "macos")]
+pub fn data_dir() -> PathBuf {
+    match (PORTABLE_DIR.get(), env::var_os("HOME")) {
+        (Some(dir), _) => dir.clone(),
+        (None, Some(home)) => PathBuf::from(home).join("Library/Application Support").join(APP_DIR),
+        (None, None) => PathBuf::from("."),
+    }
+}
+
+/// Returns the directory holding the user's configuration files; the same
+/// as `data_dir`, as is usual on macOS
+#[cfg(target_os = "macos")]
+pub fn config_dir() -> PathBuf {
+    data_dir()
+}
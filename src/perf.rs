@@ -0,0 +1,70 @@
+//! Frame and tick rate measurements for the debug overlay
+//!
+//! Rates are averaged over the last second, so the overlay settles quickly
+//! after a hiccup but doesn't flicker from frame to frame.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How far back frames and ticks are counted
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Render and simulation rates over the last second
+#[derive(Default)]
+pub struct PerfStats {
+    /// When each recent frame was rendered, and how long it took
+    frames: VecDeque<(Instant, Duration)>,
+    /// When each recent tick arrived
+    ticks: VecDeque<Instant>,
+}
+
+impl PerfStats {
+    /// Counts a rendered frame
+    ///
+    /// # Arguments
+    /// * `duration` - How long rendering the frame took
+    pub fn record_frame(&mut self, duration: Duration) {
+        let now = Instant::now();
+        self.frames.push_back((now, duration));
+        self.forget(now);
+    }
+
+    /// Counts a simulation tick
+    pub fn record_tick(&mut self) {
+        let now = Instant::now();
+        self.ticks.push_back(now);
+        self.forget(now);
+    }
+
+    /// Drops frames and ticks older than the window
+    fn forget(&mut self, now: Instant) {
+        while self.frames.front().is_some_and(|&(at, _)| now - at > WINDOW) {
+            self.frames.pop_front();
+        }
+        while self.ticks.front().is_some_and(|&at| now - at > WINDOW) {
+            self.ticks.pop_front();
+        }
+    }
+
+    /// Frames rendered in the last second
+    pub fn fps(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Ticks simulated in the last second
+    pub fn tps(&self) -> usize {
+        self.ticks.len()
+    }
+
+    /// Shortest, average and longest frame time over the last second
+    ///
+    /// # Returns
+    /// `None` before any frame has been rendered
+    pub fn frame_times(&self) -> Option<(Duration, Duration, Duration)> {
+        let durations = self.frames.iter().map(|&(_, duration)| duration);
+        let min = durations.clone().min()?;
+        let max = durations.clone().max()?;
+        let average = durations.sum::<Duration>() / self.frames.len() as u32;
+        Some((min, average, max))
+    }
+}
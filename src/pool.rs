@@ -0,0 +1,82 @@
+//! Pooled storage for bullets
+//!
+//! Bullets are fired and destroyed constantly. Rather than growing and
+//! compacting a `Vec` every tick, a pool keeps its slots and recycles the ones
+//! whose bullets are spent, so a run stops allocating once it has seen its
+//! busiest moment.
+//!
+//! A pool can also be capped: once it holds as many bullets as it may, a new
+//! bullet takes the slot of the oldest one, so no wave can fill the screen
+//! (and the frame) with more than the cap.
+
+use crate::entity::Bullet;
+use serde::{Deserialize, Serialize};
+
+/// A set of bullets whose slots are reused once the bullets are spent
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BulletPool {
+    /// Every slot ever used; `None` for slots free to reuse
+    slots: Vec<Option<Bullet>>,
+    /// When each slot's bullet was spawned, counted in spawns
+    born: Vec<u64>,
+    /// Indices of the free slots
+    free: Vec<usize>,
+    /// Most bullets the pool holds at once
+    limit: usize,
+    /// Bullets spawned so far
+    spawned: u64,
+}
+
+impl Default for BulletPool {
+    fn default() -> Self {
+        Self::with_limit(usize::MAX)
+    }
+}
+
+impl BulletPool {
+    /// Creates an empty pool holding at most a number of bullets
+    ///
+    /// # Arguments
+    /// * `limit` - Most bullets held at once; at least one
+    pub fn with_limit(limit: usize) -> Self {
+        BulletPool { slots: Vec::new(), born: Vec::new(), free: Vec::new(), limit: limit.max(1), spawned: 0 }
+    }
+
+    /// Adds a bullet, reusing a free slot if there is one, or the oldest
+    /// bullet's slot if the pool is full
+    pub fn spawn(&mut self, bullet: Bullet) {
+        self.spawned += 1;
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None if self.slots.len() < self.limit => {
+                self.slots.push(None);
+                self.born.push(0);
+                self.slots.len() - 1
+            }
+            // Full, and with no bullet spent since the last recycle
+            None => (0..self.slots.len()).min_by_key(|&index| self.born[index]).unwrap_or_default(),
+        };
+        self.slots[index] = Some(bullet);
+        self.born[index] = self.spawned;
+    }
+
+    /// Iterates over the bullets in the pool, including spent ones not yet recycled
+    pub fn iter(&self) -> impl Iterator<Item = &Bullet> {
+        self.slots.iter().flatten()
+    }
+
+    /// Iterates mutably over the bullets in the pool
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Bullet> {
+        self.slots.iter_mut().flatten()
+    }
+
+    /// Frees the slots of every spent bullet so they can be reused
+    pub fn recycle(&mut self) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.as_ref().is_some_and(|b| !b.body.alive) {
+                *slot = None;
+                self.free.push(index);
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Per-player profile persisted between runs
+
+use crate::medals::Medal;
+use crate::stats::LifetimeStats;
+use serde::{Deserialize, Serialize};
+use space_invaders::paths;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// A player's saved progress
+#[derive(Default, Serialize, Deserialize)]
+pub struct Profile {
+    /// Name the profile is stored under
+    #[serde(skip)]
+    pub name: String,
+    /// Best medal collected in each game mode
+    #[serde(default)]
+    pub medals: BTreeMap<String, Medal>,
+    /// Best score reached in each game mode
+    #[serde(default)]
+    pub high_scores: BTreeMap<String, usize>,
+    /// Furthest wave reached in each game mode
+    #[serde(default)]
+    pub best_waves: BTreeMap<String, usize>,
+    /// Totals of every run finished, in any mode
+    #[serde(default)]
+    pub lifetime: LifetimeStats,
+}
+
+impl Profile {
+    /// Loads the profile for the current user, or starts an empty one
+    ///
+    /// The profile is named after `$USER`; a missing or unreadable file gives a
+    /// fresh profile rather than an error so a run can always start.
+    pub fn load_current() -> Self {
+        let name = env::var("USER").unwrap_or_else(|_| "player".to_string());
+        let mut profile: Profile = fs::read_to_string(Self::path(&name))
+            .ok()
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+        profile.name = name;
+        profile
+    }
+
+    /// Starts a profile for someone playing without one of their own, as in
+    /// an `--arcade` session; it is never saved
+    pub fn guest() -> Self {
+        Profile { name: "guest".to_string(), ..Profile::default() }
+    }
+
+    /// Records a medal earned in a mode
+    ///
+    /// # Returns
+    /// `true` if the medal is better than the one previously collected
+    pub fn record_medal(&mut self, mode: &str, medal: Medal) -> bool {
+        match self.medals.get(mode) {
+            Some(best) if *best >= medal => false,
+            _ => {
+                self.medals.insert(mode.to_string(), medal);
+                true
+            }
+        }
+    }
+
+    /// Records the score and furthest wave of a finished run in a mode
+    ///
+    /// # Arguments
+    /// * `mode` - Game mode the run was played in
+    /// * `score` - Final score of the run
+    /// * `wave` - Wave the run ended on, counting from 1
+    ///
+    /// # Returns
+    /// `true` if either beats the previous best
+    pub fn record_score(&mut self, mode: &str, score: usize, wave: usize) -> bool {
+        let mut improved = false;
+        for (best, value) in [(&mut self.high_scores, score), (&mut self.best_waves, wave)] {
+            let best = best.entry(mode.to_string()).or_default();
+            if value > *best {
+                *best = value;
+                improved = true;
+            }
+        }
+        improved
+    }
+
+    /// Writes the profile to the data directory
+    ///
+    /// The profile is written next to the old one and moved into place, so
+    /// quitting or crashing part way through can't lose the scores in it.
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path(&self.name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        let temporary = path.with_extension("toml.tmp");
+        fs::write(&temporary, text)?;
+        fs::rename(temporary, path)
+    }
+
+    /// Location of a profile file
+    fn path(name: &str) -> PathBuf {
+        paths::data_dir().join("profiles").join(format!("{}.toml", name))
+    }
+}
@@ -0,0 +1,87 @@
+//! Sprites rasterized as pixel bitmaps, for the high-resolution renderers
+//!
+//! Renderers that pack several pixels into each terminal cell, and the
+//! graphical frontend, place sprites from their exact positions instead of
+//! whole cells, so motion is as smooth as their pixel grid allows.
+
+use crate::game::Snapshot;
+use crate::skin::Sprite;
+
+/// Pixels per cell of the fine bitmaps, horizontally and vertically
+pub const FINE_SCALE: (usize, usize) = (2, 4);
+
+/// Fine bitmap of a sprite, `#` for a set pixel
+///
+/// Bitmaps cover the sprite's whole `Sprite::size`, at `FINE_SCALE` pixels
+/// per cell.
+pub fn fine_bitmap(sprite: Sprite) -> &'static [&'static str] {
+    match sprite {
+        Sprite::Player | Sprite::PlayerHit | Sprite::PlayerTwo => &[
+            "..##..",
+            "..##..",
+            ".####.",
+            ".####.",
+            "######",
+            "######",
+            "##..##",
+            "#....#",
+        ],
+        Sprite::Wingman => &["..##..", ".####.", "######", "#.##.#"],
+        Sprite::Enemy => &[".#..#.", "######", "#.##.#", "#....#"],
+        Sprite::EnemyAlt => &[".#..#.", "######", "#.##.#", ".#..#."],
+        Sprite::PlayerBullet | Sprite::EnemyBullet => &["#.", "#.", "#.", ".."],
+        Sprite::Threat => &["#.", "#.", "..", "#."],
+        Sprite::Spark => &["#.", ".#", "#.", ".."],
+        Sprite::Ember => &["..", "#.", ".#", ".."],
+        Sprite::Ash => &["..", "..", "#.", ".."],
+        _ => &["##", "##", "##", "##"],
+    }
+}
+
+/// Draws every sprite of a snapshot into a grid of pixels
+///
+/// Sprites are anchored like in the cell renderer, at the middle column and
+/// top row of their position, and later sprites are drawn over earlier ones.
+/// Threat markers are drawn as sprites on the bottom row.
+///
+/// # Arguments
+/// * `snapshot` - The game state to draw
+/// * `alpha` - How far rendering is between the last tick and the next one
+/// * `scale` - Pixels per terminal cell, horizontally and vertically
+/// * `bitmap` - Pixel bitmap of each sprite, `#` for a set pixel, covering
+///   the sprite's whole `Sprite::size` at `scale`
+///
+/// # Returns
+/// A grid `scale` times the playfield's size, with the sprite covering each
+/// pixel or `None` where it is empty
+pub fn rasterize(
+    snapshot: &Snapshot,
+    alpha: f32,
+    scale: (usize, usize),
+    bitmap: fn(Sprite) -> &'static [&'static str],
+) -> Vec<Vec<Option<Sprite>>> {
+    let (scale_x, scale_y) = scale;
+    let (width, height) = (snapshot.width * scale_x, snapshot.height * scale_y);
+    let mut pixels = vec![vec![None; width]; height];
+
+    let threats = snapshot
+        .threats()
+        .iter()
+        .map(|&x| (Sprite::Threat, (x as f32, (snapshot.height - 1) as f32)));
+    for (sprite, (x, y)) in snapshot.positions(alpha).chain(threats) {
+        let left = ((x - (sprite.size().0 / 2) as f32) * scale_x as f32).round() as isize;
+        let top = (y * scale_y as f32).round() as isize;
+        for (row, line) in bitmap(sprite).iter().enumerate() {
+            for (column, pixel) in line.chars().enumerate() {
+                let (px, py) = (left + column as isize, top + row as isize);
+                if pixel != '#' || px < 0 || py < 0 {
+                    continue;
+                }
+                if let Some(slot) = pixels.get_mut(py as usize).and_then(|r| r.get_mut(px as usize)) {
+                    *slot = Some(sprite);
+                }
+            }
+        }
+    }
+    pixels
+}
@@ -0,0 +1,424 @@
+//! Replay files streamed to disk while a run is played
+//!
+//! A replay is the run's seed and settings followed by the commands applied on
+//! each tick, which is all `Game::step` needs to play the run again. Long runs
+//! would make for a lot of input to hold in memory, so entries are appended to
+//! the file as the run goes and flushed in chunks; a crash loses at most the
+//! last chunk.
+//!
+//! The file is JSON lines: a header line, then one line per tick that had input.
+//! Every `CHECKPOINT_TICKS` ticks a checkpoint line holds the whole game as it
+//! was at the end of that tick, so a viewer can seek to any point by picking
+//! the run up at the checkpoint before it instead of playing it from the
+//! start. The header carries `FORMAT_VERSION`, which changes whenever the
+//! format does.
+//!
+//! `InputLog` holds the same record in memory, for runs that need to be played
+//! again while the game is running (tournament results, ghosts) and for
+//! loading a replay file back. `ReplayFile` reads a replay file a piece at a
+//! time instead, for watching and seeking through runs of any length.
+
+use crate::events::Subscriber;
+use crate::game::{Game, SavedGame, Snapshot};
+use crate::input::PlayerCommand;
+use crate::modifiers::RunModifiers;
+use crate::paths;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Seek, SeekFrom, Write};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+
+/// Version of the replay format written by this build
+pub const FORMAT_VERSION: u32 = 2;
+/// Oldest replay format this build reads; version 1 files have no
+/// checkpoints, so seeking in them plays the run from the start
+pub const MIN_FORMAT_VERSION: u32 = 1;
+
+/// How many ticks apart checkpoints of the whole game are written: a minute
+/// of play at normal speed
+pub const CHECKPOINT_TICKS: u64 = 600;
+
+/// How many ticks of input are buffered before being flushed to disk
+const CHUNK_TICKS: u64 = 50;
+
+/// First line of a replay file; `M` is borrowed when writing, owned when reading
+#[derive(Serialize, Deserialize)]
+struct Header<M> {
+    version: u32,
+    seed: u64,
+    modifiers: M,
+}
+
+/// Commands applied on one tick; `I` is borrowed when writing, owned when reading
+///
+/// Checkpoint lines read as entries without commands.
+#[derive(Serialize, Deserialize)]
+struct Entry<I> {
+    tick: u64,
+    #[serde(default)]
+    inputs: I,
+}
+
+/// The whole game at the end of a tick; `G` is borrowed when writing, owned
+/// when reading, and ignored when only looking for checkpoints
+#[derive(Serialize, Deserialize)]
+struct Checkpoint<G> {
+    tick: u64,
+    game: G,
+}
+
+/// A replay file being written while its run is played
+pub struct ReplayStream {
+    file: BufWriter<File>,
+    /// Tick of the last flush
+    flushed_at: u64,
+}
+
+impl ReplayStream {
+    /// Starts the replay of a new run, replacing the previous one
+    ///
+    /// The replay is kept in `replays/latest.replay` in the data directory.
+    ///
+    /// # Arguments
+    /// * `seed` - Seed the run's game was built with
+    /// * `modifiers` - Settings the run's game was built with
+    pub fn create(seed: u64, modifiers: &RunModifiers) -> io::Result<Self> {
+        let dir = paths::data_dir().join("replays");
+        fs::create_dir_all(&dir)?;
+        Self::create_at(&dir.join("latest.replay"), seed, modifiers)
+    }
+
+    /// Starts the replay of a new run in a file of its own
+    ///
+    /// # Arguments
+    /// * `path` - The file, which is replaced if it exists
+    /// * `seed` - Seed the run's game was built with
+    /// * `modifiers` - Settings the run's game was built with
+    pub fn create_at(path: &Path, seed: u64, modifiers: &RunModifiers) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut file, &Header { version: FORMAT_VERSION, seed, modifiers })?;
+        writeln!(file)?;
+        Ok(ReplayStream { file, flushed_at: 0 })
+    }
+
+    /// Appends the commands applied on a tick; ticks without any are skipped
+    ///
+    /// Called for every tick, so buffered input is flushed on time even
+    /// while the player is idle.
+    ///
+    /// # Arguments
+    /// * `tick` - The tick the commands were applied on
+    /// * `inputs` - The commands, in the order they were applied
+    pub fn record(&mut self, tick: u64, inputs: &[PlayerCommand]) -> io::Result<()> {
+        if !inputs.is_empty() {
+            serde_json::to_writer(&mut self.file, &Entry { tick, inputs })?;
+            writeln!(self.file)?;
+        }
+        if tick - self.flushed_at >= CHUNK_TICKS {
+            self.file.flush()?;
+            self.flushed_at = tick;
+        }
+        Ok(())
+    }
+
+    /// Appends a checkpoint of the whole game, after the commands of the
+    /// tick it was saved on
+    ///
+    /// # Arguments
+    /// * `game` - The game, saved at the end of its last tick
+    pub fn checkpoint(&mut self, game: &SavedGame) -> io::Result<()> {
+        serde_json::to_writer(&mut self.file, &Checkpoint { tick: game.tick_count(), game })?;
+        writeln!(self.file)
+    }
+
+    /// Writes out everything buffered so far
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Every command applied during a run, with the seed and settings needed to
+/// play it again exactly
+///
+/// Ticks without input take no space, so even long runs stay small. The
+/// wingman is taken to have flown from the start if it flew at all, so runs
+/// that toggled it part way through don't play back the same.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputLog {
+    /// Seed the run's game was built with
+    pub seed: u64,
+    /// Settings the run's game was built with
+    pub modifiers: RunModifiers,
+    /// Last tick recorded
+    pub ticks: u64,
+    /// Commands of each tick that had any, in tick order
+    pub inputs: Vec<(u64, Vec<PlayerCommand>)>,
+}
+
+impl InputLog {
+    /// Starts the log of a new run
+    ///
+    /// # Arguments
+    /// * `seed` - Seed the run's game was built with
+    /// * `modifiers` - Settings the run's game was built with
+    pub fn new(seed: u64, modifiers: RunModifiers) -> Self {
+        InputLog { seed, modifiers, ticks: 0, inputs: Vec::new() }
+    }
+
+    /// Notes the commands applied on a tick; call for every tick, even those
+    /// without any
+    ///
+    /// # Arguments
+    /// * `tick` - The tick the commands were applied on
+    /// * `inputs` - The commands, in the order they were applied
+    pub fn record(&mut self, tick: u64, inputs: &[PlayerCommand]) {
+        self.ticks = tick;
+        if !inputs.is_empty() {
+            self.inputs.push((tick, inputs.to_vec()));
+        }
+    }
+
+    /// Reads a replay file written by `ReplayStream`, skipping its checkpoints
+    ///
+    /// Replay files only have ticks with input or a checkpoint, so the
+    /// loaded log ends on the last of those.
+    ///
+    /// # Arguments
+    /// * `path` - The replay file
+    ///
+    /// # Returns
+    /// The log, or an `InvalidData` error if the file isn't a replay in this
+    /// build's format
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header = read_header(lines.next().transpose()?)?;
+        let mut log = InputLog::new(header.seed, header.modifiers);
+        for line in lines {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let entry: Entry<Vec<PlayerCommand>> = serde_json::from_str(&line)?;
+            log.record(entry.tick, &entry.inputs);
+        }
+        Ok(log)
+    }
+
+    /// Plays the run again from the start
+    ///
+    /// # Returns
+    /// The game as it was on the last tick recorded, or when the run ended
+    /// if that came first
+    pub fn replay(&self) -> Game {
+        self.replay_with(&mut [])
+    }
+
+    /// Plays the run again from the start, passing its events on as they
+    /// happen, e.g. to rebuild statistics kept outside the game
+    ///
+    /// # Arguments
+    /// * `subscribers` - Consumers of every event of the run, in order
+    ///
+    /// # Returns
+    /// The game as it was on the last tick recorded, or when the run ended
+    /// if that came first
+    pub fn replay_with(&self, subscribers: &mut [&mut dyn Subscriber]) -> Game {
+        self.play_to(self.ticks, subscribers)
+    }
+
+    /// The run as it was at the end of a tick, e.g. to check a frame of it
+    /// against a golden file
+    ///
+    /// The run is played again from the start each time, so a tick looks the
+    /// same however often and in whatever order it is asked for. Ticks past
+    /// the last one recorded are played without input.
+    ///
+    /// # Arguments
+    /// * `tick` - The tick, or 0 for the run before its first tick
+    ///
+    /// # Returns
+    /// The game's state on that tick, or when the run ended if that came
+    /// first
+    pub fn snapshot_at(&self, tick: u64) -> Snapshot {
+        self.play_to(tick, &mut []).snapshot()
+    }
+
+    /// Plays the run again from the start up to a tick
+    ///
+    /// # Arguments
+    /// * `last` - The tick to stop after
+    /// * `subscribers` - Consumers of every event of the run, in order
+    fn play_to(&self, last: u64, subscribers: &mut [&mut dyn Subscriber]) -> Game {
+        let mut game = self.start();
+        let mut inputs = self.inputs.iter().peekable();
+        while game.tick_count() < last && game.outcome().is_none() {
+            let tick = game.tick_count() + 1;
+            let commands = inputs.next_if(|(t, _)| *t == tick).map(|(_, c)| c.as_slice());
+            game.step(commands.unwrap_or_default());
+            game.dispatch_events(subscribers);
+        }
+        game
+    }
+
+    /// Builds the run's game as it was before the first tick, e.g. to play
+    /// the run back at its own pace
+    pub fn start(&self) -> Game {
+        let modifiers = &self.modifiers;
+        let mut game = Game::builder()
+            .width(modifiers.width)
+            .height(modifiers.height)
+            .enemy_rows(modifiers.enemy_rows)
+            .enemy_columns(modifiers.enemy_columns)
+            .enemy_speed(modifiers.enemy_speed)
+            .fire_chance(modifiers.fire_chance)
+            .two_players(modifiers.two_players)
+            .versus(modifiers.versus)
+            .max_bullets(modifiers.max_player_bullets, modifiers.max_enemy_bullets)
+            .max_particles(modifiers.max_particles)
+            .campaign(modifiers.campaign.clone())
+            .seed(self.seed)
+            .build();
+        if let Some(aggressiveness) = modifiers.wingman {
+            game.toggle_wingman(aggressiveness);
+        }
+        game
+    }
+}
+
+/// A replay file read a piece at a time, so runs of any length can be
+/// watched and sought through without holding them in memory
+///
+/// Opening the file notes where each checkpoint is; seeking picks the run up
+/// at the last checkpoint before the tick sought and plays on from there.
+pub struct ReplayFile {
+    path: PathBuf,
+    /// The run's seed and settings, and its last tick; the commands are
+    /// left in the file
+    log: InputLog,
+    /// Tick and byte offset of each checkpoint line, in tick order
+    checkpoints: Vec<(u64, u64)>,
+    /// Byte offset of the first line after the header
+    body: u64,
+}
+
+impl ReplayFile {
+    /// Opens a replay file written by `ReplayStream`
+    ///
+    /// # Returns
+    /// The file, or an `InvalidData` error if it isn't a replay in a format
+    /// this build reads
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut line = String::new();
+        let mut offset = reader.read_line(&mut line)? as u64;
+        let header = read_header(Some(line.clone()).filter(|line| !line.is_empty()))?;
+        let mut log = InputLog::new(header.seed, header.modifiers);
+        let body = offset;
+
+        let mut checkpoints = Vec::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)? as u64;
+            if read == 0 {
+                break;
+            }
+            if let Ok(checkpoint) = serde_json::from_str::<Checkpoint<IgnoredAny>>(&line) {
+                checkpoints.push((checkpoint.tick, offset));
+                log.ticks = log.ticks.max(checkpoint.tick);
+            } else if !line.trim().is_empty() {
+                let entry: Entry<IgnoredAny> = serde_json::from_str(&line)?;
+                log.ticks = log.ticks.max(entry.tick);
+            }
+            offset += read;
+        }
+        Ok(ReplayFile { path: path.to_path_buf(), log, checkpoints, body })
+    }
+
+    /// The run's seed and settings, and its last tick, without its commands
+    pub fn log(&self) -> &InputLog {
+        &self.log
+    }
+
+    /// The run as it was at the end of a tick, and the commands of the ticks
+    /// after it, read from the file as they are needed
+    ///
+    /// # Arguments
+    /// * `tick` - The tick, or 0 for the run before its first tick
+    ///
+    /// # Returns
+    /// The game on that tick, or when the run ended if that came first, and
+    /// the commands of each later tick that had any, in tick order
+    pub fn seek(&self, tick: u64) -> io::Result<(Game, Peekable<ReplayInputs>)> {
+        let mut game = self.log.start();
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        match self.checkpoints.iter().rev().find(|(at, _)| *at <= tick) {
+            Some(&(_, offset)) => {
+                reader.seek(SeekFrom::Start(offset))?;
+                let mut line = String::new();
+                reader.read_line(&mut line)?;
+                let checkpoint: Checkpoint<SavedGame> = serde_json::from_str(&line)?;
+                game.restore(checkpoint.game);
+            }
+            None => {
+                reader.seek(SeekFrom::Start(self.body))?;
+            }
+        }
+
+        let mut inputs = ReplayInputs { lines: reader.lines() }.peekable();
+        while game.tick_count() < tick && game.outcome().is_none() {
+            let next = game.tick_count() + 1;
+            while inputs.next_if(|(t, _)| *t < next).is_some() {}
+            let commands = inputs.next_if(|(t, _)| *t == next).map(|(_, commands)| commands);
+            game.step(&commands.unwrap_or_default());
+            game.dispatch_events(&mut []);
+        }
+        while inputs.next_if(|(t, _)| *t <= game.tick_count()).is_some() {}
+        Ok((game, inputs))
+    }
+}
+
+/// The commands of a replay file's ticks, read from the file as they are
+/// needed; checkpoints are skipped, and reading stops at the first line that
+/// can't be read
+pub struct ReplayInputs {
+    lines: Lines<BufReader<File>>,
+}
+
+impl Iterator for ReplayInputs {
+    type Item = (u64, Vec<PlayerCommand>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?.ok()?;
+            let entry: Entry<Vec<PlayerCommand>> = serde_json::from_str(&line).ok()?;
+            if !entry.inputs.is_empty() {
+                return Some((entry.tick, entry.inputs));
+            }
+        }
+    }
+}
+
+/// Reads the header line of a replay file
+///
+/// # Arguments
+/// * `line` - The file's first line, or `None` if it is empty
+///
+/// # Returns
+/// The header, or an `InvalidData` error if the file isn't a replay in a
+/// format this build reads
+fn read_header(line: Option<String>) -> io::Result<Header<RunModifiers>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let Some(line) = line else {
+        return Err(invalid("replay file is empty".to_string()));
+    };
+    let header: Header<RunModifiers> = serde_json::from_str(&line)?;
+    if !(MIN_FORMAT_VERSION..=FORMAT_VERSION).contains(&header.version) {
+        return Err(invalid(format!(
+            "replay format version {} isn't supported (expected {} to {})",
+            header.version, MIN_FORMAT_VERSION, FORMAT_VERSION
+        )));
+    }
+    Ok(header)
+}
@@ -0,0 +1,312 @@
+//! Schema validation for TOML data files
+//!
+//! Files are checked against a small declarative schema before being
+//! deserialized, so mistakes are reported as friendly diagnostics with line
+//! numbers and suggestions instead of raw parser errors.
+
+use toml::{Table, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Expected shape of a value in a data file
+pub enum FieldKind {
+    /// `true` or `false`
+    Bool,
+    /// An integer within an inclusive range
+    Integer { min: i64, max: i64 },
+    /// A number (whole or fractional) within an inclusive range
+    Float { min: f64, max: f64 },
+    /// A string that must be one of the listed choices
+    Choice(&'static [&'static str]),
+    /// Any string
+    Text,
+    /// A table with a fixed set of keys
+    Table(&'static [Field]),
+    /// A table whose keys are free-form names, each holding the same kind of value
+    Map(&'static FieldKind),
+    /// An array whose items are all the same kind of value
+    List(&'static FieldKind),
+}
+
+/// A named key in a table schema
+pub struct Field {
+    pub name: &'static str,
+    pub kind: FieldKind,
+}
+
+/// A problem found while validating a file
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    /// Name of the file the problem is in
+    pub file: String,
+    /// 1-based line number, when it can be located
+    pub line: Option<usize>,
+    /// What is wrong
+    pub message: String,
+    /// A likely fix, if one is known
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file, line, self.message)?,
+            None => write!(f, "{}: {}", self.file, self.message)?,
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " ({})", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Validates a TOML document against a schema
+///
+/// # Arguments
+/// * `file` - File name used in diagnostics
+/// * `source` - Contents of the file
+/// * `root` - Expected shape of the whole document
+///
+/// # Returns
+/// Every problem found; an empty list means the file is valid
+pub fn validate(file: &str, source: &str, root: &FieldKind) -> Vec<Diagnostic> {
+    let table: Table = match source.parse() {
+        Ok(table) => table,
+        Err(e) => return vec![parse_error(file, source, &e)],
+    };
+    let mut checker = Checker {
+        file,
+        source,
+        diagnostics: Vec::new(),
+    };
+    checker.check(&Value::Table(table), root, &[]);
+    checker.diagnostics.sort_by_key(|d| d.line);
+    checker.diagnostics
+}
+
+/// Converts a TOML parse or deserialize error into a diagnostic
+///
+/// # Arguments
+/// * `file` - File name used in the diagnostic
+/// * `source` - Contents of the file, used to turn the error span into a line
+/// * `error` - The error reported by the parser
+pub fn parse_error(file: &str, source: &str, error: &toml::de::Error) -> Diagnostic {
+    Diagnostic {
+        file: file.to_string(),
+        line: error
+            .span()
+            .map(|span| source[..span.start].matches('\n').count() + 1),
+        message: error.message().trim().replace('\n', "; "),
+        suggestion: None,
+    }
+}
+
+/// Walks a parsed document, collecting diagnostics
+struct Checker<'a> {
+    file: &'a str,
+    source: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    /// Checks one value against its expected kind
+    ///
+    /// # Arguments
+    /// * `value` - The value to check
+    /// * `kind` - What the value should look like
+    /// * `path` - Keys leading to the value, used for messages and line lookup
+    fn check(&mut self, value: &Value, kind: &FieldKind, path: &[&str]) {
+        match (kind, value) {
+            (FieldKind::Bool, Value::Boolean(_)) => {}
+            (FieldKind::Integer { min, max }, Value::Integer(n)) => {
+                if n < min || n > max {
+                    self.report(
+                        path,
+                        format!("`{}` is {}, but must be between {} and {}", dotted(path), n, min, max),
+                        None,
+                    );
+                }
+            }
+            (FieldKind::Float { min, max }, Value::Float(_) | Value::Integer(_)) => {
+                let n = value.as_float().or(value.as_integer().map(|n| n as f64)).unwrap_or_default();
+                if n < *min || n > *max {
+                    self.report(
+                        path,
+                        format!("`{}` is {}, but must be between {} and {}", dotted(path), n, min, max),
+                        None,
+                    );
+                }
+            }
+            (FieldKind::Choice(choices), Value::String(s)) => {
+                if !choices.contains(&s.as_str()) {
+                    let suggestion = closest(s, choices.iter().copied())
+                        .map(|c| format!("did you mean \"{}\"?", c));
+                    self.report(
+                        path,
+                        format!("`{}` must be one of {}, found \"{}\"", dotted(path), quoted(choices), s),
+                        suggestion,
+                    );
+                }
+            }
+            (FieldKind::Text, Value::String(_)) => {}
+            (FieldKind::Table(fields), Value::Table(table)) => {
+                for (key, value) in table {
+                    let mut child = path.to_vec();
+                    child.push(key);
+                    match fields.iter().find(|f| f.name == key) {
+                        Some(field) => self.check(value, &field.kind, &child),
+                        None => {
+                            let suggestion = closest(key, fields.iter().map(|f| f.name))
+                                .map(|name| format!("did you mean `{}`?", name));
+                            self.report(&child, format!("unknown key `{}`", dotted(&child)), suggestion);
+                        }
+                    }
+                }
+            }
+            (FieldKind::Map(inner), Value::Table(table)) => {
+                for (key, value) in table {
+                    let mut child = path.to_vec();
+                    child.push(key);
+                    self.check(value, inner, &child);
+                }
+            }
+            (FieldKind::List(inner), Value::Array(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    let index = index.to_string();
+                    let mut child = path.to_vec();
+                    child.push(&index);
+                    self.check(item, inner, &child);
+                }
+            }
+            (kind, value) => {
+                self.report(
+                    path,
+                    format!(
+                        "`{}` should be {}, found {}",
+                        dotted(path),
+                        describe(kind),
+                        value.type_str()
+                    ),
+                    None,
+                );
+            }
+        }
+    }
+
+    /// Records a diagnostic for the key at `path`
+    fn report(&mut self, path: &[&str], message: String, suggestion: Option<String>) {
+        self.diagnostics.push(Diagnostic {
+            file: self.file.to_string(),
+            line: find_line(self.source, path),
+            message,
+            suggestion,
+        });
+    }
+}
+
+/// Finds the line a key is defined on, for `[section]` tables, `[[array]]`
+/// tables and `key = value` pairs
+///
+/// Items of an array of tables are named by their index, e.g.
+/// `waves.1.speed`. A key that can't be found is looked for in the table
+/// holding it instead, so an item of an inline array or table is found on
+/// the line it is written on.
+///
+/// # Returns
+/// The 1-based line number, or `None` if neither the key nor any table
+/// holding it can be found
+pub fn find_line(source: &str, path: &[&str]) -> Option<usize> {
+    let (key, section) = path.split_last()?;
+    let section = section.join(".");
+    let mut current = String::new();
+    let mut items: HashMap<&str, usize> = HashMap::new();
+    for (number, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix("[[").and_then(|l| l.strip_suffix("]]")) {
+            let name = header.trim();
+            let index = items.entry(name).or_default();
+            current = format!("{}.{}", name, index);
+            *index += 1;
+        } else if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = header.trim().to_string();
+        } else {
+            if current == section {
+                if let Some(rest) = line.strip_prefix(key) {
+                    if rest.trim_start().starts_with('=') {
+                        return Some(number + 1);
+                    }
+                }
+            }
+            continue;
+        }
+        if current == path.join(".") {
+            return Some(number + 1);
+        }
+    }
+    find_line(source, &path[..path.len() - 1])
+}
+
+/// Joins a key path with dots, e.g. `alerts.mode`
+fn dotted(path: &[&str]) -> String {
+    if path.is_empty() {
+        "(root)".to_string()
+    } else {
+        path.join(".")
+    }
+}
+
+/// Formats a list of choices as `"a", "b" or "c"`
+fn quoted(choices: &[&str]) -> String {
+    let quoted: Vec<String> = choices.iter().map(|c| format!("\"{}\"", c)).collect();
+    match quoted.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => quoted.join(""),
+    }
+}
+
+/// Describes a kind for "should be ..." messages
+fn describe(kind: &FieldKind) -> String {
+    match kind {
+        FieldKind::Bool => "true or false".to_string(),
+        FieldKind::Integer { min, max } => format!("a whole number from {} to {}", min, max),
+        FieldKind::Float { min, max } => format!("a number from {} to {}", min, max),
+        FieldKind::Choice(choices) => format!("one of {}", quoted(choices)),
+        FieldKind::Text => "a string".to_string(),
+        FieldKind::Table(_) | FieldKind::Map(_) => "a table".to_string(),
+        FieldKind::List(inner) => format!("a list of {}", describe_plural(inner)),
+    }
+}
+
+/// Describes the items of a list, for "should be a list of ..." messages
+fn describe_plural(kind: &FieldKind) -> String {
+    match kind {
+        FieldKind::Text => "strings".to_string(),
+        FieldKind::Table(_) | FieldKind::Map(_) => "tables".to_string(),
+        kind => format!("values that are each {}", describe(kind)),
+    }
+}
+
+/// Finds the candidate closest to a misspelled word, if any is close enough
+fn closest<'c>(word: &str, candidates: impl Iterator<Item = &'c str>) -> Option<&'c str> {
+    candidates
+        .map(|c| (edit_distance(word, c), c))
+        .filter(|(distance, c)| *distance <= (c.len() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,24 @@
+//! Scoring, driven by game events
+
+use crate::events::{GameEvent, Subscriber};
+use serde::{Deserialize, Serialize};
+
+/// Keeps the running score by listening for game events
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ScoreKeeper {
+    /// Current score, of both players together in a two-player run
+    pub score: usize,
+    /// Part of the score earned by the second player
+    pub player_two: usize,
+}
+
+impl Subscriber for ScoreKeeper {
+    fn on_event(&mut self, event: &GameEvent) {
+        if let GameEvent::EnemyKilled { player_two, points, .. } = event {
+            self.score += points;
+            if *player_two {
+                self.player_two += points;
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Putting the terminal into game mode and reliably getting it back out
+//!
+//! While the game runs, the terminal is in raw mode on the alternate screen
+//! with the cursor hidden. `TerminalGuard` undoes that when it is dropped,
+//! and a panic hook undoes it before the panic message is printed, so the
+//! shell is usable again however the game ends.
+//!
+//! On terminals supporting the enhanced keyboard protocol (kitty's), the
+//! guard also asks for key releases to be reported, so held keys can be told
+//! apart from repeated presses.
+
+use crossterm::event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags};
+use crossterm::{cursor, execute, terminal};
+use std::io::{self, stdout};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether key releases were asked for, so `restore` knows to undo it
+static KEY_RELEASES: AtomicBool = AtomicBool::new(false);
+
+/// Keeps the terminal in game mode for as long as it lives
+pub struct TerminalGuard {
+    /// Whether the terminal reports keys being released
+    key_releases: bool,
+}
+
+impl TerminalGuard {
+    /// Switches the terminal to raw mode on the alternate screen and hides
+    /// the cursor
+    ///
+    /// Also installs a panic hook restoring the terminal, chained before the
+    /// existing hook so the panic message ends up on the normal screen.
+    ///
+    /// # Arguments
+    /// * `key_releases` - Whether to ask for key releases; terminals that
+    ///   don't answer the support query hold this up for up to two seconds
+    pub fn enter(key_releases: bool) -> io::Result<Self> {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            previous_hook(info);
+        }));
+
+        terminal::enable_raw_mode()?;
+        // Play on the alternate screen so the shell's scrollback is left as it was
+        execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        let key_releases = key_releases && terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if key_releases {
+            // Escape codes are disambiguated too, or releases of Esc and
+            // modified keys come through as garbled presses
+            let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES | KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+            execute!(stdout(), PushKeyboardEnhancementFlags(flags))?;
+            KEY_RELEASES.store(true, Ordering::SeqCst);
+        }
+        Ok(TerminalGuard { key_releases })
+    }
+
+    /// Whether the terminal reports keys being released, rather than only
+    /// repeating presses while they are held
+    pub fn key_releases(&self) -> bool {
+        self.key_releases
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Returns the terminal to how the shell expects it
+///
+/// Errors are ignored: this runs while exiting or panicking, when there is
+/// nothing better to do than carry on.
+fn restore() {
+    if KEY_RELEASES.swap(false, Ordering::SeqCst) {
+        let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+    }
+    let _ = execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
@@ -0,0 +1,129 @@
+//! Input scripts: hand-written runs, for `--input-script` and tests
+//!
+//! A script lists the commands to apply on given ticks, one tick (or range of
+//! ticks) per line, so a bug report can say exactly what was pressed when and
+//! a test can play out a particular sequence:
+//!
+//! ```text
+//! # Kill the last invader while another shot is on its way
+//! seed 42
+//! 1-6 move_left
+//! 8 fire
+//! 10 fire
+//! end 40
+//! ```
+//!
+//! Commands are named like the actions in `keys.toml`. A range applies its
+//! commands on every tick in it, and lines naming the same tick add to it in
+//! order. `seed` gives the seed the run is played on and `end` the last tick
+//! to play, which is otherwise the last tick with commands. Anything after a
+//! `#` is a comment.
+//!
+//! A script only holds input: it is played on the session's settings, like
+//! any other run, by turning it into an `InputLog`.
+
+use crate::input::PlayerCommand;
+use crate::modifiers::RunModifiers;
+use crate::replay::InputLog;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A parsed input script
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputScript {
+    /// Seed the script asks for, if it gives one
+    pub seed: Option<u64>,
+    /// Last tick to play, if the script gives one
+    pub end: Option<u64>,
+    /// Commands of each tick that has any
+    pub inputs: BTreeMap<u64, Vec<PlayerCommand>>,
+}
+
+impl InputScript {
+    /// Reads a script file
+    ///
+    /// # Returns
+    /// The script, or an `InvalidData` error naming the first bad line
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+    }
+
+    /// Parses the text of a script
+    ///
+    /// # Returns
+    /// The script, or a message naming the first bad line and what is wrong
+    /// with it
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut script = InputScript::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(first) = words.next() else { continue };
+            let error = |message: String| format!("line {}: {}", number + 1, message);
+            match first {
+                "seed" | "end" => {
+                    let value = words.next().ok_or_else(|| error(format!("`{}` needs a number", first)))?;
+                    let value = value.parse().map_err(|_| error(format!("`{}` isn't a number", value)))?;
+                    if let Some(extra) = words.next() {
+                        return Err(error(format!("unexpected `{}` after `{} {}`", extra, first, value)));
+                    }
+                    if first == "seed" {
+                        script.seed = Some(value);
+                    } else {
+                        script.end = Some(value);
+                    }
+                }
+                ticks => {
+                    let (start, last) = parse_ticks(ticks).map_err(error)?;
+                    let commands = words
+                        .map(|name| PlayerCommand::by_name(name).ok_or_else(|| error(format!("unknown command `{}`", name))))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if commands.is_empty() {
+                        return Err(error(format!("no commands for tick {}", ticks)));
+                    }
+                    for tick in start..=last {
+                        script.inputs.entry(tick).or_default().extend(&commands);
+                    }
+                }
+            }
+        }
+        Ok(script)
+    }
+
+    /// The run the script describes, ready to be played or replayed
+    ///
+    /// # Arguments
+    /// * `seed` - Seed to play on if the script doesn't give one
+    /// * `modifiers` - Settings to play with
+    pub fn log(&self, seed: u64, modifiers: RunModifiers) -> InputLog {
+        let mut log = InputLog::new(self.seed.unwrap_or(seed), modifiers);
+        for (&tick, commands) in &self.inputs {
+            log.record(tick, commands);
+        }
+        log.ticks = self.end.unwrap_or(log.ticks);
+        log
+    }
+}
+
+/// Parses a tick, `12`, or an inclusive range of ticks, `10-14`
+///
+/// # Returns
+/// The first and last tick, or what is wrong with them
+fn parse_ticks(text: &str) -> Result<(u64, u64), String> {
+    let tick = |text: &str| match text.parse::<u64>() {
+        Ok(0) => Err("ticks start at 1".to_string()),
+        Ok(tick) => Ok(tick),
+        Err(_) => Err(format!("expected a tick, `seed` or `end`, found `{}`", text)),
+    };
+    let (start, last) = match text.split_once('-') {
+        Some((start, last)) => (tick(start)?, tick(last)?),
+        None => (tick(text)?, tick(text)?),
+    };
+    if last < start {
+        return Err(format!("range `{}` ends before it starts", text));
+    }
+    Ok((start, last))
+}
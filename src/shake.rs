@@ -0,0 +1,107 @@
+//! Screen shake for explosions and hits on the player
+//!
+//! Shaking only offsets the playfield as it is drawn; the simulation, and so
+//! collisions and replays, never see it.
+
+use space_invaders::events::{GameEvent, Subscriber};
+
+/// Number of game ticks the screen shakes for when the player is hit
+const HIT_TICKS: usize = 6;
+/// Number of game ticks the screen shakes for when an enemy explodes
+const EXPLOSION_TICKS: usize = 2;
+
+/// Jolts the playfield by a cell for a few ticks after a heavy impact
+pub struct ScreenShake {
+    /// Whether shaking is allowed at all; off with reduced motion
+    enabled: bool,
+    /// Ticks left on the current shake
+    ticks: usize,
+}
+
+impl ScreenShake {
+    /// Creates the effect, at rest
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether impacts shake the screen
+    pub fn new(enabled: bool) -> Self {
+        ScreenShake { enabled, ticks: 0 }
+    }
+
+    /// Starts shaking, unless a longer shake is already going
+    ///
+    /// # Arguments
+    /// * `ticks` - Number of game ticks to shake for
+    fn start(&mut self, ticks: usize) {
+        if self.enabled {
+            self.ticks = self.ticks.max(ticks);
+        }
+    }
+
+    /// Counts down the current shake; call once per game tick
+    pub fn tick(&mut self) {
+        self.ticks = self.ticks.saturating_sub(1);
+    }
+
+    /// Stops shaking straight away, e.g. when a run ends
+    pub fn reset(&mut self) {
+        self.ticks = 0;
+    }
+
+    /// How far to move the playfield this tick, in columns and rows
+    ///
+    /// The playfield jumps from side to side by one cell. A fresh shake also
+    /// bounces it up and down, and settles to side to side only as it fades.
+    pub fn offset(&self) -> (isize, isize) {
+        if self.ticks == 0 {
+            return (0, 0);
+        }
+        let sign = if self.ticks.is_multiple_of(2) { 1 } else { -1 };
+        let dy = if self.ticks > HIT_TICKS / 2 { -sign } else { 0 };
+        (sign, dy)
+    }
+
+    /// Moves a playfield frame by this tick's offset
+    ///
+    /// Cells pushed off one edge are dropped, and the opposite edge is left
+    /// empty.
+    ///
+    /// # Arguments
+    /// * `frame` - The playfield, ready to be drawn
+    pub fn apply<T: Clone>(&self, frame: &mut [Vec<Option<T>>]) {
+        let (dx, dy) = self.offset();
+        if frame.is_empty() || (dx, dy) == (0, 0) {
+            return;
+        }
+        match dy {
+            1 => {
+                frame.rotate_right(1);
+                frame[0].fill(None);
+            }
+            -1 => {
+                frame.rotate_left(1);
+                frame[frame.len() - 1].fill(None);
+            }
+            _ => {}
+        }
+        for row in frame.iter_mut().filter(|row| !row.is_empty()) {
+            if dx > 0 {
+                row.rotate_right(1);
+                row[0] = None;
+            } else {
+                row.rotate_left(1);
+                let last = row.len() - 1;
+                row[last] = None;
+            }
+        }
+    }
+}
+
+impl Subscriber for ScreenShake {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::PlayerHit => self.start(HIT_TICKS),
+            GameEvent::EnemyKilled { .. } => self.start(EXPLOSION_TICKS),
+            _ => {}
+        }
+    }
+}
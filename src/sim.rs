@@ -0,0 +1,243 @@
+//! The simulation thread
+//!
+//! A run's `Game` lives on its own thread, stepped on a fixed tick interval
+//! (`TICK_DURATION` at normal speed) regardless of how long the terminal takes
+//! to draw a frame. The main thread
+//! sends it player input over a channel and gets back a `Snapshot` of every
+//! tick to render, so slow terminal I/O can never stall or skip game logic.
+
+use crate::events::{GameEvent, Subscriber};
+use crate::game::{Game, SavedGame, Snapshot};
+use crate::input::PlayerCommand;
+use crate::netplay::Peer;
+use crate::replay::CHECKPOINT_TICKS;
+use std::iter::Peekable;
+use std::mem;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest the simulation may fall behind before it skips ahead, so a stall
+/// (e.g. a suspended process) doesn't trigger a burst of catch-up ticks
+const MAX_LAG: Duration = Duration::from_millis(250);
+/// How often a paused networked run tells the other computer it is still
+/// there, so it isn't taken for gone
+const KEEP_ALIVE: Duration = Duration::from_secs(1);
+
+/// Something the main thread asks the simulation to do
+pub enum Control {
+    /// Apply a player command on the next tick
+    Command(PlayerCommand),
+    /// Stop ticking until resumed
+    Pause,
+    /// Start ticking again
+    Resume,
+    /// Add or remove the AI wingman with the given aggressiveness
+    ToggleWingman(f32),
+}
+
+/// Something the simulation reports back to the main thread
+pub enum Update {
+    /// A tick was simulated
+    Ticked {
+        /// The game's state at the end of the tick
+        snapshot: Snapshot,
+        /// Events published during the tick, in publish order
+        events: Vec<GameEvent>,
+        /// Commands applied on the tick
+        inputs: Vec<PlayerCommand>,
+        /// The whole game, on ticks a replay checkpoint is due
+        checkpoint: Option<Box<SavedGame>>,
+    },
+    /// The game changed outside of a tick, e.g. while paused
+    Changed(Snapshot),
+    /// The other computer of a networked run left or stopped responding,
+    /// so the run can't go on; the thread has stopped
+    Disconnected(String),
+}
+
+/// Where each tick's commands come from
+enum Source {
+    /// Commands sent by the main thread
+    Local,
+    /// A recorded run's commands, of each tick that had any, in tick order
+    Script(Peekable<Box<dyn Iterator<Item = (u64, Vec<PlayerCommand>)> + Send>>),
+    /// Commands sent by the main thread, merged with the other computer's
+    Peer(Peer),
+}
+
+/// Handle to a game running on the simulation thread
+///
+/// Dropping the handle stops the thread. Drop it once the final tick has been
+/// received, as it treats the thread having stopped as a crash.
+pub struct Simulation {
+    controls: Sender<Control>,
+    updates: Receiver<Update>,
+    /// Whether the thread was last told to pause
+    paused: bool,
+}
+
+impl Simulation {
+    /// Starts simulating a game on a new thread
+    ///
+    /// The thread stops by itself once the run is over.
+    ///
+    /// # Arguments
+    /// * `game` - The run to simulate
+    /// * `interval` - Real time between ticks
+    pub fn spawn(game: Game, interval: Duration) -> Self {
+        let (controls, control_rx) = mpsc::channel();
+        let (update_tx, updates) = mpsc::channel();
+        thread::spawn(move || run(game, control_rx, update_tx, interval, Source::Local));
+        Simulation { controls, updates, paused: false }
+    }
+
+    /// Starts playing a recorded run back on a new thread
+    ///
+    /// Each tick applies the commands recorded for it; commands sent to the
+    /// simulation are ignored, though it can still be paused.
+    ///
+    /// # Arguments
+    /// * `game` - The run's game before the first tick to play back
+    /// * `inputs` - Commands of each later tick that had any, in tick order
+    /// * `interval` - Real time between ticks
+    pub fn spawn_playback<I>(game: Game, inputs: I, interval: Duration) -> Self
+    where
+        I: Iterator<Item = (u64, Vec<PlayerCommand>)> + Send + 'static,
+    {
+        let (controls, control_rx) = mpsc::channel();
+        let (update_tx, updates) = mpsc::channel();
+        let script: Box<dyn Iterator<Item = _> + Send> = Box::new(inputs);
+        thread::spawn(move || run(game, control_rx, update_tx, interval, Source::Script(script.peekable())));
+        Simulation { controls, updates, paused: false }
+    }
+
+    /// Starts simulating one side of a networked run on a new thread
+    ///
+    /// Each tick waits for the other computer's commands, so pausing either
+    /// side holds both up.
+    ///
+    /// # Arguments
+    /// * `game` - The run to simulate, built the same on both computers
+    /// * `peer` - The connection to the other computer
+    /// * `interval` - Real time between ticks
+    pub fn spawn_networked(game: Game, peer: Peer, interval: Duration) -> Self {
+        let (controls, control_rx) = mpsc::channel();
+        let (update_tx, updates) = mpsc::channel();
+        thread::spawn(move || run(game, control_rx, update_tx, interval, Source::Peer(peer)));
+        Simulation { controls, updates, paused: false }
+    }
+
+    /// Sends a request to the simulation thread
+    ///
+    /// Requests sent after the run is over are ignored.
+    pub fn send(&self, control: Control) {
+        let _ = self.controls.send(control);
+    }
+
+    /// Pauses or resumes the simulation, if that changes anything
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused != self.paused {
+            self.paused = paused;
+            self.send(if paused { Control::Pause } else { Control::Resume });
+        }
+    }
+
+    /// Takes the oldest update the simulation has reported, without blocking
+    ///
+    /// # Panics
+    /// If the simulation thread stopped before the run was over, i.e. it
+    /// panicked, so the failure isn't mistaken for a frozen game
+    pub fn try_recv(&self) -> Option<Update> {
+        match self.updates.try_recv() {
+            Ok(update) => Some(update),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => panic!("the simulation thread stopped unexpectedly"),
+        }
+    }
+}
+
+/// Collects a tick's events to send to the main thread
+impl Subscriber for Vec<GameEvent> {
+    fn on_event(&mut self, event: &GameEvent) {
+        self.push(event.clone());
+    }
+}
+
+/// Body of the simulation thread: steps the game on a fixed schedule,
+/// handling requests as they arrive in between
+///
+/// The source decides where each tick's commands come from.
+fn run(mut game: Game, controls: Receiver<Control>, updates: Sender<Update>, interval: Duration, mut source: Source) {
+    let mut pending = Vec::new();
+    let mut paused = false;
+    let mut next_tick = Instant::now() + interval;
+
+    loop {
+        let control = if paused && matches!(source, Source::Peer(_)) {
+            controls.recv_timeout(KEEP_ALIVE)
+        } else if paused {
+            controls.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            controls.recv_timeout(next_tick.saturating_duration_since(Instant::now()))
+        };
+
+        match control {
+            Ok(Control::Command(command)) => pending.push(command),
+            Ok(Control::Pause) => paused = true,
+            Ok(Control::Resume) => {
+                paused = false;
+                next_tick = Instant::now() + interval;
+            }
+            Ok(Control::ToggleWingman(aggressiveness)) => {
+                game.toggle_wingman(aggressiveness);
+                if updates.send(Update::Changed(game.snapshot())).is_err() {
+                    return;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) if paused => {
+                if let Source::Peer(peer) = &mut source {
+                    if let Err(e) = peer.keep_alive() {
+                        let _ = updates.send(Update::Disconnected(e.to_string()));
+                        return;
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                let tick = game.tick_count() + 1;
+                let inputs = match &mut source {
+                    Source::Script(script) => {
+                        pending.clear();
+                        script.next_if(|(t, _)| *t == tick).map(|(_, inputs)| inputs).unwrap_or_default()
+                    }
+                    Source::Local => mem::take(&mut pending),
+                    Source::Peer(peer) => match peer.exchange(tick, mem::take(&mut pending)) {
+                        Ok(inputs) => inputs,
+                        Err(e) => {
+                            let _ = updates.send(Update::Disconnected(e.to_string()));
+                            return;
+                        }
+                    },
+                };
+                game.step(&inputs);
+                let mut events = Vec::new();
+                game.dispatch_events(&mut [&mut events]);
+
+                let snapshot = game.snapshot();
+                let over = snapshot.outcome().is_some();
+                let checkpoint = tick.is_multiple_of(CHECKPOINT_TICKS).then(|| Box::new(game.save()));
+                if updates.send(Update::Ticked { snapshot, events, inputs, checkpoint }).is_err() || over {
+                    return;
+                }
+
+                next_tick += interval;
+                let now = Instant::now();
+                if now > next_tick + MAX_LAG {
+                    next_tick = now;
+                }
+            }
+            // The main thread dropped its handle
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Skins: the set of glyphs used to draw each kind of sprite
+//!
+//! Sprites can span several cells. Their size is fixed by the game, since it
+//! decides what collides; a skin only picks the characters that fill them.
+//!
+//! The `ascii` skin works on any terminal; richer skins rely on the terminal
+//! font covering their Unicode glyphs, which the glyph test screen can verify.
+//!
+//! Every sprite that takes part in the game has a shape of its own, so color
+//! is never the only way to tell the ship, the invaders and their bullets
+//! apart.
+
+use serde::{Deserialize, Serialize};
+
+/// Every kind of thing that gets drawn on the playfield
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Sprite {
+    Player,
+    /// Either player flashing after taking a hit
+    PlayerHit,
+    /// Second player's ship, in a two-player run
+    PlayerTwo,
+    /// AI-controlled helper ship
+    Wingman,
+    Enemy,
+    /// Enemy in its second walking pose
+    EnemyAlt,
+    PlayerBullet,
+    EnemyBullet,
+    /// Marker warning of an incoming bullet
+    Threat,
+    /// Screen-edge alert flash
+    Flash,
+    /// Explosion particle, freshly thrown out
+    Spark,
+    /// Explosion particle, fading
+    Ember,
+    /// Explosion particle, about to vanish
+    Ash,
+    /// Background star in the farthest, dimmest layer
+    StarFar,
+    /// Background star in the middle layer
+    StarMid,
+    /// Background star in the nearest, brightest layer
+    StarNear,
+}
+
+impl Sprite {
+    /// All sprites, in the order the glyph test shows them
+    pub const ALL: [Sprite; 16] = [
+        Sprite::Player,
+        Sprite::PlayerHit,
+        Sprite::PlayerTwo,
+        Sprite::Wingman,
+        Sprite::Enemy,
+        Sprite::EnemyAlt,
+        Sprite::PlayerBullet,
+        Sprite::EnemyBullet,
+        Sprite::Threat,
+        Sprite::Flash,
+        Sprite::Spark,
+        Sprite::Ember,
+        Sprite::Ash,
+        Sprite::StarFar,
+        Sprite::StarMid,
+        Sprite::StarNear,
+    ];
+
+    /// Width and height of the sprite, in cells
+    ///
+    /// An entity's position is the top row of its sprite, at the middle column.
+    pub const fn size(self) -> (usize, usize) {
+        match self {
+            Sprite::Player | Sprite::PlayerHit | Sprite::PlayerTwo => (3, 2),
+            Sprite::Wingman | Sprite::Enemy | Sprite::EnemyAlt => (3, 1),
+            _ => (1, 1),
+        }
+    }
+
+    /// Human-readable name of the sprite
+    pub fn name(self) -> &'static str {
+        match self {
+            Sprite::Player => "Player",
+            Sprite::PlayerHit => "Player (hit)",
+            Sprite::PlayerTwo => "Player two",
+            Sprite::Wingman => "Wingman",
+            Sprite::Enemy => "Enemy",
+            Sprite::EnemyAlt => "Enemy (step)",
+            Sprite::PlayerBullet => "Player bullet",
+            Sprite::EnemyBullet => "Enemy bullet",
+            Sprite::Threat => "Threat marker",
+            Sprite::Flash => "Alert flash",
+            Sprite::Spark => "Spark",
+            Sprite::Ember => "Ember",
+            Sprite::Ash => "Ash",
+            Sprite::StarFar => "Star (far)",
+            Sprite::StarMid => "Star (middle)",
+            Sprite::StarNear => "Star (near)",
+        }
+    }
+}
+
+/// One cell of a sprite, as laid out on the playfield
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    /// The sprite the cell belongs to
+    pub sprite: Sprite,
+    /// Column of the cell within the sprite
+    pub dx: usize,
+    /// Row of the cell within the sprite
+    pub dy: usize,
+}
+
+/// Rows of characters drawing a sprite, matching its `Sprite::size`
+type Shape = &'static [&'static str];
+
+/// Characters of the box drawn around the playfield
+#[derive(Clone, Copy, Debug)]
+pub struct Border {
+    pub horizontal: char,
+    pub vertical: char,
+    /// Top-left, top-right, bottom-left and bottom-right corners
+    pub corners: [char; 4],
+    /// Fills the screen around a playfield smaller than the terminal
+    pub margin: char,
+}
+
+/// Glyphs used to draw each sprite
+pub struct Skin {
+    /// Name used to select the skin in `config.toml`
+    pub name: &'static str,
+    /// Box around the playfield
+    pub border: Border,
+    player: Shape,
+    player_two: Shape,
+    wingman: Shape,
+    enemy: Shape,
+    enemy_alt: Shape,
+    player_bullet: Shape,
+    enemy_bullet: Shape,
+    threat: Shape,
+    flash: Shape,
+    spark: Shape,
+    ember: Shape,
+    ash: Shape,
+    star_far: Shape,
+    star_mid: Shape,
+    star_near: Shape,
+}
+
+/// Plain ASCII glyphs that every terminal font can display
+pub const ASCII: Skin = Skin {
+    name: "ascii",
+    border: Border { horizontal: '-', vertical: '|', corners: ['+', '+', '+', '+'], margin: ' ' },
+    player: &[" ^ ", "<#>"],
+    player_two: &[" A ", "<=>"],
+    wingman: &["/A\\"],
+    enemy: &["<W>"],
+    enemy_alt: &[">M<"],
+    player_bullet: &["|"],
+    // Told apart from the player's bullets by shape, not just color
+    enemy_bullet: &[":"],
+    threat: &["!"],
+    flash: &["#"],
+    spark: &["*"],
+    ember: &["+"],
+    ash: &["."],
+    star_far: &["."],
+    star_mid: &["."],
+    star_near: &["*"],
+};
+
+/// Unicode glyphs for terminals with good font coverage
+pub const UNICODE: Skin = Skin {
+    name: "unicode",
+    border: Border { horizontal: '─', vertical: '│', corners: ['┌', '┐', '└', '┘'], margin: '░' },
+    player: &[" ▲ ", "◢█◣"],
+    player_two: &[" △ ", "◢▓◣"],
+    wingman: &["◁△▷"],
+    enemy: &["«Ѫ»"],
+    enemy_alt: &["»Ж«"],
+    player_bullet: &["│"],
+    enemy_bullet: &["¦"],
+    threat: &["‼"],
+    flash: &["█"],
+    spark: &["✶"],
+    ember: &["+"],
+    ash: &["·"],
+    star_far: &["·"],
+    star_mid: &["∙"],
+    star_near: &["•"],
+};
+
+/// Names of all skins, as accepted in `config.toml`
+pub const SKIN_NAMES: &[&str] = &["ascii", "unicode"];
+
+impl Skin {
+    /// Looks up a skin by name
+    pub fn by_name(name: &str) -> Option<&'static Skin> {
+        [&ASCII, &UNICODE].into_iter().find(|skin| skin.name == name)
+    }
+
+    /// Returns the rows of characters a sprite is drawn with
+    pub fn shape(&self, sprite: Sprite) -> &'static [&'static str] {
+        match sprite {
+            Sprite::Player | Sprite::PlayerHit => self.player,
+            Sprite::PlayerTwo => self.player_two,
+            Sprite::Wingman => self.wingman,
+            Sprite::Enemy => self.enemy,
+            Sprite::EnemyAlt => self.enemy_alt,
+            Sprite::PlayerBullet => self.player_bullet,
+            Sprite::EnemyBullet => self.enemy_bullet,
+            Sprite::Threat => self.threat,
+            Sprite::Flash => self.flash,
+            Sprite::Spark => self.spark,
+            Sprite::Ember => self.ember,
+            Sprite::Ash => self.ash,
+            Sprite::StarFar => self.star_far,
+            Sprite::StarMid => self.star_mid,
+            Sprite::StarNear => self.star_near,
+        }
+    }
+
+    /// Returns the glyph drawn in one cell of a sprite
+    pub fn glyph(&self, cell: Cell) -> char {
+        self.shape(cell.sprite)
+            .get(cell.dy)
+            .and_then(|row| row.chars().nth(cell.dx))
+            .unwrap_or(' ')
+    }
+}
@@ -0,0 +1,107 @@
+//! Watching the game from other terminals, for `--spectate`
+//!
+//! Everything drawn on the player's terminal is copied to every spectator
+//! connected over TCP, so a run can be watched with `nc HOST PORT` or telnet
+//! from another terminal or another machine, or broadcast on a shared server.
+//! Spectators only receive: anything they type is ignored.
+//!
+//! Frames only redraw what changed, so a spectator who has just joined, or
+//! whose connection fell behind and missed frames, needs the whole screen
+//! drawn again; `Spectators` raises a flag for the game to do so.
+
+use std::io::{self, stdout, Stdout, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+
+/// Frames queued for a spectator before they start missing them
+const QUEUED_FRAMES: usize = 8;
+/// Sent to a spectator on joining: hide the cursor, as on the player's terminal
+const GREETING: &[u8] = b"\x1b[?25l";
+
+/// The player's terminal, and every spectator watching it
+///
+/// Used as the drawing backend's output, so each frame written goes to
+/// standard output and to each spectator.
+pub struct Spectators {
+    terminal: Stdout,
+    listener: TcpListener,
+    /// Each spectator's queue of frames, drained by a thread of its own so
+    /// a slow connection never holds the game up
+    watchers: Vec<SyncSender<Arc<[u8]>>>,
+    /// Set when a spectator needs the whole screen drawn again
+    catch_up: Arc<AtomicBool>,
+}
+
+impl Spectators {
+    /// Starts taking spectators on a port
+    ///
+    /// # Arguments
+    /// * `port` - TCP port to listen on, on every interface
+    ///
+    /// # Returns
+    /// The output to draw through, and the flag raised when the whole screen
+    /// has to be drawn again; or an error if the port can't be listened on
+    pub fn listen(port: u16) -> io::Result<(Self, Arc<AtomicBool>)> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        let catch_up = Arc::new(AtomicBool::new(false));
+        let spectators = Spectators { terminal: stdout(), listener, watchers: Vec::new(), catch_up: catch_up.clone() };
+        Ok((spectators, catch_up))
+    }
+
+    /// Lets in everyone who connected since the last frame
+    fn admit(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            let (frames, queue) = mpsc::sync_channel(QUEUED_FRAMES);
+            // A spectator that can't be served is just left out
+            if stream.set_nonblocking(false).is_ok() {
+                thread::spawn(move || serve(stream, queue));
+                self.watchers.push(frames);
+                self.catch_up.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl Write for Spectators {
+    /// Writes to the terminal, then queues the same bytes for every spectator
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.terminal.write_all(buf)?;
+        self.admit();
+        let frame: Arc<[u8]> = buf.into();
+        let catch_up = &self.catch_up;
+        self.watchers.retain(|watcher| match watcher.try_send(frame.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                catch_up.store(true, Ordering::Relaxed);
+                true
+            }
+            // The spectator left
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.terminal.flush()
+    }
+}
+
+/// Body of a spectator's thread: sends them frames until they leave
+///
+/// # Arguments
+/// * `stream` - The spectator's connection
+/// * `queue` - Frames to send, in order
+fn serve(mut stream: TcpStream, queue: Receiver<Arc<[u8]>>) {
+    if stream.write_all(GREETING).is_err() {
+        return;
+    }
+    for frame in queue {
+        if stream.write_all(&frame).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,1128 @@
+//! The arcade's SSH server, for `--arcade`
+//!
+//! Players connect with any SSH client, e.g. `ssh -p 2222 play@HOST`, and
+//! each gets a terminal session of their own. Only as much of SSH-2 is
+//! spoken as a game needs (RFCs 4253, 4252 and 4254): curve25519-sha256 key
+//! exchange, an ssh-ed25519 host key, the chacha20-poly1305@openssh.com
+//! cipher, and one session channel with a pseudo-terminal and a shell.
+//! Players are guests, so anyone who can reach the server may log in, under
+//! any name and without a password: the server is open, and is kept to
+//! loopback unless told otherwise.
+//!
+//! What a player types arrives as raw terminal input and is decoded into
+//! crossterm key events, and window size changes into resize events; what
+//! the game draws is sent back as channel data.
+
+use crate::paths;
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::ChaCha20Legacy;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ed25519_dalek::{Signer, SigningKey};
+use poly1305::universal_hash::KeyInit;
+use poly1305::Poly1305;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::str;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Identification string sent to clients
+const VERSION: &str = "SSH-2.0-SpaceInvadersArcade";
+/// Key exchange methods, in order of preference
+const KEX: [&str; 2] = ["curve25519-sha256", "curve25519-sha256@libssh.org"];
+/// Offered by clients that reset sequence numbers on every key change,
+/// which closes the prefix truncation attack on the first encrypted packets
+const STRICT_KEX_CLIENT: &str = "kex-strict-c-v00@openssh.com";
+/// The server's side of `STRICT_KEX_CLIENT`
+const STRICT_KEX_SERVER: &str = "kex-strict-s-v00@openssh.com";
+const HOST_KEY: &str = "ssh-ed25519";
+const CIPHER: &str = "chacha20-poly1305@openssh.com";
+/// MACs offered for form's sake: the cipher authenticates packets itself,
+/// so none is ever used
+const MACS: &str = "hmac-sha2-256,hmac-sha2-512";
+
+/// Longest packet accepted, as every implementation must (RFC 4253 6.1)
+const MAX_PACKET: usize = 35000;
+/// Most bytes of channel data in one packet, either way
+const MAX_DATA: u32 = 32768;
+/// Bytes of channel data a client may send before its window is grown
+const WINDOW: u32 = 1 << 20;
+/// Longest to wait for a client to log in and open its terminal, so stalled
+/// connections don't hold threads forever
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest to wait for a client to answer a closed session
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Most players in the arcade at once
+pub const MAX_SESSIONS: usize = 32;
+/// Sequence number of the last packet sent under the same keys, keeping the
+/// one after it unused; the cipher is nonced with sequence numbers, so they
+/// mustn't come round again
+const LAST_SEQ: u32 = u32::MAX - 1;
+
+const MSG_DISCONNECT: u8 = 1;
+const MSG_IGNORE: u8 = 2;
+const MSG_UNIMPLEMENTED: u8 = 3;
+const MSG_DEBUG: u8 = 4;
+const MSG_SERVICE_REQUEST: u8 = 5;
+const MSG_SERVICE_ACCEPT: u8 = 6;
+const MSG_KEXINIT: u8 = 20;
+const MSG_NEWKEYS: u8 = 21;
+const MSG_KEX_ECDH_INIT: u8 = 30;
+const MSG_KEX_ECDH_REPLY: u8 = 31;
+const MSG_USERAUTH_REQUEST: u8 = 50;
+const MSG_USERAUTH_SUCCESS: u8 = 52;
+const MSG_GLOBAL_REQUEST: u8 = 80;
+const MSG_REQUEST_FAILURE: u8 = 82;
+const MSG_CHANNEL_OPEN: u8 = 90;
+const MSG_CHANNEL_OPEN_CONFIRMATION: u8 = 91;
+const MSG_CHANNEL_OPEN_FAILURE: u8 = 92;
+const MSG_CHANNEL_WINDOW_ADJUST: u8 = 93;
+const MSG_CHANNEL_DATA: u8 = 94;
+const MSG_CHANNEL_EOF: u8 = 96;
+const MSG_CHANNEL_CLOSE: u8 = 97;
+const MSG_CHANNEL_REQUEST: u8 = 98;
+const MSG_CHANNEL_SUCCESS: u8 = 99;
+const MSG_CHANNEL_FAILURE: u8 = 100;
+
+/// Disconnect reasons (RFC 4253 11.1)
+const DISCONNECT_PROTOCOL_ERROR: u32 = 2;
+const DISCONNECT_KEY_EXCHANGE_FAILED: u32 = 3;
+const DISCONNECT_SERVICE_NOT_AVAILABLE: u32 = 7;
+const DISCONNECT_BY_APPLICATION: u32 = 11;
+/// Channel open failure reasons (RFC 4254 5.1)
+const OPEN_ADMINISTRATIVELY_PROHIBITED: u32 = 1;
+const OPEN_UNKNOWN_CHANNEL_TYPE: u32 = 3;
+
+/// Loads the arcade's host key, creating one on first use
+///
+/// The key is kept in `arcade_host.key` in the data directory. Clients
+/// remember it the first time they connect and warn if it ever changes.
+pub fn host_key() -> io::Result<SigningKey> {
+    let path = paths::data_dir().join("arcade_host.key");
+    match fs::read(&path) {
+        Ok(bytes) => {
+            let bytes: [u8; 32] = bytes
+                .try_into()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "arcade_host.key is corrupt"))?;
+            Ok(SigningKey::from_bytes(&bytes))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let key = SigningKey::generate(&mut OsRng);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&path, key.to_bytes())?;
+            Ok(key)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Serves the arcade until the address can no longer be listened on
+///
+/// Each player is served on a thread of their own, which runs `play` once
+/// they have logged in and asked for a shell. Connections beyond
+/// `MAX_SESSIONS` are closed as soon as they are accepted.
+///
+/// Nobody is asked for a password, so anyone who can reach `address` can
+/// play.
+///
+/// # Arguments
+/// * `address` - Address and TCP port to listen on
+/// * `host_key` - The key the server proves its identity with
+/// * `play` - Plays a session; the session ends when it returns
+pub fn serve<F>(address: SocketAddr, host_key: SigningKey, play: F) -> io::Result<()>
+where
+    F: Fn(Session) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(address)?;
+    let host_key = Arc::new(host_key);
+    let play = Arc::new(play);
+    let sessions = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        // A connection that failed before it was accepted is no concern of the others
+        let Ok(stream) = stream else { continue };
+        // Only this loop adds sessions, so the count can't grow past the check
+        if sessions.load(Ordering::SeqCst) >= MAX_SESSIONS {
+            continue;
+        }
+        sessions.fetch_add(1, Ordering::SeqCst);
+        let (host_key, play, sessions) = (host_key.clone(), play.clone(), sessions.clone());
+        thread::spawn(move || {
+            if let Ok(session) = Session::accept(stream, &host_key) {
+                play(session);
+            }
+            sessions.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+/// A player's terminal, logged in with a shell open
+pub struct Session {
+    /// The name the player logged in under
+    pub user: String,
+    /// The player's terminal type, e.g. `xterm-256color`
+    pub term: String,
+    /// The terminal's size when the shell was opened, in columns and rows
+    pub size: (u16, u16),
+    /// Key presses and resizes, in the order they happened
+    pub input: Input,
+    /// Where to draw, to the player's terminal
+    pub output: Output,
+}
+
+impl Session {
+    /// Takes a client through the key exchange and login, up to the point it
+    /// opens a shell on a terminal
+    ///
+    /// # Arguments
+    /// * `stream` - The client's connection
+    /// * `host_key` - The key the server proves its identity with
+    ///
+    /// # Returns
+    /// The session, or an error if the client disconnected, broke the
+    /// protocol or asked for something other than a shell on a terminal
+    fn accept(stream: TcpStream, host_key: &SigningKey) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(LOGIN_TIMEOUT))?;
+        let mut outbound = Outbound { stream: stream.try_clone()?, cipher: None, seq: 0 };
+        let mut inbound = Inbound { stream: BufReader::new(stream.try_clone()?), cipher: None, seq: 0 };
+
+        outbound.stream.write_all(format!("{}\r\n", VERSION).as_bytes())?;
+        let mut keys = Keys { client_version: read_version(&mut inbound.stream)?, session_id: None, strict: false };
+        let server_init = kexinit();
+        outbound.send(&server_init)?;
+        let client_init = inbound.receive()?;
+        if client_init.first() != Some(&MSG_KEXINIT) {
+            return Err(outbound.disconnect(DISCONNECT_PROTOCOL_ERROR, "expected key exchange"));
+        }
+        keys.exchange(&mut inbound, &mut outbound, host_key, &server_init, &client_init)?;
+
+        let mut user = None;
+        let mut channel = None;
+        let mut terminal = None;
+        loop {
+            let packet = inbound.receive()?;
+            let mut fields = Fields::of(&packet);
+            match fields.byte()? {
+                MSG_KEXINIT => keys.rekey(&mut inbound, &mut outbound, host_key, &packet)?,
+                MSG_SERVICE_REQUEST => match fields.text()? {
+                    "ssh-userauth" => outbound.send(&Packet::new(MSG_SERVICE_ACCEPT).string(b"ssh-userauth"))?,
+                    _ => return Err(outbound.disconnect(DISCONNECT_SERVICE_NOT_AVAILABLE, "unknown service")),
+                },
+                // Everyone is welcome, by whatever method they try first
+                MSG_USERAUTH_REQUEST => {
+                    if user.is_none() {
+                        user = Some(fields.text()?.to_string());
+                        outbound.send(&Packet::new(MSG_USERAUTH_SUCCESS))?;
+                    }
+                }
+                MSG_CHANNEL_OPEN => {
+                    let kind = fields.text()?.to_string();
+                    let (sender, window, max_data) = (fields.u32()?, fields.u32()?, fields.u32()?);
+                    let refusal = match kind.as_str() {
+                        "session" if user.is_some() && channel.is_none() => None,
+                        "session" => Some((OPEN_ADMINISTRATIVELY_PROHIBITED, "one session per connection")),
+                        _ => Some((OPEN_UNKNOWN_CHANNEL_TYPE, "only sessions are served")),
+                    };
+                    match refusal {
+                        None => {
+                            let max_data = max_data.clamp(1, MAX_DATA);
+                            channel = Some(Channel { id: sender, window, max_data, closed: false });
+                            let confirmation = Packet::new(MSG_CHANNEL_OPEN_CONFIRMATION).u32(sender).u32(0);
+                            outbound.send(&confirmation.u32(WINDOW).u32(MAX_DATA))?;
+                        }
+                        Some((reason, message)) => {
+                            let failure = Packet::new(MSG_CHANNEL_OPEN_FAILURE).u32(sender).u32(reason);
+                            outbound.send(&failure.string(message.as_bytes()).string(b""))?;
+                        }
+                    }
+                }
+                MSG_CHANNEL_REQUEST => {
+                    let Some(open) = &channel else { continue };
+                    fields.u32()?;
+                    let kind = fields.text()?.to_string();
+                    let want_reply = fields.bool()?;
+                    let granted = match kind.as_str() {
+                        "pty-req" => {
+                            let term = fields.text()?.to_string();
+                            terminal = Some((term, fields.size()?));
+                            true
+                        }
+                        "window-change" => {
+                            if let Some((_, size)) = &mut terminal {
+                                *size = fields.size()?;
+                            }
+                            true
+                        }
+                        "shell" => true,
+                        _ => false,
+                    };
+                    if want_reply {
+                        let reply = if granted { MSG_CHANNEL_SUCCESS } else { MSG_CHANNEL_FAILURE };
+                        outbound.send(&Packet::new(reply).u32(open.id))?;
+                    }
+                    if kind == "shell" {
+                        break;
+                    }
+                }
+                MSG_CHANNEL_WINDOW_ADJUST => {
+                    if let Some(open) = &mut channel {
+                        fields.u32()?;
+                        open.window = open.window.saturating_add(fields.u32()?);
+                    }
+                }
+                MSG_GLOBAL_REQUEST => {
+                    fields.string()?;
+                    if fields.bool()? {
+                        outbound.send(&Packet::new(MSG_REQUEST_FAILURE))?;
+                    }
+                }
+                // Typed before there is a game to play
+                MSG_CHANNEL_DATA | MSG_CHANNEL_EOF | MSG_IGNORE | MSG_DEBUG | MSG_UNIMPLEMENTED => {}
+                MSG_CHANNEL_CLOSE | MSG_DISCONNECT => {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "the client disconnected"));
+                }
+                _ => outbound.send(&Packet::new(MSG_UNIMPLEMENTED).u32(inbound.seq.wrapping_sub(1)))?,
+            }
+        }
+
+        let (Some(user), Some(channel)) = (user, channel) else {
+            return Err(outbound.disconnect(DISCONNECT_PROTOCOL_ERROR, "a shell was asked for before logging in"));
+        };
+        let link = Arc::new(Link { sending: Mutex::new(Sending { outbound, channel }), adjusted: Condvar::new() });
+        let mut output = Output { link: link.clone() };
+        let Some((term, size)) = terminal else {
+            let _ = output.write_all(b"The arcade needs a terminal: connect with ssh -t\r\n");
+            output.close(1);
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "no terminal was asked for"));
+        };
+
+        stream.set_read_timeout(None)?;
+        let (events, receiver) = mpsc::channel();
+        let host_key = host_key.clone();
+        thread::spawn(move || {
+            let _ = receive(inbound, keys, &link, &host_key, events);
+            // Nothing more can be sent once the client has gone
+            link.sending().channel.closed = true;
+            link.adjusted.notify_all();
+        });
+        Ok(Session { user, term, size, input: Input { events: receiver, next: None }, output })
+    }
+}
+
+/// What a player typed and how their window changed, as terminal events
+pub struct Input {
+    events: Receiver<Event>,
+    /// An event taken off the queue by `poll`, for `read` to return
+    next: Option<Event>,
+}
+
+impl Input {
+    /// Waits for an event, like crossterm's `event::poll`
+    ///
+    /// # Arguments
+    /// * `timeout` - Longest to wait
+    ///
+    /// # Returns
+    /// Whether an event is ready to read, or an error once the player has left
+    pub fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        if self.next.is_none() {
+            match self.events.recv_timeout(timeout) {
+                Ok(event) => self.next = Some(event),
+                Err(RecvTimeoutError::Timeout) => return Ok(false),
+                Err(RecvTimeoutError::Disconnected) => return Err(left()),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Takes the next event, waiting for one if there is none yet, like
+    /// crossterm's `event::read`
+    ///
+    /// # Returns
+    /// The event, or an error once the player has left
+    pub fn read(&mut self) -> io::Result<Event> {
+        match self.next.take() {
+            Some(event) => Ok(event),
+            None => self.events.recv().map_err(|_| left()),
+        }
+    }
+}
+
+/// Draws on a player's terminal
+///
+/// Writes block while the client isn't ready for more, and fail once it has
+/// left. Clones write to the same terminal.
+#[derive(Clone)]
+pub struct Output {
+    link: Arc<Link>,
+}
+
+impl Output {
+    /// Ends the session, telling the client how it ended
+    ///
+    /// # Arguments
+    /// * `status` - Exit status reported to the client, 0 for success
+    pub fn close(&self, status: u32) {
+        let mut sending = self.link.sending();
+        if !sending.channel.closed {
+            let id = sending.channel.id;
+            let exit = Packet::new(MSG_CHANNEL_REQUEST).u32(id).string(b"exit-status").bool(false).u32(status);
+            let _ = sending.outbound.send(&exit);
+            let _ = sending.outbound.send(&Packet::new(MSG_CHANNEL_EOF).u32(id));
+            let _ = sending.outbound.send(&Packet::new(MSG_CHANNEL_CLOSE).u32(id));
+            sending.channel.closed = true;
+        }
+        // The client answers by closing its end, but mustn't keep the
+        // receiving thread waiting if it never does
+        let _ = sending.outbound.stream.set_read_timeout(Some(CLOSE_TIMEOUT));
+        self.link.adjusted.notify_all();
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut sending = self.link.sending();
+        while sending.channel.window == 0 && !sending.channel.closed {
+            sending = self.link.adjusted.wait(sending).unwrap_or_else(|e| e.into_inner());
+        }
+        if sending.channel.closed {
+            return Err(left());
+        }
+        let Sending { outbound, channel } = &mut *sending;
+        let length = buf.len().min(channel.window as usize).min(channel.max_data as usize);
+        outbound.send(&Packet::new(MSG_CHANNEL_DATA).u32(channel.id).string(&buf[..length]))?;
+        channel.window -= length as u32;
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The error for a session whose player has gone
+fn left() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "the player left")
+}
+
+/// The sending side of a connection, shared by the game drawing on it and
+/// the thread answering the client
+struct Link {
+    sending: Mutex<Sending>,
+    /// Signalled when the client grows the channel's window, or the channel
+    /// closes
+    adjusted: Condvar,
+}
+
+impl Link {
+    /// Locks the sending side; a thread that panicked while sending leaves
+    /// it as usable as any other failed send would
+    fn sending(&self) -> std::sync::MutexGuard<'_, Sending> {
+        self.sending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+struct Sending {
+    outbound: Outbound,
+    channel: Channel,
+}
+
+/// The session channel, as seen from the sending side
+struct Channel {
+    /// The client's number for the channel
+    id: u32,
+    /// Bytes the client is ready to receive
+    window: u32,
+    /// Most bytes of data the client takes in one packet
+    max_data: u32,
+    /// Whether the channel was closed, by either side, or the connection lost
+    closed: bool,
+}
+
+/// Body of a session's receiving thread: hands what the player types to the
+/// game until they leave
+///
+/// # Arguments
+/// * `inbound` - The receiving side of the connection
+/// * `keys` - The connection's keys, for when the client changes them
+/// * `link` - The sending side, shared with the game
+/// * `host_key` - The key the server proves its identity with
+/// * `events` - Where the player's key presses and resizes go
+fn receive(
+    mut inbound: Inbound,
+    mut keys: Keys,
+    link: &Link,
+    host_key: &SigningKey,
+    events: Sender<Event>,
+) -> io::Result<()> {
+    let mut window = WINDOW;
+    loop {
+        let packet = inbound.receive()?;
+        let mut fields = Fields::of(&packet);
+        match fields.byte()? {
+            MSG_CHANNEL_DATA => {
+                fields.u32()?;
+                let data = fields.string()?;
+                for key in decode_keys(data) {
+                    if events.send(Event::Key(key)).is_err() {
+                        return Ok(());
+                    }
+                }
+                window = window.saturating_sub(data.len() as u32);
+                if window < WINDOW / 2 {
+                    let mut sending = link.sending();
+                    let id = sending.channel.id;
+                    sending.outbound.send(&Packet::new(MSG_CHANNEL_WINDOW_ADJUST).u32(id).u32(WINDOW - window))?;
+                    window = WINDOW;
+                }
+            }
+            MSG_CHANNEL_WINDOW_ADJUST => {
+                fields.u32()?;
+                let grown = fields.u32()?;
+                let mut sending = link.sending();
+                sending.channel.window = sending.channel.window.saturating_add(grown);
+                link.adjusted.notify_all();
+            }
+            MSG_CHANNEL_REQUEST => {
+                fields.u32()?;
+                let kind = fields.text()?;
+                let want_reply = fields.bool()?;
+                if kind == "window-change" {
+                    let (columns, rows) = fields.size()?;
+                    let _ = events.send(Event::Resize(columns, rows));
+                }
+                if want_reply {
+                    let mut sending = link.sending();
+                    let reply = if kind == "window-change" { MSG_CHANNEL_SUCCESS } else { MSG_CHANNEL_FAILURE };
+                    let id = sending.channel.id;
+                    sending.outbound.send(&Packet::new(reply).u32(id))?;
+                }
+            }
+            MSG_CHANNEL_CLOSE => {
+                let mut sending = link.sending();
+                if !sending.channel.closed {
+                    let id = sending.channel.id;
+                    sending.outbound.send(&Packet::new(MSG_CHANNEL_CLOSE).u32(id))?;
+                }
+                return Ok(());
+            }
+            MSG_KEXINIT => {
+                let mut sending = link.sending();
+                keys.rekey(&mut inbound, &mut sending.outbound, host_key, &packet)?;
+            }
+            MSG_CHANNEL_OPEN => {
+                fields.string()?;
+                let sender = fields.u32()?;
+                let failure = Packet::new(MSG_CHANNEL_OPEN_FAILURE).u32(sender).u32(OPEN_ADMINISTRATIVELY_PROHIBITED);
+                link.sending().outbound.send(&failure.string(b"one session per connection").string(b""))?;
+            }
+            MSG_GLOBAL_REQUEST => {
+                fields.string()?;
+                if fields.bool()? {
+                    link.sending().outbound.send(&Packet::new(MSG_REQUEST_FAILURE))?;
+                }
+            }
+            MSG_DISCONNECT => return Ok(()),
+            // The player's end of the channel is still open for drawing on
+            MSG_CHANNEL_EOF | MSG_IGNORE | MSG_DEBUG | MSG_UNIMPLEMENTED => {}
+            _ => {
+                let seq = inbound.seq.wrapping_sub(1);
+                link.sending().outbound.send(&Packet::new(MSG_UNIMPLEMENTED).u32(seq))?;
+            }
+        }
+    }
+}
+
+/// Reads the client's identification string
+///
+/// # Returns
+/// The string, without its line ending, or an error if the client doesn't
+/// speak SSH-2
+fn read_version(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    // Servers may send other lines first, and some clients do too
+    for _ in 0..10 {
+        let mut line = Vec::new();
+        reader.take(255).read_until(b'\n', &mut line)?;
+        if line.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the client disconnected"));
+        }
+        while matches!(line.last(), Some(b'\n' | b'\r')) {
+            line.pop();
+        }
+        if line.starts_with(b"SSH-2.0-") || line.starts_with(b"SSH-1.99-") {
+            return Ok(line);
+        }
+        if line.starts_with(b"SSH-") {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "the client only speaks SSH-1"));
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "the client doesn't speak SSH"))
+}
+
+/// The server's offer of algorithms, the only ones it supports
+fn kexinit() -> Packet {
+    let mut packet = Packet::new(MSG_KEXINIT);
+    let mut cookie = [0; 16];
+    OsRng.fill_bytes(&mut cookie);
+    packet.0.extend_from_slice(&cookie);
+    packet
+        .string([KEX[0], KEX[1], STRICT_KEX_SERVER].join(",").as_bytes())
+        .string(HOST_KEY.as_bytes())
+        .string(CIPHER.as_bytes())
+        .string(CIPHER.as_bytes())
+        .string(MACS.as_bytes())
+        .string(MACS.as_bytes())
+        .string(b"none")
+        .string(b"none")
+        .string(b"")
+        .string(b"")
+        .bool(false)
+        .u32(0)
+}
+
+/// What the connection's keys are derived from
+struct Keys {
+    /// The client's identification string, part of every exchange hash
+    client_version: Vec<u8>,
+    /// Hash of the first key exchange, which identifies the connection
+    session_id: Option<[u8; 32]>,
+    /// Whether sequence numbers start over with each key change, agreed on
+    /// in the first exchange
+    strict: bool,
+}
+
+impl Keys {
+    /// Answers a client asking for new keys
+    ///
+    /// # Arguments
+    /// * `client_init` - The client's `KEXINIT`
+    fn rekey(
+        &mut self,
+        inbound: &mut Inbound,
+        outbound: &mut Outbound,
+        host_key: &SigningKey,
+        client_init: &[u8],
+    ) -> io::Result<()> {
+        let server_init = kexinit();
+        outbound.send(&server_init)?;
+        self.exchange(inbound, outbound, host_key, &server_init, client_init)
+    }
+
+    /// Agrees on new keys with the client, once both sides have sent their
+    /// `KEXINIT`, and starts using them
+    ///
+    /// # Arguments
+    /// * `inbound` - The receiving side of the connection
+    /// * `outbound` - The sending side of the connection
+    /// * `host_key` - The key the server proves its identity with
+    /// * `server_init` - The `KEXINIT` the server sent
+    /// * `client_init` - The `KEXINIT` the client sent
+    fn exchange(
+        &mut self,
+        inbound: &mut Inbound,
+        outbound: &mut Outbound,
+        host_key: &SigningKey,
+        server_init: &Packet,
+        client_init: &[u8],
+    ) -> io::Result<()> {
+        let initial = self.session_id.is_none();
+        let mut fields = Fields::of(client_init);
+        fields.take(17)?;
+        let kex = fields.names()?;
+        let host_keys = fields.names()?;
+        let ciphers = [fields.names()?, fields.names()?];
+        fields.names()?;
+        fields.names()?;
+        let compression = [fields.names()?, fields.names()?];
+        fields.names()?;
+        fields.names()?;
+        let guessed = fields.bool()?;
+        if initial {
+            self.strict = kex.contains(&STRICT_KEX_CLIENT);
+        }
+        let supported = kex.iter().any(|name| KEX.contains(name))
+            && host_keys.contains(&HOST_KEY)
+            && ciphers.iter().all(|names| names.contains(&CIPHER))
+            && compression.iter().all(|names| names.contains(&"none"));
+        if !supported {
+            return Err(outbound.disconnect(DISCONNECT_KEY_EXCHANGE_FAILED, "no algorithms in common"));
+        }
+        // A packet sent on a guess of the algorithms is dropped if the guess
+        // was wrong, i.e. the client's favourites aren't the ones used
+        if guessed && (!kex.first().is_some_and(|name| KEX.contains(name)) || host_keys.first() != Some(&HOST_KEY)) {
+            inbound.receive()?;
+        }
+
+        let init = self.expect(inbound, outbound, MSG_KEX_ECDH_INIT, initial)?;
+        let mut fields = Fields::of(&init);
+        fields.byte()?;
+        let client_public: [u8; 32] = fields.string()?.try_into().map_err(|_| malformed())?;
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let server_public = PublicKey::from(&secret);
+        let shared = secret.diffie_hellman(&PublicKey::from(client_public));
+        if !shared.was_contributory() {
+            return Err(outbound.disconnect(DISCONNECT_KEY_EXCHANGE_FAILED, "the client's key is weak"));
+        }
+
+        let host_public = Packet::default().string(HOST_KEY.as_bytes()).string(host_key.verifying_key().as_bytes());
+        let secret = Packet::default().mpint(shared.as_bytes());
+        let exchanged = Packet::default()
+            .string(&self.client_version)
+            .string(VERSION.as_bytes())
+            .string(client_init)
+            .string(&server_init.0)
+            .string(&host_public.0)
+            .string(&client_public)
+            .string(server_public.as_bytes());
+        let hash: [u8; 32] = Sha256::new().chain_update(&exchanged.0).chain_update(&secret.0).finalize().into();
+        let session_id = *self.session_id.get_or_insert(hash);
+        let signature = Packet::default().string(HOST_KEY.as_bytes()).string(&host_key.sign(&hash).to_bytes());
+        let reply = Packet::new(MSG_KEX_ECDH_REPLY).string(&host_public.0).string(server_public.as_bytes());
+        outbound.send(&reply.string(&signature.0))?;
+        outbound.send(&Packet::new(MSG_NEWKEYS))?;
+
+        // Each direction's 64 bytes of key are two hashes' worth (RFC 4253 7.2)
+        let derive = |letter: u8| {
+            let key = Sha256::new().chain_update(&secret.0).chain_update(hash);
+            let first = key.clone().chain_update([letter]).chain_update(session_id).finalize();
+            let second = key.chain_update(first).finalize();
+            let mut both = [0; 64];
+            both[..32].copy_from_slice(&first);
+            both[32..].copy_from_slice(&second);
+            Cipher::new(&both)
+        };
+        outbound.cipher = Some(derive(b'D'));
+        self.expect(inbound, outbound, MSG_NEWKEYS, initial)?;
+        inbound.cipher = Some(derive(b'C'));
+        if self.strict {
+            outbound.seq = 0;
+            inbound.seq = 0;
+        }
+        Ok(())
+    }
+
+    /// Receives the next message of a key exchange
+    ///
+    /// Messages the client may send in between are skipped, except in the
+    /// first exchange of a strict connection, where nothing else is allowed.
+    ///
+    /// # Arguments
+    /// * `kind` - The message's type
+    /// * `initial` - Whether this is the connection's first exchange
+    fn expect(&self, inbound: &mut Inbound, outbound: &mut Outbound, kind: u8, initial: bool) -> io::Result<Vec<u8>> {
+        loop {
+            let packet = inbound.receive()?;
+            match packet.first() {
+                Some(&found) if found == kind => return Ok(packet),
+                Some(&(MSG_IGNORE | MSG_DEBUG)) if !(initial && self.strict) => continue,
+                _ => {
+                    let message = "unexpected message during key exchange";
+                    return Err(outbound.disconnect(DISCONNECT_PROTOCOL_ERROR, message));
+                }
+            }
+        }
+    }
+}
+
+/// The receiving side of a connection
+struct Inbound {
+    stream: BufReader<TcpStream>,
+    /// Keys packets are decrypted with; `None` before the first exchange
+    cipher: Option<Cipher>,
+    /// Number of the next packet
+    seq: u32,
+}
+
+impl Inbound {
+    /// Receives the next packet
+    ///
+    /// # Returns
+    /// The packet's payload, starting with its message type, or an error if
+    /// the connection failed or the packet was tampered with
+    fn receive(&mut self) -> io::Result<Vec<u8>> {
+        // Past the last packet a key may nonce, as for sending
+        if self.seq > LAST_SEQ {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the client sent too many packets"));
+        }
+        let mut length = [0; 4];
+        self.stream.read_exact(&mut length)?;
+        let packet_length = match &self.cipher {
+            Some(cipher) => cipher.length(self.seq, length),
+            None => u32::from_be_bytes(length),
+        } as usize;
+        if !(5..=MAX_PACKET).contains(&packet_length) {
+            return Err(malformed());
+        }
+        let tag_length = if self.cipher.is_some() { TAG_LENGTH } else { 0 };
+        let mut packet = vec![0; 4 + packet_length + tag_length];
+        packet[..4].copy_from_slice(&length);
+        self.stream.read_exact(&mut packet[4..])?;
+        if let Some(cipher) = &self.cipher {
+            let tag = packet.split_off(4 + packet_length);
+            cipher.open(self.seq, &mut packet, &tag)?;
+        }
+        self.seq += 1;
+        let padding = usize::from(packet[4]);
+        if padding + 1 > packet_length {
+            return Err(malformed());
+        }
+        Ok(packet[5..4 + packet_length - padding].to_vec())
+    }
+}
+
+/// The sending side of a connection
+struct Outbound {
+    stream: TcpStream,
+    /// Keys packets are encrypted with; `None` before the first exchange
+    cipher: Option<Cipher>,
+    /// Number of the next packet
+    seq: u32,
+}
+
+impl Outbound {
+    /// Sends a packet
+    ///
+    /// Sessions never come near 2^32 packets, so rather than changing keys
+    /// before the sequence number wraps, the connection is dropped with the
+    /// last packet the keys may send.
+    fn send(&mut self, packet: &Packet) -> io::Result<()> {
+        match self.seq {
+            LAST_SEQ if packet.0.first() != Some(&MSG_DISCONNECT) => {
+                return Err(self.disconnect(DISCONNECT_BY_APPLICATION, "the session ran out of packets"));
+            }
+            u32::MAX => return Err(io::Error::new(io::ErrorKind::InvalidData, "out of packets")),
+            _ => {}
+        }
+        let payload = &packet.0;
+        // Padded to whole blocks of 8 bytes, of which the cipher leaves the
+        // length out, with at least 4 bytes of padding
+        let counted = if self.cipher.is_some() { 1 + payload.len() } else { 5 + payload.len() };
+        let padding = 4 + (8 - (counted + 4) % 8) % 8;
+        let mut bytes = Vec::with_capacity(5 + payload.len() + padding + TAG_LENGTH);
+        bytes.extend_from_slice(&((1 + payload.len() + padding) as u32).to_be_bytes());
+        bytes.push(padding as u8);
+        bytes.extend_from_slice(payload);
+        let mut filler = [0; 12];
+        rand::thread_rng().fill_bytes(&mut filler[..padding]);
+        bytes.extend_from_slice(&filler[..padding]);
+        if let Some(cipher) = &self.cipher {
+            cipher.seal(self.seq, &mut bytes);
+        }
+        self.seq += 1;
+        self.stream.write_all(&bytes)
+    }
+
+    /// Tells the client why the connection is being dropped
+    ///
+    /// # Returns
+    /// The reason, as an error to return
+    fn disconnect(&mut self, reason: u32, message: &str) -> io::Error {
+        let _ = self.send(&Packet::new(MSG_DISCONNECT).u32(reason).string(message.as_bytes()).string(b""));
+        io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+    }
+}
+
+/// Bytes of the tag authenticating each encrypted packet
+const TAG_LENGTH: usize = 16;
+
+/// One direction's chacha20-poly1305@openssh.com keys
+///
+/// The packet length is encrypted with one key, and the rest of the packet
+/// with the other, whose first block of keystream is the Poly1305 key for
+/// the packet's tag. Both are nonced with the packet's sequence number.
+struct Cipher {
+    /// Key for the rest of the packet and its tag
+    main: [u8; 32],
+    /// Key for the packet length
+    length: [u8; 32],
+}
+
+impl Cipher {
+    /// Splits 64 bytes of derived key into the cipher's two keys
+    fn new(key: &[u8; 64]) -> Self {
+        let mut cipher = Cipher { main: [0; 32], length: [0; 32] };
+        cipher.main.copy_from_slice(&key[..32]);
+        cipher.length.copy_from_slice(&key[32..]);
+        cipher
+    }
+
+    /// Keystream for a packet
+    fn keystream(key: &[u8; 32], seq: u32) -> ChaCha20Legacy {
+        let nonce = u64::from(seq).to_be_bytes();
+        ChaCha20Legacy::new(chacha20::Key::from_slice(key), chacha20::LegacyNonce::from_slice(&nonce))
+    }
+
+    /// The authenticator for a packet's tag, and the keystream for the rest
+    /// of the packet, positioned past the block the authenticator's key
+    /// came from
+    fn packet_keys(&self, seq: u32) -> (Poly1305, ChaCha20Legacy) {
+        let mut keystream = Self::keystream(&self.main, seq);
+        let mut key = [0; 32];
+        keystream.apply_keystream(&mut key);
+        keystream.seek(64u64);
+        (Poly1305::new(poly1305::Key::from_slice(&key)), keystream)
+    }
+
+    /// Encrypts a packet in place and appends its tag
+    ///
+    /// # Arguments
+    /// * `seq` - The packet's sequence number
+    /// * `packet` - The packet, length first
+    fn seal(&self, seq: u32, packet: &mut Vec<u8>) {
+        Self::keystream(&self.length, seq).apply_keystream(&mut packet[..4]);
+        let (authenticator, mut keystream) = self.packet_keys(seq);
+        keystream.apply_keystream(&mut packet[4..]);
+        let tag = authenticator.compute_unpadded(packet);
+        packet.extend_from_slice(&tag);
+    }
+
+    /// Decrypts a packet's length
+    fn length(&self, seq: u32, mut encrypted: [u8; 4]) -> u32 {
+        Self::keystream(&self.length, seq).apply_keystream(&mut encrypted);
+        u32::from_be_bytes(encrypted)
+    }
+
+    /// Checks a packet's tag, then decrypts the packet in place
+    ///
+    /// # Arguments
+    /// * `seq` - The packet's sequence number
+    /// * `packet` - The packet, encrypted length first
+    /// * `tag` - The tag received after it
+    fn open(&self, seq: u32, packet: &mut [u8], tag: &[u8]) -> io::Result<()> {
+        let (authenticator, mut keystream) = self.packet_keys(seq);
+        let expected = authenticator.compute_unpadded(packet);
+        // Compared in constant time, so timing can't tell how close a forgery came
+        if tag.len() != TAG_LENGTH || expected.iter().zip(tag).fold(0, |diff, (a, b)| diff | (a ^ b)) != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "a packet was tampered with"));
+        }
+        let length = self.length(seq, packet[..4].try_into().unwrap_or_default());
+        packet[..4].copy_from_slice(&length.to_be_bytes());
+        keystream.apply_keystream(&mut packet[4..]);
+        Ok(())
+    }
+}
+
+/// A message being built field by field (RFC 4251 5); the default one has
+/// no message type, for encoding values to hash or nest
+#[derive(Default)]
+struct Packet(Vec<u8>);
+
+impl Packet {
+    fn new(kind: u8) -> Self {
+        Packet(vec![kind])
+    }
+
+    fn bool(mut self, value: bool) -> Self {
+        self.0.push(u8::from(value));
+        self
+    }
+
+    fn u32(mut self, value: u32) -> Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    fn string(self, value: &[u8]) -> Self {
+        let mut packet = self.u32(value.len() as u32);
+        packet.0.extend_from_slice(value);
+        packet
+    }
+
+    /// Adds a non-negative integer given as big-endian bytes
+    fn mpint(self, value: &[u8]) -> Self {
+        let digits = &value[value.iter().take_while(|&&byte| byte == 0).count()..];
+        // A leading bit set would make the number negative
+        if digits.first().is_some_and(|&byte| byte & 0x80 != 0) {
+            self.string(&[&[0], digits].concat())
+        } else {
+            self.string(digits)
+        }
+    }
+}
+
+/// Reads a message's fields in order
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn of(packet: &'a [u8]) -> Self {
+        Fields(packet)
+    }
+
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < count {
+            return Err(malformed());
+        }
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> io::Result<bool> {
+        Ok(self.byte()? != 0)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().map_err(|_| malformed())?))
+    }
+
+    fn string(&mut self) -> io::Result<&'a [u8]> {
+        let length = self.u32()? as usize;
+        self.take(length)
+    }
+
+    fn text(&mut self) -> io::Result<&'a str> {
+        str::from_utf8(self.string()?).map_err(|_| malformed())
+    }
+
+    /// Reads a terminal size, in columns and rows
+    fn size(&mut self) -> io::Result<(u16, u16)> {
+        let (columns, rows) = (self.u32()?, self.u32()?);
+        Ok((columns.min(u16::MAX.into()) as u16, rows.min(u16::MAX.into()) as u16))
+    }
+
+    /// Reads a comma-separated list of names
+    fn names(&mut self) -> io::Result<Vec<&'a str>> {
+        Ok(self.text()?.split(',').filter(|name| !name.is_empty()).collect())
+    }
+}
+
+/// The error for a message that can't be read
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed packet")
+}
+
+/// Decodes what a terminal sends for the keys typed on it
+///
+/// Escape sequences are expected whole, as terminals send each key at once;
+/// an escape at the end is the Esc key. Keys no game screen uses, such as
+/// function keys, are decoded too, so they aren't mistaken for others.
+///
+/// # Arguments
+/// * `bytes` - Input from the terminal, e.g. one packet's worth
+///
+/// # Returns
+/// The keys, in the order they were typed
+pub fn decode_keys(mut bytes: &[u8]) -> Vec<KeyEvent> {
+    let mut keys = Vec::new();
+    while !bytes.is_empty() {
+        let (key, length) = match bytes {
+            [0x1b] | [0x1b, 0x1b, ..] => (Some(KeyEvent::from(KeyCode::Esc)), 1),
+            [0x1b, b'[', rest @ ..] => {
+                let (key, length) = control_sequence(rest);
+                (key, 2 + length)
+            }
+            [0x1b, b'O', last, ..] => (ss3(*last).map(KeyEvent::from), 3),
+            [0x1b, rest @ ..] => {
+                // Meta sends Alt-modified keys as an escape before them
+                let (key, length) = plain_key(rest);
+                (key.map(|key| KeyEvent::new(key.code, key.modifiers | KeyModifiers::ALT)), 1 + length)
+            }
+            _ => plain_key(bytes),
+        };
+        keys.extend(key);
+        bytes = &bytes[length.min(bytes.len())..];
+    }
+    keys
+}
+
+/// Decodes a key that isn't an escape sequence
+///
+/// # Returns
+/// The key, if it is one, and how many bytes it took
+fn plain_key(bytes: &[u8]) -> (Option<KeyEvent>, usize) {
+    let key = |code| Some(KeyEvent::from(code));
+    match bytes[0] {
+        b'\r' | b'\n' => (key(KeyCode::Enter), 1),
+        b'\t' => (key(KeyCode::Tab), 1),
+        0x7f | 0x08 => (key(KeyCode::Backspace), 1),
+        0x00 => (Some(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::CONTROL)), 1),
+        byte @ 0x01..=0x1a => (Some(KeyEvent::new(KeyCode::Char((b'a' + byte - 1) as char), KeyModifiers::CONTROL)), 1),
+        0x1b..=0x1f => (None, 1),
+        first => {
+            let length = match first {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            match bytes.get(..length).and_then(|char| str::from_utf8(char).ok()) {
+                Some(char) => (char.chars().next().map(|c| KeyEvent::from(KeyCode::Char(c))), length),
+                // Not a character: skip a byte and try again from the next
+                None => (None, 1),
+            }
+        }
+    }
+}
+
+/// Decodes a control sequence, `ESC [` followed by parameters and a final
+/// byte, as sent for cursor, editing and function keys
+///
+/// # Arguments
+/// * `bytes` - What follows the `ESC [`
+///
+/// # Returns
+/// The key, if it is one, and how many bytes the sequence took
+fn control_sequence(bytes: &[u8]) -> (Option<KeyEvent>, usize) {
+    let Some(end) = bytes.iter().position(|byte| (0x40..=0x7e).contains(byte)) else {
+        return (None, bytes.len());
+    };
+    let parameters: Vec<u16> =
+        str::from_utf8(&bytes[..end]).unwrap_or_default().split(';').map(|p| p.parse().unwrap_or(1)).collect();
+    // The second parameter is 1 plus a bit each for Shift, Alt and Ctrl
+    let modifiers = parameters.get(1).map_or(0, |m| m.saturating_sub(1));
+    let modifiers = [(1, KeyModifiers::SHIFT), (2, KeyModifiers::ALT), (4, KeyModifiers::CONTROL)]
+        .into_iter()
+        .filter(|(bit, _)| modifiers & bit != 0)
+        .fold(KeyModifiers::NONE, |all, (_, modifier)| all | modifier);
+    let code = match bytes[end] {
+        b'~' => match parameters[0] {
+            1 | 7 => Some(KeyCode::Home),
+            2 => Some(KeyCode::Insert),
+            3 => Some(KeyCode::Delete),
+            4 | 8 => Some(KeyCode::End),
+            5 => Some(KeyCode::PageUp),
+            6 => Some(KeyCode::PageDown),
+            n @ 11..=15 => Some(KeyCode::F((n - 10) as u8)),
+            n @ 17..=21 => Some(KeyCode::F((n - 11) as u8)),
+            n @ 23..=24 => Some(KeyCode::F((n - 12) as u8)),
+            _ => None,
+        },
+        b'Z' => Some(KeyCode::BackTab),
+        last => ss3(last),
+    };
+    (code.map(|code| KeyEvent::new(code, modifiers)), end + 1)
+}
+
+/// Decodes the final byte of a cursor or function key sequence, as sent
+/// after `ESC O` or `ESC [`
+fn ss3(last: u8) -> Option<KeyCode> {
+    match last {
+        b'A' => Some(KeyCode::Up),
+        b'B' => Some(KeyCode::Down),
+        b'C' => Some(KeyCode::Right),
+        b'D' => Some(KeyCode::Left),
+        b'H' => Some(KeyCode::Home),
+        b'F' => Some(KeyCode::End),
+        b'M' => Some(KeyCode::Enter),
+        b'P'..=b'S' => Some(KeyCode::F(last - b'P' + 1)),
+        _ => None,
+    }
+}
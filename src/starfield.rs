@@ -0,0 +1,93 @@
+//! Parallax starfield drawn behind the title menu and the playfield
+//!
+//! Up to three layers of stars drift down the screen, the farther ones dimmer
+//! and slower, so the ship seems to be flying through space. Stars are
+//! scenery: they only fill empty cells of the frame being drawn and never
+//! reach the simulation.
+
+use space_invaders::skin::Sprite;
+use std::time::Duration;
+
+/// One layer of stars
+struct Layer {
+    /// Sprite the layer's stars are drawn with
+    sprite: Sprite,
+    /// How fast the layer drifts down, in cells per second
+    speed: f32,
+    /// Screen cells per star, so the layer's density doesn't depend on the
+    /// screen size
+    spacing: usize,
+}
+
+/// All layers, farthest first
+const LAYERS: [Layer; 3] = [
+    Layer { sprite: Sprite::StarFar, speed: 0.5, spacing: 60 },
+    Layer { sprite: Sprite::StarMid, speed: 1.0, spacing: 120 },
+    Layer { sprite: Sprite::StarNear, speed: 2.0, spacing: 240 },
+];
+
+/// Background stars, scrolling at a different speed per layer
+pub struct Starfield {
+    /// Number of layers drawn, from the farthest
+    layers: usize,
+    /// Whether the stars drift; off with reduced motion
+    moving: bool,
+}
+
+impl Starfield {
+    /// Creates the starfield
+    ///
+    /// # Arguments
+    /// * `layers` - Number of layers to draw, from 0 (no stars) to 3
+    /// * `moving` - Whether the stars drift or stay still
+    pub fn new(layers: usize, moving: bool) -> Self {
+        Starfield { layers: layers.min(LAYERS.len()), moving }
+    }
+
+    /// Lays out the stars at a point in time
+    ///
+    /// Stars sit at fixed, evenly scattered columns and wrap around to the
+    /// top after drifting off the bottom.
+    ///
+    /// # Arguments
+    /// * `width` - Width of the screen, in cells
+    /// * `height` - Height of the screen, in cells
+    /// * `time` - Clock driving the drift
+    ///
+    /// # Returns
+    /// The position and sprite of every star, farthest layer first
+    pub fn stars(&self, width: usize, height: usize, time: Duration) -> Vec<(usize, usize, Sprite)> {
+        if width == 0 || height == 0 {
+            return Vec::new();
+        }
+        let time = if self.moving { time.as_secs_f32() } else { 0.0 };
+        LAYERS[..self.layers]
+            .iter()
+            .enumerate()
+            .flat_map(|(layer_index, layer)| {
+                let drift = (time * layer.speed) as usize;
+                (0..width * height / layer.spacing).map(move |i| {
+                    let x = (i * 37 + layer_index * 13 + 11) % width;
+                    let y = (i * 17 + layer_index * 29 + 3 + drift) % height;
+                    (x, y, layer.sprite)
+                })
+            })
+            .collect()
+    }
+
+    /// Fills the empty cells of a playfield frame with stars
+    ///
+    /// # Arguments
+    /// * `frame` - The playfield, ready to be drawn
+    /// * `time` - Clock driving the drift
+    /// * `star` - Makes the frame's contents for a star drawn with a sprite
+    pub fn fill<T>(&self, frame: &mut [Vec<Option<T>>], time: Duration, star: impl Fn(Sprite) -> T) {
+        let width = frame.first().map_or(0, Vec::len);
+        for (x, y, sprite) in self.stars(width, frame.len(), time) {
+            let cell = &mut frame[y][x];
+            if cell.is_none() {
+                *cell = Some(star(sprite));
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Statistics of a run for the results screen, gathered from game events,
+//! and their running totals over every run the player has finished
+
+use serde::{Deserialize, Serialize};
+use space_invaders::events::{GameEvent, Subscriber};
+use std::time::Duration;
+
+/// Tallies what happened during a run
+#[derive(Default)]
+pub struct RunStats {
+    /// Bullets fired by the player and the wingman
+    pub shots: usize,
+    /// Invaders destroyed
+    pub kills: usize,
+    /// Waves destroyed down to the last invader
+    pub waves_cleared: usize,
+    /// Hits the player's ships took
+    pub hits_taken: usize,
+}
+
+impl RunStats {
+    /// Share of shots that destroyed an invader, as a percentage
+    ///
+    /// # Returns
+    /// `None` if nothing was fired
+    pub fn accuracy(&self) -> Option<usize> {
+        (self.shots > 0).then(|| (self.kills * 100 / self.shots).min(100))
+    }
+}
+
+/// Totals of every finished run, kept in the player's profile
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    /// Runs played to the end
+    pub runs: usize,
+    /// Bullets fired by the player and the wingman
+    pub shots: usize,
+    /// Invaders destroyed; they are the only kind of enemy so far
+    pub invader_kills: usize,
+    /// Waves destroyed down to the last invader
+    pub waves_cleared: usize,
+    /// Ships lost to enemy fire
+    pub deaths: usize,
+    /// Game time played, in whole seconds; pauses don't count
+    pub playtime: u64,
+}
+
+impl LifetimeStats {
+    /// Adds a finished run to the totals
+    ///
+    /// # Arguments
+    /// * `run` - What happened during the run
+    /// * `playtime` - Game time the run lasted
+    pub fn add(&mut self, run: &RunStats, playtime: Duration) {
+        self.runs += 1;
+        self.shots += run.shots;
+        self.invader_kills += run.kills;
+        self.waves_cleared += run.waves_cleared;
+        self.deaths += run.hits_taken;
+        self.playtime += playtime.as_secs_f64().round() as u64;
+    }
+
+    /// Share of all shots that destroyed an invader, as a percentage
+    ///
+    /// # Returns
+    /// `None` if nothing was ever fired
+    pub fn accuracy(&self) -> Option<usize> {
+        (self.shots > 0).then(|| (self.invader_kills * 100 / self.shots).min(100))
+    }
+}
+
+impl Subscriber for RunStats {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::ShotFired => self.shots += 1,
+            GameEvent::EnemyKilled { .. } => self.kills += 1,
+            GameEvent::WaveCleared => self.waves_cleared += 1,
+            GameEvent::PlayerHit => self.hits_taken += 1,
+            _ => {}
+        }
+    }
+}
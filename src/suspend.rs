@@ -0,0 +1,76 @@
+//! A run put aside by quitting part way through, to be continued from the
+//! title screen
+//!
+//! The run is kept as its input log rather than a copy of every entity: the
+//! simulation is deterministic, so playing the log again from its seed
+//! rebuilds the `Game` exactly as it was left. It is kept in
+//! `suspended.json` in the data directory until it is continued.
+
+use serde::{Deserialize, Serialize};
+use space_invaders::paths;
+use space_invaders::replay::{InputLog, FORMAT_VERSION, MIN_FORMAT_VERSION};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// A run waiting to be continued
+#[derive(Serialize, Deserialize)]
+pub struct SuspendedRun {
+    /// Replay format the log was saved in
+    version: u32,
+    /// Every command of the run so far, with its seed and settings
+    pub log: InputLog,
+    /// Score when the run was put aside, to show before continuing
+    pub score: usize,
+    /// Whether the wingman was flying when the run was put aside
+    pub has_wingman: bool,
+}
+
+impl SuspendedRun {
+    /// Puts a run aside
+    ///
+    /// # Arguments
+    /// * `log` - Every command of the run so far
+    /// * `score` - The run's score
+    /// * `has_wingman` - Whether the wingman is flying
+    pub fn new(log: InputLog, score: usize, has_wingman: bool) -> Self {
+        SuspendedRun { version: FORMAT_VERSION, log, score, has_wingman }
+    }
+
+    /// Loads the run put aside last, if there is one
+    ///
+    /// Runs saved by a build with another replay format can't be played
+    /// again the same, so they are ignored like a missing file.
+    pub fn load() -> Option<Self> {
+        let text = fs::read_to_string(Self::path()).ok()?;
+        let versions = MIN_FORMAT_VERSION..=FORMAT_VERSION;
+        serde_json::from_str(&text).ok().filter(|run: &SuspendedRun| versions.contains(&run.version))
+    }
+
+    /// Writes the run to the data directory, replacing any other put aside
+    ///
+    /// The file is written next to the old one and moved into place, so
+    /// quitting part way through can't leave half a run behind.
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_string(self)?)?;
+        fs::rename(temporary, path)
+    }
+
+    /// Deletes the run from the data directory, once it is being continued
+    pub fn discard() -> io::Result<()> {
+        match fs::remove_file(Self::path()) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Location of the suspended run
+    fn path() -> PathBuf {
+        paths::data_dir().join("suspended.json")
+    }
+}
@@ -0,0 +1,195 @@
+//! Pluggable game systems
+//!
+//! A system is a self-contained piece of game logic that runs once per tick,
+//! after entities have moved and before collisions are resolved. Features
+//! such as enemy AI are written as systems and registered with
+//! `Game::add_system`, so new ones can be added without touching `Game::tick`.
+
+use crate::campaign::Wave;
+use crate::entity::{Bullet, Entity};
+use crate::events::GameEvent;
+use crate::game::Game;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// Rows above the bottom edge at which invading enemies end the game
+pub(crate) const INVASION_MARGIN: usize = 3;
+
+/// A piece of game logic run every simulation tick
+///
+/// Systems must be `Send` since the simulation runs on its own thread.
+pub trait System: Send {
+    /// Advances the system by one tick
+    ///
+    /// # Arguments
+    /// * `game` - The game to act on
+    /// * `dt` - Length of the tick
+    fn update(&mut self, game: &mut Game, dt: Duration);
+
+    /// Gets ready for the next wave of a campaign, which has just been
+    /// spawned; the default does nothing
+    ///
+    /// # Arguments
+    /// * `wave` - The new wave
+    fn start_wave(&mut self, _wave: &Wave) {}
+
+    /// What the system keeps from one tick to the next, for saving a run
+    /// part way through; the default keeps nothing
+    fn save(&self) -> Value {
+        Value::Null
+    }
+
+    /// Picks up where a saved run left off, from what `save` kept; the
+    /// default does nothing
+    ///
+    /// # Arguments
+    /// * `state` - What `save` returned
+    fn restore(&mut self, _state: Value) {}
+}
+
+/// Marches the enemy formation from side to side, dropping a row at each edge
+#[derive(Serialize, Deserialize)]
+pub struct FormationMarch {
+    /// Direction the formation is marching: 1.0 for right, -1.0 for left
+    direction: f32,
+    /// Horizontal speed of the formation, in cells per second
+    speed: f32,
+}
+
+impl FormationMarch {
+    /// Creates the system with the formation marching right
+    ///
+    /// # Arguments
+    /// * `speed` - Horizontal speed of the formation, in cells per second
+    pub fn new(speed: f32) -> Self {
+        FormationMarch { direction: 1.0, speed }
+    }
+}
+
+impl System for FormationMarch {
+    /// Marches the new formation right, at its own speed
+    fn start_wave(&mut self, wave: &Wave) {
+        self.direction = 1.0;
+        self.speed = wave.speed;
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    fn restore(&mut self, state: Value) {
+        if let Ok(saved) = serde_json::from_value(state) {
+            *self = saved;
+        }
+    }
+
+    /// Turns the formation around when any enemy reaches a screen edge
+    ///
+    /// Enemies are clamped so their whole sprite stays on screen, and when
+    /// one touches an edge the whole formation reverses direction and drops
+    /// one row. Reaching the invasion row ends the game.
+    fn update(&mut self, game: &mut Game, _dt: Duration) {
+        let mut move_down = false;
+
+        // Diving invaders have left the formation and fly on their own
+        for enemy in game.enemies.iter_mut().filter(|e| !e.diving) {
+            if enemy.is_alive() {
+                let half_width = (enemy.render_glyph().size().0 / 2) as f32;
+                let (left_edge, right_edge) = (half_width, (game.width - 1) as f32 - half_width);
+                enemy.body.x = enemy.body.x.clamp(left_edge, right_edge);
+
+                // Change direction and move down when hitting screen edges
+                if enemy.body.x <= left_edge || enemy.body.x >= right_edge {
+                    move_down = true;
+                }
+            }
+        }
+
+        if move_down {
+            let invasion_row = game.height - INVASION_MARGIN;
+            self.direction = -self.direction;
+
+            let mut lowest = 0;
+            for enemy in game.enemies.iter_mut().filter(|e| !e.diving) {
+                if enemy.is_alive() {
+                    enemy.body.y += 1.0;
+                    let (_, row) = enemy.body.cell();
+                    lowest = lowest.max(row);
+
+                    // Game over if enemies reach bottom
+                    if row >= invasion_row {
+                        game.game_over = true;
+                    }
+                }
+            }
+
+            if lowest + 1 == invasion_row {
+                game.publish(GameEvent::InvasionImminent);
+            }
+        }
+
+        // Head the way the formation is marching on the next tick
+        for enemy in game.enemies.iter_mut().filter(|e| !e.diving) {
+            enemy.body.vx = self.direction * self.speed;
+        }
+    }
+}
+
+/// Makes live enemies fire downwards at random
+#[derive(Serialize, Deserialize)]
+pub struct EnemyFire {
+    /// Chance of each enemy firing on any one tick
+    chance: f64,
+}
+
+impl EnemyFire {
+    /// Creates the system
+    ///
+    /// # Arguments
+    /// * `chance` - Chance, from 0.0 to 1.0, of each enemy firing on any one tick
+    pub fn new(chance: f64) -> Self {
+        EnemyFire { chance }
+    }
+}
+
+impl System for EnemyFire {
+    fn start_wave(&mut self, wave: &Wave) {
+        self.chance = wave.fire_chance;
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    fn restore(&mut self, state: Value) {
+        if let Ok(saved) = serde_json::from_value(state) {
+            *self = saved;
+        }
+    }
+
+    fn update(&mut self, game: &mut Game, _dt: Duration) {
+        for enemy in &game.enemies {
+            if enemy.is_alive() && game.rng.gen_bool(self.chance) {
+                game.enemy_bullets
+                    .spawn(Bullet::enemy(enemy.body.x, enemy.body.y + 1.0));
+            }
+        }
+    }
+}
+
+/// Ages explosion particles and clears them away once they have faded out
+pub struct Particles;
+
+impl System for Particles {
+    fn update(&mut self, game: &mut Game, dt: Duration) {
+        let (width, height) = (game.width as f32, game.height as f32);
+        for particle in &mut game.particles {
+            particle.life -= dt.as_secs_f32();
+        }
+        game.particles.retain(|p| {
+            p.life > 0.0 && (0.0..width).contains(&p.body.x) && (0.0..height).contains(&p.body.y)
+        });
+    }
+}
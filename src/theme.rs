@@ -0,0 +1,318 @@
+//! Themes: the colors used to draw each sprite and the text around them
+
+use crate::skin::Sprite;
+use crossterm::style::Color;
+
+/// Foreground and optional background color of a sprite
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+    pub fg: Color,
+    /// Cell background; `None` leaves the terminal background showing
+    pub bg: Option<Color>,
+}
+
+impl Style {
+    const fn new(fg: Color, bg: Option<Color>) -> Self {
+        Style { fg, bg }
+    }
+}
+
+/// Colors of the text around the playfield: menus, HUD and messages
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiColors {
+    /// Titles and headings
+    pub title: Color,
+    /// Prompts and ordinary text
+    pub text: Color,
+    /// Hints and labels that shouldn't draw the eye
+    pub muted: Color,
+    /// Notices that should, like the pause banner
+    pub highlight: Color,
+    /// The player's score
+    pub score: Color,
+    /// Errors and losing the game
+    pub danger: Color,
+    /// Medals, from bronze to gold
+    pub medals: [Color; 3],
+}
+
+/// A color for every sprite
+#[derive(Clone)]
+pub struct Theme {
+    /// Name used to select the theme in `config.toml`
+    pub name: &'static str,
+    /// Colors of menus, the HUD and messages
+    pub ui: UiColors,
+    player: Style,
+    player_hit: Style,
+    player_two: Style,
+    wingman: Style,
+    enemy: Style,
+    player_bullet: Style,
+    enemy_bullet: Style,
+    threat: Style,
+    flash: Style,
+    particle: Style,
+    star_far: Style,
+    star_mid: Style,
+    star_near: Style,
+}
+
+/// The original colors: red invaders, green player, white bullets
+pub const CLASSIC: Theme = Theme {
+    name: "classic",
+    ui: UiColors {
+        title: Color::Green,
+        text: Color::White,
+        muted: Color::DarkGrey,
+        highlight: Color::Yellow,
+        score: Color::Blue,
+        danger: Color::Red,
+        medals: [Color::DarkYellow, Color::Grey, Color::Yellow],
+    },
+    player: Style::new(Color::Green, Some(Color::DarkGreen)),
+    // The player's colors inverted
+    player_hit: Style::new(Color::DarkGreen, Some(Color::Green)),
+    player_two: Style::new(Color::Magenta, Some(Color::DarkMagenta)),
+    wingman: Style::new(Color::Cyan, Some(Color::DarkCyan)),
+    enemy: Style::new(Color::Red, Some(Color::DarkRed)),
+    player_bullet: Style::new(Color::White, Some(Color::DarkGrey)),
+    enemy_bullet: Style::new(Color::White, Some(Color::DarkGrey)),
+    // Threat markers in yellow so they stand out from bullets
+    threat: Style::new(Color::Yellow, None),
+    flash: Style::new(Color::Red, None),
+    particle: Style::new(Color::Yellow, None),
+    // Farther stars are dimmer
+    star_far: Style::new(Color::DarkGrey, None),
+    star_mid: Style::new(Color::Grey, None),
+    star_near: Style::new(Color::White, None),
+};
+
+/// Glowing magenta invaders against a cyan ship
+pub const NEON: Theme = Theme {
+    name: "neon",
+    ui: UiColors {
+        title: Color::Magenta,
+        text: Color::White,
+        muted: Color::DarkCyan,
+        highlight: Color::Yellow,
+        score: Color::Cyan,
+        danger: Color::Red,
+        medals: [Color::DarkYellow, Color::Grey, Color::Yellow],
+    },
+    player: Style::new(Color::Cyan, Some(Color::DarkBlue)),
+    player_hit: Style::new(Color::DarkBlue, Some(Color::Cyan)),
+    player_two: Style::new(Color::Yellow, Some(Color::DarkYellow)),
+    wingman: Style::new(Color::Green, Some(Color::DarkGreen)),
+    enemy: Style::new(Color::Magenta, Some(Color::DarkMagenta)),
+    player_bullet: Style::new(Color::Cyan, None),
+    enemy_bullet: Style::new(Color::Magenta, None),
+    threat: Style::new(Color::Yellow, None),
+    flash: Style::new(Color::Magenta, None),
+    particle: Style::new(Color::Yellow, None),
+    star_far: Style::new(Color::DarkBlue, None),
+    star_mid: Style::new(Color::DarkMagenta, None),
+    star_near: Style::new(Color::Cyan, None),
+};
+
+/// Everything in phosphor green, like an old monochrome monitor
+pub const RETRO_GREEN: Theme = monochrome("retro-green-monitor", Color::Green, Color::DarkGreen);
+
+/// Everything in amber, like an old monochrome monitor
+pub const AMBER: Theme = monochrome("amber", Color::Yellow, Color::DarkYellow);
+
+/// A theme drawn in two shades of one color
+///
+/// The ship stands out with a filled background, and the shapes of the
+/// skin's glyphs tell everything else apart.
+///
+/// # Arguments
+/// * `name` - Name of the theme
+/// * `bright` - Shade of most sprites and text
+/// * `dim` - Shade of backgrounds, hints and distant stars
+const fn monochrome(name: &'static str, bright: Color, dim: Color) -> Theme {
+    Theme {
+        name,
+        ui: UiColors {
+            title: bright,
+            text: bright,
+            muted: dim,
+            highlight: bright,
+            score: bright,
+            danger: bright,
+            medals: [dim, bright, bright],
+        },
+        player: Style::new(bright, Some(dim)),
+        player_hit: Style::new(dim, Some(bright)),
+        // Told apart from the first player by the skin's glyphs
+        player_two: Style::new(bright, None),
+        wingman: Style::new(bright, None),
+        enemy: Style::new(bright, None),
+        player_bullet: Style::new(bright, None),
+        enemy_bullet: Style::new(bright, None),
+        threat: Style::new(bright, None),
+        flash: Style::new(bright, None),
+        particle: Style::new(bright, None),
+        star_far: Style::new(dim, None),
+        star_mid: Style::new(dim, None),
+        star_near: Style::new(bright, None),
+    }
+}
+
+/// Blue ship against yellow invaders, for players who can't tell red from
+/// green because their green cones are missing
+pub const DEUTERANOPIA: Theme = red_green_safe("deuteranopia");
+
+/// Blue ship against yellow invaders, for players who can't tell red from
+/// green because their red cones are missing; reds look dark to them, so the
+/// theme uses none
+pub const PROTANOPIA: Theme = red_green_safe("protanopia");
+
+/// Cyan ship against red invaders, for players who can't tell blue from green
+/// or yellow from violet
+pub const TRITANOPIA: Theme = Theme {
+    name: "tritanopia",
+    ui: UiColors {
+        title: Color::Cyan,
+        text: Color::White,
+        muted: Color::DarkGrey,
+        highlight: Color::Magenta,
+        score: Color::Cyan,
+        danger: Color::Red,
+        medals: [Color::DarkRed, Color::Grey, Color::White],
+    },
+    player: Style::new(Color::Cyan, Some(Color::DarkCyan)),
+    player_hit: Style::new(Color::DarkCyan, Some(Color::Cyan)),
+    // The wingman never flies in a two-player run, so the second player can
+    // share its colors
+    player_two: Style::new(Color::White, Some(Color::DarkGrey)),
+    // Cyan and green look alike, so the wingman goes white
+    wingman: Style::new(Color::White, Some(Color::DarkGrey)),
+    enemy: Style::new(Color::Red, Some(Color::DarkRed)),
+    player_bullet: Style::new(Color::White, Some(Color::DarkGrey)),
+    enemy_bullet: Style::new(Color::Magenta, Some(Color::DarkGrey)),
+    threat: Style::new(Color::White, None),
+    flash: Style::new(Color::Red, None),
+    particle: Style::new(Color::Magenta, None),
+    star_far: Style::new(Color::DarkGrey, None),
+    star_mid: Style::new(Color::Grey, None),
+    star_near: Style::new(Color::White, None),
+};
+
+/// A theme that tells the ship and the invaders apart by blue and yellow,
+/// which both kinds of red-green color blindness keep
+///
+/// # Arguments
+/// * `name` - Name of the theme
+const fn red_green_safe(name: &'static str) -> Theme {
+    Theme {
+        name,
+        ui: UiColors {
+            title: Color::Blue,
+            text: Color::White,
+            muted: Color::DarkGrey,
+            highlight: Color::Yellow,
+            score: Color::Cyan,
+            danger: Color::Magenta,
+            medals: [Color::DarkYellow, Color::Grey, Color::Yellow],
+        },
+        player: Style::new(Color::Blue, Some(Color::DarkBlue)),
+        player_hit: Style::new(Color::DarkBlue, Some(Color::Blue)),
+        // The wingman never flies in a two-player run, so the second player
+        // can share its colors
+        player_two: Style::new(Color::Cyan, Some(Color::DarkCyan)),
+        wingman: Style::new(Color::Cyan, Some(Color::DarkCyan)),
+        enemy: Style::new(Color::Yellow, Some(Color::DarkYellow)),
+        player_bullet: Style::new(Color::White, Some(Color::DarkGrey)),
+        enemy_bullet: Style::new(Color::Yellow, Some(Color::DarkGrey)),
+        threat: Style::new(Color::Magenta, None),
+        flash: Style::new(Color::Yellow, None),
+        particle: Style::new(Color::White, None),
+        star_far: Style::new(Color::DarkGrey, None),
+        star_mid: Style::new(Color::Grey, None),
+        star_near: Style::new(Color::White, None),
+    }
+}
+
+/// Every theme, in the order the title menu cycles through them
+pub const THEMES: [&Theme; 7] = [
+    &CLASSIC,
+    &NEON,
+    &RETRO_GREEN,
+    &AMBER,
+    &DEUTERANOPIA,
+    &PROTANOPIA,
+    &TRITANOPIA,
+];
+
+/// Names of all themes, as accepted in `config.toml`
+pub const THEME_NAMES: &[&str] = &[
+    "classic",
+    "neon",
+    "retro-green-monitor",
+    "amber",
+    "deuteranopia",
+    "protanopia",
+    "tritanopia",
+];
+
+impl Theme {
+    /// Looks up a theme by name
+    pub fn by_name(name: &str) -> Option<&'static Theme> {
+        THEMES.into_iter().find(|theme| theme.name == name)
+    }
+
+    /// The theme after this one in `THEMES`, wrapping around
+    pub fn next(&self) -> &'static Theme {
+        let index = THEMES.iter().position(|theme| theme.name == self.name).unwrap_or(0);
+        THEMES[(index + 1) % THEMES.len()]
+    }
+
+    /// Returns the colors a sprite is drawn in
+    pub fn style(&self, sprite: Sprite) -> Style {
+        *self.slot(sprite)
+    }
+
+    /// Replaces the colors a sprite is drawn in
+    pub fn set_style(&mut self, sprite: Sprite, style: Style) {
+        *self.slot_mut(sprite) = style;
+    }
+
+    /// Both enemy poses share the enemy's colors, and every stage of an
+    /// explosion particle shares one color
+    fn slot(&self, sprite: Sprite) -> &Style {
+        match sprite {
+            Sprite::Player => &self.player,
+            Sprite::PlayerHit => &self.player_hit,
+            Sprite::PlayerTwo => &self.player_two,
+            Sprite::Wingman => &self.wingman,
+            Sprite::Enemy | Sprite::EnemyAlt => &self.enemy,
+            Sprite::PlayerBullet => &self.player_bullet,
+            Sprite::EnemyBullet => &self.enemy_bullet,
+            Sprite::Threat => &self.threat,
+            Sprite::Flash => &self.flash,
+            Sprite::Spark | Sprite::Ember | Sprite::Ash => &self.particle,
+            Sprite::StarFar => &self.star_far,
+            Sprite::StarMid => &self.star_mid,
+            Sprite::StarNear => &self.star_near,
+        }
+    }
+
+    fn slot_mut(&mut self, sprite: Sprite) -> &mut Style {
+        match sprite {
+            Sprite::Player => &mut self.player,
+            Sprite::PlayerHit => &mut self.player_hit,
+            Sprite::PlayerTwo => &mut self.player_two,
+            Sprite::Wingman => &mut self.wingman,
+            Sprite::Enemy | Sprite::EnemyAlt => &mut self.enemy,
+            Sprite::PlayerBullet => &mut self.player_bullet,
+            Sprite::EnemyBullet => &mut self.enemy_bullet,
+            Sprite::Threat => &mut self.threat,
+            Sprite::Flash => &mut self.flash,
+            Sprite::Spark | Sprite::Ember | Sprite::Ash => &mut self.particle,
+            Sprite::StarFar => &mut self.star_far,
+            Sprite::StarMid => &mut self.star_mid,
+            Sprite::StarNear => &mut self.star_near,
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! Tournament mode: organizer-signed rulesets and verifiable results
+//!
+//! An organizer writes a ruleset (seed and game settings) and signs it with
+//! their ed25519 key, producing a token they hand out to players. Playing
+//! with a token locks the game to its ruleset; each finished run produces a
+//! result blob holding the token, the score and every input.
+//!
+//! Result blobs are unsigned replays: players hold no key the organizer
+//! trusts, so anyone can write or edit one. They are verified only by
+//! re-playing them in [`RunRecord::verify`]. The simulation is deterministic,
+//! so the organizer re-plays the recorded inputs under the signed ruleset and
+//! accepts the score only if it comes out the same.
+
+use crate::game::{Game, GameBuilder};
+use crate::input::PlayerCommand;
+use crate::paths;
+use crate::schema::{self, Diagnostic, Field, FieldKind};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Expected layout of a ruleset file, checked before deserializing
+const RULESET_SCHEMA: FieldKind = FieldKind::Table(&[
+    Field {
+        name: "name",
+        kind: FieldKind::Text,
+    },
+    Field {
+        name: "seed",
+        kind: FieldKind::Integer { min: 0, max: i64::MAX },
+    },
+    Field {
+        name: "width",
+        kind: FieldKind::Integer { min: 56, max: 250 },
+    },
+    Field {
+        name: "height",
+        kind: FieldKind::Integer { min: 20, max: 100 },
+    },
+    Field {
+        name: "enemy_rows",
+        kind: FieldKind::Integer { min: 1, max: 8 },
+    },
+    Field {
+        name: "enemy_columns",
+        kind: FieldKind::Integer { min: 1, max: 40 },
+    },
+    Field {
+        name: "enemy_speed",
+        kind: FieldKind::Float { min: 0.5, max: 20.0 },
+    },
+    Field {
+        name: "fire_chance",
+        kind: FieldKind::Float { min: 0.0, max: 1.0 },
+    },
+]);
+
+/// The conditions every run in a tournament is played under
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ruleset {
+    /// Name of the tournament, shown to players
+    pub name: String,
+    /// Seed for every run, so all players face the same game
+    pub seed: u64,
+    /// Width of the playfield, in cells
+    pub width: usize,
+    /// Height of the playfield, in cells
+    pub height: usize,
+    /// Number of rows in the enemy formation
+    pub enemy_rows: usize,
+    /// Number of enemies in each formation row
+    pub enemy_columns: usize,
+    /// Horizontal speed of the enemy formation, in cells per second
+    pub enemy_speed: f32,
+    /// Chance of each enemy firing on any one tick
+    pub fire_chance: f64,
+}
+
+impl Ruleset {
+    /// Loads and validates a ruleset file
+    ///
+    /// # Returns
+    /// The ruleset, or every problem found in the file
+    pub fn load(path: &Path) -> Result<Self, Vec<Diagnostic>> {
+        let file = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|e| {
+            vec![Diagnostic {
+                file: file.clone(),
+                line: None,
+                message: format!("could not be read: {}", e),
+                suggestion: None,
+            }]
+        })?;
+
+        let diagnostics = schema::validate(&file, &text, &RULESET_SCHEMA);
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        toml::from_str(&text).map_err(|e| vec![schema::parse_error(&file, &text, &e)])
+    }
+
+    /// A builder for games played under this ruleset
+    pub fn builder(&self) -> GameBuilder {
+        Game::builder()
+            .width(self.width)
+            .height(self.height)
+            .enemy_rows(self.enemy_rows)
+            .enemy_columns(self.enemy_columns)
+            .enemy_speed(self.enemy_speed)
+            .fire_chance(self.fire_chance)
+            .seed(self.seed)
+    }
+}
+
+/// Why a token or result blob was rejected
+#[derive(Debug)]
+pub enum TournamentError {
+    /// The text isn't a token or blob at all
+    Malformed(&'static str),
+    /// The token's signature doesn't match its ruleset
+    BadSignature,
+    /// Re-playing the inputs didn't reproduce the claimed result
+    Mismatch { claimed: usize, replayed: usize },
+}
+
+impl fmt::Display for TournamentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TournamentError::Malformed(what) => write!(f, "malformed {}", what),
+            TournamentError::BadSignature => write!(f, "signature does not match"),
+            TournamentError::Mismatch { claimed, replayed } => write!(
+                f,
+                "claimed score {} but replaying the inputs scores {}",
+                claimed, replayed
+            ),
+        }
+    }
+}
+
+/// A ruleset signed by a tournament organizer
+#[derive(Clone)]
+pub struct Token {
+    /// The conditions the tournament is played under
+    pub ruleset: Ruleset,
+    /// Public key of the organizer who signed the ruleset
+    pub organizer: VerifyingKey,
+    /// The token as handed out, kept so results can carry it verbatim
+    text: String,
+}
+
+impl Token {
+    /// Signs a ruleset, producing the token an organizer hands out
+    ///
+    /// The token is `ruleset.key.signature`, each part URL-safe base64.
+    pub fn sign(ruleset: &Ruleset, key: &SigningKey) -> String {
+        let ruleset = serde_json::to_vec(ruleset).expect("rulesets always serialize");
+        let signature = key.sign(&ruleset);
+        format!(
+            "{}.{}.{}",
+            URL_SAFE_NO_PAD.encode(&ruleset),
+            URL_SAFE_NO_PAD.encode(key.verifying_key().as_bytes()),
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        )
+    }
+
+    /// Reads a token, checking the organizer's signature
+    pub fn parse(text: &str) -> Result<Self, TournamentError> {
+        let text = text.trim();
+        let parts: Vec<&str> = text.split('.').collect();
+        let [ruleset, key, signature] = parts[..] else {
+            return Err(TournamentError::Malformed("token"));
+        };
+
+        let ruleset = decode(ruleset, "token")?;
+        let key: [u8; 32] = decode(key, "token")?
+            .try_into()
+            .map_err(|_| TournamentError::Malformed("token"))?;
+        let signature: [u8; 64] = decode(signature, "token")?
+            .try_into()
+            .map_err(|_| TournamentError::Malformed("token"))?;
+
+        let organizer =
+            VerifyingKey::from_bytes(&key).map_err(|_| TournamentError::Malformed("token"))?;
+        organizer
+            .verify(&ruleset, &Signature::from_bytes(&signature))
+            .map_err(|_| TournamentError::BadSignature)?;
+
+        Ok(Token {
+            ruleset: serde_json::from_slice(&ruleset)
+                .map_err(|_| TournamentError::Malformed("token"))?,
+            organizer,
+            text: text.to_string(),
+        })
+    }
+
+    /// The token as handed out
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Short fingerprint of the organizer's key, for comparing at a glance
+    pub fn organizer_fingerprint(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.organizer.as_bytes()[..6])
+    }
+}
+
+/// Everything needed to check a finished tournament run
+#[derive(Serialize, Deserialize)]
+pub struct RunRecord {
+    /// The token the run was played under, verbatim
+    pub token: String,
+    /// Final score
+    pub score: usize,
+    /// Ticks the run lasted
+    pub ticks: u64,
+    /// Commands issued during the run, keyed by the tick they were applied on
+    pub inputs: Vec<(u64, Vec<PlayerCommand>)>,
+}
+
+impl RunRecord {
+    /// Writes the record as an unsigned replay blob to send to the organizer
+    ///
+    /// The blob is the record in URL-safe base64. Nothing in it is signed:
+    /// only re-playing it with [`RunRecord::verify`] shows the score is real.
+    pub fn to_blob(&self) -> String {
+        let record = serde_json::to_vec(self).expect("records always serialize");
+        URL_SAFE_NO_PAD.encode(&record)
+    }
+
+    /// Reads an unsigned replay blob, checking the token inside it
+    ///
+    /// Blobs written by older versions end in a `.mac` part keyed by the
+    /// token, which proved nothing and is ignored.
+    ///
+    /// # Returns
+    /// The record and its parsed token, neither of which is verified until
+    /// the record is re-played
+    pub fn from_blob(blob: &str) -> Result<(Self, Token), TournamentError> {
+        let record = blob.trim().split('.').next().unwrap_or_default();
+        let record = decode(record, "result")?;
+        let parsed: RunRecord =
+            serde_json::from_slice(&record).map_err(|_| TournamentError::Malformed("result"))?;
+        let token = Token::parse(&parsed.token)?;
+        Ok((parsed, token))
+    }
+
+    /// Re-plays the recorded inputs under the token's ruleset
+    ///
+    /// # Returns
+    /// The score, if the replay reproduces the one claimed
+    pub fn verify(&self, token: &Token) -> Result<usize, TournamentError> {
+        let mut game = token.ruleset.builder().build();
+        let mut inputs = self.inputs.iter().peekable();
+        while game.tick_count() < self.ticks && !game.game_over && !game.enemies.is_empty() {
+            let tick = game.tick_count() + 1;
+            let commands = inputs.next_if(|(t, _)| *t == tick).map(|(_, c)| c.as_slice());
+            game.step(commands.unwrap_or_default());
+            game.dispatch_events(&mut []);
+        }
+
+        if game.score() == self.score {
+            Ok(self.score)
+        } else {
+            Err(TournamentError::Mismatch {
+                claimed: self.score,
+                replayed: game.score(),
+            })
+        }
+    }
+
+    /// Writes the record's replay blob to the data directory
+    ///
+    /// # Returns
+    /// Where the result was saved
+    pub fn save(&self, ruleset: &Ruleset) -> io::Result<PathBuf> {
+        let dir = paths::data_dir().join("tournaments");
+        fs::create_dir_all(&dir)?;
+        let name: String = ruleset
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let mut path = dir.join(format!("{}-{}.result", name, self.score));
+        let mut attempt = 1;
+        while path.exists() {
+            attempt += 1;
+            path = dir.join(format!("{}-{}-{}.result", name, self.score, attempt));
+        }
+        fs::write(&path, self.to_blob())?;
+        Ok(path)
+    }
+}
+
+/// Loads the organizer's signing key, creating one on first use
+///
+/// The key is kept in `tournament.key` in the data directory.
+pub fn organizer_key() -> io::Result<SigningKey> {
+    let path = paths::data_dir().join("tournament.key");
+    match fs::read(&path) {
+        Ok(bytes) => {
+            let bytes: [u8; 32] = bytes.try_into().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, "tournament.key is corrupt")
+            })?;
+            Ok(SigningKey::from_bytes(&bytes))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let key = SigningKey::generate(&mut rand::rngs::OsRng);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&path, key.to_bytes())?;
+            Ok(key)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Signs a ruleset file with the organizer's key (`--sign-tournament`)
+///
+/// # Returns
+/// The token to hand out, or a message explaining what went wrong
+pub fn sign_file(path: &Path) -> Result<String, String> {
+    let ruleset = Ruleset::load(path).map_err(|diagnostics| {
+        diagnostics.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("\n")
+    })?;
+    let key = organizer_key().map_err(|e| format!("could not load the organizer key: {}", e))?;
+    Ok(Token::sign(&ruleset, &key))
+}
+
+/// Checks a result file against the organizer's key (`--verify-result`)
+///
+/// # Returns
+/// A summary of the verified result, or why it was rejected
+pub fn verify_file(path: &Path) -> Result<String, String> {
+    let blob = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (record, token) = RunRecord::from_blob(&blob).map_err(|e| e.to_string())?;
+    let key = organizer_key().map_err(|e| format!("could not load the organizer key: {}", e))?;
+    if token.organizer != key.verifying_key() {
+        return Err(format!(
+            "result is for a tournament signed by another organizer ({})",
+            token.organizer_fingerprint()
+        ));
+    }
+    let score = record.verify(&token).map_err(|e| e.to_string())?;
+    Ok(format!(
+        "valid: {} - score {} in {} ticks",
+        token.ruleset.name, score, record.ticks
+    ))
+}
+
+/// Decodes one URL-safe base64 part of a token or blob
+fn decode(part: &str, what: &'static str) -> Result<Vec<u8>, TournamentError> {
+    URL_SAFE_NO_PAD
+        .decode(part)
+        .map_err(|_| TournamentError::Malformed(what))
+}
@@ -0,0 +1,52 @@
+//! Fading trails drawn behind bullets, so they are easier to follow
+//!
+//! A bullet leaves a `:` in the cell just behind it and a `.` in the one
+//! after that, pointing back the way it came: the direction is taken from
+//! where the bullet was on the last tick. Like the starfield, trails are only
+//! drawn; they never collide with anything.
+
+use crate::gradient::Gradients;
+use crate::Glyph;
+use space_invaders::game::Snapshot;
+use space_invaders::skin::Sprite;
+use space_invaders::theme::{Style, Theme};
+use std::cmp::Ordering;
+
+/// Characters of a trail, nearest the bullet first
+const TRAIL: [char; 2] = [':', '.'];
+
+/// Adds trails behind the bullets of a snapshot to a playfield frame
+///
+/// Trails only go into empty cells, and stop at the first cell that is
+/// taken.
+///
+/// # Arguments
+/// * `frame` - The playfield, ready to be drawn
+/// * `snapshot` - The game state being drawn
+/// * `alpha` - How far rendering is between the last tick and the next one
+/// * `theme` - Colors of the bullets the trails follow
+/// * `gradients` - Fades trails into the sky, on terminals with the colors
+pub fn add(frame: &mut [Vec<Option<Glyph>>], snapshot: &Snapshot, alpha: f32, theme: &Theme, gradients: Option<&Gradients>) {
+    let height = frame.len();
+    let bullets = snapshot
+        .positions(0.0)
+        .zip(snapshot.positions(1.0))
+        .filter(|((sprite, _), _)| matches!(sprite, Sprite::PlayerBullet | Sprite::EnemyBullet));
+    for ((sprite, (_, last_y)), (_, (x, y))) in bullets {
+        // Bullets that haven't moved since the last tick have nothing behind them
+        let step: isize = match y.partial_cmp(&last_y) {
+            Some(Ordering::Greater) => -1,
+            Some(Ordering::Less) => 1,
+            _ => continue,
+        };
+        let y = last_y + (y - last_y) * alpha;
+        let (x, y) = (x.round().max(0.0) as usize, y.round().max(0.0) as usize);
+        let fg = theme.style(sprite).fg;
+        for (index, ch) in TRAIL.into_iter().enumerate() {
+            let Some(trail_y) = y.checked_add_signed(step * (index as isize + 1)).filter(|&y| y < height) else { break };
+            let Some(cell) = frame[trail_y].get_mut(x).filter(|cell| cell.is_none()) else { break };
+            let style = gradients.map(|gradients| Style { fg: gradients.fade(fg, trail_y, height, index), bg: None });
+            *cell = Some(Glyph { style, ..Glyph::new(ch, sprite) });
+        }
+    }
+}
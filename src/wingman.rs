@@ -0,0 +1,141 @@
+//! AI-controlled wingman that assists a solo player
+//!
+//! The wingman flies alongside the player, lines up under enemy columns to
+//! shoot, and sidesteps enemy bullets. Its aggressiveness trades caution for
+//! fire rate.
+
+use crate::entity::{Bullet, Entity};
+use crate::game::{Game, GameObject};
+use crate::skin::Sprite;
+use serde::{Deserialize, Serialize};
+
+/// Horizontal speed of the wingman, in cells per second
+const WINGMAN_SPEED: f32 = 8.0;
+/// Fastest the wingman can fire, in seconds between shots, at full aggressiveness
+const MIN_FIRE_INTERVAL: f32 = 0.3;
+/// Extra delay between shots for a completely cautious wingman, in seconds
+const CAUTION_FIRE_DELAY: f32 = 1.2;
+
+/// A computer-controlled ship fighting on the player's side
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Wingman {
+    /// The wingman's ship
+    pub body: GameObject,
+    /// From 0.0 (cautious) to 1.0 (aggressive)
+    aggressiveness: f32,
+    /// Seconds until the wingman may fire again
+    cooldown: f32,
+}
+
+impl Wingman {
+    /// Creates a wingman next to the player
+    ///
+    /// # Arguments
+    /// * `player` - The player's ship, used to pick a starting position
+    /// * `width` - Width of the playfield, in cells
+    /// * `aggressiveness` - From 0.0 (cautious) to 1.0 (aggressive)
+    pub fn new(player: &GameObject, width: usize, aggressiveness: f32) -> Self {
+        let x = if player.x > (width / 2) as f32 {
+            player.x - 4.0
+        } else {
+            player.x + 4.0
+        };
+        Wingman {
+            body: GameObject::new(x, player.y),
+            aggressiveness: aggressiveness.clamp(0.0, 1.0),
+            cooldown: 0.0,
+        }
+    }
+
+    /// How aggressive the wingman is, from 0.0 (cautious) to 1.0 (aggressive)
+    pub fn aggressiveness(&self) -> f32 {
+        self.aggressiveness
+    }
+
+    /// Decides which way the wingman heads this tick and whether it fires
+    ///
+    /// # Arguments
+    /// * `game` - Current game state to react to
+    /// * `dt` - Elapsed simulation time, in seconds
+    ///
+    /// # Returns
+    /// A bullet if the wingman fired
+    pub fn think(&mut self, game: &Game, dt: f32) -> Option<Bullet> {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+
+        // Cautious wingmen watch for bullets from further away
+        let lookahead = 4.0 + 6.0 * (1.0 - self.aggressiveness);
+        let direction = match self.incoming_bullet(game, lookahead) {
+            Some(bullet_x) => self.dodge_direction(bullet_x, game.width),
+            None => self.target_direction(game),
+        };
+
+        // Stop rather than fly off the edge of the screen
+        let next_x = self.body.x + direction * WINGMAN_SPEED * dt;
+        let on_screen = next_x >= 1.0 && next_x <= (game.width - 2) as f32;
+        self.body.vx = if on_screen { direction * WINGMAN_SPEED } else { 0.0 };
+
+        let lined_up = game
+            .enemies
+            .iter()
+            .any(|e| e.is_alive() && (e.body.x - self.body.x).abs() < 0.5);
+        if lined_up && self.cooldown == 0.0 {
+            self.cooldown = MIN_FIRE_INTERVAL + CAUTION_FIRE_DELAY * (1.0 - self.aggressiveness);
+            return Some(Bullet::player(self.body.x, self.body.y - 1.0));
+        }
+        None
+    }
+
+    /// Finds an enemy bullet about to hit the wingman
+    ///
+    /// # Returns
+    /// The bullet's x-coordinate, if one is within `lookahead` rows above
+    fn incoming_bullet(&self, game: &Game, lookahead: f32) -> Option<f32> {
+        game.enemy_bullets
+            .iter()
+            .map(|b| &b.body)
+            .filter(|b| b.alive && (b.x - self.body.x).abs() < 1.5)
+            .find(|b| b.y < self.body.y && self.body.y - b.y <= lookahead)
+            .map(|b| b.x)
+    }
+
+    /// Picks the direction away from an incoming bullet, staying on screen
+    fn dodge_direction(&self, bullet_x: f32, width: usize) -> f32 {
+        let away = if self.body.x >= bullet_x { 1.0 } else { -1.0 };
+        let next = self.body.x + away * 2.0;
+        if next < 1.0 || next > (width - 2) as f32 {
+            -away
+        } else {
+            away
+        }
+    }
+
+    /// Picks the direction towards the nearest enemy column
+    fn target_direction(&self, game: &Game) -> f32 {
+        let nearest = game
+            .enemies
+            .iter()
+            .filter(|e| e.is_alive())
+            .map(|e| e.body.x - self.body.x)
+            .min_by(|a, b| a.abs().total_cmp(&b.abs()));
+        match nearest {
+            Some(dx) if dx > 0.25 => 1.0,
+            Some(dx) if dx < -0.25 => -1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Entity for Wingman {
+    fn body(&self) -> &GameObject {
+        &self.body
+    }
+
+    fn body_mut(&mut self) -> &mut GameObject {
+        &mut self.body
+    }
+
+    fn render_glyph(&self) -> Sprite {
+        Sprite::Wingman
+    }
+}
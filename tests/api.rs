@@ -0,0 +1,157 @@
+//! Checks of the public API as a downstream crate sees it
+//!
+//! These tests only use the documented modules. If one of them has to change,
+//! the change is breaking and needs a major version bump.
+
+mod common;
+
+use common::Recorder;
+use space_invaders::config::Config;
+use space_invaders::events::GameEvent;
+use space_invaders::game::{self, Game};
+use space_invaders::input::PlayerCommand;
+use space_invaders::replay;
+use space_invaders::skin;
+use space_invaders::systems::System;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn builder_defaults_match_the_documented_playfield() {
+    let snapshot = Game::builder().build().snapshot();
+    assert_eq!(snapshot.width, game::DEFAULT_WIDTH);
+    assert_eq!(snapshot.height, game::DEFAULT_HEIGHT);
+    assert_eq!(snapshot.tick_count, 0);
+    assert_eq!(snapshot.score, 0);
+    assert_eq!(snapshot.outcome(), None);
+}
+
+#[test]
+fn tiny_playfields_are_raised_to_the_minimum() {
+    for (width, height) in [(0, 0), (1, 2), (3, 1), (game::MIN_PLAYFIELD_WIDTH, game::MIN_PLAYFIELD_HEIGHT)] {
+        let mut game = Game::builder().width(width).height(height).two_players(true).build();
+        game.toggle_wingman(1.0);
+        for _ in 0..50 {
+            game.step(&[PlayerCommand::MoveLeft, PlayerCommand::Fire]);
+        }
+        let snapshot = game.snapshot();
+        assert_eq!((snapshot.width, snapshot.height), (game::MIN_PLAYFIELD_WIDTH, game::MIN_PLAYFIELD_HEIGHT));
+        assert_eq!(snapshot.modifiers.width, game::MIN_PLAYFIELD_WIDTH);
+        assert_eq!(snapshot.render(1.0, &skin::ASCII).lines().count(), game::MIN_PLAYFIELD_HEIGHT);
+    }
+}
+
+#[test]
+fn same_seed_and_inputs_play_out_identically() {
+    let inputs = [
+        vec![PlayerCommand::MoveLeft, PlayerCommand::Fire],
+        vec![],
+        vec![PlayerCommand::MoveRight, PlayerCommand::MoveRight, PlayerCommand::Fire],
+    ];
+    let play = || {
+        let mut game = Game::builder().seed(7).fire_chance(0.2).build();
+        for tick in 0..300 {
+            game.step(&inputs[tick % inputs.len()]);
+            game.dispatch_events(&mut []);
+        }
+        game.snapshot()
+    };
+
+    let (first, second) = (play(), play());
+    assert_eq!(first.tick_count, 300);
+    assert_eq!(first.score, second.score);
+    assert_eq!(first.game_over, second.game_over);
+    assert_eq!(first.sprites(1.0), second.sprites(1.0));
+}
+
+#[test]
+fn input_log_plays_the_run_again() {
+    let builder = Game::builder().seed(11).fire_chance(0.2);
+    let mut game = builder.build();
+    let mut log = replay::InputLog::new(11, builder.modifiers());
+    for tick in 0..200u64 {
+        let inputs: &[PlayerCommand] = match tick % 4 {
+            0 => &[PlayerCommand::Fire],
+            1 => &[PlayerCommand::MoveLeft],
+            _ => &[],
+        };
+        game.step(inputs);
+        game.dispatch_events(&mut []);
+        log.record(game.tick_count(), inputs);
+        if game.game_over {
+            break;
+        }
+    }
+
+    let replayed = log.replay();
+    assert_eq!(replayed.tick_count(), game.tick_count());
+    assert_eq!(replayed.score(), game.score());
+    assert_eq!(replayed.snapshot().sprites(1.0), game.snapshot().sprites(1.0));
+}
+
+#[test]
+fn subscribers_receive_published_events() {
+    let mut game = Game::builder().build();
+    game.publish(GameEvent::WaveCleared);
+    let mut recorder = Recorder(Vec::new());
+    game.dispatch_events(&mut [&mut recorder]);
+    assert_eq!(recorder.0, vec![GameEvent::WaveCleared]);
+}
+
+#[test]
+fn custom_systems_run_every_tick() {
+    struct Counter(Arc<AtomicUsize>);
+    impl System for Counter {
+        fn update(&mut self, _game: &mut Game, dt: Duration) {
+            assert_eq!(dt, game::TICK_DURATION);
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let mut game = Game::builder().build();
+    game.add_system(Box::new(Counter(Arc::clone(&ticks))));
+    for _ in 0..5 {
+        game.step(&[]);
+    }
+    assert_eq!(ticks.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn player_commands_keep_their_serialized_names() {
+    // Replay and tournament files store commands by these names
+    let names = serde_json::to_string(&[
+        PlayerCommand::MoveLeft,
+        PlayerCommand::MoveRight,
+        PlayerCommand::Fire,
+        PlayerCommand::Pause,
+        PlayerCommand::Quit,
+    ])
+    .unwrap();
+    assert_eq!(names, r#"["MoveLeft","MoveRight","Fire","Pause","Quit"]"#);
+}
+
+#[test]
+fn replay_format_version_is_pinned() {
+    assert_eq!(replay::FORMAT_VERSION, 2);
+}
+
+#[test]
+fn empty_config_uses_the_defaults() {
+    let config = Config::parse("config.toml", "").unwrap();
+    assert_eq!(config.display.width, None);
+    assert!(config.display.colored);
+    assert_eq!(config.game.enemy_rows, 5);
+    assert!(!config.wingman.enabled);
+}
+
+#[test]
+fn config_problems_are_reported_with_their_line() {
+    let Err(problems) = Config::parse("config.toml", "[display]\nskin = \"fancy\"\n") else {
+        panic!("an unknown skin should be rejected");
+    };
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].file, "config.toml");
+    assert_eq!(problems[0].line, Some(2));
+}
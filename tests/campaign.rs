@@ -0,0 +1,150 @@
+//! Checks of campaign files, and runs played through their waves
+
+use space_invaders::campaign::{self, Campaign};
+use space_invaders::config::{MIN_HEIGHT, MIN_WIDTH};
+use space_invaders::game::{Game, GameBuilder, DEFAULT_ENEMY_SPEED, DEFAULT_FIRE_CHANCE};
+use space_invaders::input::PlayerCommand;
+use space_invaders::modifiers::RunModifiers;
+use space_invaders::replay::InputLog;
+
+/// Two waves of one invader each, right above the ship, that never fire:
+/// an armored one worth 30, then a plain one
+const TWO_WAVES: &str = r#"
+name = "Test"
+
+[types]
+o = { points = 30, hits = 2 }
+x = {}
+
+[[waves]]
+speed = 0.5
+fire_chance = 0.0
+layout = ["..o"]
+
+[[waves]]
+speed = 0.5
+fire_chance = 0.0
+layout = ["..x"]
+"#;
+
+/// A small game playing `TWO_WAVES`
+fn two_waves() -> GameBuilder {
+    let campaign = Campaign::parse("two_waves.toml", TWO_WAVES).unwrap();
+    Game::builder().seed(1).width(30).height(12).campaign(Some(campaign))
+}
+
+/// Fires on the given ticks, then plays on until a number of ticks have passed
+fn play(game: &mut Game, fire_on: &[u64], until: u64) {
+    while game.tick_count() < until {
+        let fire = fire_on.contains(&(game.tick_count() + 1));
+        game.step(if fire { &[PlayerCommand::Fire] } else { &[] });
+        game.dispatch_events(&mut []);
+    }
+}
+
+#[test]
+fn bundled_campaign_starts_with_the_standard_formation() {
+    let campaign = Campaign::parse("levels/classic.toml", include_str!("../levels/classic.toml")).unwrap();
+
+    assert_eq!(campaign.waves.len(), 4);
+    let first = &campaign.waves[0];
+    assert_eq!((first.speed, first.fire_chance), (DEFAULT_ENEMY_SPEED, DEFAULT_FIRE_CHANCE));
+    let standard = Game::builder().build();
+    let game = Game::builder().campaign(Some(campaign)).build();
+    assert_eq!(game.enemies.len(), standard.enemies.len());
+}
+
+#[test]
+fn waves_follow_one_another_until_the_last_is_cleared() {
+    let mut game = two_waves().build();
+
+    // The armored invader lives through the first shot but not the second,
+    // and the next wave comes in at once
+    play(&mut game, &[1], 12);
+    assert_eq!((game.enemies.len(), game.score()), (1, 0));
+    play(&mut game, &[13], 25);
+    let snapshot = game.snapshot();
+    assert_eq!((snapshot.wave, snapshot.waves), (2, 2));
+    assert_eq!(snapshot.score, 30);
+    assert_eq!(snapshot.outcome(), None);
+
+    play(&mut game, &[26], 40);
+    assert_eq!(game.score(), 40);
+    assert_eq!(game.outcome(), Some(true));
+}
+
+#[test]
+fn replays_play_the_campaign_they_were_recorded_with() {
+    let modifiers = two_waves().modifiers();
+    let text = serde_json::to_string(&modifiers).unwrap();
+    let loaded: RunModifiers = serde_json::from_str(&text).unwrap();
+    assert_eq!(loaded, modifiers);
+
+    let mut log = InputLog::new(1, loaded);
+    log.record(1, &[PlayerCommand::Fire]);
+    log.record(13, &[PlayerCommand::Fire]);
+    log.record(25, &[]);
+    assert_eq!(log.snapshot_at(25).wave, 2);
+    assert_eq!(modifiers.to_string(), "30x12 playfield, Test campaign");
+}
+
+#[test]
+fn errors_name_the_line() {
+    let errors = |text: &str| {
+        let problems = Campaign::parse("test.toml", text).unwrap_err();
+        problems.iter().map(|problem| (problem.line, problem.message.clone())).collect::<Vec<_>>()
+    };
+    let waves = "name = \"Test\"\n[types]\nx = {}\n[[waves]]\nlayout = [\"x\"]\n[[waves]]\n";
+
+    assert_eq!(
+        errors(&format!("{}speed = 40\nlayout = [\"x\"]\n", waves)),
+        vec![(Some(7), "`waves.1.speed` is 40, but must be between 0.5 and 20".to_string())]
+    );
+    assert_eq!(
+        errors(&format!("{}layout = [\n  \"x.x\",\n  \"q\",\n]\n", waves)),
+        vec![(Some(7), "wave 2 uses `q`, which isn't an enemy type".to_string())]
+    );
+    assert_eq!(
+        errors(&format!("{}layout = [\"...\"]\n", waves)),
+        vec![(Some(7), "wave 2 has no invaders".to_string())]
+    );
+    assert_eq!(
+        errors("name = \"Test\"\n[types]\nxx = {}\n"),
+        vec![
+            (Some(3), "enemy type `xx` must be named by one character other than `.` or a space".to_string()),
+            (None, "a campaign needs at least one `[[waves]]` table".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn waves_must_fit_the_smallest_playfield() {
+    let errors = |layout: &str| {
+        let text = format!("name = \"Test\"\n[types]\nx = {{}}\n[[waves]]\nlayout = {}\n", layout);
+        let problems = Campaign::parse("test.toml", &text).unwrap_err();
+        problems.iter().map(|problem| (problem.line, problem.message.clone())).collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        errors(r#"["x", "x", "x", "x", "x", "x"]"#),
+        vec![(Some(5), "wave 1 has 6 rows, but at most 5 fit".to_string())]
+    );
+    assert_eq!(
+        errors(r#"["..........x"]"#),
+        vec![(Some(5), "wave 1 has no invaders in its first 10 columns, so none would fit the narrowest playfield".to_string())]
+    );
+}
+
+#[test]
+fn the_tallest_widest_wave_starts_above_the_ship_on_the_smallest_playfield() {
+    let row = "x".repeat(campaign::MAX_COLUMNS);
+    let layout = vec![format!("\"{}\"", row); campaign::MAX_ROWS].join(", ");
+    let text = format!("name = \"Test\"\n[types]\nx = {{}}\n[[waves]]\nfire_chance = 0.0\nlayout = [{}]\n", layout);
+    let campaign = Campaign::parse("test.toml", &text).unwrap();
+    let mut game = Game::builder().width(MIN_WIDTH).height(MIN_HEIGHT).campaign(Some(campaign)).build();
+
+    assert_eq!(game.enemies.len(), campaign::MAX_ROWS * campaign::MIN_FIT_COLUMNS);
+    assert!(game.enemies.iter().all(|enemy| enemy.body.y < game.player.body.y - 1.0));
+    play(&mut game, &[], 10);
+    assert_eq!(game.outcome(), None);
+}
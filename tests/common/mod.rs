@@ -0,0 +1,13 @@
+//! Fixtures shared by the test files
+
+use space_invaders::events::{GameEvent, Subscriber};
+
+/// Keeps every event dispatched to it
+#[derive(Default)]
+pub struct Recorder(pub Vec<GameEvent>);
+
+impl Subscriber for Recorder {
+    fn on_event(&mut self, event: &GameEvent) {
+        self.0.push(event.clone());
+    }
+}
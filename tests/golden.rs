@@ -0,0 +1,81 @@
+//! Golden-file checks of rendering and movement
+//!
+//! Frames of scripted runs are drawn with `Snapshot::render` and compared
+//! with the text files in `tests/golden`. When a change to the game is meant
+//! to alter them, run the tests with `UPDATE_GOLDEN=1` to write the new
+//! frames, and review the difference before committing it.
+
+use space_invaders::game::Game;
+use space_invaders::input::PlayerCommand;
+use space_invaders::replay::InputLog;
+use space_invaders::skin;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// A run of the standard game with a few moves and shots
+fn scripted_run() -> InputLog {
+    let builder = Game::builder().seed(42);
+    let mut log = InputLog::new(42, builder.modifiers());
+    log.record(1, &[PlayerCommand::MoveLeft; 5]);
+    log.record(2, &[PlayerCommand::Fire]);
+    log.record(8, &[PlayerCommand::MoveRight, PlayerCommand::MoveRight, PlayerCommand::Fire]);
+    log.record(20, &[]);
+    log
+}
+
+/// Compares a frame with its golden file, or writes the file when
+/// `UPDATE_GOLDEN` is set
+///
+/// # Arguments
+/// * `name` - The golden file's name, without `.txt`
+/// * `frame` - The frame drawn by the test
+fn check(name: &str, frame: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.txt", name));
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, frame).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1 to create it)", path.display(), e));
+    assert!(frame == expected, "{} doesn't match; the frame drawn was:\n{}", path.display(), frame);
+}
+
+#[test]
+fn render_draws_one_line_per_row() {
+    let snapshot = Game::builder().width(30).height(12).build().snapshot();
+    let frame = snapshot.render(1.0, &skin::ASCII);
+    assert_eq!(frame.lines().count(), 12);
+    assert!(frame.lines().all(|line| line.chars().count() == 30));
+    assert!(frame.ends_with('\n'));
+}
+
+#[test]
+fn opening_frame() {
+    check("opening", &scripted_run().snapshot_at(0).render(1.0, &skin::ASCII));
+}
+
+#[test]
+fn ship_moves_and_fires() {
+    check("first_shot", &scripted_run().snapshot_at(4).render(1.0, &skin::ASCII));
+}
+
+#[test]
+fn formation_marches_and_fires() {
+    check("march", &scripted_run().snapshot_at(60).render(1.0, &skin::ASCII));
+}
+
+#[test]
+fn unicode_skin() {
+    check("unicode", &scripted_run().snapshot_at(12).render(1.0, &skin::UNICODE));
+}
+
+#[test]
+fn frames_are_the_same_every_time() {
+    let log = scripted_run();
+    let later = log.snapshot_at(30);
+    let earlier = log.snapshot_at(10);
+    assert_eq!(log.snapshot_at(30).render(1.0, &skin::ASCII), later.render(1.0, &skin::ASCII));
+    assert_eq!(log.snapshot_at(10).render(1.0, &skin::ASCII), earlier.render(1.0, &skin::ASCII));
+}
@@ -0,0 +1,69 @@
+//! Checks that the collision grid finds every entity in a rectangle once,
+//! including entities spanning several cells
+
+use space_invaders::entity::Bounds;
+use space_invaders::grid::CellGrid;
+
+/// A rectangle, by its top left cell and size
+fn bounds(x: usize, y: usize, width: usize, height: usize) -> Bounds {
+    Bounds { x, y, width, height }
+}
+
+/// A grid of the given entities, in index order
+fn grid_of(entities: &[Bounds]) -> CellGrid {
+    let mut grid = CellGrid::default();
+    grid.rebuild(entities.iter().copied());
+    grid
+}
+
+/// The entities a grid finds in a rectangle, sorted
+fn found(grid: &CellGrid, area: Bounds) -> Vec<usize> {
+    let mut indices: Vec<usize> = grid.overlapping(&area).collect();
+    indices.sort_unstable();
+    indices
+}
+
+#[test]
+fn entities_spanning_several_cells_are_found_once() {
+    // A 3-wide invader, two rows tall
+    let grid = grid_of(&[bounds(4, 2, 3, 2)]);
+
+    assert_eq!(found(&grid, bounds(0, 0, 20, 10)), vec![0]);
+    assert_eq!(found(&grid, bounds(5, 0, 1, 10)), vec![0]);
+    assert_eq!(found(&grid, bounds(0, 3, 20, 1)), vec![0]);
+    // Sharing only its bottom right cell with the rectangle
+    assert_eq!(found(&grid, bounds(6, 3, 4, 4)), vec![0]);
+    assert_eq!(found(&grid, bounds(2, 1, 3, 2)), vec![0]);
+}
+
+#[test]
+fn only_entities_sharing_a_cell_are_found() {
+    let grid = grid_of(&[bounds(0, 0, 3, 1), bounds(3, 0, 3, 1), bounds(2, 1, 2, 2)]);
+
+    // Each edge cell belongs to one entity alone
+    assert_eq!(found(&grid, bounds(2, 0, 1, 1)), vec![0]);
+    assert_eq!(found(&grid, bounds(3, 0, 1, 1)), vec![1]);
+    // A bullet's path down a column crosses all it touches
+    assert_eq!(found(&grid, bounds(2, 0, 1, 5)), vec![0, 2]);
+    assert_eq!(found(&grid, bounds(3, 0, 1, 5)), vec![1, 2]);
+    assert_eq!(found(&grid, bounds(1, 0, 4, 3)), vec![0, 1, 2]);
+    assert!(found(&grid, bounds(6, 0, 2, 2)).is_empty());
+    assert!(found(&grid, bounds(0, 3, 10, 1)).is_empty());
+}
+
+#[test]
+fn overlapping_entities_are_each_found() {
+    let grid = grid_of(&[bounds(0, 0, 4, 2), bounds(2, 1, 4, 2), bounds(2, 1, 1, 1)]);
+
+    assert_eq!(found(&grid, bounds(2, 1, 1, 1)), vec![0, 1, 2]);
+    assert_eq!(found(&grid, bounds(0, 0, 8, 8)), vec![0, 1, 2]);
+}
+
+#[test]
+fn rebuilding_forgets_where_entities_were() {
+    let mut grid = grid_of(&[bounds(0, 0, 2, 1), bounds(5, 5, 2, 1)]);
+    grid.rebuild([bounds(10, 0, 2, 1)].into_iter());
+
+    assert!(found(&grid, bounds(0, 0, 8, 8)).is_empty());
+    assert_eq!(found(&grid, bounds(11, 0, 1, 1)), vec![0]);
+}
@@ -0,0 +1,112 @@
+//! Checks of loading `keys.toml` and the problems reported in it
+
+use crossterm::event::KeyCode;
+use space_invaders::input::{KeyBindings, PlayerCommand};
+use space_invaders::paths;
+use std::env;
+use std::fs;
+use std::process;
+
+/// The line and message of every problem found in a `keys.toml`
+fn problems(text: &str) -> Vec<(Option<usize>, String)> {
+    match KeyBindings::parse("keys.toml", text) {
+        Ok(_) => Vec::new(),
+        Err(diagnostics) => diagnostics.into_iter().map(|d| (d.line, d.message)).collect(),
+    }
+}
+
+/// Bindings read from a `keys.toml` expected to have no problems
+fn bindings(text: &str) -> KeyBindings {
+    KeyBindings::parse("keys.toml", text).unwrap_or_else(|problems| panic!("unexpected problems: {:?}", problems))
+}
+
+#[test]
+fn listed_actions_get_exactly_their_keys() {
+    let keys = bindings("move_left = [\"Left\", \"q\"]\nfire = [\"Enter\", \"F5\"]\n");
+
+    assert_eq!(keys.command_for(KeyCode::Char('q')), Some(PlayerCommand::MoveLeft));
+    // Letters work with Caps Lock on
+    assert_eq!(keys.command_for(KeyCode::Char('Q')), Some(PlayerCommand::MoveLeft));
+    assert_eq!(keys.command_for(KeyCode::Enter), Some(PlayerCommand::Fire));
+    assert_eq!(keys.command_for(KeyCode::F(5)), Some(PlayerCommand::Fire));
+    // The defaults of listed actions are gone, and the others' kept
+    assert_eq!(keys.command_for(KeyCode::Char('a')), None);
+    assert_eq!(keys.command_for(KeyCode::Char(' ')), None);
+    assert_eq!(keys.command_for(KeyCode::Right), Some(PlayerCommand::MoveRight));
+    assert_eq!(keys.command_for(KeyCode::Esc), Some(PlayerCommand::Quit));
+}
+
+#[test]
+fn key_names_ignore_case() {
+    let keys = bindings("pause = [\"pageup\", \"SPACE\", \"f12\"]\n");
+
+    assert_eq!(keys.command_for(KeyCode::PageUp), Some(PlayerCommand::Pause));
+    assert_eq!(keys.command_for(KeyCode::Char(' ')), Some(PlayerCommand::Pause));
+    assert_eq!(keys.command_for(KeyCode::F(12)), Some(PlayerCommand::Pause));
+}
+
+#[test]
+fn unknown_key_names_are_reported_on_their_line() {
+    let text = "fire = [\"Space\"]\nmove_left = [\"Left\", \"Leftt\", \"F13\"]\n";
+    assert_eq!(
+        problems(text),
+        vec![
+            (Some(2), "`move_left` has unknown key \"Leftt\"".to_string()),
+            (Some(2), "`move_left` has unknown key \"F13\"".to_string()),
+        ]
+    );
+    let diagnostics = KeyBindings::parse("keys.toml", text).err().unwrap();
+    assert!(diagnostics[0].suggestion.as_deref().is_some_and(|s| s.contains("F1 to F12")));
+}
+
+#[test]
+fn keys_bound_to_two_actions_are_reported() {
+    assert_eq!(
+        problems("fire = [\"x\"]\npause = [\"p\", \"x\"]\n"),
+        vec![(Some(2), "\"x\" is bound to both `fire` and `pause`".to_string())]
+    );
+    // Named keys too, whichever way they are written
+    assert_eq!(
+        problems("fire = [\"Enter\"]\nquit = [\"enter\"]\n"),
+        vec![(Some(2), "\"enter\" is bound to both `fire` and `quit`".to_string())]
+    );
+}
+
+#[test]
+fn keys_listed_twice_or_shared_between_players_are_fine() {
+    assert!(problems("fire = [\"x\", \"x\"]\n").is_empty());
+
+    // The second player's keys take over the first's in two-player runs
+    let keys = bindings("fire = [\"w\"]\np2_fire = [\"w\"]\n");
+    assert_eq!(keys.command_for(KeyCode::Char('w')), Some(PlayerCommand::Fire));
+    assert_eq!(keys.two_player_command_for(KeyCode::Char('w')), Some(PlayerCommand::PlayerTwoFire));
+}
+
+#[test]
+fn actions_without_keys_and_unknown_actions_are_reported() {
+    assert_eq!(problems("fire = []\n"), vec![(Some(1), "`fire` has no keys, so it could never be used".to_string())]);
+    assert_eq!(problems("fire = [\"x\"]\njump = [\"j\"]\n").len(), 1);
+    assert_eq!(problems("fire = \"x\"\n").len(), 1);
+}
+
+#[test]
+fn keys_load_from_the_config_directory() {
+    let dir = env::temp_dir().join(format!("space-invaders-{}-keys", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    paths::set_portable_dir(dir.clone());
+
+    // Without a file, the default keys are used
+    let keys = KeyBindings::load().unwrap();
+    assert_eq!(keys.command_for(KeyCode::Char(' ')), Some(PlayerCommand::Fire));
+
+    fs::write(KeyBindings::path(), "fire = [\"Up\"]\n").unwrap();
+    let keys = KeyBindings::load().unwrap();
+    assert_eq!(keys.command_for(KeyCode::Up), Some(PlayerCommand::Fire));
+    assert_eq!(keys.command_for(KeyCode::Char(' ')), None);
+
+    fs::write(KeyBindings::path(), "fire = [\"Up\", \"Nope\"]\n").unwrap();
+    let problems = KeyBindings::load().err().unwrap();
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].file, KeyBindings::path().display().to_string());
+    fs::remove_dir_all(dir).unwrap();
+}
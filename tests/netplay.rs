@@ -0,0 +1,100 @@
+//! Checks that joining a networked run takes on only runs this game could
+//! have set up itself
+
+use serde_json::json;
+use space_invaders::game::Game;
+use space_invaders::modifiers::RunModifiers;
+use space_invaders::netplay::{Peer, Session, PROTOCOL_VERSION};
+use std::io::{self, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+/// Settings a host would offer for a standard co-op run
+fn co_op() -> RunModifiers {
+    Game::builder().two_players(true).modifiers()
+}
+
+/// Joins a pretend host on this computer that offers a run
+///
+/// # Returns
+/// What joining it came to
+fn join_offer(modifiers: RunModifiers) -> io::Result<Session> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let host = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let hello = json!({ "Hello": { "version": PROTOCOL_VERSION, "seed": 7, "modifiers": modifiers, "delay": 2 } });
+        writeln!(stream, "{}", hello).unwrap();
+        stream
+    });
+    let joined = Peer::join(address);
+    drop(host.join().unwrap());
+    joined
+}
+
+/// The message of an error joining, for a run that was expected to be refused
+fn refusal(joined: io::Result<Session>) -> String {
+    match joined {
+        Ok(_) => panic!("expected the run to be refused"),
+        Err(e) => {
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            e.to_string()
+        }
+    }
+}
+
+#[test]
+fn runs_config_could_set_up_are_taken_on() {
+    let session = join_offer(co_op()).unwrap();
+    assert_eq!(session.seed, 7);
+    assert_eq!(session.modifiers, co_op());
+}
+
+#[test]
+fn a_hosted_run_is_joined_on_its_address() {
+    let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let host = thread::spawn(move || Peer::host(address, 7, co_op()));
+    // The host may not be listening yet
+    let joined = (0..50)
+        .find_map(|_| Peer::join(address).ok().or_else(|| {
+            thread::sleep(Duration::from_millis(20));
+            None
+        }))
+        .expect("the host never listened");
+    let hosted = host.join().unwrap().unwrap();
+    assert_eq!((joined.seed, &joined.modifiers), (hosted.seed, &hosted.modifiers));
+}
+
+#[test]
+fn playfields_out_of_range_are_refused() {
+    let mut huge = co_op();
+    huge.width = 100_000;
+    assert!(refusal(join_offer(huge)).contains("playfield width"));
+
+    let mut flat = co_op();
+    flat.height = 3;
+    assert!(refusal(join_offer(flat)).contains("playfield height"));
+}
+
+#[test]
+fn formations_and_caps_out_of_range_are_refused() {
+    let mut crowded = co_op();
+    crowded.enemy_columns = 5_000;
+    assert!(refusal(join_offer(crowded)).contains("enemy columns"));
+
+    let mut fast = co_op();
+    fast.enemy_speed = 1_000.0;
+    assert!(refusal(join_offer(fast)).contains("enemy speed"));
+
+    let mut uncapped = co_op();
+    uncapped.max_particles = usize::MAX;
+    assert!(refusal(join_offer(uncapped)).contains("particles"));
+}
+
+#[test]
+fn runs_that_arent_co_op_are_refused() {
+    let mut alone = co_op();
+    alone.two_players = false;
+    assert!(refusal(join_offer(alone)).contains("co-op"));
+}
@@ -0,0 +1,75 @@
+//! Checks of the bullet pool's slot reuse, growth and cap
+
+use space_invaders::entity::Bullet;
+use space_invaders::pool::BulletPool;
+
+/// A pool holding one bullet at each of the given columns, in slot order
+fn pool_of(limit: usize, columns: &[f32]) -> BulletPool {
+    let mut pool = BulletPool::with_limit(limit);
+    for &x in columns {
+        pool.spawn(Bullet::player(x, 10.0));
+    }
+    pool
+}
+
+/// The columns of the bullets in the pool, in slot order
+fn columns(pool: &BulletPool) -> Vec<f32> {
+    pool.iter().map(|bullet| bullet.body.x).collect()
+}
+
+/// Marks the bullet at a column as spent
+fn spend(pool: &mut BulletPool, x: f32) {
+    for bullet in pool.iter_mut().filter(|bullet| bullet.body.x == x) {
+        bullet.body.alive = false;
+    }
+}
+
+#[test]
+fn new_bullets_grow_the_pool_until_a_slot_is_free() {
+    let pool = pool_of(usize::MAX, &[1.0, 2.0, 3.0]);
+    assert_eq!(columns(&pool), vec![1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn spent_bullets_stay_until_recycled_and_are_then_skipped() {
+    let mut pool = pool_of(usize::MAX, &[1.0, 2.0, 3.0]);
+
+    spend(&mut pool, 2.0);
+    assert_eq!(columns(&pool), vec![1.0, 2.0, 3.0]);
+    assert!(pool.iter().any(|bullet| !bullet.body.alive));
+
+    pool.recycle();
+    assert_eq!(columns(&pool), vec![1.0, 3.0]);
+    assert_eq!(pool.iter_mut().count(), 2);
+}
+
+#[test]
+fn recycled_slots_are_reused_before_the_pool_grows() {
+    let mut pool = pool_of(usize::MAX, &[1.0, 2.0, 3.0, 4.0]);
+    spend(&mut pool, 2.0);
+    spend(&mut pool, 4.0);
+    pool.recycle();
+
+    // Both freed slots are filled, in place, before a new one is added
+    pool.spawn(Bullet::player(5.0, 10.0));
+    pool.spawn(Bullet::player(6.0, 10.0));
+    assert_eq!(columns(&pool), vec![1.0, 6.0, 3.0, 5.0]);
+    pool.spawn(Bullet::player(7.0, 10.0));
+    assert_eq!(columns(&pool), vec![1.0, 6.0, 3.0, 5.0, 7.0]);
+}
+
+#[test]
+fn a_full_pool_replaces_its_oldest_bullet() {
+    let mut pool = pool_of(3, &[1.0, 2.0, 3.0]);
+
+    pool.spawn(Bullet::player(4.0, 10.0));
+    assert_eq!(columns(&pool), vec![4.0, 2.0, 3.0]);
+    pool.spawn(Bullet::player(5.0, 10.0));
+    assert_eq!(columns(&pool), vec![4.0, 5.0, 3.0]);
+
+    // A slot freed by recycling is used before anything is replaced
+    spend(&mut pool, 4.0);
+    pool.recycle();
+    pool.spawn(Bullet::player(6.0, 10.0));
+    assert_eq!(columns(&pool), vec![6.0, 5.0, 3.0]);
+}
@@ -0,0 +1,147 @@
+//! Checks that replay files read back as they were written, and that seeking
+//! through one finds the run just as it was played
+
+use serde_json::Value;
+use space_invaders::game::Game;
+use space_invaders::input::PlayerCommand;
+use space_invaders::replay::{InputLog, ReplayFile, ReplayStream, CHECKPOINT_TICKS};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Ticks the recorded run is saved at, to check seeking against
+const SEEK_POINTS: [u64; 7] = [0, 1, 599, 600, 601, 1000, 1300];
+
+/// Commands of each tick that had any, in tick order
+type Inputs = Vec<(u64, Vec<PlayerCommand>)>;
+
+/// A file in the temporary directory for this test run alone
+fn scratch(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("space-invaders-{}-{}", process::id(), name))
+}
+
+/// A game that lasts well past a few checkpoints
+fn game() -> Game {
+    Game::builder().seed(11).fire_chance(0.0).enemy_speed(0.5).build()
+}
+
+/// What the player does on a tick, now and then
+fn commands(tick: u64) -> Vec<PlayerCommand> {
+    match tick % 40 {
+        0 => vec![PlayerCommand::Fire],
+        1..=3 => vec![PlayerCommand::MoveLeft],
+        20..=22 => vec![PlayerCommand::MoveRight],
+        _ => Vec::new(),
+    }
+}
+
+/// Everything about a game, comparable
+fn state(game: &Game) -> Value {
+    serde_json::to_value(game.save()).unwrap()
+}
+
+/// A run of 1300 ticks recorded to a file the way the game records one
+///
+/// # Returns
+/// The commands of each tick that had any, and the game's state at each of
+/// `SEEK_POINTS`
+fn record(path: &Path) -> (Inputs, BTreeMap<u64, Value>) {
+    let mut game = game();
+    let mut stream = ReplayStream::create_at(path, 11, game.modifiers()).unwrap();
+    let mut inputs = Vec::new();
+    let mut states = BTreeMap::from([(0, state(&game))]);
+    for tick in 1..=1300 {
+        let commands = commands(tick);
+        game.step(&commands);
+        game.dispatch_events(&mut []);
+        stream.record(tick, &commands).unwrap();
+        if tick.is_multiple_of(CHECKPOINT_TICKS) {
+            stream.checkpoint(&game.save()).unwrap();
+        }
+        if !commands.is_empty() {
+            inputs.push((tick, commands));
+        }
+        if SEEK_POINTS.contains(&tick) {
+            states.insert(tick, state(&game));
+        }
+    }
+    assert!(game.outcome().is_none(), "the run should last all 1300 ticks");
+    stream.flush().unwrap();
+    (inputs, states)
+}
+
+#[test]
+fn streamed_files_load_back_as_recorded() {
+    let path = scratch("round-trip.replay");
+    let (inputs, states) = record(&path);
+
+    let log = InputLog::load(&path).unwrap();
+    assert_eq!(log.seed, 11);
+    assert_eq!(&log.modifiers, game().modifiers());
+    assert_eq!(log.ticks, 1300);
+    assert_eq!(log.inputs, inputs);
+    assert_eq!(state(&log.replay()), states[&1300]);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn restored_games_play_on_the_same() {
+    let mut played = game();
+    for tick in 1..=300 {
+        played.step(&commands(tick));
+        played.dispatch_events(&mut []);
+    }
+    let saved = serde_json::to_string(&played.save()).unwrap();
+
+    let mut restored = game();
+    restored.restore(serde_json::from_str(&saved).unwrap());
+    assert_eq!(state(&restored), state(&played));
+    for tick in 301..=700 {
+        for game in [&mut played, &mut restored] {
+            game.step(&commands(tick));
+            game.dispatch_events(&mut []);
+        }
+    }
+    assert_eq!(state(&restored), state(&played));
+}
+
+#[test]
+fn seeking_finds_the_run_as_it_was_played() {
+    let path = scratch("seek.replay");
+    let (inputs, states) = record(&path);
+    let file = ReplayFile::open(&path).unwrap();
+    assert_eq!(file.log().ticks, 1300);
+
+    for (&tick, expected) in &states {
+        let (game, rest) = file.seek(tick).unwrap();
+        assert_eq!(game.tick_count(), tick);
+        assert_eq!(&state(&game), expected, "seeking to tick {}", tick);
+        let after: Vec<_> = inputs.iter().filter(|(t, _)| *t > tick).cloned().collect();
+        assert_eq!(rest.collect::<Vec<_>>(), after, "commands after tick {}", tick);
+    }
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn version_1_files_without_checkpoints_still_load() {
+    let path = scratch("version-1.replay");
+    let modifiers = serde_json::to_string(game().modifiers()).unwrap();
+    let text = format!(
+        "{{\"version\":1,\"seed\":11,\"modifiers\":{}}}\n{{\"tick\":1,\"inputs\":[\"Fire\"]}}\n{{\"tick\":5,\"inputs\":[\"MoveLeft\"]}}\n",
+        modifiers
+    );
+    fs::write(&path, text).unwrap();
+
+    let log = InputLog::load(&path).unwrap();
+    assert_eq!(log.inputs, vec![(1, vec![PlayerCommand::Fire]), (5, vec![PlayerCommand::MoveLeft])]);
+    let (game, rest) = ReplayFile::open(&path).unwrap().seek(3).unwrap();
+    assert_eq!(game.tick_count(), 3);
+    assert_eq!(rest.collect::<Vec<_>>(), vec![(5, vec![PlayerCommand::MoveLeft])]);
+
+    fs::write(&path, format!("{{\"version\":99,\"seed\":11,\"modifiers\":{}}}\n", modifiers)).unwrap();
+    assert!(InputLog::load(&path).is_err());
+    assert!(ReplayFile::open(&path).is_err());
+    fs::remove_file(path).unwrap();
+}
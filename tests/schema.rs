@@ -0,0 +1,125 @@
+//! Checks of data file validation and the diagnostics it reports
+
+use space_invaders::schema::{self, Diagnostic, Field, FieldKind};
+
+/// A small file layout using every kind of field
+const SCHEMA: FieldKind = FieldKind::Table(&[
+    Field {
+        name: "display",
+        kind: FieldKind::Table(&[
+            Field { name: "colored", kind: FieldKind::Bool },
+            Field { name: "width", kind: FieldKind::Integer { min: 56, max: 250 } },
+            Field { name: "scale", kind: FieldKind::Float { min: 0.5, max: 2.0 } },
+            Field { name: "skin", kind: FieldKind::Choice(&["ascii", "unicode"]) },
+        ]),
+    },
+    Field { name: "aliases", kind: FieldKind::Map(&FieldKind::Text) },
+    Field {
+        name: "waves",
+        kind: FieldKind::List(&FieldKind::Table(&[Field { name: "speed", kind: FieldKind::Float { min: 0.5, max: 20.0 } }])),
+    },
+]);
+
+/// The line and message of every problem found in a file
+fn problems(text: &str) -> Vec<(Option<usize>, String)> {
+    schema::validate("test.toml", text, &SCHEMA).into_iter().map(|d| (d.line, d.message)).collect()
+}
+
+#[test]
+fn valid_files_have_no_problems() {
+    let text = r#"
+[display]
+colored = false
+width = 80
+scale = 1
+skin = "unicode"
+
+[aliases]
+fire = "space"
+
+[[waves]]
+speed = 2.5
+"#;
+    assert_eq!(problems(text), vec![]);
+}
+
+#[test]
+fn unknown_keys_are_reported_with_a_suggestion() {
+    let found = schema::validate("test.toml", "[display]\nwidht = 80\nborder = true\n", &SCHEMA);
+    let found: Vec<_> = found.iter().map(|d| (d.line, d.message.as_str(), d.suggestion.as_deref())).collect();
+
+    assert_eq!(
+        found,
+        vec![
+            (Some(2), "unknown key `display.widht`", Some("did you mean `width`?")),
+            (Some(3), "unknown key `display.border`", None),
+        ]
+    );
+}
+
+#[test]
+fn wrong_types_are_reported() {
+    let text = "[display]\ncolored = \"yes\"\nwidth = 80.5\n\n[aliases]\nfire = 1\n";
+
+    assert_eq!(
+        problems(text),
+        vec![
+            (Some(2), "`display.colored` should be true or false, found string".to_string()),
+            (Some(3), "`display.width` should be a whole number from 56 to 250, found float".to_string()),
+            (Some(6), "`aliases.fire` should be a string, found integer".to_string()),
+        ]
+    );
+    assert_eq!(problems("display = 3\n"), vec![(Some(1), "`display` should be a table, found integer".to_string())]);
+}
+
+#[test]
+fn values_out_of_range_or_not_a_choice_are_reported() {
+    let text = "[display]\nwidth = 20\nscale = 3.5\nskin = \"unicod\"\n";
+    let found = schema::validate("test.toml", text, &SCHEMA);
+
+    assert_eq!(
+        found.iter().map(|d| (d.line, d.message.as_str())).collect::<Vec<_>>(),
+        vec![
+            (Some(2), "`display.width` is 20, but must be between 56 and 250"),
+            (Some(3), "`display.scale` is 3.5, but must be between 0.5 and 2"),
+            (Some(4), "`display.skin` must be one of \"ascii\" or \"unicode\", found \"unicod\""),
+        ]
+    );
+    assert_eq!(found[2].suggestion.as_deref(), Some("did you mean \"unicode\"?"));
+}
+
+#[test]
+fn items_of_lists_are_found_on_their_own_lines() {
+    let text = "[[waves]]\nspeed = 1.0\n\n[[waves]]\nspeed = 40\n\n[[waves]]\nspead = 2.0\n";
+
+    assert_eq!(
+        problems(text),
+        vec![
+            (Some(5), "`waves.1.speed` is 40, but must be between 0.5 and 20".to_string()),
+            (Some(8), "unknown key `waves.2.spead`".to_string()),
+        ]
+    );
+    assert_eq!(schema::find_line("waves = [{ speed = 1 }, { speed = 2 }]\n", &["waves", "1", "speed"]), Some(1));
+}
+
+#[test]
+fn unreadable_files_are_reported_where_parsing_stopped() {
+    let found = problems("[display]\nwidth = 80\nskin = \n");
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, Some(3));
+}
+
+#[test]
+fn diagnostics_print_file_line_and_suggestion() {
+    let diagnostic = Diagnostic {
+        file: "config.toml".to_string(),
+        line: Some(4),
+        message: "unknown key `display.widht`".to_string(),
+        suggestion: Some("did you mean `width`?".to_string()),
+    };
+    assert_eq!(diagnostic.to_string(), "config.toml:4: unknown key `display.widht` (did you mean `width`?)");
+
+    let diagnostic = Diagnostic { line: None, suggestion: None, ..diagnostic };
+    assert_eq!(diagnostic.to_string(), "config.toml: unknown key `display.widht`");
+}
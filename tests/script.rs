@@ -0,0 +1,87 @@
+//! Checks of input scripts, and scenarios played from them
+//!
+//! Scenario scripts live in `tests/scripts` and are played on small games
+//! whose invaders hold still and never fire, so that only what a script does
+//! can happen.
+
+mod common;
+
+use common::Recorder;
+use space_invaders::events::GameEvent;
+use space_invaders::game::{Game, GameBuilder};
+use space_invaders::input::PlayerCommand;
+use space_invaders::replay::InputLog;
+use space_invaders::script::InputScript;
+use std::path::PathBuf;
+
+/// Two invaders in a row, which hold still and never fire
+fn still() -> GameBuilder {
+    Game::builder().seed(1).fire_chance(0.0).enemy_speed(0.0).width(30).height(12).enemy_rows(1).enemy_columns(2)
+}
+
+/// Loads a scenario from `tests/scripts` and makes a run of it
+///
+/// # Arguments
+/// * `name` - The script's file name, without `.txt`
+/// * `builder` - Settings to play the script on
+fn scenario(name: &str, builder: GameBuilder) -> InputLog {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/scripts").join(format!("{}.txt", name));
+    let script = InputScript::load(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    script.log(0, builder.modifiers())
+}
+
+#[test]
+fn parses_ticks_ranges_and_settings() {
+    let script = InputScript::parse("# a comment\nseed 7\n3 fire  # trailing comment\n\n1-2 move_left\n3 move_right\nend 10\n").unwrap();
+
+    assert_eq!(script.seed, Some(7));
+    assert_eq!(script.end, Some(10));
+    let inputs: Vec<_> = script.inputs.into_iter().collect();
+    assert_eq!(
+        inputs,
+        vec![
+            (1, vec![PlayerCommand::MoveLeft]),
+            (2, vec![PlayerCommand::MoveLeft]),
+            (3, vec![PlayerCommand::Fire, PlayerCommand::MoveRight]),
+        ]
+    );
+}
+
+#[test]
+fn errors_name_the_line() {
+    let error = |text: &str| InputScript::parse(text).unwrap_err();
+
+    assert_eq!(error("1 fire\n2 jump"), "line 2: unknown command `jump`");
+    assert_eq!(error("0 fire"), "line 1: ticks start at 1");
+    assert_eq!(error("5-3 fire"), "line 1: range `5-3` ends before it starts");
+    assert_eq!(error("\n\n4"), "line 3: no commands for tick 4");
+    assert_eq!(error("seed many"), "line 1: `many` isn't a number");
+    assert_eq!(error("fire 3"), "line 1: expected a tick, `seed` or `end`, found `fire`");
+}
+
+#[test]
+fn a_script_plays_on_its_own_seed_and_up_to_its_end() {
+    let script = InputScript::parse("seed 9\n2 fire\nend 30").unwrap();
+    let log = script.log(1, Game::builder().modifiers());
+    assert_eq!(log.seed, 9);
+    assert_eq!(log.ticks, 30);
+
+    let unseeded = InputScript::parse("2 fire").unwrap().log(1, Game::builder().modifiers());
+    assert_eq!(unseeded.seed, 1);
+    assert_eq!(unseeded.ticks, 2);
+}
+
+#[test]
+fn killing_the_last_enemy_with_a_shot_in_flight_clears_the_wave() {
+    let log = scenario("last_enemy_in_flight", still());
+    let mut recorder = Recorder::default();
+    let game = log.replay_with(&mut [&mut recorder]);
+
+    assert_eq!(game.outcome(), Some(true));
+    assert!(game.enemies.is_empty());
+    assert_eq!(game.player_bullets.iter().count(), 1, "the second shot should still be in flight");
+    let kills = recorder.0.iter().filter(|event| matches!(event, GameEvent::EnemyKilled { .. })).count();
+    assert_eq!(kills, 2);
+    assert_eq!(recorder.0.iter().filter(|&event| *event == GameEvent::WaveCleared).count(), 1);
+    assert_eq!(recorder.0.last(), Some(&GameEvent::WaveCleared));
+}
@@ -0,0 +1,87 @@
+//! Checks of the key sequence matcher behind the Konami code
+
+use crossterm::event::KeyCode::{self, Down, Left, Right, Up};
+use space_invaders::input::{KeySequence, KONAMI_CODE};
+
+/// The keys completing the Konami code once its opening Up Up is typed
+const AFTER_UP_UP: [KeyCode; 8] = [Down, Down, Left, Right, Left, Right, KeyCode::Char('b'), KeyCode::Char('a')];
+
+/// Types keys in order
+///
+/// # Returns
+/// After how many of them the sequence was completed
+fn completions(sequence: &mut KeySequence, keys: &[KeyCode]) -> Vec<usize> {
+    keys.iter().enumerate().filter(|&(_, &key)| sequence.push(key)).map(|(typed, _)| typed + 1).collect()
+}
+
+#[test]
+fn the_code_completes_on_its_last_key() {
+    let mut konami = KeySequence::new(&KONAMI_CODE);
+    assert_eq!(completions(&mut konami, &KONAMI_CODE), vec![10]);
+}
+
+#[test]
+fn letters_match_in_either_case() {
+    let mut konami = KeySequence::new(&KONAMI_CODE);
+    let mut keys = KONAMI_CODE;
+    keys[8] = KeyCode::Char('B');
+    keys[9] = KeyCode::Char('A');
+    assert_eq!(completions(&mut konami, &keys), vec![10]);
+}
+
+#[test]
+fn extra_presses_of_the_opening_key_still_count() {
+    let mut konami = KeySequence::new(&KONAMI_CODE);
+    let mut keys = vec![Up, Up, Up];
+    keys.extend(AFTER_UP_UP);
+    assert_eq!(completions(&mut konami, &keys), vec![11]);
+
+    let mut keys = vec![Up; 6];
+    keys.extend(AFTER_UP_UP);
+    assert_eq!(completions(&mut konami, &keys), vec![14]);
+}
+
+#[test]
+fn a_wrong_key_starts_the_code_over() {
+    let mut konami = KeySequence::new(&KONAMI_CODE);
+    // Right where Left was due, then the rest as if nothing happened
+    let mut keys = vec![Up, Up, Down, Down, Right];
+    keys.extend(&KONAMI_CODE[5..]);
+    assert!(completions(&mut konami, &keys).is_empty());
+
+    // Typed again in full, the code goes through
+    assert_eq!(completions(&mut konami, &KONAMI_CODE), vec![10]);
+}
+
+#[test]
+fn a_code_broken_off_by_its_opening_restarts_from_there() {
+    let mut konami = KeySequence::new(&KONAMI_CODE);
+    // Up Up Down, then Up Up as the start of a fresh attempt
+    let mut keys = vec![Up, Up, Down, Up, Up];
+    keys.extend(AFTER_UP_UP);
+    assert_eq!(completions(&mut konami, &keys), vec![13]);
+
+    // Given up on just before the end
+    let mut keys = KONAMI_CODE[..9].to_vec();
+    keys.extend(KONAMI_CODE);
+    assert_eq!(completions(&mut konami, &keys), vec![19]);
+}
+
+#[test]
+fn completing_the_code_starts_it_over() {
+    let mut konami = KeySequence::new(&KONAMI_CODE);
+    // The last key again doesn't complete it a second time
+    let mut keys = KONAMI_CODE.to_vec();
+    keys.push(KeyCode::Char('a'));
+    keys.extend(KONAMI_CODE);
+    assert_eq!(completions(&mut konami, &keys), vec![10, 21]);
+}
+
+#[test]
+fn sequences_repeating_themselves_are_matched_from_any_point() {
+    static ABAC: [KeyCode; 4] = [KeyCode::Char('a'), KeyCode::Char('b'), KeyCode::Char('a'), KeyCode::Char('c')];
+    let mut sequence = KeySequence::new(&ABAC);
+    // After "a b a", a "b" leaves "a b" matched rather than nothing
+    let keys: Vec<KeyCode> = "ababac".chars().map(KeyCode::Char).collect();
+    assert_eq!(completions(&mut sequence, &keys), vec![6]);
+}
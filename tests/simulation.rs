@@ -0,0 +1,249 @@
+//! Checks of the game rules, played through the public simulation API
+//!
+//! Each test sets up a small game, places entities where it needs them and
+//! steps it tick by tick, without a terminal or a clock.
+
+mod common;
+
+use common::Recorder;
+use space_invaders::entity::Bullet;
+use space_invaders::events::GameEvent;
+use space_invaders::game::{Game, GameBuilder, TICK_DURATION};
+use space_invaders::input::PlayerCommand;
+use std::time::Duration;
+
+/// A game whose invaders never fire, so only what a test does can happen
+fn quiet() -> GameBuilder {
+    Game::builder().seed(1).fire_chance(0.0)
+}
+
+#[test]
+fn player_bullet_destroys_the_enemy_it_reaches() {
+    let mut game = quiet().enemy_rows(1).enemy_columns(2).build();
+    let target = game.enemies[0].body.clone();
+    game.player_bullets.spawn(Bullet::player(target.x, target.y + 1.0));
+
+    game.step(&[]);
+    let mut recorder = Recorder::default();
+    game.dispatch_events(&mut [&mut recorder]);
+
+    assert_eq!(game.enemies.len(), 1);
+    assert_eq!(game.player_bullets.iter().count(), 0);
+    assert!(matches!(recorder.0[..], [GameEvent::EnemyKilled { player_two: false, .. }]));
+    assert!(game.score() > 0);
+}
+
+#[test]
+fn enemy_bullet_costs_the_player_a_life() {
+    let mut game = quiet().build();
+    let ship = game.player.body.clone();
+    game.enemy_bullets.spawn(Bullet::enemy(ship.x, ship.y - 1.0));
+
+    game.step(&[]);
+    let mut recorder = Recorder::default();
+    game.dispatch_events(&mut [&mut recorder]);
+
+    assert_eq!(game.player.lives, 2);
+    assert_eq!(recorder.0, vec![GameEvent::PlayerHit]);
+    assert_eq!(game.outcome(), None);
+}
+
+#[test]
+fn a_hit_ship_is_briefly_invulnerable() {
+    let mut game = quiet().build();
+    let ship = game.player.body.clone();
+    game.enemy_bullets.spawn(Bullet::enemy(ship.x, ship.y - 1.0));
+    game.step(&[]);
+    game.enemy_bullets.spawn(Bullet::enemy(ship.x, ship.y - 1.0));
+    game.step(&[]);
+
+    assert_eq!(game.player.lives, 2);
+}
+
+#[test]
+fn losing_the_last_life_ends_the_run() {
+    let mut game = quiet().lives(1).build();
+    let ship = game.player.body.clone();
+    game.enemy_bullets.spawn(Bullet::enemy(ship.x, ship.y - 1.0));
+
+    game.step(&[]);
+
+    assert!(game.game_over);
+    assert_eq!(game.outcome(), Some(false));
+    assert_eq!(game.snapshot().outcome(), Some(false));
+}
+
+#[test]
+fn destroying_the_last_enemy_clears_the_wave() {
+    let mut game = quiet().enemy_rows(1).enemy_columns(1).build();
+    let target = game.enemies[0].body.clone();
+    game.player_bullets.spawn(Bullet::player(target.x, target.y + 1.0));
+
+    game.step(&[]);
+    let mut recorder = Recorder::default();
+    game.dispatch_events(&mut [&mut recorder]);
+
+    assert!(recorder.0.contains(&GameEvent::WaveCleared));
+    assert_eq!(game.outcome(), Some(true));
+    assert_eq!(game.snapshot().outcome(), Some(true));
+}
+
+#[test]
+fn ship_stops_at_the_edges_of_the_screen() {
+    let mut game = quiet().width(40).build();
+    game.step(&[PlayerCommand::MoveLeft; 50]);
+    assert_eq!(game.player.body.x, 1.0);
+
+    game.step(&[PlayerCommand::MoveRight; 50]);
+    assert_eq!(game.player.body.x, 38.0);
+}
+
+#[test]
+fn bullets_are_retired_once_they_leave_the_screen() {
+    let mut game = quiet().enemy_rows(1).enemy_columns(1).build();
+    game.player_bullets.spawn(Bullet::player(50.0, 20.0));
+    game.enemy_bullets.spawn(Bullet::enemy(2.0, 5.0));
+
+    for _ in 0..25 {
+        game.step(&[]);
+    }
+
+    assert_eq!(game.player_bullets.iter().count(), 0);
+    assert_eq!(game.enemy_bullets.iter().count(), 0);
+    assert_eq!(game.player.lives, 3);
+}
+
+#[test]
+fn formation_turns_and_drops_at_the_edge_of_the_screen() {
+    let mut game = quiet().width(30).enemy_rows(1).enemy_columns(3).build();
+    let top = game.enemies[0].body.y;
+
+    for _ in 0..100 {
+        game.step(&[]);
+        for enemy in &game.enemies {
+            assert!(enemy.body.x >= 1.0 && enemy.body.x <= 28.0, "invader left the screen at x = {}", enemy.body.x);
+        }
+        if game.enemies[0].body.y > top {
+            break;
+        }
+    }
+
+    assert_eq!(game.enemies[0].body.y, top + 1.0);
+    assert!(game.enemies.iter().all(|enemy| enemy.body.vx < 0.0));
+}
+
+#[test]
+fn invaders_reaching_the_bottom_end_the_run() {
+    let mut game = quiet().width(20).enemy_rows(1).enemy_columns(1).build();
+
+    game.update(Duration::from_secs(600));
+
+    assert_eq!(game.outcome(), Some(false));
+    assert_eq!(game.player.lives, 3);
+}
+
+#[test]
+fn update_runs_whole_ticks_and_carries_the_rest_over() {
+    let mut game = quiet().build();
+
+    assert_eq!(game.update(TICK_DURATION / 2), 0);
+    assert_eq!(game.update(TICK_DURATION * 2), 2);
+    assert_eq!(game.update(TICK_DURATION / 2), 1);
+    assert_eq!(game.tick_count(), 3);
+    assert_eq!(game.elapsed(), TICK_DURATION * 3);
+}
+
+#[test]
+fn handled_commands_wait_for_the_next_tick() {
+    let mut game = quiet().build();
+    let x = game.player.body.x;
+    game.handle_command(PlayerCommand::MoveLeft);
+    game.handle_command(PlayerCommand::MoveLeft);
+
+    game.update(Duration::ZERO);
+    assert_eq!(game.player.body.x, x);
+
+    game.update(TICK_DURATION * 2);
+    assert_eq!(game.player.body.x, x - 2.0);
+}
+
+#[test]
+fn update_plays_out_like_step() {
+    let mut stepped = Game::builder().seed(3).build();
+    let mut updated = Game::builder().seed(3).build();
+    for tick in 0..100 {
+        let commands: &[PlayerCommand] = if tick % 3 == 0 { &[PlayerCommand::Fire] } else { &[] };
+        stepped.step(commands);
+        for &command in commands {
+            updated.handle_command(command);
+        }
+        updated.update(TICK_DURATION);
+    }
+
+    assert_eq!(updated.tick_count(), stepped.tick_count());
+    assert_eq!(updated.snapshot().sprites(1.0), stepped.snapshot().sprites(1.0));
+}
+
+#[test]
+fn a_bullet_hits_an_enemy_it_swaps_cells_with() {
+    let mut game = quiet().enemy_speed(0.0).enemy_rows(1).enemy_columns(1).build();
+    // A diver falling a cell a tick, and a bullet rising at it from the cell below
+    let enemy = &mut game.enemies[0];
+    enemy.diving = true;
+    enemy.body.vy = 10.0;
+    let (x, y) = (enemy.body.x, enemy.body.y);
+    game.player_bullets.spawn(Bullet::player(x, y + 1.0));
+
+    game.step(&[]);
+
+    assert!(game.enemies.is_empty());
+    assert_eq!(game.player_bullets.iter().count(), 0);
+}
+
+#[test]
+fn point_blank_shots_connect() {
+    let mut game = quiet().enemy_speed(0.0).enemy_rows(1).enemy_columns(1).build();
+    let ship = game.player.body.clone();
+    game.enemies[0].body.x = ship.x;
+    game.enemies[0].body.y = ship.y - 1.0;
+
+    game.step(&[PlayerCommand::Fire]);
+
+    assert!(game.enemies.is_empty());
+    assert_eq!(game.outcome(), Some(true));
+}
+
+#[test]
+fn past_the_cap_new_bullets_replace_the_oldest() {
+    let mut game = quiet().max_bullets(3, 2).build();
+    for x in 1..=5 {
+        game.player_bullets.spawn(Bullet::player(x as f32, 10.0));
+        game.enemy_bullets.spawn(Bullet::enemy(x as f32, 5.0));
+    }
+
+    let xs = |bullets: &mut dyn Iterator<Item = &Bullet>| {
+        let mut xs: Vec<f32> = bullets.map(|bullet| bullet.body.x).collect();
+        xs.sort_by(f32::total_cmp);
+        xs
+    };
+    assert_eq!(xs(&mut game.player_bullets.iter()), vec![3.0, 4.0, 5.0]);
+    assert_eq!(xs(&mut game.enemy_bullets.iter()), vec![4.0, 5.0]);
+}
+
+#[test]
+fn past_the_cap_the_oldest_particles_go_first() {
+    let mut game = quiet().enemy_rows(1).enemy_columns(3).max_particles(10).build();
+    for enemy in game.enemies.clone() {
+        game.player_bullets.spawn(Bullet::player(enemy.body.x, enemy.body.y + 1.0));
+    }
+
+    game.step(&[]);
+
+    // Bursts of six, left to right: the first is dropped, and two of the second
+    assert!(game.enemies.is_empty());
+    let xs: Vec<f32> = game.particles.iter().map(|particle| particle.body.x).collect();
+    assert_eq!(xs.len(), 10);
+    assert!(xs[..4].iter().all(|&x| x == xs[0]));
+    assert!(xs[4..].iter().all(|&x| x == xs[4]));
+    assert!(xs[0] < xs[4]);
+}
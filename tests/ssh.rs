@@ -0,0 +1,58 @@
+//! Checks that what arcade players' terminals send decodes to the keys they
+//! pressed
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use space_invaders::ssh::decode_keys;
+
+/// The codes of the keys decoded from some input, ignoring modifiers
+fn codes(bytes: &[u8]) -> Vec<KeyCode> {
+    decode_keys(bytes).into_iter().map(|key| key.code).collect()
+}
+
+#[test]
+fn plain_characters_and_control_keys_decode_one_by_one() {
+    assert_eq!(codes(b"q p"), vec![KeyCode::Char('q'), KeyCode::Char(' '), KeyCode::Char('p')]);
+    let (enter, backspace) = (KeyCode::Enter, KeyCode::Backspace);
+    assert_eq!(codes(b"\r\n\t\x7f\x08"), vec![enter, enter, KeyCode::Tab, backspace, backspace]);
+    assert_eq!(decode_keys(b"\x03"), vec![KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)]);
+    assert_eq!(codes("é→".as_bytes()), vec![KeyCode::Char('é'), KeyCode::Char('→')]);
+}
+
+#[test]
+fn cursor_keys_decode_in_both_modes() {
+    let arrows = vec![KeyCode::Up, KeyCode::Down, KeyCode::Right, KeyCode::Left];
+    assert_eq!(codes(b"\x1b[A\x1b[B\x1b[C\x1b[D"), arrows);
+    // Sent in the terminal's application cursor mode
+    assert_eq!(codes(b"\x1bOA\x1bOB\x1bOC\x1bOD"), arrows);
+    assert_eq!(codes(b"\x1b[H\x1b[F\x1b[1~\x1b[4~"), vec![KeyCode::Home, KeyCode::End, KeyCode::Home, KeyCode::End]);
+}
+
+#[test]
+fn editing_and_function_keys_decode_from_their_numbers() {
+    assert_eq!(
+        codes(b"\x1b[2~\x1b[3~\x1b[5~\x1b[6~\x1b[Z"),
+        vec![KeyCode::Insert, KeyCode::Delete, KeyCode::PageUp, KeyCode::PageDown, KeyCode::BackTab]
+    );
+    assert_eq!(codes(b"\x1bOP\x1b[15~\x1b[24~"), vec![KeyCode::F(1), KeyCode::F(5), KeyCode::F(12)]);
+}
+
+#[test]
+fn modifiers_are_read_from_sequences_and_escapes() {
+    assert_eq!(decode_keys(b"\x1b[1;5D"), vec![KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL)]);
+    assert_eq!(decode_keys(b"\x1b[1;2A"), vec![KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT)]);
+    assert_eq!(decode_keys(b"\x1bx"), vec![KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT)]);
+}
+
+#[test]
+fn a_lone_escape_is_the_esc_key() {
+    assert_eq!(codes(b"\x1b"), vec![KeyCode::Esc]);
+    assert_eq!(codes(b"\x1b\x1b"), vec![KeyCode::Esc, KeyCode::Esc]);
+    assert_eq!(codes(b"\x1b\x1b[A"), vec![KeyCode::Esc, KeyCode::Up]);
+}
+
+#[test]
+fn unknown_sequences_are_skipped_whole() {
+    assert_eq!(codes(b"\x1b[99~a\x1b[?1;2cb"), vec![KeyCode::Char('a'), KeyCode::Char('b')]);
+    // A byte that starts no character is dropped, and decoding goes on after it
+    assert_eq!(codes(b"\xffq"), vec![KeyCode::Char('q')]);
+}
@@ -0,0 +1,105 @@
+//! Checks that tournament tokens and results hold up, and that tampering
+//! with either is caught
+
+use ed25519_dalek::SigningKey;
+use space_invaders::input::PlayerCommand;
+use space_invaders::tournament::{RunRecord, Ruleset, Token, TournamentError};
+
+/// A ruleset with the standard formation and playfield
+fn ruleset() -> Ruleset {
+    Ruleset {
+        name: "Test cup".to_string(),
+        seed: 7,
+        width: 60,
+        height: 25,
+        enemy_rows: 5,
+        enemy_columns: 10,
+        enemy_speed: 2.0,
+        fire_chance: 0.0,
+    }
+}
+
+/// The organizer's key
+fn organizer() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+/// A run under `token` that fires on every tick, with its honest score
+fn played(token: &Token) -> RunRecord {
+    let inputs: Vec<(u64, Vec<PlayerCommand>)> = (1..=100).map(|tick| (tick, vec![PlayerCommand::Fire])).collect();
+    let mut game = token.ruleset.builder().build();
+    for (_, commands) in &inputs {
+        game.step(commands);
+        game.dispatch_events(&mut []);
+    }
+    RunRecord { token: token.as_str().to_string(), score: game.score(), ticks: game.tick_count(), inputs }
+}
+
+/// Swaps one character of a base64 part for another, keeping it decodable
+fn flip(part: &str) -> String {
+    let mut chars: Vec<char> = part.chars().collect();
+    chars[0] = if chars[0] == 'A' { 'B' } else { 'A' };
+    chars.into_iter().collect()
+}
+
+#[test]
+fn signed_tokens_and_replayed_results_verify() {
+    let text = Token::sign(&ruleset(), &organizer());
+    let token = Token::parse(&text).unwrap();
+    assert_eq!(token.ruleset, ruleset());
+    assert_eq!(token.organizer, organizer().verifying_key());
+
+    let record = played(&token);
+    assert!(record.score > 0);
+    let (opened, opened_token) = RunRecord::from_blob(&record.to_blob()).unwrap();
+    assert_eq!(opened_token.ruleset, ruleset());
+    assert_eq!(opened.verify(&opened_token).unwrap(), record.score);
+}
+
+#[test]
+fn tampered_tokens_are_rejected() {
+    let text = Token::sign(&ruleset(), &organizer());
+    let parts: Vec<&str> = text.split('.').collect();
+
+    // A different signature, or the signature over a different ruleset
+    let signature = format!("{}.{}.{}", parts[0], parts[1], flip(parts[2]));
+    assert!(matches!(Token::parse(&signature), Err(TournamentError::BadSignature)));
+    let easier = Ruleset { fire_chance: 0.5, ..ruleset() };
+    let other = Token::sign(&easier, &organizer());
+    let swapped = format!("{}.{}.{}", other.split('.').next().unwrap(), parts[1], parts[2]);
+    assert!(matches!(Token::parse(&swapped), Err(TournamentError::BadSignature)));
+
+    assert!(matches!(Token::parse("not a token"), Err(TournamentError::Malformed(_))));
+}
+
+#[test]
+fn tampered_results_are_rejected() {
+    let token = Token::parse(&Token::sign(&ruleset(), &organizer())).unwrap();
+    let honest = played(&token);
+
+    // A blob that isn't a record at all
+    assert!(matches!(RunRecord::from_blob("not a result"), Err(TournamentError::Malformed(_))));
+
+    // Blobs are unsigned, so a higher score or different inputs read back
+    // fine and are caught only by re-playing the run
+    let inflated = RunRecord { score: honest.score + 100, ..played(&token) };
+    let (opened, opened_token) = RunRecord::from_blob(&inflated.to_blob()).unwrap();
+    match opened.verify(&opened_token) {
+        Err(TournamentError::Mismatch { claimed, replayed }) => assert_eq!((claimed, replayed), (honest.score + 100, honest.score)),
+        other => panic!("expected a mismatch, got {:?}", other),
+    }
+
+    let idle = RunRecord { inputs: Vec::new(), ..played(&token) };
+    let (opened, opened_token) = RunRecord::from_blob(&idle.to_blob()).unwrap();
+    assert!(matches!(opened.verify(&opened_token), Err(TournamentError::Mismatch { replayed: 0, .. })));
+}
+
+#[test]
+fn results_name_the_organizer_who_signed_their_token() {
+    let stranger = SigningKey::from_bytes(&[9; 32]);
+    let token = Token::parse(&Token::sign(&ruleset(), &stranger)).unwrap();
+    let (_, opened_token) = RunRecord::from_blob(&played(&token).to_blob()).unwrap();
+
+    assert_ne!(opened_token.organizer, organizer().verifying_key());
+    assert_eq!(opened_token.organizer, stranger.verifying_key());
+}